@@ -0,0 +1,291 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::{
+    cipher::{CipherKey, CipherKind},
+    entry::{timestamp, Entry},
+    error::KrabError,
+    kdf::KdfParams,
+};
+
+/// The file extension of a backup
+pub const BACKUP_EXTENSION: &str = "krab";
+
+const BACKUP_MAGIC: [u8; 4] = [0x89, b'K', b'R', b'X'];
+const BACKUP_VERSION: u8 = 1;
+const SALT_LENGTH: usize = 16;
+
+/// The encrypted blob of an attachment, copied as it is into a backup
+/// The key of the blob stays in its entry, so it is only readable with the backup
+///
+/// # Fields
+/// * `id` - The id of the attachment
+/// * `blob` - The encrypted blob, base64 encoded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttachmentBlob {
+    pub id: String,
+    blob: String,
+}
+
+impl AttachmentBlob {
+    /// Creates a new `AttachmentBlob`
+    ///
+    /// # Arguments
+    /// * `id` - The id of the attachment
+    /// * `blob` - The encrypted blob
+    ///
+    /// # Returns
+    /// A new `AttachmentBlob`
+    pub(crate) fn new(id: &str, blob: &[u8]) -> Self {
+        AttachmentBlob {
+            id: id.to_string(),
+            blob: STANDARD.encode(blob),
+        }
+    }
+
+    /// Decodes the blob
+    ///
+    /// # Returns
+    /// The encrypted blob or None if it is not valid base64
+    pub(crate) fn blob(&self) -> Option<Vec<u8>> {
+        STANDARD.decode(&self.blob).ok()
+    }
+}
+
+/// A backup of a vault
+/// It is sealed with an export passphrase of its own, so it can be kept
+/// apart from the vault and restored into any vault or as a new user
+///
+/// # Fields
+/// * `created` - The time the backup was created
+/// * `username` - The username of the exported vault
+/// * `app_version` - The version of krab that created the backup
+/// * `entries` - The entries
+/// * `attachments` - The blobs of the attachments of the entries
+///
+/// # Methods
+/// * `new` - Creates a new backup
+/// * `seal` - Encrypts the backup with an export passphrase
+/// * `open` - Decrypts a backup with its export passphrase
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Backup {
+    pub created: i64,
+    pub username: String,
+    pub app_version: String,
+    pub entries: Vec<Entry>,
+    #[serde(default)]
+    pub attachments: Vec<AttachmentBlob>,
+}
+
+impl Backup {
+    /// Creates a new backup stamped with the current time
+    ///
+    /// # Arguments
+    /// * `username` - The username of the exported vault
+    /// * `entries` - The entries
+    /// * `attachments` - The blobs of the attachments of the entries
+    ///
+    /// # Returns
+    /// A new `Backup`
+    pub fn new(username: &str, entries: Vec<Entry>, attachments: Vec<AttachmentBlob>) -> Self {
+        Backup {
+            created: timestamp(),
+            username: username.to_string(),
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            entries,
+            attachments,
+        }
+    }
+
+    /// Encrypts the backup with a key derived from an export passphrase
+    /// The bundle is the magic, the format version, the cipher, the key
+    /// derivation parameters, the salt and the nonce followed by the
+    /// ciphertext, the header is authenticated along with it
+    ///
+    /// # Arguments
+    /// * `passphrase` - The export passphrase
+    /// * `kdf` - The key derivation parameters
+    /// * `cipher` - The cipher
+    ///
+    /// # Returns
+    /// The bundle or an error
+    pub fn seal(
+        &self,
+        passphrase: &str,
+        kdf: &KdfParams,
+        cipher: CipherKind,
+    ) -> Result<Vec<u8>, KrabError> {
+        if !kdf.is_supported() {
+            return Err(KrabError::UnsupportedKdf);
+        }
+        let mut salt = vec![0u8; SALT_LENGTH];
+        rand::thread_rng().fill_bytes(&mut salt);
+
+        let mut bundle = BACKUP_MAGIC.to_vec();
+        bundle.push(BACKUP_VERSION);
+        bundle.push(cipher.id());
+        kdf.write(&mut bundle);
+        bundle.push(salt.len() as u8);
+        bundle.extend_from_slice(&salt);
+
+        let key = Self::derive_key(passphrase, kdf, cipher, &salt)?;
+        let plaintext =
+            Zeroizing::new(serde_json::to_vec(self).map_err(|_| KrabError::Encryption)?);
+        let nonce = cipher.generate_nonce();
+        let ciphertext = key
+            .encrypt(&nonce, &plaintext, &bundle)
+            .map_err(|_| KrabError::Encryption)?;
+        bundle.extend_from_slice(&nonce);
+        bundle.extend_from_slice(&ciphertext);
+        Ok(bundle)
+    }
+
+    /// Decrypts a bundle created by `seal`
+    ///
+    /// # Arguments
+    /// * `bundle` - The bundle
+    /// * `passphrase` - The export passphrase
+    ///
+    /// # Returns
+    /// The backup, `KrabError::InvalidBackup` if the bundle is not a backup
+    /// or `KrabError::WrongPassphrase` if it cannot be decrypted
+    pub fn open(bundle: &[u8], passphrase: &str) -> Result<Self, KrabError> {
+        let (cipher, kdf, salt, header_length) =
+            Self::read_header(bundle).ok_or(KrabError::InvalidBackup)?;
+        let (header, rest) = bundle.split_at(header_length);
+        let (nonce, ciphertext) = rest.split_at(cipher.nonce_length());
+
+        let key = Self::derive_key(passphrase, &kdf, cipher, salt)?;
+        let plaintext = Zeroizing::new(
+            key.decrypt(nonce, ciphertext, header)
+                .map_err(|_| KrabError::WrongPassphrase)?,
+        );
+        serde_json::from_slice(&plaintext).map_err(|_| KrabError::InvalidBackup)
+    }
+
+    /// Reads the header of a bundle
+    ///
+    /// # Arguments
+    /// * `bundle` - The bundle
+    ///
+    /// # Returns
+    /// The cipher, the key derivation parameters, the salt and the length of
+    /// the header without the nonce, None if the header is invalid
+    fn read_header(bundle: &[u8]) -> Option<(CipherKind, KdfParams, &[u8], usize)> {
+        if !bundle.starts_with(&BACKUP_MAGIC) {
+            return None;
+        }
+        let mut offset = BACKUP_MAGIC.len();
+        if *bundle.get(offset)? != BACKUP_VERSION {
+            return None;
+        }
+        let cipher = CipherKind::from_id(*bundle.get(offset + 1)?)?;
+        offset += 2;
+
+        let (kdf, kdf_len) = KdfParams::read(bundle.get(offset..)?)?;
+        offset += kdf_len;
+
+        let salt_len = *bundle.get(offset)? as usize;
+        let salt = bundle.get(offset + 1..offset + 1 + salt_len)?;
+        offset += 1 + salt_len;
+
+        bundle.get(offset..offset + cipher.nonce_length())?;
+        Some((cipher, kdf, salt, offset))
+    }
+
+    /// Derives the key of a bundle from the export passphrase
+    ///
+    /// # Arguments
+    /// * `passphrase` - The export passphrase
+    /// * `kdf` - The key derivation parameters
+    /// * `cipher` - The cipher
+    /// * `salt` - The salt
+    ///
+    /// # Returns
+    /// The key or an error
+    fn derive_key(
+        passphrase: &str,
+        kdf: &KdfParams,
+        cipher: CipherKind,
+        salt: &[u8],
+    ) -> Result<CipherKey, KrabError> {
+        let mut key = Zeroizing::new(vec![0u8; cipher.key_length()]);
+        kdf.derive(passphrase.as_bytes(), salt, &mut key);
+        CipherKey::from_slice(cipher, &key).ok_or(KrabError::Encryption)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fast_kdf() -> KdfParams {
+        KdfParams::Scrypt {
+            log_n: 10,
+            r: 8,
+            p: 1,
+        }
+    }
+
+    fn backup() -> Backup {
+        let mut entry = Entry::new("example.com", "password");
+        entry.id = Entry::generate_id();
+        entry.username = "krab".to_string();
+        Backup::new(
+            "krab",
+            vec![entry],
+            vec![AttachmentBlob::new("attachment", b"blob")],
+        )
+    }
+
+    #[test]
+    fn test_backup_roundtrip() {
+        let backup = backup();
+        for cipher in [
+            CipherKind::Aes128GcmSiv,
+            CipherKind::Aes256GcmSiv,
+            CipherKind::XChaCha20Poly1305,
+        ] {
+            let bundle = backup.seal("passphrase", &fast_kdf(), cipher).unwrap();
+
+            assert!(bundle.starts_with(&BACKUP_MAGIC));
+            assert!(!bundle
+                .windows(b"password".len())
+                .any(|window| window == b"password"));
+            assert_eq!(Backup::open(&bundle, "passphrase").unwrap(), backup);
+        }
+        assert_eq!(backup.attachments[0].blob().unwrap(), b"blob".to_vec());
+    }
+
+    #[test]
+    fn test_backup_rejects_wrong_passphrase_and_tampering() {
+        let bundle = backup()
+            .seal("passphrase", &fast_kdf(), CipherKind::default())
+            .unwrap();
+        let mut tampered = bundle.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        let mut tampered_header = bundle.clone();
+        tampered_header[5] = CipherKind::Aes128GcmSiv.id();
+
+        assert_eq!(
+            Backup::open(&bundle, "wrong").unwrap_err(),
+            KrabError::WrongPassphrase
+        );
+        assert_eq!(
+            Backup::open(&tampered, "passphrase").unwrap_err(),
+            KrabError::WrongPassphrase
+        );
+        assert!(Backup::open(&tampered_header, "passphrase").is_err());
+        assert_eq!(
+            Backup::open(b"entry,password", "passphrase").unwrap_err(),
+            KrabError::InvalidBackup
+        );
+        assert_eq!(
+            Backup::open(&bundle[..10], "passphrase").unwrap_err(),
+            KrabError::InvalidBackup
+        );
+    }
+}
@@ -0,0 +1,296 @@
+use aes_gcm_siv::{
+    aead::{self, Aead, KeyInit, OsRng, Payload},
+    Aes128GcmSiv, Aes256GcmSiv,
+};
+use chacha20poly1305::XChaCha20Poly1305;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use zeroize::Zeroizing;
+
+const CIPHER_AES_128_GCM_SIV: u8 = 0;
+const CIPHER_AES_256_GCM_SIV: u8 = 1;
+const CIPHER_XCHACHA20_POLY1305: u8 = 2;
+
+/// The AEAD cipher used to encrypt the records of a vault
+/// Its identifier is stored in the vault header
+///
+/// # Variants
+/// * `Aes128GcmSiv` - AES-128-GCM-SIV, used by vaults of older versions
+/// * `Aes256GcmSiv` - AES-256-GCM-SIV
+/// * `XChaCha20Poly1305` - XChaCha20-Poly1305
+///
+/// # Methods
+/// * `id` - Returns the identifier stored in the vault header
+/// * `from_id` - Returns the cipher of an identifier
+/// * `key_length` - Returns the length of a key in bytes
+/// * `nonce_length` - Returns the length of a nonce in bytes
+///
+/// # Implements
+/// * `Default` - AES-256-GCM-SIV
+/// * `Display`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CipherKind {
+    Aes128GcmSiv,
+    #[default]
+    Aes256GcmSiv,
+    XChaCha20Poly1305,
+}
+
+/// A key of a cipher
+/// The key bytes are zeroed on drop and hidden from `Debug`
+///
+/// # Fields
+/// * `kind` - The cipher the key belongs to
+/// * `key` - The key bytes, `kind.key_length()` long
+#[derive(Clone, PartialEq)]
+pub(crate) struct CipherKey {
+    kind: CipherKind,
+    key: Zeroizing<Vec<u8>>,
+}
+
+impl fmt::Debug for CipherKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CipherKey")
+            .field("kind", &self.kind)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for CipherKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CipherKind::Aes128GcmSiv => "AES-128-GCM-SIV",
+            CipherKind::Aes256GcmSiv => "AES-256-GCM-SIV",
+            CipherKind::XChaCha20Poly1305 => "XChaCha20-Poly1305",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl CipherKind {
+    /// Returns the identifier stored in the vault header
+    ///
+    /// # Returns
+    /// The identifier
+    pub fn id(&self) -> u8 {
+        match self {
+            CipherKind::Aes128GcmSiv => CIPHER_AES_128_GCM_SIV,
+            CipherKind::Aes256GcmSiv => CIPHER_AES_256_GCM_SIV,
+            CipherKind::XChaCha20Poly1305 => CIPHER_XCHACHA20_POLY1305,
+        }
+    }
+
+    /// Returns the cipher of an identifier
+    ///
+    /// # Arguments
+    /// * `id` - The identifier
+    ///
+    /// # Returns
+    /// The cipher or None if the identifier is unknown
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            CIPHER_AES_128_GCM_SIV => Some(CipherKind::Aes128GcmSiv),
+            CIPHER_AES_256_GCM_SIV => Some(CipherKind::Aes256GcmSiv),
+            CIPHER_XCHACHA20_POLY1305 => Some(CipherKind::XChaCha20Poly1305),
+            _ => None,
+        }
+    }
+
+    /// Returns the length of a key in bytes
+    ///
+    /// # Returns
+    /// The key length
+    pub fn key_length(&self) -> usize {
+        match self {
+            CipherKind::Aes128GcmSiv => 16,
+            CipherKind::Aes256GcmSiv | CipherKind::XChaCha20Poly1305 => 32,
+        }
+    }
+
+    /// Returns the length of a nonce in bytes
+    ///
+    /// # Returns
+    /// The nonce length
+    pub fn nonce_length(&self) -> usize {
+        match self {
+            CipherKind::Aes128GcmSiv | CipherKind::Aes256GcmSiv => 12,
+            CipherKind::XChaCha20Poly1305 => 24,
+        }
+    }
+
+    /// Generates a random nonce
+    ///
+    /// # Returns
+    /// The nonce
+    pub(crate) fn generate_nonce(&self) -> Vec<u8> {
+        let mut nonce = vec![0u8; self.nonce_length()];
+        OsRng.fill_bytes(&mut nonce);
+        nonce
+    }
+}
+
+impl CipherKey {
+    /// Generates a random key
+    ///
+    /// # Arguments
+    /// * `kind` - The cipher
+    ///
+    /// # Returns
+    /// A new `CipherKey`
+    pub(crate) fn generate(kind: CipherKind) -> Self {
+        let mut key = Zeroizing::new(vec![0u8; kind.key_length()]);
+        OsRng.fill_bytes(&mut key);
+        CipherKey { kind, key }
+    }
+
+    /// Creates a key from bytes
+    ///
+    /// # Arguments
+    /// * `kind` - The cipher
+    /// * `key` - The key bytes
+    ///
+    /// # Returns
+    /// A new `CipherKey` or None if the length does not fit the cipher
+    pub(crate) fn from_slice(kind: CipherKind, key: &[u8]) -> Option<Self> {
+        if key.len() != kind.key_length() {
+            return None;
+        }
+        Some(CipherKey {
+            kind,
+            key: Zeroizing::new(key.to_vec()),
+        })
+    }
+
+    /// Returns the cipher of the key
+    ///
+    /// # Returns
+    /// The cipher
+    pub(crate) fn kind(&self) -> CipherKind {
+        self.kind
+    }
+
+    /// Returns the key bytes
+    ///
+    /// # Returns
+    /// The key bytes
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        &self.key
+    }
+
+    /// Encrypts a message
+    ///
+    /// # Arguments
+    /// * `nonce` - The nonce, `nonce_length()` long
+    /// * `msg` - The message
+    /// * `aad` - The associated data
+    ///
+    /// # Returns
+    /// The ciphertext or an error
+    pub(crate) fn encrypt(
+        &self,
+        nonce: &[u8],
+        msg: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, aead::Error> {
+        if nonce.len() != self.kind.nonce_length() {
+            return Err(aead::Error);
+        }
+        let payload = Payload { msg, aad };
+        match self.kind {
+            CipherKind::Aes128GcmSiv => Aes128GcmSiv::new_from_slice(&self.key)
+                .map_err(|_| aead::Error)?
+                .encrypt(nonce.into(), payload),
+            CipherKind::Aes256GcmSiv => Aes256GcmSiv::new_from_slice(&self.key)
+                .map_err(|_| aead::Error)?
+                .encrypt(nonce.into(), payload),
+            CipherKind::XChaCha20Poly1305 => XChaCha20Poly1305::new_from_slice(&self.key)
+                .map_err(|_| aead::Error)?
+                .encrypt(nonce.into(), payload),
+        }
+    }
+
+    /// Decrypts a ciphertext
+    ///
+    /// # Arguments
+    /// * `nonce` - The nonce, `nonce_length()` long
+    /// * `msg` - The ciphertext
+    /// * `aad` - The associated data
+    ///
+    /// # Returns
+    /// The message or an error if the key is wrong or the data was tampered with
+    pub(crate) fn decrypt(
+        &self,
+        nonce: &[u8],
+        msg: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, aead::Error> {
+        if nonce.len() != self.kind.nonce_length() {
+            return Err(aead::Error);
+        }
+        let payload = Payload { msg, aad };
+        match self.kind {
+            CipherKind::Aes128GcmSiv => Aes128GcmSiv::new_from_slice(&self.key)
+                .map_err(|_| aead::Error)?
+                .decrypt(nonce.into(), payload),
+            CipherKind::Aes256GcmSiv => Aes256GcmSiv::new_from_slice(&self.key)
+                .map_err(|_| aead::Error)?
+                .decrypt(nonce.into(), payload),
+            CipherKind::XChaCha20Poly1305 => XChaCha20Poly1305::new_from_slice(&self.key)
+                .map_err(|_| aead::Error)?
+                .decrypt(nonce.into(), payload),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CIPHERS: [CipherKind; 3] = [
+        CipherKind::Aes128GcmSiv,
+        CipherKind::Aes256GcmSiv,
+        CipherKind::XChaCha20Poly1305,
+    ];
+
+    #[test]
+    fn test_cipher_roundtrip() {
+        for kind in CIPHERS {
+            let key = CipherKey::generate(kind);
+            let nonce = kind.generate_nonce();
+
+            let ciphertext = key.encrypt(&nonce, b"secret", b"aad").unwrap();
+            let plaintext = key.decrypt(&nonce, &ciphertext, b"aad").unwrap();
+
+            assert_eq!(key.as_slice().len(), kind.key_length());
+            assert_eq!(plaintext, b"secret");
+            assert!(key.decrypt(&nonce, &ciphertext, b"other").is_err());
+        }
+    }
+
+    #[test]
+    fn test_cipher_id_roundtrip() {
+        for kind in CIPHERS {
+            assert_eq!(CipherKind::from_id(kind.id()), Some(kind));
+        }
+        assert_eq!(CipherKind::from_id(0xFF), None);
+    }
+
+    #[test]
+    fn test_cipher_rejects_wrong_lengths() {
+        let key = CipherKey::generate(CipherKind::XChaCha20Poly1305);
+
+        assert!(key.encrypt(&[0u8; 12], b"secret", b"").is_err());
+        assert!(CipherKey::from_slice(CipherKind::Aes256GcmSiv, &[0u8; 16]).is_none());
+    }
+
+    #[test]
+    fn test_cipher_key_debug_is_redacted() {
+        let key = CipherKey::from_slice(CipherKind::Aes128GcmSiv, &[0xAB; 16]).unwrap();
+
+        let debug = format!("{:?}", key);
+
+        assert!(!debug.contains("171"));
+        assert!(debug.contains("Aes128GcmSiv"));
+    }
+}
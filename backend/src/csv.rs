@@ -0,0 +1,180 @@
+use std::fmt;
+
+/// Error while reading CSV text
+/// It never contains any field of the text, only where the error is
+///
+/// # Variants
+/// * `UnterminatedQuote` - A quoted field is not closed, with the line it starts on
+/// * `UnexpectedQuote` - A quote inside an unquoted field or text after a closing quote, with its line
+///
+/// # Implements
+/// * `Display`
+/// * `Error`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvError {
+    UnterminatedQuote(usize),
+    UnexpectedQuote(usize),
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::UnterminatedQuote(line) => {
+                write!(f, "The quoted field on line {} is not closed", line)
+            }
+            CsvError::UnexpectedQuote(line) => write!(f, "Unexpected quote on line {}", line),
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+/// Parses CSV text as described in RFC 4180
+/// Fields are separated by commas and records by CRLF or LF, a field in double quotes
+/// may contain commas, line breaks and quotes written as two quotes.
+/// A byte order mark and a line break after the last record are ignored
+///
+/// # Arguments
+/// * `text` - The CSV text
+///
+/// # Returns
+/// The records with their fields or an error if a quote is misplaced
+pub fn parse(text: &str) -> Result<Vec<Vec<String>>, CsvError> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    // an empty quoted field is still a field, even at the end of the text
+    let mut quoted = false;
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if field.is_empty() && !quoted => {
+                let start = line;
+                quoted = true;
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.push('"');
+                        }
+                        Some('"') => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            field.push(c);
+                        }
+                        None => return Err(CsvError::UnterminatedQuote(start)),
+                    }
+                }
+                match chars.peek() {
+                    None | Some(',') | Some('\n') | Some('\r') => {}
+                    Some(_) => return Err(CsvError::UnexpectedQuote(line)),
+                }
+            }
+            '"' => return Err(CsvError::UnexpectedQuote(line)),
+            ',' => {
+                record.push(std::mem::take(&mut field));
+                quoted = false;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+                quoted = false;
+                line += 1;
+            }
+            c => field.push(c),
+        }
+    }
+
+    if quoted || !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Appends a record to CSV text as described in RFC 4180
+/// A field is quoted if it contains a comma, a quote or a line break,
+/// quotes inside it are doubled, and the record ends with CRLF
+///
+/// # Arguments
+/// * `text` - The CSV text
+/// * `fields` - The fields of the record
+pub fn write_record(text: &mut String, fields: &[&str]) {
+    for (index, field) in fields.iter().enumerate() {
+        if index > 0 {
+            text.push(',');
+        }
+        if field.contains([',', '"', '\r', '\n']) {
+            text.push('"');
+            for c in field.chars() {
+                if c == '"' {
+                    text.push('"');
+                }
+                text.push(c);
+            }
+            text.push('"');
+        } else {
+            text.push_str(field);
+        }
+    }
+    text.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_parse() {
+        let text = "\u{feff}name,password,notes\r\n\
+                    example.com,\"pa,ss\"\"word\",\"line 1\r\nline 2\"\r\n\
+                    ,\"\",\n\
+                    last,\"\"\"\",end";
+        let records = parse(text).unwrap();
+
+        assert_eq!(
+            records,
+            vec![
+                vec!["name", "password", "notes"],
+                vec!["example.com", "pa,ss\"word", "line 1\r\nline 2"],
+                vec!["", "", ""],
+                vec!["last", "\"", "end"],
+            ]
+        );
+        assert_eq!(parse("a,b\n").unwrap(), vec![vec!["a", "b"]]);
+        assert_eq!(parse("\"\"").unwrap(), vec![vec![""]]);
+        assert!(parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_csv_write_record_roundtrip() {
+        let records = vec![
+            vec!["domain", "password", "notes"],
+            vec!["example.com", "pa,ss\"word", "line 1\nline 2\r\n"],
+            vec!["", "\"", " spaced "],
+        ];
+        let mut text = String::new();
+        for record in records.iter() {
+            write_record(&mut text, record);
+        }
+
+        assert!(text.starts_with("domain,password,notes\r\nexample.com,\"pa,ss\"\"word\","));
+        assert_eq!(parse(&text).unwrap(), records);
+    }
+
+    #[test]
+    fn test_csv_parse_errors() {
+        assert_eq!(
+            parse("a,b\n\"open,field\nnext"),
+            Err(CsvError::UnterminatedQuote(2))
+        );
+        assert_eq!(parse("a,b\nc\"d,e"), Err(CsvError::UnexpectedQuote(2)));
+        assert_eq!(parse("\"a\nb\"c,d"), Err(CsvError::UnexpectedQuote(2)));
+    }
+}
@@ -0,0 +1,420 @@
+use aes_gcm_siv::aead::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    attachment::Attachment,
+    kind::{EntryError, EntryKind},
+    secret::SecretString,
+};
+
+/// The length of an entry id in bytes, before hex encoding
+pub(crate) const ID_LENGTH: usize = 16;
+/// Number of previous passwords kept in the history of an entry
+pub const MAX_PASSWORD_HISTORY: usize = 10;
+
+/// An entry of a vault
+/// It is serialized with serde and encrypted as a whole, so every field is secret on disk
+///
+/// # Fields
+/// * `id` - The stable id of the entry, assigned when it is added to a vault
+/// * `domain` - The domain, shown to the user but not necessarily unique
+/// * `username` - The username or email used to log in
+/// * `password` - The password
+/// * `totp` - The TOTP secret, in base32 or as an `otpauth://totp/` URI, empty if none
+/// * `url` - The login URL
+/// * `notes` - Free-form notes
+/// * `fields` - Additional fields, like security questions or PINs
+/// * `created` - When the entry was added, as a Unix timestamp, `0` if unknown
+/// * `modified` - When the entry was last changed, as a Unix timestamp, `0` if unknown
+/// * `last_used` - When the password or username was last copied, as a Unix timestamp, `0` if never
+/// * `history` - The previous passwords, the most recent one first
+/// * `tags` - Tags for organizing the entries
+/// * `folder` - The folder of the entry, with subfolders separated by `/`, empty for the root
+/// * `attachments` - The attached files, their content is stored separately
+/// * `kind` - The kind of the entry, with the fields only that kind has
+///
+/// # Methods
+/// * `new` - Creates a new `Entry` with a domain and a password
+/// * `generate_id` - Generates a random entry id
+/// * `label` - Returns the text shown for the entry
+/// * `revise` - Returns an edited version of the entry with updated metadata
+/// * `has_tag` - Returns whether the entry has a tag
+/// * `in_folder` - Returns whether the entry is in a folder or one of its subfolders
+/// * `normalize_folder` - Removes empty parts and surrounding whitespace from a folder path
+/// * `validate` - Checks that the fields the kind of the entry requires are set and valid
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    #[serde(default)]
+    pub id: String,
+    pub domain: String,
+    #[serde(default)]
+    pub username: String,
+    pub password: SecretString,
+    #[serde(default)]
+    pub totp: SecretString,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub notes: SecretString,
+    #[serde(default)]
+    pub fields: Vec<CustomField>,
+    #[serde(default)]
+    pub created: i64,
+    #[serde(default)]
+    pub modified: i64,
+    #[serde(default)]
+    pub last_used: i64,
+    #[serde(default)]
+    pub history: Vec<PreviousPassword>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub folder: String,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    #[serde(default)]
+    pub kind: EntryKind,
+}
+
+/// An additional field of an entry
+///
+/// # Fields
+/// * `name` - The name of the field
+/// * `value` - The value of the field
+/// * `hidden` - Whether the value is hidden like a password when shown
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomField {
+    pub name: String,
+    pub value: SecretString,
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+/// A previous password of an entry
+///
+/// # Fields
+/// * `password` - The password
+/// * `replaced` - When it was replaced, as a Unix timestamp
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreviousPassword {
+    pub password: SecretString,
+    #[serde(default)]
+    pub replaced: i64,
+}
+
+/// Returns the current time as a Unix timestamp
+///
+/// # Returns
+/// The number of seconds since the Unix epoch
+pub fn timestamp() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+impl Entry {
+    /// Creates a new `Entry` with a domain and a password
+    ///
+    /// # Arguments
+    /// * `domain` - The domain
+    /// * `password` - The password
+    ///
+    /// # Returns
+    /// A new `Entry`
+    pub fn new(domain: &str, password: &str) -> Self {
+        Entry {
+            domain: domain.to_string(),
+            password: SecretString::from(password),
+            ..Default::default()
+        }
+    }
+
+    /// Generates a random entry id
+    ///
+    /// # Returns
+    /// The id, hex encoded
+    pub fn generate_id() -> String {
+        let mut id = [0u8; ID_LENGTH];
+        OsRng.fill_bytes(&mut id);
+        id.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Returns the text shown for the entry
+    /// Entries may share a domain, so the username is added when there is one
+    ///
+    /// # Returns
+    /// The domain, followed by the username in parentheses if it is set
+    pub fn label(&self) -> String {
+        if self.username.is_empty() {
+            self.domain.clone()
+        } else {
+            format!("{} ({})", self.domain, self.username)
+        }
+    }
+
+    /// Returns an edited version of the entry with updated metadata
+    /// The id, creation time, history and attachments are taken from the stored entry,
+    /// so an outdated copy cannot overwrite them. A changed password is
+    /// moved to the history, which keeps at most `MAX_PASSWORD_HISTORY` passwords
+    ///
+    /// # Arguments
+    /// * `edited` - The edited entry
+    /// * `now` - The current time as a Unix timestamp
+    ///
+    /// # Returns
+    /// The entry to store
+    pub fn revise(&self, edited: &Entry, now: i64) -> Entry {
+        let mut revised = edited.clone();
+        revised.id = self.id.clone();
+        revised.created = self.created;
+        revised.modified = now;
+        revised.last_used = self.last_used;
+        revised.history = self.history.clone();
+        revised.attachments = self.attachments.clone();
+        if revised.password != self.password {
+            revised.history.insert(
+                0,
+                PreviousPassword {
+                    password: self.password.clone(),
+                    replaced: now,
+                },
+            );
+            revised.history.truncate(MAX_PASSWORD_HISTORY);
+        }
+        revised
+    }
+
+    /// Returns whether the entry has a tag, ignoring case
+    ///
+    /// # Arguments
+    /// * `tag` - The tag
+    ///
+    /// # Returns
+    /// `true` if one of the tags of the entry matches
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    /// Returns whether the entry is in a folder or one of its subfolders
+    ///
+    /// # Arguments
+    /// * `folder` - The folder path, the root folder contains every entry
+    ///
+    /// # Returns
+    /// `true` if the entry is in the folder
+    pub fn in_folder(&self, folder: &str) -> bool {
+        let folder = Entry::normalize_folder(folder);
+        let own = Entry::normalize_folder(&self.folder);
+        folder.is_empty()
+            || own == folder
+            || own
+                .strip_prefix(&folder)
+                .is_some_and(|rest| rest.starts_with('/'))
+    }
+
+    /// Removes empty parts and surrounding whitespace from a folder path,
+    /// so `" /Work//Clients/ "` becomes `"Work/Clients"`
+    ///
+    /// # Arguments
+    /// * `folder` - The folder path
+    ///
+    /// # Returns
+    /// The normalized folder path
+    pub fn normalize_folder(folder: &str) -> String {
+        folder
+            .split('/')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Checks that the fields the kind of the entry requires are set and valid
+    ///
+    /// # Returns
+    /// An error describing the first missing or invalid field
+    pub fn validate(&self) -> Result<(), EntryError> {
+        match &self.kind {
+            EntryKind::Login if self.domain.is_empty() || self.password.is_empty() => {
+                Err(EntryError::MissingField("Domain and password"))
+            }
+            EntryKind::Login => Ok(()),
+            _ if self.domain.trim().is_empty() => Err(EntryError::MissingField("Title")),
+            EntryKind::Note if self.notes.is_empty() => Err(EntryError::MissingField("Note")),
+            EntryKind::Note => Ok(()),
+            EntryKind::Card(card) => card.validate(),
+            EntryKind::SshKey(key) if key.private_key.is_empty() => {
+                Err(EntryError::MissingField("Private key"))
+            }
+            EntryKind::SshKey(_) => Ok(()),
+            EntryKind::Identity(identity) if identity.full_name.is_empty() => {
+                Err(EntryError::MissingField("Full name"))
+            }
+            EntryKind::Identity(_) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cipher::CipherKind,
+        kind::{Card, Identity, SshKey},
+    };
+
+    #[test]
+    fn test_entry_serde_roundtrip() {
+        let entry = Entry {
+            id: Entry::generate_id(),
+            domain: "example.com".to_string(),
+            username: "krab@example.com".to_string(),
+            password: SecretString::from("password"),
+            totp: SecretString::from("otpauth://totp/Example?secret=GEZDGNBVGY3TQOJQ"),
+            url: "https://example.com/login".to_string(),
+            notes: SecretString::from("first line\nsecond line"),
+            fields: vec![CustomField {
+                name: "PIN".to_string(),
+                value: SecretString::from("1234"),
+                hidden: true,
+            }],
+            created: 1700000000,
+            modified: 1700000100,
+            last_used: 1700000200,
+            history: vec![PreviousPassword {
+                password: SecretString::from("old password"),
+                replaced: 1700000100,
+            }],
+            tags: vec!["work".to_string(), "2fa".to_string()],
+            folder: "Work/Clients".to_string(),
+            attachments: vec![
+                Attachment::seal("codes.txt", b"recovery codes", CipherKind::default())
+                    .unwrap()
+                    .0,
+            ],
+            kind: EntryKind::Login,
+        };
+
+        let json = serde_json::to_string(&entry).unwrap();
+        let read: Entry = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(read, entry);
+    }
+
+    #[test]
+    fn test_entry_deserialize_defaults() {
+        let read: Entry =
+            serde_json::from_str(r#"{"domain":"example.com","password":"password"}"#).unwrap();
+
+        assert_eq!(read, Entry::new("example.com", "password"));
+        assert!(serde_json::from_str::<Entry>(r#"{"domain":"example.com"}"#).is_err());
+    }
+
+    #[test]
+    fn test_entry_kinds() {
+        let mut card = Entry::new("Visa", "");
+        card.kind = EntryKind::Card(Card {
+            holder: "Krab".to_string(),
+            number: SecretString::from("4111111111111111"),
+            expiry: "12/27".to_string(),
+            cvv: SecretString::from("123"),
+        });
+        let mut note = Entry::new("Wi-Fi", "");
+        note.kind = EntryKind::Note;
+        let mut key = Entry::new("GitHub deploy key", "");
+        key.kind = EntryKind::SshKey(SshKey::default());
+        let mut identity = Entry::new("Personal", "");
+        identity.kind = EntryKind::Identity(Identity {
+            full_name: "Krab Crab".to_string(),
+            ..Default::default()
+        });
+
+        let json = serde_json::to_string(&card).unwrap();
+        let read: Entry = serde_json::from_str(&json).unwrap();
+
+        assert!(json.contains(r#""kind":{"type":"card","holder":"Krab""#));
+        assert_eq!(read, card);
+        assert_eq!(card.validate(), Ok(()));
+        assert_eq!(
+            Entry::new("example.com", "").validate(),
+            Err(EntryError::MissingField("Domain and password"))
+        );
+        assert_eq!(note.validate(), Err(EntryError::MissingField("Note")));
+        note.notes = SecretString::from("password: hunter2");
+        assert_eq!(note.validate(), Ok(()));
+        assert_eq!(key.validate(), Err(EntryError::MissingField("Private key")));
+        assert_eq!(identity.validate(), Ok(()));
+        identity.domain = " ".to_string();
+        assert_eq!(identity.validate(), Err(EntryError::MissingField("Title")));
+    }
+
+    #[test]
+    fn test_entry_generate_id() {
+        let id = Entry::generate_id();
+
+        assert_eq!(id.len(), 2 * ID_LENGTH);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(id, Entry::generate_id());
+    }
+
+    #[test]
+    fn test_entry_label() {
+        let mut entry = Entry::new("github.com", "password");
+        assert_eq!(entry.label(), "github.com");
+
+        entry.username = "work@example.com".to_string();
+        assert_eq!(entry.label(), "github.com (work@example.com)");
+    }
+
+    #[test]
+    fn test_entry_revise() {
+        let mut stored = Entry::new("example.com", "password");
+        stored.id = Entry::generate_id();
+        stored.created = 100;
+        stored.last_used = 150;
+
+        let mut edited = stored.clone();
+        edited.username = "krab".to_string();
+        let revised = stored.revise(&edited, 200);
+        assert_eq!(revised.username, "krab");
+        assert_eq!(revised.created, 100);
+        assert_eq!(revised.modified, 200);
+        assert_eq!(revised.last_used, 150);
+        assert!(revised.history.is_empty());
+
+        let mut current = revised;
+        for i in 0..MAX_PASSWORD_HISTORY + 2 {
+            let mut edited = Entry::new("example.com", &format!("password{}", i));
+            edited.history = vec![];
+            edited.created = 0;
+            current = current.revise(&edited, 300 + i as i64);
+        }
+        assert_eq!(current.id, stored.id);
+        assert_eq!(current.created, 100);
+        assert_eq!(current.history.len(), MAX_PASSWORD_HISTORY);
+        assert_eq!(
+            current.history[0].password.expose_secret(),
+            format!("password{}", MAX_PASSWORD_HISTORY)
+        );
+        assert_eq!(
+            current.history[0].replaced,
+            300 + MAX_PASSWORD_HISTORY as i64 + 1
+        );
+    }
+
+    #[test]
+    fn test_entry_tags_and_folder() {
+        let mut entry = Entry::new("example.com", "password");
+        entry.tags = vec!["Work".to_string()];
+        entry.folder = "Work/Clients".to_string();
+
+        assert!(entry.has_tag("work"));
+        assert!(!entry.has_tag("personal"));
+        assert!(entry.in_folder(""));
+        assert!(entry.in_folder("Work"));
+        assert!(entry.in_folder("/Work/Clients/"));
+        assert!(!entry.in_folder("Work/Client"));
+        assert!(!entry.in_folder("Personal"));
+        assert_eq!(Entry::normalize_folder(" /Work//Clients/ "), "Work/Clients");
+        assert_eq!(Entry::normalize_folder("/"), "");
+    }
+}
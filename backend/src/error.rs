@@ -0,0 +1,134 @@
+use std::{fmt, io};
+
+use crate::attachment::{MAX_ATTACHMENTS, MAX_ATTACHMENT_SIZE};
+
+/// Error returned by the operations on users and vaults
+///
+/// # Variants
+/// * `WrongCredentials` - The master password does not unlock the vault
+/// * `Corrupted` - The vault file is damaged, with the byte offset of the damaged part and the reason
+/// * `Io` - The vault file could not be read or written, with the kind of the I/O error
+/// * `UserNotFound` - No vault exists for the username
+/// * `UserAlreadyExists` - A vault already exists for the username
+/// * `RecordNotFound` - No record has the given id
+/// * `RecordAlreadyExists` - A record with the same id already exists
+/// * `Locked` - Another process holds the lock of the vault file
+/// * `Conflict` - Another process changed the vault file since it was loaded
+/// * `Encryption` - Data could not be encrypted
+/// * `UnsupportedKdf` - The key derivation parameters are outside of the supported range
+/// * `LastRecord` - The last record of a legacy vault cannot be removed
+/// * `AttachmentNotFound` - The blob of an attachment is missing from the store
+/// * `AttachmentTooLarge` - An attachment exceeds `MAX_ATTACHMENT_SIZE`, with its size in bytes
+/// * `TooManyAttachments` - An entry already has `MAX_ATTACHMENTS` attachments
+/// * `AttachmentCorrupted` - The blob of an attachment is damaged or was tampered with
+/// * `WrongPassphrase` - The export passphrase does not open the backup, or the backup was tampered with
+/// * `InvalidBackup` - The file is not a backup or was created by an unsupported version
+///
+/// # Implements
+/// * `Display`
+/// * `Error`
+/// * `From<io::Error>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KrabError {
+    WrongCredentials,
+    Corrupted { offset: usize, reason: String },
+    Io(io::ErrorKind),
+    UserNotFound,
+    UserAlreadyExists,
+    RecordNotFound,
+    RecordAlreadyExists,
+    Locked,
+    Conflict,
+    Encryption,
+    UnsupportedKdf,
+    LastRecord,
+    AttachmentNotFound,
+    AttachmentTooLarge(usize),
+    TooManyAttachments,
+    AttachmentCorrupted,
+    WrongPassphrase,
+    InvalidBackup,
+}
+
+impl fmt::Display for KrabError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KrabError::WrongCredentials => write!(f, "Wrong master password"),
+            KrabError::Corrupted { offset, reason } => {
+                write!(f, "The vault is corrupted at byte {}: {}", offset, reason)
+            }
+            KrabError::Io(kind) => write!(f, "Could not access the vault file: {}", kind),
+            KrabError::UserNotFound => write!(f, "User not found"),
+            KrabError::UserAlreadyExists => write!(f, "User already exists"),
+            KrabError::RecordNotFound => write!(f, "Record not found"),
+            KrabError::RecordAlreadyExists => write!(f, "Record already exists"),
+            KrabError::Locked => write!(f, "The vault is locked by another process"),
+            KrabError::Conflict => write!(f, "The vault was changed by another process"),
+            KrabError::Encryption => write!(f, "Could not encrypt data"),
+            KrabError::UnsupportedKdf => write!(f, "Unsupported key derivation parameters"),
+            KrabError::LastRecord => write!(f, "Cannot remove the last record"),
+            KrabError::AttachmentNotFound => write!(f, "Attachment not found"),
+            KrabError::AttachmentTooLarge(size) => write!(
+                f,
+                "The attachment is too large ({} bytes, at most {})",
+                size, MAX_ATTACHMENT_SIZE
+            ),
+            KrabError::TooManyAttachments => write!(
+                f,
+                "An entry can have at most {} attachments",
+                MAX_ATTACHMENTS
+            ),
+            KrabError::AttachmentCorrupted => {
+                write!(f, "The attachment is damaged or was tampered with")
+            }
+            KrabError::WrongPassphrase => write!(f, "Wrong export passphrase"),
+            KrabError::InvalidBackup => write!(f, "Not a valid backup"),
+        }
+    }
+}
+
+impl std::error::Error for KrabError {}
+
+impl From<io::Error> for KrabError {
+    fn from(e: io::Error) -> Self {
+        KrabError::Io(e.kind())
+    }
+}
+
+impl KrabError {
+    /// Creates a `Corrupted` error
+    ///
+    /// # Arguments
+    /// * `offset` - The byte offset of the damaged part in the vault file
+    /// * `reason` - What is wrong with it
+    ///
+    /// # Returns
+    /// A new `KrabError`
+    pub(crate) fn corrupted(offset: usize, reason: impl fmt::Display) -> Self {
+        KrabError::Corrupted {
+            offset,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_display() {
+        let corrupted = KrabError::corrupted(42, "truncated record");
+        let io_error = KrabError::from(io::Error::from(io::ErrorKind::PermissionDenied));
+
+        assert_eq!(
+            corrupted.to_string(),
+            "The vault is corrupted at byte 42: truncated record"
+        );
+        assert_eq!(io_error, KrabError::Io(io::ErrorKind::PermissionDenied));
+        assert_eq!(
+            KrabError::WrongCredentials.to_string(),
+            "Wrong master password"
+        );
+    }
+}
@@ -0,0 +1,653 @@
+use std::{borrow::Cow, fmt, io, path::Path, str};
+
+use zeroize::Zeroizing;
+
+use crate::{csv, entry::Entry, kind::EntryKind, write_private_file};
+
+/// The format of a clear text export
+///
+/// # Variants
+/// * `Csv` - CSV as described in RFC 4180, with a header naming the fields
+/// * `Json` - A JSON array with an object for each entry
+///
+/// # Methods
+/// * `extension` - Returns the file extension of the format
+/// * `next` - Returns the format following this one
+///
+/// # Implements
+/// * `Display`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+/// A field of an entry that can be exported
+/// The names of the text fields are the ones the CSV importer recognizes,
+/// so an export can be imported again
+///
+/// # Variants
+/// * `Title` - The domain of a login or the title of another kind of entry
+/// * `Username` - The username
+/// * `Password` - The password
+/// * `Url` - The login URL
+/// * `Totp` - The TOTP secret
+/// * `Notes` - The notes
+/// * `Fields` - The custom fields, as a JSON array of objects
+/// * `Kind` - The kind of the entry, like `card`
+/// * `Details` - The fields of a card, SSH key or identity, as a JSON object
+/// * `Folder` - The folder
+/// * `Tags` - The tags
+/// * `Created` - When the entry was added, as an RFC 3339 time
+/// * `Modified` - When the entry was last changed, as an RFC 3339 time
+///
+/// # Methods
+/// * `name` - Returns the name of the field in the export
+/// * `label` - Returns the name of the field shown to the user
+/// * `value` - Returns the value of a text field of an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportField {
+    Title,
+    Username,
+    Password,
+    Url,
+    Totp,
+    Notes,
+    Fields,
+    Kind,
+    Details,
+    Folder,
+    Tags,
+    Created,
+    Modified,
+}
+
+/// Every field that can be exported, in the order they are written
+pub const EXPORT_FIELDS: [ExportField; 13] = [
+    ExportField::Title,
+    ExportField::Username,
+    ExportField::Password,
+    ExportField::Url,
+    ExportField::Totp,
+    ExportField::Notes,
+    ExportField::Fields,
+    ExportField::Kind,
+    ExportField::Details,
+    ExportField::Folder,
+    ExportField::Tags,
+    ExportField::Created,
+    ExportField::Modified,
+];
+
+/// What a clear text export contains
+///
+/// # Fields
+/// * `format` - The format of the file
+/// * `fields` - The exported fields, written in the order of `EXPORT_FIELDS`
+///
+/// # Implements
+/// * `Default` - Every field as CSV
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub fields: Vec<ExportField>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            format: ExportFormat::default(),
+            fields: EXPORT_FIELDS.to_vec(),
+        }
+    }
+}
+
+impl ExportFormat {
+    /// Returns the file extension of the format
+    ///
+    /// # Returns
+    /// The extension, without a dot
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+
+    /// Returns the format following this one, to cycle through them
+    ///
+    /// # Returns
+    /// The next format
+    pub fn next(&self) -> ExportFormat {
+        match self {
+            ExportFormat::Csv => ExportFormat::Json,
+            ExportFormat::Json => ExportFormat::Csv,
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportFormat::Csv => write!(f, "CSV"),
+            ExportFormat::Json => write!(f, "JSON"),
+        }
+    }
+}
+
+impl ExportField {
+    /// Returns the name of the field in the export
+    ///
+    /// # Returns
+    /// The name, lowercase
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportField::Title => "title",
+            ExportField::Username => "username",
+            ExportField::Password => "password",
+            ExportField::Url => "url",
+            ExportField::Totp => "totp",
+            ExportField::Notes => "notes",
+            ExportField::Fields => "fields",
+            ExportField::Kind => "kind",
+            ExportField::Details => "details",
+            ExportField::Folder => "folder",
+            ExportField::Tags => "tags",
+            ExportField::Created => "created",
+            ExportField::Modified => "modified",
+        }
+    }
+
+    /// Returns the name of the field shown to the user
+    ///
+    /// # Returns
+    /// The label
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportField::Title => "Title",
+            ExportField::Username => "Username",
+            ExportField::Password => "Password",
+            ExportField::Url => "URL",
+            ExportField::Totp => "TOTP Secret",
+            ExportField::Notes => "Notes",
+            ExportField::Fields => "Custom Fields",
+            ExportField::Kind => "Kind",
+            ExportField::Details => "Card, SSH Key or Identity",
+            ExportField::Folder => "Folder",
+            ExportField::Tags => "Tags",
+            ExportField::Created => "Created",
+            ExportField::Modified => "Modified",
+        }
+    }
+
+    /// Returns the value of a text field of an entry
+    /// The secrets are borrowed, so no copy of them is left behind
+    ///
+    /// # Arguments
+    /// * `entry` - The entry
+    ///
+    /// # Returns
+    /// The value, tags are separated by commas and unknown times are empty,
+    /// None for the custom fields and details, which are written as JSON
+    pub fn value<'a>(&self, entry: &'a Entry) -> Option<Cow<'a, str>> {
+        let time = |seconds: i64| -> Cow<'a, str> {
+            match chrono::DateTime::from_timestamp(seconds, 0) {
+                Some(time) if seconds > 0 => Cow::Owned(time.to_rfc3339()),
+                _ => Cow::Borrowed(""),
+            }
+        };
+        Some(match self {
+            ExportField::Title => Cow::Borrowed(&entry.domain),
+            ExportField::Username => Cow::Borrowed(&entry.username),
+            ExportField::Password => Cow::Borrowed(entry.password.expose_secret()),
+            ExportField::Url => Cow::Borrowed(&entry.url),
+            ExportField::Totp => Cow::Borrowed(entry.totp.expose_secret()),
+            ExportField::Notes => Cow::Borrowed(entry.notes.expose_secret()),
+            ExportField::Fields | ExportField::Details => return None,
+            ExportField::Kind => Cow::Borrowed(kind_name(&entry.kind)),
+            ExportField::Folder => Cow::Borrowed(&entry.folder),
+            ExportField::Tags => Cow::Owned(entry.tags.join(",")),
+            ExportField::Created => time(entry.created),
+            ExportField::Modified => time(entry.modified),
+        })
+    }
+
+    /// Returns the largest size the JSON of the field of an entry can take
+    /// A character takes at most six bytes once escaped, like `\u001f`
+    ///
+    /// # Arguments
+    /// * `entry` - The entry
+    ///
+    /// # Returns
+    /// The size in bytes
+    fn json_length(&self, entry: &Entry) -> usize {
+        let text = |value: &str| 6 * value.len() + 2;
+        match self {
+            ExportField::Tags => 2 + entry.tags.iter().map(|tag| text(tag) + 2).sum::<usize>(),
+            ExportField::Fields => {
+                2 + entry
+                    .fields
+                    .iter()
+                    .map(|field| text(&field.name) + text(field.value.expose_secret()) + 48)
+                    .sum::<usize>()
+            }
+            ExportField::Details => {
+                2 + details(&entry.kind)
+                    .iter()
+                    .map(|(name, value)| text(name) + text(value) + 4)
+                    .sum::<usize>()
+            }
+            _ => self.value(entry).map_or(0, |value| text(&value)),
+        }
+    }
+}
+
+/// Returns the name of the kind of an entry in an export
+///
+/// # Arguments
+/// * `kind` - The kind
+///
+/// # Returns
+/// The name, as stored in the vault
+fn kind_name(kind: &EntryKind) -> &'static str {
+    match kind {
+        EntryKind::Login => "login",
+        EntryKind::Note => "note",
+        EntryKind::Card(_) => "card",
+        EntryKind::SshKey(_) => "ssh_key",
+        EntryKind::Identity(_) => "identity",
+    }
+}
+
+/// Returns the fields only entries of a kind have, named as stored in the vault
+///
+/// # Arguments
+/// * `kind` - The kind
+///
+/// # Returns
+/// The names and values, none for logins and notes
+fn details(kind: &EntryKind) -> Vec<(&'static str, &str)> {
+    match kind {
+        EntryKind::Login | EntryKind::Note => vec![],
+        EntryKind::Card(card) => vec![
+            ("holder", &card.holder),
+            ("number", card.number.expose_secret()),
+            ("expiry", &card.expiry),
+            ("cvv", card.cvv.expose_secret()),
+        ],
+        EntryKind::SshKey(key) => vec![
+            ("private_key", key.private_key.expose_secret()),
+            ("public_key", &key.public_key),
+        ],
+        EntryKind::Identity(identity) => vec![
+            ("full_name", &identity.full_name),
+            ("email", &identity.email),
+            ("phone", &identity.phone),
+            ("address", &identity.address),
+            ("company", &identity.company),
+            ("id_number", identity.id_number.expose_secret()),
+        ],
+    }
+}
+
+/// Returns the largest size a clear text export can take
+/// Quoting a CSV value at most doubles it, the custom fields and details
+/// are JSON inside a CSV value
+///
+/// # Arguments
+/// * `entries` - The entries
+/// * `fields` - The exported fields
+/// * `format` - The format
+///
+/// # Returns
+/// The size in bytes
+fn capacity(entries: &[Entry], fields: &[ExportField], format: ExportFormat) -> usize {
+    let names: usize = fields.iter().map(|field| 6 * field.name().len() + 16).sum();
+    let values: usize = entries
+        .iter()
+        .flat_map(|entry| {
+            fields.iter().map(move |field| match format {
+                ExportFormat::Json => field.json_length(entry),
+                ExportFormat::Csv => match field.value(entry) {
+                    Some(value) => 2 * value.len() + 2,
+                    None => 2 * field.json_length(entry) + 2,
+                },
+            })
+        })
+        .sum();
+    names * (entries.len() + 1) + values + 16 * entries.len() + 16
+}
+
+/// Writes entries in clear text
+///
+/// # Arguments
+/// * `entries` - The entries
+/// * `options` - The format and the fields
+///
+/// # Returns
+/// The content of the export
+pub fn render(entries: &[Entry], options: &ExportOptions) -> Zeroizing<Vec<u8>> {
+    let fields: Vec<ExportField> = EXPORT_FIELDS
+        .into_iter()
+        .filter(|field| options.fields.contains(field))
+        .collect();
+    // reserve everything up front, growing would leave copies of the secrets behind
+    let capacity = capacity(entries, &fields, options.format);
+
+    match options.format {
+        ExportFormat::Csv => {
+            let mut text = Zeroizing::new(String::with_capacity(capacity));
+            let header: Vec<&str> = fields.iter().map(ExportField::name).collect();
+            csv::write_record(&mut text, &header);
+            for entry in entries.iter() {
+                // the custom fields and details are written as JSON first
+                let json: Vec<Zeroizing<Vec<u8>>> = fields
+                    .iter()
+                    .map(|field| {
+                        let mut json = Zeroizing::new(vec![]);
+                        if field.value(entry).is_none() {
+                            json.reserve_exact(field.json_length(entry));
+                            write_json_field(&mut json, *field, entry);
+                        }
+                        json
+                    })
+                    .collect();
+                let values: Vec<Cow<str>> = fields
+                    .iter()
+                    .zip(json.iter())
+                    .map(|(field, json)| {
+                        field.value(entry).unwrap_or_else(|| {
+                            Cow::Borrowed(str::from_utf8(json).unwrap_or_default())
+                        })
+                    })
+                    .collect();
+                let values: Vec<&str> = values.iter().map(|value| value.as_ref()).collect();
+                csv::write_record(&mut text, &values);
+            }
+            Zeroizing::new(std::mem::take(&mut *text).into_bytes())
+        }
+        ExportFormat::Json => {
+            let mut json = Zeroizing::new(Vec::with_capacity(capacity));
+            json.push(b'[');
+            for (index, entry) in entries.iter().enumerate() {
+                json.extend_from_slice(if index == 0 { b"\n  {" } else { b",\n  {" });
+                for (position, field) in fields.iter().enumerate() {
+                    json.extend_from_slice(if position == 0 { b"\n    " } else { b",\n    " });
+                    write_json(&mut json, field.name());
+                    json.extend_from_slice(b": ");
+                    write_json_field(&mut json, *field, entry);
+                }
+                json.extend_from_slice(b"\n  }");
+            }
+            json.extend_from_slice(if entries.is_empty() { b"]\n" } else { b"\n]\n" });
+            json
+        }
+    }
+}
+
+/// Writes entries in clear text to a new file only readable by the user
+/// An existing file is never overwritten
+///
+/// # Arguments
+/// * `path` - The path of the new file
+/// * `entries` - The entries
+/// * `options` - The format and the fields
+///
+/// # Returns
+/// An error if the file exists or could not be written
+pub fn export(path: &Path, entries: &[Entry], options: &ExportOptions) -> io::Result<()> {
+    write_private_file(path, &render(entries, options))
+}
+
+/// Appends a JSON string to a buffer
+///
+/// # Arguments
+/// * `json` - The buffer
+/// * `value` - The string, escaped as JSON requires
+fn write_json(json: &mut Vec<u8>, value: &str) {
+    // writing to a vector cannot fail
+    let _ = serde_json::to_writer(&mut *json, value);
+}
+
+/// Appends the JSON of a field of an entry to a buffer
+/// Tags are an array, custom fields an array of objects and details an object
+///
+/// # Arguments
+/// * `json` - The buffer
+/// * `field` - The field
+/// * `entry` - The entry
+fn write_json_field(json: &mut Vec<u8>, field: ExportField, entry: &Entry) {
+    match field {
+        ExportField::Tags => {
+            json.push(b'[');
+            for (index, tag) in entry.tags.iter().enumerate() {
+                if index > 0 {
+                    json.extend_from_slice(b", ");
+                }
+                write_json(json, tag);
+            }
+            json.push(b']');
+        }
+        ExportField::Fields => {
+            json.push(b'[');
+            for (index, custom) in entry.fields.iter().enumerate() {
+                if index > 0 {
+                    json.extend_from_slice(b", ");
+                }
+                json.extend_from_slice(b"{\"name\": ");
+                write_json(json, &custom.name);
+                json.extend_from_slice(b", \"value\": ");
+                write_json(json, custom.value.expose_secret());
+                json.extend_from_slice(if custom.hidden {
+                    b", \"hidden\": true}"
+                } else {
+                    b", \"hidden\": false}"
+                });
+            }
+            json.push(b']');
+        }
+        ExportField::Details => {
+            json.push(b'{');
+            for (index, (name, value)) in details(&entry.kind).into_iter().enumerate() {
+                if index > 0 {
+                    json.extend_from_slice(b", ");
+                }
+                write_json(json, name);
+                json.extend_from_slice(b": ");
+                write_json(json, value);
+            }
+            json.push(b'}');
+        }
+        _ => write_json(json, &field.value(entry).unwrap_or_default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        entry::CustomField,
+        import::{
+            csv::{Csv, CsvFormat},
+            Importer,
+        },
+        kind::Card,
+        test_util::TempDir,
+    };
+    use std::fs;
+
+    fn entries() -> Vec<Entry> {
+        let mut entry = Entry::new("example.com", "pa,ss\"word\n");
+        entry.username = "krab".to_string();
+        entry.url = "https://example.com".to_string();
+        entry.notes = "line 1\r\nline 2".into();
+        entry.totp = "JBSWY3DPEHPK3PXP".into();
+        entry.folder = "Work/Mail".to_string();
+        entry.tags = vec!["work".to_string(), "mail".to_string()];
+        entry.created = 1_700_000_000;
+        vec![entry, Entry::new("other.com", "password")]
+    }
+
+    #[test]
+    fn test_export_csv_roundtrip() {
+        let entries = entries();
+        let csv = render(&entries, &ExportOptions::default());
+        let import = Csv(CsvFormat::Generic).import(&csv, "").unwrap();
+        let limited = render(
+            &entries,
+            &ExportOptions {
+                format: ExportFormat::Csv,
+                fields: vec![ExportField::Password, ExportField::Title],
+            },
+        );
+
+        assert!(csv.starts_with(
+            b"title,username,password,url,totp,notes,fields,kind,details,folder,tags,created,modified\r\n"
+        ));
+        assert_eq!(import.entries.len(), 2);
+        let entry = &import.entries[0];
+        assert_eq!(entry.domain, "example.com");
+        assert_eq!(entry.password.expose_secret(), "pa,ss\"word\n");
+        assert_eq!(entry.notes.expose_secret(), "line 1\r\nline 2");
+        assert_eq!(entry.totp.expose_secret(), "JBSWY3DPEHPK3PXP");
+        assert_eq!(entry.folder, "Work/Mail");
+        assert_eq!(entry.tags, vec!["work", "mail"]);
+        assert_eq!(entry.created, 1_700_000_000);
+        assert_eq!(import.entries[1].created, 0);
+        assert_eq!(
+            limited.as_slice(),
+            b"title,password\r\nexample.com,\"pa,ss\"\"word\n\"\r\nother.com,password\r\n"
+        );
+    }
+
+    #[test]
+    fn test_export_json() {
+        let entries = entries();
+        let json = render(
+            &entries,
+            &ExportOptions {
+                format: ExportFormat::Json,
+                fields: vec![ExportField::Tags, ExportField::Title, ExportField::Password],
+            },
+        );
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let empty = render(
+            &[],
+            &ExportOptions {
+                format: ExportFormat::Json,
+                fields: EXPORT_FIELDS.to_vec(),
+            },
+        );
+
+        assert_eq!(
+            value,
+            serde_json::json!([
+                {"title": "example.com", "password": "pa,ss\"word\n", "tags": ["work", "mail"]},
+                {"title": "other.com", "password": "password", "tags": []}
+            ])
+        );
+        assert_eq!(empty.as_slice(), b"[]\n");
+    }
+
+    #[test]
+    fn test_export_kinds_and_custom_fields() {
+        let mut login = Entry::new("example.com", "password");
+        login.fields = vec![CustomField {
+            name: "PIN".to_string(),
+            value: "12\u{1f}\u{1f}34".into(),
+            hidden: true,
+        }];
+        let mut card = Entry::new("Visa", "");
+        card.kind = EntryKind::Card(Card {
+            holder: "Krab".to_string(),
+            number: "4111 1111 1111 1111".into(),
+            expiry: "12/27".to_string(),
+            cvv: "123".into(),
+        });
+        let entries = vec![login, card];
+        let options = ExportOptions {
+            format: ExportFormat::Json,
+            fields: vec![
+                ExportField::Title,
+                ExportField::Fields,
+                ExportField::Kind,
+                ExportField::Details,
+            ],
+        };
+        let json = render(&entries, &options);
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let csv_options = ExportOptions {
+            format: ExportFormat::Csv,
+            ..options.clone()
+        };
+        let csv = render(&entries, &csv_options);
+        let records = csv::parse(str::from_utf8(&csv).unwrap()).unwrap();
+
+        assert_eq!(
+            value,
+            serde_json::json!([
+                {
+                    "title": "example.com",
+                    "fields": [{"name": "PIN", "value": "12\u{1f}\u{1f}34", "hidden": true}],
+                    "kind": "login",
+                    "details": {}
+                },
+                {
+                    "title": "Visa",
+                    "fields": [],
+                    "kind": "card",
+                    "details": {
+                        "holder": "Krab",
+                        "number": "4111 1111 1111 1111",
+                        "expiry": "12/27",
+                        "cvv": "123"
+                    }
+                }
+            ])
+        );
+        assert_eq!(records[0], vec!["title", "fields", "kind", "details"]);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&records[1][1]).unwrap(),
+            value[0]["fields"]
+        );
+        assert_eq!(records[2][2], "card");
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&records[2][3]).unwrap(),
+            value[1]["details"]
+        );
+        // everything is reserved up front, control characters included
+        for options in [options, csv_options] {
+            let fields: Vec<ExportField> = EXPORT_FIELDS
+                .into_iter()
+                .filter(|field| options.fields.contains(field))
+                .collect();
+            let rendered = render(&entries, &options);
+            assert_eq!(
+                rendered.capacity(),
+                capacity(&entries, &fields, options.format)
+            );
+        }
+    }
+
+    #[test]
+    fn test_export_file_is_private() {
+        let dir = TempDir::new();
+        let path = dir.path().join("krab-export.csv");
+
+        export(&path, &entries(), &ExportOptions::default()).unwrap();
+        let again = export(&path, &entries(), &ExportOptions::default());
+        let content = fs::read(&path).unwrap();
+        #[cfg(unix)]
+        let mode =
+            std::os::unix::fs::PermissionsExt::mode(&fs::metadata(&path).unwrap().permissions());
+
+        assert_eq!(again.unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(
+            content.as_slice(),
+            render(&entries(), &ExportOptions::default()).as_slice()
+        );
+        #[cfg(unix)]
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,494 @@
+use std::fmt;
+
+use crate::{
+    entry::{CustomField, Entry},
+    totp::Totp,
+};
+
+pub mod bitwarden;
+pub mod csv;
+pub mod kdbx;
+pub mod onepassword;
+
+/// The importers tried by `detect`, the more specific formats first
+pub static IMPORTERS: &[&dyn Importer] = &[
+    &kdbx::KeePass,
+    &onepassword::OnePux,
+    &bitwarden::Bitwarden,
+    &csv::Csv(csv::CsvFormat::LastPass),
+    &csv::Csv(csv::CsvFormat::OnePassword),
+    &csv::Csv(csv::CsvFormat::Firefox),
+    &csv::Csv(csv::CsvFormat::Chrome),
+    &csv::Csv(csv::CsvFormat::Generic),
+];
+
+/// Reads the file of another password manager
+/// Every format is a unit of its own, see `IMPORTERS` for the known ones
+///
+/// # Methods
+/// * `name` - Returns the name of the format
+/// * `needs_password` - Returns whether the file is encrypted with a password
+/// * `detect` - Returns whether a file is of the format
+/// * `import` - Reads the entries of a file
+pub trait Importer: Sync {
+    /// Returns the name of the format, shown to the user
+    ///
+    /// # Returns
+    /// The name
+    fn name(&self) -> &'static str;
+
+    /// Returns whether the file is encrypted with a password
+    ///
+    /// # Returns
+    /// `true` if `import` needs the password of the file
+    fn needs_password(&self) -> bool {
+        false
+    }
+
+    /// Returns whether a file is of the format
+    /// It only looks at the structure of the file, which may still be damaged
+    ///
+    /// # Arguments
+    /// * `data` - The content of the file
+    ///
+    /// # Returns
+    /// `true` if the file is of the format
+    fn detect(&self, data: &[u8]) -> bool;
+
+    /// Reads the entries of a file
+    ///
+    /// # Arguments
+    /// * `data` - The content of the file
+    /// * `password` - The password of the file, ignored unless `needs_password`
+    ///
+    /// # Returns
+    /// The entries and the skipped items, or an error
+    fn import(&self, data: &[u8], password: &str) -> Result<Import, ImportError>;
+}
+
+/// Returns the importer of the format of a file
+///
+/// # Arguments
+/// * `data` - The content of the file
+///
+/// # Returns
+/// The first importer of `IMPORTERS` that detects the file, or None
+pub fn detect(data: &[u8]) -> Option<&'static dyn Importer> {
+    IMPORTERS
+        .iter()
+        .find(|importer| importer.detect(data))
+        .copied()
+}
+
+/// What happens to an imported entry with the same domain and username as another entry
+///
+/// # Variants
+/// * `Skip` - The imported entry is left out
+/// * `Overwrite` - The imported entry replaces the other one, whose password moves to the history
+/// * `KeepBoth` - Both entries are kept
+///
+/// # Methods
+/// * `name` - Returns the name of the policy
+/// * `next` - Returns the policy following this one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    #[default]
+    Skip,
+    Overwrite,
+    KeepBoth,
+}
+
+impl DuplicatePolicy {
+    /// Returns the name of the policy
+    ///
+    /// # Returns
+    /// The name
+    pub fn name(&self) -> &'static str {
+        match self {
+            DuplicatePolicy::Skip => "Skip",
+            DuplicatePolicy::Overwrite => "Overwrite",
+            DuplicatePolicy::KeepBoth => "Keep both",
+        }
+    }
+
+    /// Returns the policy following this one, to cycle through them
+    ///
+    /// # Returns
+    /// The next policy
+    pub fn next(&self) -> DuplicatePolicy {
+        match self {
+            DuplicatePolicy::Skip => DuplicatePolicy::Overwrite,
+            DuplicatePolicy::Overwrite => DuplicatePolicy::KeepBoth,
+            DuplicatePolicy::KeepBoth => DuplicatePolicy::Skip,
+        }
+    }
+}
+
+/// The number of imported entries by what happened to them
+///
+/// # Fields
+/// * `added` - The entries added as new records
+/// * `replaced` - The entries that replaced a duplicate
+/// * `duplicates` - The entries left out as duplicates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportCounts {
+    pub added: usize,
+    pub replaced: usize,
+    pub duplicates: usize,
+}
+
+/// The changes to a vault that import entries
+///
+/// # Fields
+/// * `added` - The new entries
+/// * `replaced` - The revised entries replacing the records with their id
+/// * `counts` - The number of entries by what happened to them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Merge {
+    pub(crate) added: Vec<Entry>,
+    pub(crate) replaced: Vec<Entry>,
+    pub(crate) counts: ImportCounts,
+}
+
+/// Returns whether two entries are duplicates
+/// They are if both the domain and the username match, ignoring case and surrounding whitespace
+///
+/// # Arguments
+/// * `a` - An entry
+/// * `b` - The other entry
+///
+/// # Returns
+/// `true` if the entries are duplicates
+pub fn is_duplicate(a: &Entry, b: &Entry) -> bool {
+    a.domain.trim().eq_ignore_ascii_case(b.domain.trim())
+        && a.username.trim().eq_ignore_ascii_case(b.username.trim())
+}
+
+/// Decides how imported entries change a vault
+/// An imported entry is also compared with the ones imported before it
+///
+/// # Arguments
+/// * `existing` - The entries of the vault
+/// * `entries` - The imported entries
+/// * `policy` - What happens to duplicates
+/// * `now` - The current time as a Unix timestamp
+///
+/// # Returns
+/// The changes
+pub(crate) fn merge(
+    existing: &[Entry],
+    entries: &[Entry],
+    policy: DuplicatePolicy,
+    now: i64,
+) -> Merge {
+    let mut merge = Merge::default();
+    for entry in entries.iter() {
+        if policy == DuplicatePolicy::KeepBoth {
+            merge.added.push(entry.clone());
+            continue;
+        }
+
+        if let Some(added) = merge.added.iter_mut().find(|a| is_duplicate(a, entry)) {
+            if policy == DuplicatePolicy::Overwrite {
+                *added = entry.clone();
+            }
+            merge.counts.duplicates += 1;
+        } else if let Some(replaced) = merge.replaced.iter_mut().find(|r| is_duplicate(r, entry)) {
+            // the entry of the vault is revised again, the password imported
+            // before this one never was in the vault so it is not kept in the history
+            if let Some(previous) = existing.iter().find(|e| e.id == replaced.id) {
+                if policy == DuplicatePolicy::Overwrite {
+                    *replaced = previous.revise(entry, now);
+                }
+            }
+            merge.counts.duplicates += 1;
+        } else if let Some(previous) = existing.iter().find(|e| is_duplicate(e, entry)) {
+            if policy == DuplicatePolicy::Overwrite {
+                merge.replaced.push(previous.revise(entry, now));
+            } else {
+                merge.counts.duplicates += 1;
+            }
+        } else {
+            merge.added.push(entry.clone());
+        }
+    }
+    merge.counts.added = merge.added.len();
+    merge.counts.replaced = merge.replaced.len();
+    merge
+}
+
+/// An item of a foreign file that was not turned into an entry
+///
+/// # Fields
+/// * `name` - The name of the item, like the title of the entry
+/// * `reason` - Why it was skipped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedItem {
+    pub name: String,
+    pub reason: String,
+}
+
+/// The entries read from the file of another password manager
+/// The entries have no id yet and are ready to be added to a vault
+///
+/// # Fields
+/// * `entries` - The entries, in the order of the file
+/// * `skipped` - The items that could not be imported
+///
+/// # Methods
+/// * `push` - Adds an entry if it is valid, or records why it is skipped
+/// * `skip` - Records a skipped item
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Import {
+    pub entries: Vec<Entry>,
+    pub skipped: Vec<SkippedItem>,
+}
+
+impl Import {
+    /// Adds an entry if the fields its kind requires are set,
+    /// otherwise it is recorded as skipped
+    ///
+    /// # Arguments
+    /// * `entry` - The entry
+    pub fn push(&mut self, entry: Entry) {
+        match entry.validate() {
+            Ok(()) => self.entries.push(entry),
+            Err(e) => {
+                let name = if entry.domain.is_empty() {
+                    "Untitled".to_string()
+                } else {
+                    entry.label()
+                };
+                self.skip(&name, &e.to_string());
+            }
+        }
+    }
+
+    /// Records a skipped item
+    ///
+    /// # Arguments
+    /// * `name` - The name of the item
+    /// * `reason` - Why it is skipped
+    pub fn skip(&mut self, name: &str, reason: &str) {
+        self.skipped.push(SkippedItem {
+            name: name.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+}
+
+/// Error while reading the file of another password manager
+/// It never contains any secret of the file
+///
+/// # Variants
+/// * `WrongPassword` - The password does not open the file
+/// * `Unsupported` - The file uses a feature or version that cannot be read, with its name
+/// * `Malformed` - The file is damaged or not of the expected format, with the reason
+///
+/// # Implements
+/// * `Display`
+/// * `Error`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportError {
+    WrongPassword,
+    Unsupported(String),
+    Malformed(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::WrongPassword => {
+                write!(f, "Wrong password, or the file also needs a key file")
+            }
+            ImportError::Unsupported(feature) => write!(f, "Not supported: {}", feature),
+            ImportError::Malformed(reason) => write!(f, "The file is not valid: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Returns the host of a URL, used as the title of entries without one
+///
+/// # Arguments
+/// * `url` - The URL
+///
+/// # Returns
+/// The host, empty if the URL is empty
+pub(crate) fn url_host(url: &str) -> String {
+    let rest = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    rest.split(['/', '?', '#']).next().unwrap_or("").to_string()
+}
+
+/// Adds a custom field to an entry, unless its value is empty
+///
+/// # Arguments
+/// * `entry` - The entry
+/// * `name` - The name of the field
+/// * `value` - The value of the field
+/// * `hidden` - Whether the value is hidden like a password
+pub(crate) fn add_field(entry: &mut Entry, name: &str, value: &str, hidden: bool) {
+    if value.trim().is_empty() {
+        return;
+    }
+    entry.fields.push(CustomField {
+        name: name.to_string(),
+        value: value.into(),
+        hidden,
+    });
+}
+
+/// Sets the TOTP secret of an entry
+/// A value that is not a base32 secret or an `otpauth://totp/` URI,
+/// like the URI of another kind of one-time codes, is kept as a hidden field
+///
+/// # Arguments
+/// * `entry` - The entry
+/// * `name` - The name of the field keeping an invalid value
+/// * `value` - The secret or URI
+pub(crate) fn set_totp(entry: &mut Entry, name: &str, value: &str) {
+    let value = value.trim();
+    if value.is_empty() {
+        return;
+    }
+    if entry.totp.is_empty() && Totp::parse(value).is_ok() {
+        entry.totp = value.into();
+    } else {
+        add_field(entry, name, value, true);
+    }
+}
+
+/// Parses a time of an exported file
+///
+/// # Arguments
+/// * `value` - Seconds or milliseconds since the Unix epoch, or an RFC 3339 time
+///
+/// # Returns
+/// The time as a Unix timestamp, `0` if it is unknown
+pub(crate) fn parse_time(value: &str) -> i64 {
+    // milliseconds would be more than 3000 years in seconds
+    const MAX_SECONDS: i64 = 32_503_680_000;
+    let value = value.trim();
+    let seconds = match value.parse::<i64>() {
+        Ok(number) if number > MAX_SECONDS => Some(number / 1000),
+        Ok(number) => Some(number),
+        Err(_) => chrono::DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|time| time.timestamp()),
+    };
+    seconds.unwrap_or_default().max(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(domain: &str, username: &str, password: &str) -> Entry {
+        let mut entry = Entry::new(domain, password);
+        entry.username = username.to_string();
+        entry
+    }
+
+    #[test]
+    fn test_import_merge_policies() {
+        let mut existing = entry("example.com", "krab", "old");
+        existing.id = "1".to_string();
+        existing.created = 100;
+        let existing = vec![existing, entry("other.com", "krab", "password")];
+        let entries = vec![
+            entry("Example.com", "Krab ", "new"),
+            entry("example.com", "krab", "newer"),
+            entry("new.com", "krab", "first"),
+            entry("new.com", "KRAB", "second"),
+            entry("new.com", "other", "third"),
+        ];
+
+        let skip = merge(&existing, &entries, DuplicatePolicy::Skip, 200);
+        let overwrite = merge(&existing, &entries, DuplicatePolicy::Overwrite, 200);
+        let keep_both = merge(&existing, &entries, DuplicatePolicy::KeepBoth, 200);
+        let passwords = |entries: &[Entry]| -> Vec<String> {
+            entries
+                .iter()
+                .map(|e| e.password.expose_secret().to_string())
+                .collect()
+        };
+
+        assert_eq!(passwords(&skip.added), vec!["first", "third"]);
+        assert!(skip.replaced.is_empty());
+        assert_eq!(
+            skip.counts,
+            ImportCounts {
+                added: 2,
+                replaced: 0,
+                duplicates: 3
+            }
+        );
+        assert_eq!(passwords(&overwrite.added), vec!["second", "third"]);
+        assert_eq!(passwords(&overwrite.replaced), vec!["newer"]);
+        assert_eq!(overwrite.replaced[0].id, "1");
+        assert_eq!(overwrite.replaced[0].created, 100);
+        assert_eq!(overwrite.replaced[0].domain, "example.com");
+        let history = &overwrite.replaced[0].history;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].password.expose_secret(), "old");
+        assert_eq!(
+            overwrite.counts,
+            ImportCounts {
+                added: 2,
+                replaced: 1,
+                duplicates: 2
+            }
+        );
+        assert_eq!(keep_both.added, entries);
+        assert_eq!(keep_both.counts.duplicates, 0);
+        assert_eq!(
+            DuplicatePolicy::default().next().next().next(),
+            DuplicatePolicy::Skip
+        );
+    }
+
+    #[test]
+    fn test_import_detect_and_helpers() {
+        let names: Vec<Option<&str>> = [
+            &b"\x03\xd9\xa2\x9a\x67\xfb\x4b\xb5"[..],
+            b"{\"encrypted\": false, \"items\": []}",
+            b"url,username,password,totp,extra,name,grouping,fav\n",
+            b"name,url,username,password,note\n",
+            b"Title,Url,Username,Password,OTPAuth,Favorite,Archived,Tags,Notes\n",
+            b"website,login,password\n",
+            b"no password here\n",
+        ]
+        .iter()
+        .map(|data| detect(data).map(|importer| importer.name()))
+        .collect();
+        let mut entry = Entry::new("example.com", "password");
+        set_totp(&mut entry, "TOTP", "JBSWY3DPEHPK3PXP");
+        set_totp(&mut entry, "TOTP", "steam://JBSWY3DPEHPK3PXP");
+        add_field(&mut entry, "Empty", " ", false);
+
+        assert_eq!(
+            names,
+            vec![
+                Some("KeePass (KDBX 4)"),
+                Some("Bitwarden JSON"),
+                Some("LastPass CSV"),
+                Some("Chrome CSV"),
+                Some("1Password CSV"),
+                Some("CSV"),
+                None,
+            ]
+        );
+        assert_eq!(entry.totp.expose_secret(), "JBSWY3DPEHPK3PXP");
+        assert_eq!(entry.fields.len(), 1);
+        assert!(entry.fields[0].hidden);
+        assert_eq!(
+            url_host("https://example.com:8080/login?a=b"),
+            "example.com:8080"
+        );
+        assert_eq!(parse_time("1700000000"), 1_700_000_000);
+        assert_eq!(parse_time("1700000000123"), 1_700_000_000);
+        assert_eq!(parse_time("2023-11-14T22:13:20.000Z"), 1_700_000_000);
+        assert_eq!(parse_time("yesterday"), 0);
+    }
+}
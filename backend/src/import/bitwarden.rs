@@ -0,0 +1,444 @@
+use std::cmp::Reverse;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    entry::{Entry, PreviousPassword, MAX_PASSWORD_HISTORY},
+    import::{add_field, parse_time, set_totp, url_host, Import, ImportError, Importer},
+    kind::{Card, EntryKind, Identity, SshKey},
+    secret::SecretString,
+};
+
+const TYPE_LOGIN: u8 = 1;
+const TYPE_NOTE: u8 = 2;
+const TYPE_CARD: u8 = 3;
+const TYPE_IDENTITY: u8 = 4;
+const TYPE_SSH_KEY: u8 = 5;
+
+const FIELD_HIDDEN: u8 = 1;
+const FIELD_LINKED: u8 = 3;
+
+/// The importer of unencrypted Bitwarden JSON exports
+///
+/// # Implements
+/// * `Importer`
+#[derive(Debug, Clone, Copy)]
+pub struct Bitwarden;
+
+/// A Bitwarden export, the parts of it that are imported
+/// Bitwarden writes `null` for missing values, so most of them are optional,
+/// the structs below follow the names and nesting of the export
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Export {
+    #[serde(default)]
+    encrypted: bool,
+    folders: Option<Vec<Folder>>,
+    items: Option<Vec<Item>>,
+}
+
+#[derive(Deserialize)]
+struct Folder {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Item {
+    #[serde(rename = "type")]
+    kind: u8,
+    name: Option<String>,
+    notes: Option<SecretString>,
+    folder_id: Option<String>,
+    fields: Option<Vec<Field>>,
+    login: Option<Login>,
+    card: Option<BitwardenCard>,
+    identity: Option<BitwardenIdentity>,
+    ssh_key: Option<BitwardenSshKey>,
+    password_history: Option<Vec<History>>,
+    creation_date: Option<String>,
+    revision_date: Option<String>,
+    deleted_date: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Field {
+    name: Option<String>,
+    value: Option<SecretString>,
+    #[serde(rename = "type", default)]
+    kind: u8,
+}
+
+#[derive(Deserialize)]
+struct Login {
+    uris: Option<Vec<Uri>>,
+    username: Option<String>,
+    password: Option<SecretString>,
+    totp: Option<SecretString>,
+}
+
+#[derive(Deserialize)]
+struct Uri {
+    uri: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BitwardenCard {
+    cardholder_name: Option<String>,
+    brand: Option<String>,
+    number: Option<SecretString>,
+    exp_month: Option<String>,
+    exp_year: Option<String>,
+    code: Option<SecretString>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BitwardenIdentity {
+    title: Option<String>,
+    first_name: Option<String>,
+    middle_name: Option<String>,
+    last_name: Option<String>,
+    address1: Option<String>,
+    address2: Option<String>,
+    address3: Option<String>,
+    city: Option<String>,
+    state: Option<String>,
+    postal_code: Option<String>,
+    country: Option<String>,
+    company: Option<String>,
+    email: Option<String>,
+    phone: Option<String>,
+    ssn: Option<SecretString>,
+    username: Option<String>,
+    passport_number: Option<SecretString>,
+    license_number: Option<SecretString>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BitwardenSshKey {
+    private_key: Option<SecretString>,
+    public_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct History {
+    last_used_date: Option<String>,
+    password: Option<SecretString>,
+}
+
+impl Importer for Bitwarden {
+    fn name(&self) -> &'static str {
+        "Bitwarden JSON"
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        if data.trim_ascii_start().first() != Some(&b'{') {
+            return false;
+        }
+        serde_json::from_slice::<Value>(data)
+            .is_ok_and(|value| value.get("items").is_some() || value.get("encrypted").is_some())
+    }
+
+    fn import(&self, data: &[u8], _password: &str) -> Result<Import, ImportError> {
+        let export: Export = serde_json::from_slice(data)
+            .map_err(|e| ImportError::Malformed(format!("the JSON cannot be read, {}", e)))?;
+        if export.encrypted {
+            return Err(ImportError::Unsupported(
+                "encrypted Bitwarden exports, export the vault unencrypted".to_string(),
+            ));
+        }
+        let folders = export.folders.unwrap_or_default();
+
+        let mut import = Import::default();
+        for item in export.items.unwrap_or_default() {
+            let name = item.name.clone().unwrap_or_default();
+            if item.deleted_date.is_some() {
+                import.skip(&name, "In the trash");
+                continue;
+            }
+            let folder = item
+                .folder_id
+                .as_ref()
+                .and_then(|id| folders.iter().find(|folder| folder.id == *id))
+                .map(|folder| Entry::normalize_folder(&folder.name))
+                .unwrap_or_default();
+            match read_item(item) {
+                Some(mut entry) => {
+                    entry.folder = folder;
+                    import.push(entry);
+                }
+                None => import.skip(&name, "The item type is not supported"),
+            }
+        }
+        Ok(import)
+    }
+}
+
+/// Turns an item of the export into an entry
+///
+/// # Arguments
+/// * `item` - The item
+///
+/// # Returns
+/// The entry, or None if the type of the item is not known
+fn read_item(item: Item) -> Option<Entry> {
+    let mut entry = Entry {
+        domain: item.name.unwrap_or_default().trim().to_string(),
+        notes: item.notes.unwrap_or_default(),
+        created: item.creation_date.as_deref().map(parse_time).unwrap_or(0),
+        modified: item.revision_date.as_deref().map(parse_time).unwrap_or(0),
+        ..Default::default()
+    };
+
+    match item.kind {
+        TYPE_LOGIN => {
+            if let Some(login) = item.login {
+                let mut uris = login
+                    .uris
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|uri| uri.uri)
+                    .filter(|uri| !uri.trim().is_empty());
+                entry.url = uris.next().unwrap_or_default();
+                for uri in uris {
+                    add_field(&mut entry, "URL", &uri, false);
+                }
+                entry.username = login.username.unwrap_or_default();
+                entry.password = login.password.unwrap_or_default();
+                if let Some(totp) = login.totp {
+                    set_totp(&mut entry, "TOTP", totp.expose_secret());
+                }
+            }
+            if entry.domain.is_empty() {
+                entry.domain = url_host(&entry.url);
+            }
+        }
+        TYPE_NOTE => entry.kind = EntryKind::Note,
+        TYPE_CARD => {
+            let card = item.card?;
+            let month = card.exp_month.unwrap_or_default();
+            let year = card.exp_year.unwrap_or_default();
+            let (month, year) = (month.trim(), year.trim());
+            let expiry = if month.is_empty() || year.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    "{:0>2}/{}",
+                    month,
+                    year.get(year.len().saturating_sub(2)..).unwrap_or(year)
+                )
+            };
+            add_field(&mut entry, "Brand", &card.brand.unwrap_or_default(), false);
+            entry.kind = EntryKind::Card(Card {
+                holder: card.cardholder_name.unwrap_or_default(),
+                number: card.number.unwrap_or_default(),
+                expiry,
+                cvv: card.code.unwrap_or_default(),
+            });
+        }
+        TYPE_IDENTITY => {
+            let identity = item.identity?;
+            let join = |parts: &[&Option<String>], separator: &str| -> String {
+                parts
+                    .iter()
+                    .filter_map(|part| part.as_deref())
+                    .map(str::trim)
+                    .filter(|part| !part.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(separator)
+            };
+            let full_name = join(
+                &[
+                    &identity.title,
+                    &identity.first_name,
+                    &identity.middle_name,
+                    &identity.last_name,
+                ],
+                " ",
+            );
+            let city = join(&[&identity.postal_code, &identity.city], " ");
+            let address = join(
+                &[
+                    &identity.address1,
+                    &identity.address2,
+                    &identity.address3,
+                    &Some(city),
+                    &identity.state,
+                    &identity.country,
+                ],
+                ", ",
+            );
+            let mut id_number = identity.passport_number.unwrap_or_default();
+            if id_number.is_empty() {
+                id_number = identity.license_number.unwrap_or_default();
+            }
+            if let Some(ssn) = identity.ssn {
+                add_field(&mut entry, "SSN", ssn.expose_secret(), true);
+            }
+            entry.username = identity.username.unwrap_or_default();
+            entry.kind = EntryKind::Identity(Identity {
+                full_name,
+                email: identity.email.unwrap_or_default(),
+                phone: identity.phone.unwrap_or_default(),
+                address,
+                company: identity.company.unwrap_or_default(),
+                id_number,
+            });
+        }
+        TYPE_SSH_KEY => {
+            let key = item.ssh_key?;
+            entry.kind = EntryKind::SshKey(SshKey {
+                private_key: key.private_key.unwrap_or_default(),
+                public_key: key.public_key.unwrap_or_default(),
+            });
+        }
+        _ => return None,
+    }
+
+    for field in item.fields.unwrap_or_default() {
+        if field.kind == FIELD_LINKED {
+            continue;
+        }
+        let value = field.value.unwrap_or_default();
+        add_field(
+            &mut entry,
+            &field.name.unwrap_or_default(),
+            value.expose_secret(),
+            field.kind == FIELD_HIDDEN,
+        );
+    }
+
+    let mut history: Vec<PreviousPassword> = item
+        .password_history
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|previous| {
+            Some(PreviousPassword {
+                password: previous.password.filter(|password| !password.is_empty())?,
+                replaced: previous
+                    .last_used_date
+                    .as_deref()
+                    .map(parse_time)
+                    .unwrap_or(0),
+            })
+        })
+        .collect();
+    history.sort_by_key(|previous| Reverse(previous.replaced));
+    history.truncate(MAX_PASSWORD_HISTORY);
+    entry.history = history;
+    Some(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = r#"{
+        "encrypted": false,
+        "folders": [{"id": "f1", "name": "Work/Mail"}],
+        "items": [
+            {
+                "id": "1", "folderId": "f1", "type": 1, "name": "Example", "notes": null,
+                "favorite": false, "creationDate": "2023-11-14T22:13:20.000Z",
+                "revisionDate": "2023-11-14T22:15:28.000Z", "deletedDate": null,
+                "fields": [
+                    {"name": "PIN", "value": "1234", "type": 1, "linkedId": null},
+                    {"name": "Linked", "value": null, "type": 3, "linkedId": 100}
+                ],
+                "login": {
+                    "uris": [{"match": null, "uri": "https://example.com"}, {"match": null, "uri": "https://example.org"}],
+                    "username": "krab", "password": "password", "totp": "JBSWY3DPEHPK3PXP"
+                },
+                "passwordHistory": [
+                    {"lastUsedDate": "2023-11-14T22:13:20.000Z", "password": "older"},
+                    {"lastUsedDate": "2023-11-14T22:14:20.000Z", "password": "old"}
+                ]
+            },
+            {"id": "2", "folderId": null, "type": 2, "name": "Note", "notes": "secret", "secureNote": {"type": 0}},
+            {
+                "id": "3", "type": 3, "name": "Visa",
+                "card": {"cardholderName": "Krab", "brand": "Visa", "number": "4111 1111 1111 1111", "expMonth": "5", "expYear": "2030", "code": "123"}
+            },
+            {
+                "id": "4", "type": 4, "name": "Me",
+                "identity": {"title": "Mr", "firstName": "Krab", "middleName": null, "lastName": "Crab", "address1": "1 Sea Road", "city": "Ocean", "postalCode": "12345", "country": "NL", "email": "krab@example.com", "ssn": "000", "passportNumber": null, "licenseNumber": "L1"}
+            },
+            {"id": "5", "type": 1, "name": "Deleted", "deletedDate": "2024-01-01T00:00:00.000Z", "login": {"password": "p"}},
+            {"id": "6", "type": 9, "name": "Future"}
+        ]
+    }"#;
+
+    #[test]
+    fn test_bitwarden_import() {
+        let import = Bitwarden.import(EXPORT.as_bytes(), "").unwrap();
+
+        assert_eq!(import.entries.len(), 4);
+        let login = &import.entries[0];
+        assert_eq!(login.domain, "Example");
+        assert_eq!(login.folder, "Work/Mail");
+        assert_eq!(login.url, "https://example.com");
+        assert_eq!(login.username, "krab");
+        assert_eq!(login.password.expose_secret(), "password");
+        assert_eq!(login.totp.expose_secret(), "JBSWY3DPEHPK3PXP");
+        assert_eq!(login.created, 1_700_000_000);
+        assert_eq!(login.modified, 1_700_000_128);
+        let fields: Vec<(&str, &str, bool)> = login
+            .fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.value.expose_secret(), f.hidden))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![("URL", "https://example.org", false), ("PIN", "1234", true)]
+        );
+        assert_eq!(login.history[0].password.expose_secret(), "old");
+        assert_eq!(login.history[1].password.expose_secret(), "older");
+        assert_eq!(import.entries[1].kind, EntryKind::Note);
+        match &import.entries[2].kind {
+            EntryKind::Card(card) => {
+                assert_eq!(card.expiry, "05/30");
+                assert_eq!(card.cvv.expose_secret(), "123");
+            }
+            _ => unreachable!(),
+        }
+        match &import.entries[3].kind {
+            EntryKind::Identity(identity) => {
+                assert_eq!(identity.full_name, "Mr Krab Crab");
+                assert_eq!(identity.address, "1 Sea Road, 12345 Ocean, NL");
+                assert_eq!(identity.id_number.expose_secret(), "L1");
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(import.entries[3].fields[0].name, "SSN");
+        let skipped: Vec<(&str, &str)> = import
+            .skipped
+            .iter()
+            .map(|item| (item.name.as_str(), item.reason.as_str()))
+            .collect();
+        assert_eq!(
+            skipped,
+            vec![
+                ("Deleted", "In the trash"),
+                ("Future", "The item type is not supported")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bitwarden_import_encrypted() {
+        let data = br#"{"encrypted": true, "passwordProtected": true, "data": "2.abc"}"#;
+
+        assert!(Bitwarden.detect(data));
+        assert!(matches!(
+            Bitwarden.import(data, ""),
+            Err(ImportError::Unsupported(_))
+        ));
+        assert!(!Bitwarden.detect(b"{\"accounts\": []}"));
+    }
+}
@@ -0,0 +1,413 @@
+use std::str;
+
+use crate::{
+    csv,
+    entry::Entry,
+    import::{add_field, parse_time, set_totp, url_host, Import, ImportError, Importer},
+    kind::EntryKind,
+};
+
+/// The URL LastPass gives its secure notes
+const LASTPASS_NOTE_URL: &str = "http://sn";
+
+/// The header names of each column, lowercase, in the order they are looked for
+const TITLE_COLUMNS: &[&str] = &["title", "name"];
+const URL_COLUMNS: &[&str] = &["url", "login_uri", "website", "uri", "login url"];
+const USERNAME_COLUMNS: &[&str] = &[
+    "username",
+    "login_username",
+    "user name",
+    "user",
+    "login",
+    "email",
+];
+const PASSWORD_COLUMNS: &[&str] = &["password", "login_password"];
+const TOTP_COLUMNS: &[&str] = &["totp", "otpauth", "login_totp", "one-time password", "otp"];
+const NOTES_COLUMNS: &[&str] = &["notes", "extra", "note", "comments"];
+const FOLDER_COLUMNS: &[&str] = &["folder", "grouping", "group"];
+const TAGS_COLUMNS: &[&str] = &["tags"];
+const CREATED_COLUMNS: &[&str] = &["timecreated", "created"];
+const MODIFIED_COLUMNS: &[&str] = &["timepasswordchanged", "modified"];
+const LAST_USED_COLUMNS: &[&str] = &["timelastused", "last_used"];
+/// Columns of the known formats that have no use in an entry
+const IGNORED_COLUMNS: &[&str] = &[
+    "fav",
+    "favorite",
+    "archived",
+    "httprealm",
+    "formactionorigin",
+    "guid",
+];
+
+/// The flavor of a CSV export, each password manager names its columns differently
+///
+/// # Variants
+/// * `LastPass` - A LastPass export, secure notes have the URL `http://sn` and folders use `\`
+/// * `OnePassword` - A 1Password export
+/// * `Firefox` - A Firefox export, the entries have no title
+/// * `Chrome` - A Chrome, Edge or Brave export
+/// * `Generic` - Any other CSV file with a header naming a password column
+///
+/// # Methods
+/// * `name` - Returns the name of the format
+/// * `matches` - Returns whether a header is of the flavor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvFormat {
+    LastPass,
+    OnePassword,
+    Firefox,
+    Chrome,
+    Generic,
+}
+
+/// The importer of the CSV exports of browsers and password managers
+/// The columns are found by the names in the header, the columns
+/// that are not known are kept as custom fields
+///
+/// # Fields
+/// * `0` - The flavor of the export
+///
+/// # Implements
+/// * `Importer`
+#[derive(Debug, Clone, Copy)]
+pub struct Csv(pub CsvFormat);
+
+/// The position of each known column of a CSV file
+///
+/// # Fields
+/// * `title` - The title
+/// * `url` - The URL
+/// * `username` - The username
+/// * `password` - The password
+/// * `totp` - The TOTP secret
+/// * `notes` - The notes
+/// * `folder` - The folder
+/// * `tags` - The tags
+/// * `created` - The creation time
+/// * `modified` - The modification time
+/// * `last_used` - When the entry was last used
+/// * `extra` - The other columns, with their name
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Columns {
+    title: Option<usize>,
+    url: Option<usize>,
+    username: Option<usize>,
+    password: Option<usize>,
+    totp: Option<usize>,
+    notes: Option<usize>,
+    folder: Option<usize>,
+    tags: Option<usize>,
+    created: Option<usize>,
+    modified: Option<usize>,
+    last_used: Option<usize>,
+    extra: Vec<(usize, String)>,
+}
+
+impl CsvFormat {
+    /// Returns the name of the format
+    ///
+    /// # Returns
+    /// The name
+    pub fn name(&self) -> &'static str {
+        match self {
+            CsvFormat::LastPass => "LastPass CSV",
+            CsvFormat::OnePassword => "1Password CSV",
+            CsvFormat::Firefox => "Firefox CSV",
+            CsvFormat::Chrome => "Chrome CSV",
+            CsvFormat::Generic => "CSV",
+        }
+    }
+
+    /// Returns whether a header is of the flavor
+    ///
+    /// # Arguments
+    /// * `header` - The lowercase names of the columns
+    ///
+    /// # Returns
+    /// `true` if the header is of the flavor
+    pub fn matches(&self, header: &[String]) -> bool {
+        let has = |name: &str| header.iter().any(|column| column == name);
+        match self {
+            CsvFormat::LastPass => has("grouping") && has("extra"),
+            CsvFormat::OnePassword => has("otpauth") || has("archived"),
+            CsvFormat::Firefox => has("formactionorigin") || has("timepasswordchanged"),
+            CsvFormat::Chrome => {
+                ["name", "url", "username", "password"]
+                    .iter()
+                    .all(|name| has(name))
+                    && header.iter().all(|column| {
+                        ["name", "url", "username", "password", "note"].contains(&column.as_str())
+                    })
+            }
+            CsvFormat::Generic => {
+                find(header, PASSWORD_COLUMNS).is_some()
+                    && (find(header, TITLE_COLUMNS).is_some()
+                        || find(header, URL_COLUMNS).is_some())
+            }
+        }
+    }
+}
+
+impl Columns {
+    /// Finds the known columns in a header
+    ///
+    /// # Arguments
+    /// * `header` - The lowercase names of the columns
+    /// * `names` - The names of the columns as written in the file
+    ///
+    /// # Returns
+    /// The columns
+    fn new(header: &[String], names: &[String]) -> Self {
+        let mut columns = Columns {
+            title: find(header, TITLE_COLUMNS),
+            url: find(header, URL_COLUMNS),
+            username: find(header, USERNAME_COLUMNS),
+            password: find(header, PASSWORD_COLUMNS),
+            totp: find(header, TOTP_COLUMNS),
+            notes: find(header, NOTES_COLUMNS),
+            folder: find(header, FOLDER_COLUMNS),
+            tags: find(header, TAGS_COLUMNS),
+            created: find(header, CREATED_COLUMNS),
+            modified: find(header, MODIFIED_COLUMNS),
+            last_used: find(header, LAST_USED_COLUMNS),
+            extra: vec![],
+        };
+        let known = [
+            columns.title,
+            columns.url,
+            columns.username,
+            columns.password,
+            columns.totp,
+            columns.notes,
+            columns.folder,
+            columns.tags,
+            columns.created,
+            columns.modified,
+            columns.last_used,
+        ];
+        for (index, name) in header.iter().enumerate() {
+            if !known.contains(&Some(index)) && !IGNORED_COLUMNS.contains(&name.as_str()) {
+                columns.extra.push((index, names[index].trim().to_string()));
+            }
+        }
+        columns
+    }
+}
+
+/// Returns the first column with one of the names
+///
+/// # Arguments
+/// * `header` - The lowercase names of the columns
+/// * `names` - The names, the preferred one first
+///
+/// # Returns
+/// The index of the column or None
+fn find(header: &[String], names: &[&str]) -> Option<usize> {
+    names
+        .iter()
+        .find_map(|name| header.iter().position(|column| column == name))
+}
+
+/// Reads the header of a CSV file
+///
+/// # Arguments
+/// * `data` - The content of the file
+///
+/// # Returns
+/// The lowercase names of the columns, or None if the file is not CSV text
+fn read_header(data: &[u8]) -> Option<Vec<String>> {
+    let text = str::from_utf8(data).ok()?;
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    // the header is the first line, a quoted name never contains a line break
+    let line = text.lines().next()?;
+    let header = csv::parse(line).ok()?.into_iter().next()?;
+    Some(
+        header
+            .iter()
+            .map(|name| name.trim().to_lowercase())
+            .collect(),
+    )
+}
+
+impl Importer for Csv {
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        read_header(data).is_some_and(|header| self.0.matches(&header))
+    }
+
+    fn import(&self, data: &[u8], _password: &str) -> Result<Import, ImportError> {
+        let text = str::from_utf8(data)
+            .map_err(|_| ImportError::Malformed("the file is not UTF-8 text".to_string()))?;
+        let mut records = csv::parse(text)
+            .map_err(|e| ImportError::Malformed(e.to_string()))?
+            .into_iter();
+        let names = records
+            .next()
+            .ok_or_else(|| ImportError::Malformed("the file is empty".to_string()))?;
+        let header: Vec<String> = names
+            .iter()
+            .map(|name| name.trim().to_lowercase())
+            .collect();
+        if !self.0.matches(&header) {
+            return Err(ImportError::Malformed(format!(
+                "the columns are not the ones of a {} file",
+                self.0.name()
+            )));
+        }
+        let columns = Columns::new(&header, &names);
+
+        let mut import = Import::default();
+        for record in records {
+            if record.iter().all(|field| field.trim().is_empty()) {
+                continue;
+            }
+            import.push(self.read_entry(&columns, &record));
+        }
+        Ok(import)
+    }
+}
+
+impl Csv {
+    /// Turns a record of the file into an entry
+    ///
+    /// # Arguments
+    /// * `columns` - The columns of the file
+    /// * `record` - The fields of the record, missing ones are empty
+    ///
+    /// # Returns
+    /// The entry
+    fn read_entry(&self, columns: &Columns, record: &[String]) -> Entry {
+        let get = |column: Option<usize>| -> &str {
+            column
+                .and_then(|index| record.get(index))
+                .map(String::as_str)
+                .unwrap_or("")
+        };
+
+        let mut entry = Entry {
+            domain: get(columns.title).trim().to_string(),
+            username: get(columns.username).to_string(),
+            password: get(columns.password).into(),
+            url: get(columns.url).trim().to_string(),
+            notes: get(columns.notes).into(),
+            created: parse_time(get(columns.created)),
+            modified: parse_time(get(columns.modified)),
+            last_used: parse_time(get(columns.last_used)),
+            ..Default::default()
+        };
+        let mut folder = get(columns.folder).to_string();
+        if self.0 == CsvFormat::LastPass {
+            folder = folder.replace('\\', "/");
+            if entry.url == LASTPASS_NOTE_URL {
+                entry.url.clear();
+                entry.kind = EntryKind::Note;
+            }
+        }
+        entry.folder = Entry::normalize_folder(&folder);
+        entry.tags = get(columns.tags)
+            .split([',', ';'])
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect();
+        if entry.modified == 0 {
+            entry.modified = entry.created;
+        }
+        if entry.domain.is_empty() {
+            entry.domain = url_host(&entry.url);
+        }
+        set_totp(&mut entry, "TOTP", get(columns.totp));
+        for (index, name) in columns.extra.iter() {
+            add_field(&mut entry, name, get(Some(*index)), false);
+        }
+        if entry.password.is_empty() && !entry.notes.is_empty() {
+            entry.kind = EntryKind::Note;
+        }
+        entry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_import_lastpass() {
+        let data = "url,username,password,totp,extra,name,grouping,fav\r\n\
+                    https://example.com/login,krab,\"pa,ss\"\"word\",JBSWY3DPEHPK3PXP,\"line 1\nline 2\",Example,Work\\Clients,0\r\n\
+                    http://sn,,,,secret note,Note,,1\r\n\
+                    https://empty.com,krab,,,,,,0\r\n";
+        let import = Csv(CsvFormat::LastPass)
+            .import(data.as_bytes(), "")
+            .unwrap();
+
+        assert_eq!(import.entries.len(), 2);
+        let login = &import.entries[0];
+        assert_eq!(login.domain, "Example");
+        assert_eq!(login.url, "https://example.com/login");
+        assert_eq!(login.username, "krab");
+        assert_eq!(login.password.expose_secret(), "pa,ss\"word");
+        assert_eq!(login.totp.expose_secret(), "JBSWY3DPEHPK3PXP");
+        assert_eq!(login.notes.expose_secret(), "line 1\nline 2");
+        assert_eq!(login.folder, "Work/Clients");
+        assert!(login.fields.is_empty());
+        let note = &import.entries[1];
+        assert_eq!(note.kind, EntryKind::Note);
+        assert_eq!(note.notes.expose_secret(), "secret note");
+        assert!(note.url.is_empty());
+        assert_eq!(import.skipped.len(), 1);
+        assert_eq!(import.skipped[0].name, "empty.com (krab)");
+    }
+
+    #[test]
+    fn test_csv_import_browsers_and_generic() {
+        let firefox = "\"url\",\"username\",\"password\",\"httpRealm\",\"formActionOrigin\",\"guid\",\"timeCreated\",\"timeLastUsed\",\"timePasswordChanged\"\n\
+                       \"https://example.com\",\"krab\",\"password\",,\"https://example.com\",\"{1}\",\"1700000000000\",\"1700000128000\",\"1700000064000\"\n";
+        let chrome = "name,url,username,password,note\n\
+                      example.com,https://example.com/,krab,password,\n";
+        let onepassword = "Title,Url,Username,Password,OTPAuth,Favorite,Archived,Tags,Notes\n\
+                           Example,https://example.com,krab,password,otpauth://totp/Example?secret=JBSWY3DPEHPK3PXP,false,false,\"work,mail\",\n";
+        let generic = "Website,Login,Password,PIN\n\
+                       https://bank.com,krab,password,1234\n";
+
+        let firefox = Csv(CsvFormat::Firefox)
+            .import(firefox.as_bytes(), "")
+            .unwrap();
+        let chrome = Csv(CsvFormat::Chrome)
+            .import(chrome.as_bytes(), "")
+            .unwrap();
+        let onepassword = Csv(CsvFormat::OnePassword)
+            .import(onepassword.as_bytes(), "")
+            .unwrap();
+        let generic = Csv(CsvFormat::Generic)
+            .import(generic.as_bytes(), "")
+            .unwrap();
+
+        let entry = &firefox.entries[0];
+        assert_eq!(entry.domain, "example.com");
+        assert_eq!(entry.created, 1_700_000_000);
+        assert_eq!(entry.modified, 1_700_000_064);
+        assert_eq!(entry.last_used, 1_700_000_128);
+        assert!(entry.fields.is_empty());
+        assert_eq!(chrome.entries[0].domain, "example.com");
+        assert_eq!(chrome.entries[0].url, "https://example.com/");
+        let entry = &onepassword.entries[0];
+        assert_eq!(entry.domain, "Example");
+        assert!(entry.totp.expose_secret().starts_with("otpauth://"));
+        assert_eq!(entry.tags, vec!["work", "mail"]);
+        let entry = &generic.entries[0];
+        assert_eq!(entry.domain, "bank.com");
+        assert_eq!(entry.username, "krab");
+        assert_eq!(entry.fields[0].name, "PIN");
+        assert_eq!(entry.fields[0].value.expose_secret(), "1234");
+        assert_eq!(
+            Csv(CsvFormat::Chrome).import(b"a,b\n\"c", ""),
+            Err(ImportError::Malformed(
+                "The quoted field on line 2 is not closed".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,1542 @@
+use std::{collections::HashMap, io::Read, str};
+
+use aes::{
+    cipher::{
+        block_padding::Pkcs7, generic_array::GenericArray, BlockDecryptMut, BlockEncrypt, KeyInit,
+        KeyIvInit, StreamCipher,
+    },
+    Aes256,
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20::ChaCha20;
+use flate2::read::GzDecoder;
+use hmac::{Hmac, Mac};
+use roxmltree::{Document, Node, NodeId};
+use salsa20::Salsa20;
+use sha2::{Digest, Sha256, Sha512};
+use zeroize::Zeroizing;
+
+use crate::{
+    entry::{CustomField, Entry, PreviousPassword, MAX_PASSWORD_HISTORY},
+    import::{url_host, Import, ImportError, Importer},
+    kind::EntryKind,
+    secret::SecretString,
+    totp::Totp,
+};
+
+/// The file extension of a KeePass database
+pub const KDBX_EXTENSION: &str = "kdbx";
+
+const SIGNATURE_1: u32 = 0x9AA2_D903;
+const SIGNATURE_2: u32 = 0xB54B_FB67;
+const MAJOR_VERSION: u32 = 4;
+/// Signatures and version
+const PREFIX_LENGTH: usize = 12;
+/// Field id and size of the header fields
+const FIELD_HEADER_LENGTH: usize = 5;
+/// HMAC and size of the blocks
+const BLOCK_HEADER_LENGTH: usize = 36;
+const HASH_LENGTH: usize = 32;
+
+const FIELD_END: u8 = 0;
+const FIELD_CIPHER: u8 = 2;
+const FIELD_COMPRESSION: u8 = 3;
+const FIELD_MASTER_SEED: u8 = 4;
+const FIELD_IV: u8 = 7;
+const FIELD_KDF_PARAMETERS: u8 = 11;
+
+const INNER_FIELD_END: u8 = 0;
+const INNER_FIELD_STREAM_ID: u8 = 1;
+const INNER_FIELD_STREAM_KEY: u8 = 2;
+
+const CIPHER_AES256: [u8; 16] = [
+    0x31, 0xC1, 0xF2, 0xE6, 0xBF, 0x71, 0x43, 0x50, 0xBE, 0x58, 0x05, 0x21, 0x6A, 0xFC, 0x5A, 0xFF,
+];
+const CIPHER_CHACHA20: [u8; 16] = [
+    0xD6, 0x03, 0x8A, 0x2B, 0x8B, 0x6F, 0x4C, 0xB5, 0xA5, 0x24, 0x33, 0x9A, 0x31, 0xDB, 0xB5, 0x9A,
+];
+const KDF_AES: [u8; 16] = [
+    0xC9, 0xD9, 0xF3, 0x9A, 0x62, 0x8A, 0x44, 0x60, 0xBF, 0x74, 0x0D, 0x08, 0xC1, 0x8A, 0x4F, 0xEA,
+];
+const KDF_ARGON2D: [u8; 16] = [
+    0xEF, 0x63, 0x6D, 0xDF, 0x8C, 0x29, 0x44, 0x4B, 0x91, 0xF7, 0xA9, 0xA4, 0x03, 0xE3, 0x0A, 0x0C,
+];
+const KDF_ARGON2ID: [u8; 16] = [
+    0x9E, 0x29, 0x8B, 0x19, 0x56, 0xDB, 0x47, 0x73, 0xB2, 0x3D, 0xFC, 0x3E, 0xC6, 0xF0, 0xA1, 0xE6,
+];
+/// The largest Argon2 memory cost accepted, in KiB
+const MAX_ARGON2_MEMORY: u64 = 1024 * 1024;
+/// The largest number of Argon2 iterations accepted
+const MAX_ARGON2_ITERATIONS: u64 = 256;
+/// The largest Argon2 parallelism accepted
+const MAX_ARGON2_PARALLELISM: u64 = 64;
+/// The largest number of AES-KDF rounds accepted, a few seconds on current hardware
+const MAX_AES_ROUNDS: u64 = 100_000_000;
+/// The largest content that is read, before and after decompressing it
+const MAX_CONTENT_SIZE: u64 = 256 * 1024 * 1024;
+
+const STREAM_SALSA20: u32 = 2;
+const STREAM_CHACHA20: u32 = 3;
+const SALSA20_NONCE: [u8; 8] = [0xE8, 0x30, 0x09, 0x4B, 0x97, 0x20, 0x5D, 0x2A];
+
+/// Seconds between 0001-01-01 and the Unix epoch, the times of KDBX 4 count from the former
+const EPOCH_OFFSET: i64 = 62_135_596_800;
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The cipher of the payload of a database
+///
+/// # Variants
+/// * `Aes256` - AES-256 in CBC mode
+/// * `ChaCha20` - ChaCha20
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PayloadCipher {
+    Aes256,
+    ChaCha20,
+}
+
+/// The key derivation function of a database
+///
+/// # Variants
+/// * `Aes` - Rounds of AES-256 with a seed as the key
+/// * `Argon2` - Argon2d or Argon2id
+#[derive(Debug, Clone, PartialEq)]
+enum Kdf {
+    Aes {
+        seed: Vec<u8>,
+        rounds: u64,
+    },
+    Argon2 {
+        algorithm: Algorithm,
+        version: Version,
+        salt: Vec<u8>,
+        memory: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+}
+
+/// The outer header of a database
+///
+/// # Fields
+/// * `cipher` - The cipher of the payload
+/// * `compressed` - Whether the payload is compressed with gzip
+/// * `master_seed` - The seed mixed into the keys
+/// * `iv` - The IV of the payload
+/// * `kdf` - The key derivation function
+/// * `length` - The length of the header in bytes
+#[derive(Debug, Clone, PartialEq)]
+struct Header {
+    cipher: PayloadCipher,
+    compressed: bool,
+    master_seed: Vec<u8>,
+    iv: Vec<u8>,
+    kdf: Kdf,
+    length: usize,
+}
+
+/// The cipher that protects the values marked as protected in the XML
+/// Its key stream runs through the protected values in the order of the document
+///
+/// # Variants
+/// * `ChaCha20` - ChaCha20 keyed with the SHA-512 of the stream key
+/// * `Salsa20` - Salsa20 keyed with the SHA-256 of the stream key
+enum InnerStream {
+    ChaCha20(ChaCha20),
+    Salsa20(Salsa20),
+}
+
+/// Reads the entries of a KeePass KDBX 4 database protected with a password
+/// Groups become folders, the root group is left out. The title, username,
+/// password, URL and notes map to the entry, TOTP attributes of KeePassXC
+/// and KeePass to its TOTP secret and the other strings to custom fields,
+/// protected ones hidden. Entries in the recycle bin and attachments are skipped
+///
+/// # Arguments
+/// * `data` - The content of the database file
+/// * `password` - The password of the database
+///
+/// # Returns
+/// The entries and the skipped items, or an error
+pub fn import(data: &[u8], password: &str) -> Result<Import, ImportError> {
+    let header = Header::read(data)?;
+    let stored_hash = data
+        .get(header.length..header.length + HASH_LENGTH)
+        .ok_or_else(|| malformed("the header is truncated"))?;
+    if Sha256::digest(&data[..header.length]).as_slice() != stored_hash {
+        return Err(malformed("the header is damaged"));
+    }
+
+    let transformed = header.kdf.transform(&composite_key(password))?;
+    let (cipher_key, hmac_key) = derive_keys(&header.master_seed, &transformed[..]);
+    let stored_mac = data
+        .get(header.length + HASH_LENGTH..header.length + 2 * HASH_LENGTH)
+        .ok_or_else(|| malformed("the header is truncated"))?;
+    let mut mac = block_mac(&hmac_key, u64::MAX);
+    mac.update(&data[..header.length]);
+    mac.verify_slice(stored_mac)
+        .map_err(|_| ImportError::WrongPassword)?;
+
+    let ciphertext = read_blocks(
+        &data[header.length + 2 * HASH_LENGTH..],
+        &hmac_key,
+        MAX_CONTENT_SIZE,
+    )?;
+    let mut payload = decrypt(header.cipher, &cipher_key, &header.iv, ciphertext)?;
+    if header.compressed {
+        payload = decompress(&payload, MAX_CONTENT_SIZE)?;
+    }
+
+    let (stream, xml_offset) = read_inner_header(&payload)?;
+    let xml = str::from_utf8(&payload[xml_offset..])
+        .map_err(|_| malformed("the XML is not valid UTF-8"))?;
+    read_xml(xml, stream)
+}
+
+/// The importer of KeePass KDBX 4 databases
+///
+/// # Implements
+/// * `Importer`
+#[derive(Debug, Clone, Copy)]
+pub struct KeePass;
+
+impl Importer for KeePass {
+    fn name(&self) -> &'static str {
+        "KeePass (KDBX 4)"
+    }
+
+    fn needs_password(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        read_u32(data, 0) == Some(SIGNATURE_1) && read_u32(data, 4) == Some(SIGNATURE_2)
+    }
+
+    fn import(&self, data: &[u8], password: &str) -> Result<Import, ImportError> {
+        import(data, password)
+    }
+}
+
+impl Header {
+    /// Reads the outer header of a database
+    ///
+    /// # Arguments
+    /// * `data` - The content of the database file
+    ///
+    /// # Returns
+    /// The header or an error
+    fn read(data: &[u8]) -> Result<Header, ImportError> {
+        if read_u32(data, 0) != Some(SIGNATURE_1) || read_u32(data, 4) != Some(SIGNATURE_2) {
+            return Err(malformed("it is not a KeePass database"));
+        }
+        let major = read_u32(data, 8).unwrap_or_default() >> 16;
+        if major < MAJOR_VERSION {
+            return Err(ImportError::Unsupported(
+                "KDBX 3 and older databases".to_string(),
+            ));
+        }
+        if major > MAJOR_VERSION {
+            return Err(ImportError::Unsupported(format!("KDBX {}", major)));
+        }
+
+        let mut cipher = None;
+        let mut compressed = false;
+        let mut master_seed = None;
+        let mut iv = None;
+        let mut kdf = None;
+        let mut offset = PREFIX_LENGTH;
+        loop {
+            let (id, value, length) =
+                read_field(data, offset).ok_or_else(|| malformed("the header is truncated"))?;
+            offset += length;
+            match id {
+                FIELD_END => break,
+                FIELD_CIPHER if value == CIPHER_AES256 => cipher = Some(PayloadCipher::Aes256),
+                FIELD_CIPHER if value == CIPHER_CHACHA20 => cipher = Some(PayloadCipher::ChaCha20),
+                FIELD_CIPHER => {
+                    return Err(ImportError::Unsupported(
+                        "The cipher of the database".to_string(),
+                    ))
+                }
+                FIELD_COMPRESSION => compressed = read_u32(value, 0) == Some(1),
+                FIELD_MASTER_SEED if value.len() == HASH_LENGTH => {
+                    master_seed = Some(value.to_vec())
+                }
+                FIELD_IV => iv = Some(value.to_vec()),
+                FIELD_KDF_PARAMETERS => kdf = Some(Kdf::read(value)?),
+                _ => {}
+            }
+        }
+
+        Ok(Header {
+            cipher: cipher.ok_or_else(|| malformed("the cipher is missing"))?,
+            compressed,
+            master_seed: master_seed.ok_or_else(|| malformed("the master seed is missing"))?,
+            iv: iv.ok_or_else(|| malformed("the IV is missing"))?,
+            kdf: kdf.ok_or_else(|| malformed("the key derivation parameters are missing"))?,
+            length: offset,
+        })
+    }
+}
+
+impl Kdf {
+    /// Reads the key derivation parameters, stored as a variant dictionary
+    ///
+    /// # Arguments
+    /// * `data` - The variant dictionary
+    ///
+    /// # Returns
+    /// The key derivation function or an error
+    fn read(data: &[u8]) -> Result<Kdf, ImportError> {
+        let parameters = read_variant_dictionary(data)
+            .ok_or_else(|| malformed("the key derivation parameters are damaged"))?;
+        let bytes = |name: &str| parameters.get(name).map(Vec::as_slice);
+        let number = |name: &str| match bytes(name) {
+            Some(value) if value.len() == 4 => read_u32(value, 0).map(u64::from),
+            Some(value) if value.len() == 8 => read_u64(value, 0),
+            _ => None,
+        };
+        let invalid = || malformed("the key derivation parameters are incomplete");
+
+        let uuid = bytes("$UUID").ok_or_else(invalid)?;
+        if uuid == KDF_AES {
+            let rounds = number("R").ok_or_else(invalid)?;
+            if rounds > MAX_AES_ROUNDS {
+                return Err(ImportError::Unsupported(
+                    "More than 100 million AES-KDF rounds".to_string(),
+                ));
+            }
+            return Ok(Kdf::Aes {
+                seed: bytes("S")
+                    .filter(|seed| seed.len() == HASH_LENGTH)
+                    .ok_or_else(invalid)?
+                    .to_vec(),
+                rounds,
+            });
+        }
+        let algorithm = match uuid {
+            uuid if uuid == KDF_ARGON2D => Algorithm::Argon2d,
+            uuid if uuid == KDF_ARGON2ID => Algorithm::Argon2id,
+            _ => {
+                return Err(ImportError::Unsupported(
+                    "The key derivation function of the database".to_string(),
+                ))
+            }
+        };
+        let version = match number("V") {
+            Some(0x10) => Version::V0x10,
+            Some(0x13) => Version::V0x13,
+            _ => return Err(invalid()),
+        };
+        let memory = number("M").ok_or_else(invalid)? / 1024;
+        if memory > MAX_ARGON2_MEMORY {
+            return Err(ImportError::Unsupported(
+                "An Argon2 memory cost above 1 GiB".to_string(),
+            ));
+        }
+        let iterations = number("I").ok_or_else(invalid)?;
+        if iterations > MAX_ARGON2_ITERATIONS {
+            return Err(ImportError::Unsupported(
+                "More than 256 Argon2 iterations".to_string(),
+            ));
+        }
+        let parallelism = number("P").ok_or_else(invalid)?;
+        if parallelism > MAX_ARGON2_PARALLELISM {
+            return Err(ImportError::Unsupported(
+                "An Argon2 parallelism above 64".to_string(),
+            ));
+        }
+        Ok(Kdf::Argon2 {
+            algorithm,
+            version,
+            salt: bytes("S").ok_or_else(invalid)?.to_vec(),
+            memory: memory as u32,
+            iterations: iterations as u32,
+            parallelism: parallelism as u32,
+        })
+    }
+
+    /// Transforms the composite key of a database
+    ///
+    /// # Arguments
+    /// * `composite` - The composite key
+    ///
+    /// # Returns
+    /// The transformed key or an error if the parameters are out of range
+    fn transform(&self, composite: &[u8]) -> Result<Zeroizing<Vec<u8>>, ImportError> {
+        match self {
+            Kdf::Aes { seed, rounds } => {
+                let cipher = Aes256::new_from_slice(seed)
+                    .map_err(|_| malformed("the AES-KDF seed is invalid"))?;
+                let mut key = Zeroizing::new(composite.to_vec());
+                for _ in 0..*rounds {
+                    for block in key.chunks_exact_mut(16) {
+                        cipher.encrypt_block(GenericArray::from_mut_slice(block));
+                    }
+                }
+                Ok(Zeroizing::new(Sha256::digest(&key[..]).to_vec()))
+            }
+            Kdf::Argon2 {
+                algorithm,
+                version,
+                salt,
+                memory,
+                iterations,
+                parallelism,
+            } => {
+                let unsupported = || {
+                    ImportError::Unsupported("The Argon2 parameters of the database".to_string())
+                };
+                let params = Params::new(*memory, *iterations, *parallelism, Some(HASH_LENGTH))
+                    .map_err(|_| unsupported())?;
+                let mut key = Zeroizing::new(vec![0u8; HASH_LENGTH]);
+                Argon2::new(*algorithm, *version, params)
+                    .hash_password_into(composite, salt, &mut key)
+                    .map_err(|_| unsupported())?;
+                Ok(key)
+            }
+        }
+    }
+}
+
+impl InnerStream {
+    /// Creates the inner stream cipher
+    ///
+    /// # Arguments
+    /// * `id` - The id of the cipher from the inner header
+    /// * `key` - The stream key from the inner header
+    ///
+    /// # Returns
+    /// The inner stream or an error if the cipher is not supported
+    fn new(id: u32, key: &[u8]) -> Result<InnerStream, ImportError> {
+        match id {
+            STREAM_CHACHA20 => {
+                let hash = Zeroizing::new(Sha512::digest(key).to_vec());
+                ChaCha20::new_from_slices(&hash[..32], &hash[32..44])
+                    .map(InnerStream::ChaCha20)
+                    .map_err(|_| malformed("the inner stream key is invalid"))
+            }
+            STREAM_SALSA20 => Salsa20::new_from_slices(&Sha256::digest(key), &SALSA20_NONCE)
+                .map(InnerStream::Salsa20)
+                .map_err(|_| malformed("the inner stream key is invalid")),
+            _ => Err(ImportError::Unsupported(
+                "The inner stream cipher of the database".to_string(),
+            )),
+        }
+    }
+
+    /// Decrypts the next protected value
+    ///
+    /// # Arguments
+    /// * `value` - The base64 encoded value
+    ///
+    /// # Returns
+    /// The value or an error if it is damaged
+    fn unprotect(&mut self, value: &str) -> Result<SecretString, ImportError> {
+        let mut bytes = Zeroizing::new(
+            STANDARD
+                .decode(value.trim())
+                .map_err(|_| malformed("a protected value is damaged"))?,
+        );
+        match self {
+            InnerStream::ChaCha20(cipher) => cipher.apply_keystream(&mut bytes),
+            InnerStream::Salsa20(cipher) => cipher.apply_keystream(&mut bytes),
+        }
+        String::from_utf8(bytes.to_vec())
+            .map(SecretString::new)
+            .map_err(|_| malformed("a protected value is damaged"))
+    }
+}
+
+/// Returns the composite key of a password
+///
+/// # Arguments
+/// * `password` - The password
+///
+/// # Returns
+/// The composite key
+fn composite_key(password: &str) -> Zeroizing<Vec<u8>> {
+    let password_hash = Zeroizing::new(Sha256::digest(password.as_bytes()).to_vec());
+    Zeroizing::new(Sha256::digest(&password_hash[..]).to_vec())
+}
+
+/// Derives the key of the payload and the base key of the HMACs
+///
+/// # Arguments
+/// * `master_seed` - The master seed
+/// * `transformed` - The transformed key
+///
+/// # Returns
+/// The key of the payload and the base key of the HMACs
+fn derive_keys(master_seed: &[u8], transformed: &[u8]) -> (Zeroizing<Vec<u8>>, Zeroizing<Vec<u8>>) {
+    let mut cipher_key = Sha256::new();
+    cipher_key.update(master_seed);
+    cipher_key.update(transformed);
+    let mut hmac_key = Sha512::new();
+    hmac_key.update(master_seed);
+    hmac_key.update(transformed);
+    hmac_key.update([1u8]);
+    (
+        Zeroizing::new(cipher_key.finalize().to_vec()),
+        Zeroizing::new(hmac_key.finalize().to_vec()),
+    )
+}
+
+/// Creates the HMAC of a block, the header uses the index `u64::MAX`
+///
+/// # Arguments
+/// * `hmac_key` - The base key of the HMACs
+/// * `index` - The index of the block
+///
+/// # Returns
+/// The HMAC, keyed for the block
+fn block_mac(hmac_key: &[u8], index: u64) -> HmacSha256 {
+    let mut key = Sha512::new();
+    key.update(index.to_le_bytes());
+    key.update(hmac_key);
+    let key = Zeroizing::new(key.finalize().to_vec());
+    <HmacSha256 as Mac>::new_from_slice(&key).expect("HMAC accepts keys of any length")
+}
+
+/// Reads the blocks of the payload and verifies their HMACs
+///
+/// # Arguments
+/// * `data` - The blocks
+/// * `hmac_key` - The base key of the HMACs
+/// * `limit` - The largest payload that is read
+///
+/// # Returns
+/// The encrypted payload or an error if a block is damaged
+/// or the payload is larger than the limit
+fn read_blocks(data: &[u8], hmac_key: &[u8], limit: u64) -> Result<Vec<u8>, ImportError> {
+    let mut payload = vec![];
+    let mut offset = 0;
+    for index in 0.. {
+        let size = read_u32(data, offset + HASH_LENGTH)
+            .ok_or_else(|| malformed("the content is truncated"))?;
+        if payload.len() as u64 + size as u64 > limit {
+            return Err(malformed("the content is too large"));
+        }
+        let start = offset + BLOCK_HEADER_LENGTH;
+        let block = data
+            .get(start..start + size as usize)
+            .ok_or_else(|| malformed("the content is truncated"))?;
+
+        let mut mac = block_mac(hmac_key, index);
+        mac.update(&index.to_le_bytes());
+        mac.update(&size.to_le_bytes());
+        mac.update(block);
+        mac.verify_slice(&data[offset..offset + HASH_LENGTH])
+            .map_err(|_| malformed("a block of the content is damaged"))?;
+        if block.is_empty() {
+            break;
+        }
+        payload.extend_from_slice(block);
+        offset = start + block.len();
+    }
+    Ok(payload)
+}
+
+/// Decrypts the payload
+///
+/// # Arguments
+/// * `cipher` - The cipher of the payload
+/// * `key` - The key of the payload
+/// * `iv` - The IV
+/// * `ciphertext` - The encrypted payload
+///
+/// # Returns
+/// The payload or an error
+fn decrypt(
+    cipher: PayloadCipher,
+    key: &[u8],
+    iv: &[u8],
+    ciphertext: Vec<u8>,
+) -> Result<Zeroizing<Vec<u8>>, ImportError> {
+    let mut payload = Zeroizing::new(ciphertext);
+    match cipher {
+        PayloadCipher::Aes256 => {
+            let length = cbc::Decryptor::<Aes256>::new_from_slices(key, iv)
+                .map_err(|_| malformed("the IV is invalid"))?
+                .decrypt_padded_mut::<Pkcs7>(&mut payload)
+                .map_err(|_| malformed("the content cannot be decrypted"))?
+                .len();
+            payload.truncate(length);
+        }
+        PayloadCipher::ChaCha20 => {
+            ChaCha20::new_from_slices(key, iv)
+                .map_err(|_| malformed("the IV is invalid"))?
+                .apply_keystream(&mut payload);
+        }
+    }
+    Ok(payload)
+}
+
+/// Decompresses the gzip compressed payload
+///
+/// # Arguments
+/// * `payload` - The compressed payload
+/// * `limit` - The largest decompressed size accepted
+///
+/// # Returns
+/// The decompressed payload or an error if it is damaged or larger than the limit
+fn decompress(payload: &[u8], limit: u64) -> Result<Zeroizing<Vec<u8>>, ImportError> {
+    let mut decompressed = Zeroizing::new(vec![]);
+    GzDecoder::new(payload)
+        .take(limit + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|_| malformed("the content cannot be decompressed"))?;
+    if decompressed.len() as u64 > limit {
+        return Err(ImportError::Unsupported(format!(
+            "Databases larger than {} MiB once decompressed",
+            limit / (1024 * 1024)
+        )));
+    }
+    Ok(decompressed)
+}
+
+/// Reads the inner header in front of the XML
+///
+/// # Arguments
+/// * `payload` - The decrypted payload
+///
+/// # Returns
+/// The inner stream and the offset of the XML, or an error
+fn read_inner_header(payload: &[u8]) -> Result<(InnerStream, usize), ImportError> {
+    let mut stream_id = None;
+    let mut stream_key = None;
+    let mut offset = 0;
+    loop {
+        let (id, value, length) = read_field(payload, offset)
+            .ok_or_else(|| malformed("the inner header is truncated"))?;
+        offset += length;
+        match id {
+            INNER_FIELD_END => break,
+            INNER_FIELD_STREAM_ID => stream_id = read_u32(value, 0),
+            INNER_FIELD_STREAM_KEY => stream_key = Some(Zeroizing::new(value.to_vec())),
+            _ => {}
+        }
+    }
+    let stream = match (stream_id, stream_key) {
+        (Some(id), Some(key)) => InnerStream::new(id, &key)?,
+        _ => return Err(malformed("the inner stream is missing")),
+    };
+    Ok((stream, offset))
+}
+
+/// Reads the groups and entries of the XML
+///
+/// # Arguments
+/// * `xml` - The XML
+/// * `stream` - The inner stream
+///
+/// # Returns
+/// The entries and the skipped items, or an error
+fn read_xml(xml: &str, mut stream: InnerStream) -> Result<Import, ImportError> {
+    let document = Document::parse(xml).map_err(|_| malformed("the XML is damaged"))?;
+    let mut protected = HashMap::new();
+    for node in document.descendants().filter(|node| {
+        node.has_tag_name("Value")
+            && node
+                .attribute("Protected")
+                .is_some_and(|p| p.eq_ignore_ascii_case("true"))
+    }) {
+        protected.insert(node.id(), stream.unprotect(node.text().unwrap_or(""))?);
+    }
+
+    let root = document.root_element();
+    let recycle_bin = child(root, "Meta")
+        .filter(|meta| child_text(*meta, "RecycleBinEnabled").eq_ignore_ascii_case("true"))
+        .map(|meta| child_text(meta, "RecycleBinUUID").to_string());
+    let group = child(root, "Root")
+        .and_then(|root| child(root, "Group"))
+        .ok_or_else(|| malformed("the root group is missing"))?;
+
+    let reader = XmlReader {
+        protected,
+        recycle_bin,
+    };
+    let mut import = Import::default();
+    reader.read_group(group, "", false, &mut import);
+    Ok(import)
+}
+
+/// Reads the groups and entries of the XML once the protected values are decrypted
+///
+/// # Fields
+/// * `protected` - The decrypted protected values, by their `Value` node
+/// * `recycle_bin` - The UUID of the recycle bin group, if it is enabled
+struct XmlReader {
+    protected: HashMap<NodeId, SecretString>,
+    recycle_bin: Option<String>,
+}
+
+impl XmlReader {
+    /// Reads the entries of a group and its subgroups
+    ///
+    /// # Arguments
+    /// * `group` - The group
+    /// * `folder` - The folder of the entries of the group
+    /// * `recycled` - Whether the group is in the recycle bin
+    /// * `import` - The import the entries are added to
+    fn read_group(&self, group: Node, folder: &str, recycled: bool, import: &mut Import) {
+        let recycled = recycled
+            || self
+                .recycle_bin
+                .as_deref()
+                .is_some_and(|uuid| child_text(group, "UUID") == uuid);
+        for node in group.children().filter(Node::is_element) {
+            match node.tag_name().name() {
+                "Entry" if recycled => {
+                    let strings = self.strings(node);
+                    let title = strings
+                        .iter()
+                        .find(|(key, _, _)| key == "Title")
+                        .map(|(_, title, _)| title.expose_secret().to_string())
+                        .filter(|title| !title.is_empty())
+                        .unwrap_or("Untitled".to_string());
+                    import.skip(&title, "In the recycle bin");
+                }
+                "Entry" => {
+                    let entry = self.read_entry(node, folder, import);
+                    import.push(entry);
+                }
+                "Group" => {
+                    let name = child_text(node, "Name").replace('/', "-");
+                    let subfolder = Entry::normalize_folder(&format!("{}/{}", folder, name));
+                    self.read_group(node, &subfolder, recycled, import);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Reads an entry, its attachments are recorded as skipped
+    ///
+    /// # Arguments
+    /// * `node` - The entry
+    /// * `folder` - The folder of the entry
+    /// * `import` - The import the attachments are recorded in
+    ///
+    /// # Returns
+    /// The entry, not validated yet
+    fn read_entry(&self, node: Node, folder: &str, import: &mut Import) -> Entry {
+        let mut entry = Entry {
+            folder: folder.to_string(),
+            ..Default::default()
+        };
+        let mut otp = vec![];
+        for (key, value, hidden) in self.strings(node) {
+            match key.as_str() {
+                "Title" => entry.domain = value.expose_secret().trim().to_string(),
+                "UserName" => entry.username = value.expose_secret().to_string(),
+                "Password" => entry.password = value,
+                "URL" => entry.url = value.expose_secret().trim().to_string(),
+                "Notes" => entry.notes = value,
+                _ if is_otp_attribute(&key) => otp.push((key, value)),
+                _ if value.is_empty() => {}
+                _ => entry.fields.push(CustomField {
+                    name: key,
+                    value,
+                    hidden,
+                }),
+            }
+        }
+        match totp_secret(&otp) {
+            Some(totp) if Totp::parse(totp.expose_secret()).is_ok() => entry.totp = totp,
+            // an attribute that cannot be turned into a TOTP secret is kept as it is
+            _ => entry
+                .fields
+                .extend(otp.into_iter().map(|(name, value)| CustomField {
+                    name,
+                    value,
+                    hidden: true,
+                })),
+        }
+
+        if entry.domain.is_empty() {
+            entry.domain = url_host(&entry.url);
+        }
+        if entry.password.is_empty() && !entry.notes.is_empty() {
+            entry.kind = EntryKind::Note;
+        }
+        entry.tags = child_text(node, "Tags")
+            .split([';', ','])
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect();
+        if let Some(times) = child(node, "Times") {
+            entry.created = parse_time(child_text(times, "CreationTime"));
+            entry.modified = parse_time(child_text(times, "LastModificationTime"));
+        }
+        entry.history = self.read_history(node, &entry);
+
+        for binary in children(node, "Binary") {
+            import.skip(
+                &format!("{} ({})", child_text(binary, "Key"), entry.label()),
+                "Attachments are not imported",
+            );
+        }
+        entry
+    }
+
+    /// Reads the previous passwords from the history of an entry
+    /// KeePass keeps whole copies of the entry, oldest first, so only
+    /// the copies whose password was changed afterwards are kept
+    ///
+    /// # Arguments
+    /// * `node` - The entry
+    /// * `entry` - The entry read so far
+    ///
+    /// # Returns
+    /// The previous passwords, the most recent one first
+    fn read_history(&self, node: Node, entry: &Entry) -> Vec<PreviousPassword> {
+        let mut versions: Vec<(SecretString, i64)> = child(node, "History")
+            .map(|history| {
+                children(history, "Entry")
+                    .map(|version| {
+                        let password = self
+                            .strings(version)
+                            .into_iter()
+                            .find(|(key, _, _)| key == "Password")
+                            .map(|(_, password, _)| password)
+                            .unwrap_or_default();
+                        let modified = child(version, "Times")
+                            .map(|times| parse_time(child_text(times, "LastModificationTime")))
+                            .unwrap_or_default();
+                        (password, modified)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        versions.push((entry.password.clone(), entry.modified));
+
+        let mut history: Vec<PreviousPassword> = versions
+            .windows(2)
+            .filter(|pair| !pair[0].0.is_empty() && pair[0].0 != pair[1].0)
+            .map(|pair| PreviousPassword {
+                password: pair[0].0.clone(),
+                replaced: pair[1].1,
+            })
+            .collect();
+        history.reverse();
+        history.truncate(MAX_PASSWORD_HISTORY);
+        history
+    }
+
+    /// Returns the strings of an entry
+    ///
+    /// # Arguments
+    /// * `node` - The entry
+    ///
+    /// # Returns
+    /// The key, the value and whether the value is protected of every string
+    fn strings(&self, node: Node) -> Vec<(String, SecretString, bool)> {
+        children(node, "String")
+            .map(|string| {
+                let key = child_text(string, "Key").to_string();
+                match child(string, "Value") {
+                    Some(value) => match self.protected.get(&value.id()) {
+                        Some(protected) => (key, protected.clone(), true),
+                        None => (key, SecretString::from(value.text().unwrap_or("")), false),
+                    },
+                    None => (key, SecretString::default(), false),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Returns whether a string of an entry holds a part of its TOTP secret
+///
+/// # Arguments
+/// * `key` - The key of the string
+///
+/// # Returns
+/// `true` for the attributes of KeePassXC, KeePass and the TOTP plugins
+fn is_otp_attribute(key: &str) -> bool {
+    key == "otp" || key == "TOTP Seed" || key == "TOTP Settings" || key.starts_with("TimeOtp-")
+}
+
+/// Builds the TOTP secret of an entry from its TOTP attributes
+/// KeePassXC stores an `otpauth://` URI or `key=...&step=...&size=...` in `otp`,
+/// KeePass the `TimeOtp-` attributes and older plugins `TOTP Seed` and `TOTP Settings`
+///
+/// # Arguments
+/// * `attributes` - The TOTP attributes
+///
+/// # Returns
+/// The secret as an `otpauth://totp/` URI, None if there is none
+fn totp_secret(attributes: &[(String, SecretString)]) -> Option<SecretString> {
+    let get = |name: &str| {
+        attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.expose_secret().trim())
+            .filter(|value| !value.is_empty())
+    };
+
+    if let Some(otp) = get("otp") {
+        if otp.to_ascii_lowercase().starts_with("otpauth://") {
+            return Some(SecretString::from(otp));
+        }
+        let parameters: HashMap<&str, &str> = otp
+            .split('&')
+            .filter_map(|parameter| parameter.split_once('='))
+            .collect();
+        let algorithm = match parameters.get("otpHashMode").copied() {
+            Some("Sha256") => "SHA256",
+            Some("Sha512") => "SHA512",
+            _ => "SHA1",
+        };
+        return Some(totp_uri(
+            parameters.get("key")?,
+            parameters.get("size").copied().unwrap_or("6"),
+            parameters.get("step").copied().unwrap_or("30"),
+            algorithm,
+        ));
+    }
+
+    let secret = get("TimeOtp-Secret-Base32")
+        .map(str::to_string)
+        .or_else(|| get("TimeOtp-Secret").map(|secret| encode_base32(secret.as_bytes())))
+        .or_else(|| {
+            get("TimeOtp-Secret-Hex")
+                .and_then(decode_hex)
+                .map(|secret| encode_base32(&secret))
+        })
+        .or_else(|| {
+            get("TimeOtp-Secret-Base64")
+                .and_then(|secret| STANDARD.decode(secret).ok())
+                .map(|secret| encode_base32(&Zeroizing::new(secret)))
+        });
+    if let Some(secret) = secret.map(Zeroizing::new) {
+        let algorithm = match get("TimeOtp-Algorithm") {
+            Some("HMAC-SHA-256") => "SHA256",
+            Some("HMAC-SHA-512") => "SHA512",
+            _ => "SHA1",
+        };
+        return Some(totp_uri(
+            &secret,
+            get("TimeOtp-Length").unwrap_or("6"),
+            get("TimeOtp-Period").unwrap_or("30"),
+            algorithm,
+        ));
+    }
+
+    let seed = get("TOTP Seed")?;
+    let (period, digits) = get("TOTP Settings")
+        .and_then(|settings| settings.split_once(';'))
+        .unwrap_or(("30", "6"));
+    Some(totp_uri(seed, digits, period, "SHA1"))
+}
+
+/// Builds an `otpauth://totp/` URI
+///
+/// # Arguments
+/// * `secret` - The base32 encoded secret
+/// * `digits` - The number of digits
+/// * `period` - The period in seconds
+/// * `algorithm` - The name of the hash algorithm
+///
+/// # Returns
+/// The URI
+fn totp_uri(secret: &str, digits: &str, period: &str, algorithm: &str) -> SecretString {
+    let secret: Zeroizing<String> =
+        Zeroizing::new(secret.chars().filter(char::is_ascii_alphanumeric).collect());
+    SecretString::new(format!(
+        "otpauth://totp/KeePass?secret={}&digits={}&period={}&algorithm={}",
+        secret.as_str(),
+        digits.trim(),
+        period.trim(),
+        algorithm
+    ))
+}
+
+/// Encodes bytes in base32 without padding
+///
+/// # Arguments
+/// * `bytes` - The bytes
+///
+/// # Returns
+/// The encoded bytes
+fn encode_base32(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    for chunk in bytes.chunks(5) {
+        let mut buffer = [0u8; 5];
+        buffer[..chunk.len()].copy_from_slice(chunk);
+        let bits = buffer.iter().fold(0u64, |bits, &b| (bits << 8) | b as u64);
+        let characters = (chunk.len() * 8).div_ceil(5);
+        for i in 0..characters {
+            let index = (bits >> (35 - 5 * i)) & 0x1F;
+            encoded.push(BASE32_ALPHABET[index as usize] as char);
+        }
+    }
+    encoded
+}
+
+/// Decodes a hex string
+///
+/// # Arguments
+/// * `value` - The hex string, spaces are ignored
+///
+/// # Returns
+/// The bytes or None if the string is not hex
+fn decode_hex(value: &str) -> Option<Zeroizing<Vec<u8>>> {
+    let digits: Zeroizing<Vec<u8>> = Zeroizing::new(
+        value
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<_>>()?,
+    );
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    Some(Zeroizing::new(
+        digits
+            .chunks(2)
+            .map(|pair| pair[0] << 4 | pair[1])
+            .collect(),
+    ))
+}
+
+/// Parses a time of the XML
+/// KDBX 4 stores the seconds since 0001-01-01 base64 encoded, older versions an ISO 8601 date
+///
+/// # Arguments
+/// * `value` - The time
+///
+/// # Returns
+/// The time as a Unix timestamp, `0` if it is unknown
+fn parse_time(value: &str) -> i64 {
+    let seconds = match STANDARD.decode(value.trim()) {
+        Ok(bytes) if bytes.len() == 8 => read_u64(&bytes, 0).map(|s| s as i64 - EPOCH_OFFSET),
+        _ => chrono::DateTime::parse_from_rfc3339(value.trim())
+            .ok()
+            .map(|time| time.timestamp()),
+    };
+    seconds.unwrap_or_default().max(0)
+}
+
+/// Returns the first child element with a name
+///
+/// # Arguments
+/// * `node` - The parent
+/// * `name` - The name of the child
+///
+/// # Returns
+/// The child or None
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+/// Returns the child elements with a name
+///
+/// # Arguments
+/// * `node` - The parent
+/// * `name` - The name of the children
+///
+/// # Returns
+/// The children
+fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children()
+        .filter(move |child| child.has_tag_name(name))
+}
+
+/// Returns the text of the first child element with a name
+///
+/// # Arguments
+/// * `node` - The parent
+/// * `name` - The name of the child
+///
+/// # Returns
+/// The text, empty if there is no such child
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> &'a str {
+    child(node, name)
+        .and_then(|child| child.text())
+        .unwrap_or("")
+}
+
+/// Reads a field of the outer or inner header, an id followed by a size and a value
+///
+/// # Arguments
+/// * `data` - The header
+/// * `offset` - The offset of the field
+///
+/// # Returns
+/// The id, the value and the length of the field, None if it is truncated
+fn read_field(data: &[u8], offset: usize) -> Option<(u8, &[u8], usize)> {
+    let id = *data.get(offset)?;
+    let size = read_u32(data, offset + 1)? as usize;
+    let start = offset + FIELD_HEADER_LENGTH;
+    let value = data.get(start..start.checked_add(size)?)?;
+    Some((id, value, FIELD_HEADER_LENGTH + size))
+}
+
+/// Reads a variant dictionary, a typed map KeePass uses for parameters
+/// The values are kept as bytes, their type follows from their length
+///
+/// # Arguments
+/// * `data` - The variant dictionary
+///
+/// # Returns
+/// The values by name, None if the dictionary is damaged
+fn read_variant_dictionary(data: &[u8]) -> Option<HashMap<String, Vec<u8>>> {
+    let version = u16::from_le_bytes(data.get(..2)?.try_into().ok()?);
+    if version >> 8 != 1 {
+        return None;
+    }
+    let mut values = HashMap::new();
+    let mut offset = 2;
+    loop {
+        let kind = *data.get(offset)?;
+        if kind == 0 {
+            return Some(values);
+        }
+        let name_length = read_u32(data, offset + 1)? as usize;
+        let name_start = offset + FIELD_HEADER_LENGTH;
+        let name = str::from_utf8(data.get(name_start..name_start.checked_add(name_length)?)?)
+            .ok()?
+            .to_string();
+        let value_length = read_u32(data, name_start + name_length)? as usize;
+        let value_start = name_start + name_length + 4;
+        let value = data.get(value_start..value_start.checked_add(value_length)?)?;
+        values.insert(name, value.to_vec());
+        offset = value_start + value_length;
+    }
+}
+
+/// Reads a little endian `u32`
+///
+/// # Arguments
+/// * `data` - The data
+/// * `offset` - The offset of the number
+///
+/// # Returns
+/// The number or None if the data is too short
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset.checked_add(4)?)?.try_into().ok()?,
+    ))
+}
+
+/// Reads a little endian `u64`
+///
+/// # Arguments
+/// * `data` - The data
+/// * `offset` - The offset of the number
+///
+/// # Returns
+/// The number or None if the data is too short
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        data.get(offset..offset.checked_add(8)?)?.try_into().ok()?,
+    ))
+}
+
+/// Returns the error of a damaged database
+///
+/// # Arguments
+/// * `reason` - What is damaged
+///
+/// # Returns
+/// The error
+fn malformed(reason: &str) -> ImportError {
+    ImportError::Malformed(reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aes::cipher::BlockEncryptMut;
+    use flate2::{write::GzEncoder, Compression};
+    use rand::RngCore;
+    use std::io::Write;
+
+    const XML: &str = r#"<?xml version="1.0" encoding="utf-8" standalone="yes"?>
+<KeePassFile>
+  <Meta>
+    <RecycleBinEnabled>True</RecycleBinEnabled>
+    <RecycleBinUUID>cmVjeWNsZWJpbnV1aWQxMg==</RecycleBinUUID>
+  </Meta>
+  <Root>
+    <Group>
+      <UUID>cm9vdGdyb3VwdXVpZDEyMw==</UUID>
+      <Name>Passwords</Name>
+      <Entry>
+        <UUID>ZW50cnl1dWlkMTIzNDU2Nw==</UUID>
+        <Tags>work;email</Tags>
+        <Times>
+          <CreationTime>AOjl3A4AAAA=</CreationTime>
+          <LastModificationTime>gOjl3A4AAAA=</LastModificationTime>
+        </Times>
+        <String><Key>Title</Key><Value>Mail &amp; Calendar</Value></String>
+        <String><Key>UserName</Key><Value>krab@example.com</Value></String>
+        <String><Key>Password</Key><Value Protected="True">{protected}</Value></String>
+        <String><Key>URL</Key><Value>https://mail.example.com/login</Value></String>
+        <String><Key>Notes</Key><Value>Line one
+Line two</Value></String>
+        <String><Key>PIN</Key><Value Protected="True">{protected}</Value></String>
+        <String><Key>Recovery email</Key><Value>backup@example.com</Value></String>
+        <String><Key>Empty</Key><Value/></String>
+        <String><Key>otp</Key><Value Protected="True">{protected}</Value></String>
+        <Binary><Key>codes.txt</Key><Value Ref="0"/></Binary>
+        <History>
+          <Entry>
+            <Times><LastModificationTime>AOjl3A4AAAA=</LastModificationTime></Times>
+            <String><Key>Password</Key><Value Protected="True">{protected}</Value></String>
+          </Entry>
+        </History>
+      </Entry>
+      <Group>
+        <UUID>c3ViZ3JvdXB1dWlkMTIzNA==</UUID>
+        <Name>Banking</Name>
+        <Group>
+          <UUID>c3Vic3ViZ3JvdXB1dWlkMQ==</UUID>
+          <Name>Cards/Old</Name>
+          <Entry>
+            <String><Key>Title</Key><Value>Bank</Value></String>
+            <String><Key>Password</Key><Value Protected="True">{protected}</Value></String>
+            <String><Key>TimeOtp-Secret-Hex</Key><Value Protected="True">{protected}</Value></String>
+            <String><Key>TimeOtp-Algorithm</Key><Value>HMAC-SHA-256</Value></String>
+            <String><Key>TimeOtp-Length</Key><Value>8</Value></String>
+          </Entry>
+        </Group>
+        <Entry>
+          <String><Key>Title</Key><Value>Safe combination</Value></String>
+          <String><Key>Password</Key><Value Protected="True"></Value></String>
+          <String><Key>Notes</Key><Value>12-34-56</Value></String>
+        </Entry>
+        <Entry>
+          <String><Key>Title</Key><Value>Empty</Value></String>
+          <String><Key>TOTP Seed</Key><Value>not base32!</Value></String>
+        </Entry>
+        <Entry>
+          <String><Key>URL</Key><Value>https://shop.example.com/</Value></String>
+          <String><Key>Password</Key><Value Protected="True">{protected}</Value></String>
+        </Entry>
+      </Group>
+      <Group>
+        <UUID>cmVjeWNsZWJpbnV1aWQxMg==</UUID>
+        <Name>Recycle Bin</Name>
+        <Entry>
+          <String><Key>Title</Key><Value>Deleted</Value></String>
+          <String><Key>Password</Key><Value Protected="True">{protected}</Value></String>
+        </Entry>
+      </Group>
+    </Group>
+  </Root>
+</KeePassFile>"#;
+
+    const PROTECTED: [&str; 8] = [
+        "correct horse",
+        "1234",
+        "otpauth://totp/Mail?secret=GEZDGNBVGY3TQOJQ&period=30",
+        "battery staple",
+        "s3cr3t",
+        "3132333435363738393031323334353637383930313233343536373839303132",
+        "shop-password",
+        "deleted-password",
+    ];
+
+    fn random(length: usize) -> Vec<u8> {
+        let mut bytes = vec![0u8; length];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        bytes
+    }
+
+    fn field(id: u8, value: &[u8]) -> Vec<u8> {
+        let mut field = vec![id];
+        field.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        field.extend_from_slice(value);
+        field
+    }
+
+    fn variant_dictionary(values: &[(u8, &str, Vec<u8>)]) -> Vec<u8> {
+        let mut dictionary = 0x0100u16.to_le_bytes().to_vec();
+        for (kind, name, value) in values {
+            dictionary.push(*kind);
+            dictionary.extend_from_slice(&(name.len() as u32).to_le_bytes());
+            dictionary.extend_from_slice(name.as_bytes());
+            dictionary.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            dictionary.extend_from_slice(value);
+        }
+        dictionary.push(0);
+        dictionary
+    }
+
+    fn argon2_parameters() -> Vec<u8> {
+        variant_dictionary(&[
+            (0x42, "$UUID", KDF_ARGON2ID.to_vec()),
+            (0x42, "S", random(32)),
+            (0x04, "P", 1u32.to_le_bytes().to_vec()),
+            (0x05, "M", (64u64 * 1024).to_le_bytes().to_vec()),
+            (0x05, "I", 2u64.to_le_bytes().to_vec()),
+            (0x04, "V", 0x13u32.to_le_bytes().to_vec()),
+        ])
+    }
+
+    fn aes_parameters() -> Vec<u8> {
+        variant_dictionary(&[
+            (0x42, "$UUID", KDF_AES.to_vec()),
+            (0x42, "S", random(32)),
+            (0x05, "R", 100u64.to_le_bytes().to_vec()),
+        ])
+    }
+
+    /// Writes a database like KeePass does, the protected values
+    /// replace the `{protected}` placeholders of the XML in order
+    fn write_kdbx(
+        password: &str,
+        cipher: PayloadCipher,
+        kdf_parameters: Vec<u8>,
+        stream_id: u32,
+        compressed: bool,
+    ) -> Vec<u8> {
+        let stream_key = random(64);
+        let mut stream = InnerStream::new(stream_id, &stream_key).unwrap();
+        let mut xml = XML.to_string();
+        for value in PROTECTED {
+            let mut bytes = value.as_bytes().to_vec();
+            match &mut stream {
+                InnerStream::ChaCha20(cipher) => cipher.apply_keystream(&mut bytes),
+                InnerStream::Salsa20(cipher) => cipher.apply_keystream(&mut bytes),
+            }
+            xml = xml.replacen("{protected}", &STANDARD.encode(bytes), 1);
+        }
+
+        let mut payload = field(INNER_FIELD_STREAM_ID, &stream_id.to_le_bytes());
+        payload.extend(field(INNER_FIELD_STREAM_KEY, &stream_key));
+        payload.extend(field(3, b"\x00codes"));
+        payload.extend(field(INNER_FIELD_END, &[]));
+        payload.extend_from_slice(xml.as_bytes());
+        if compressed {
+            let mut encoder = GzEncoder::new(vec![], Compression::default());
+            encoder.write_all(&payload).unwrap();
+            payload = encoder.finish().unwrap();
+        }
+
+        let master_seed = random(32);
+        let (cipher_id, iv) = match cipher {
+            PayloadCipher::Aes256 => (CIPHER_AES256, random(16)),
+            PayloadCipher::ChaCha20 => (CIPHER_CHACHA20, random(12)),
+        };
+        let mut header = SIGNATURE_1.to_le_bytes().to_vec();
+        header.extend_from_slice(&SIGNATURE_2.to_le_bytes());
+        header.extend_from_slice(&0x0004_0001u32.to_le_bytes());
+        header.extend(field(FIELD_CIPHER, &cipher_id));
+        header.extend(field(FIELD_COMPRESSION, &(compressed as u32).to_le_bytes()));
+        header.extend(field(FIELD_MASTER_SEED, &master_seed));
+        header.extend(field(FIELD_IV, &iv));
+        header.extend(field(FIELD_KDF_PARAMETERS, &kdf_parameters));
+        header.extend(field(FIELD_END, b"\r\n\r\n"));
+
+        let transformed = Kdf::read(&kdf_parameters)
+            .unwrap()
+            .transform(&composite_key(password))
+            .unwrap();
+        let (cipher_key, hmac_key) = derive_keys(&master_seed, &transformed);
+        let ciphertext = match cipher {
+            PayloadCipher::Aes256 => {
+                let length = payload.len();
+                payload.resize(length + 16 - length % 16, 0);
+                cbc::Encryptor::<Aes256>::new_from_slices(&cipher_key, &iv)
+                    .unwrap()
+                    .encrypt_padded_mut::<Pkcs7>(&mut payload, length)
+                    .unwrap()
+                    .to_vec()
+            }
+            PayloadCipher::ChaCha20 => {
+                ChaCha20::new_from_slices(&cipher_key, &iv)
+                    .unwrap()
+                    .apply_keystream(&mut payload);
+                payload
+            }
+        };
+
+        let mut data = header.clone();
+        data.extend_from_slice(&Sha256::digest(&header));
+        let mut mac = block_mac(&hmac_key, u64::MAX);
+        mac.update(&header);
+        data.extend_from_slice(&mac.finalize().into_bytes());
+        data.extend_from_slice(&write_blocks(&ciphertext, &hmac_key));
+        data
+    }
+
+    fn write_blocks(payload: &[u8], hmac_key: &[u8]) -> Vec<u8> {
+        let mut data = vec![];
+        let blocks: Vec<&[u8]> = payload.chunks(256).chain([&[][..]]).collect();
+        for (index, block) in blocks.into_iter().enumerate() {
+            let mut mac = block_mac(hmac_key, index as u64);
+            mac.update(&(index as u64).to_le_bytes());
+            mac.update(&(block.len() as u32).to_le_bytes());
+            mac.update(block);
+            data.extend_from_slice(&mac.finalize().into_bytes());
+            data.extend_from_slice(&(block.len() as u32).to_le_bytes());
+            data.extend_from_slice(block);
+        }
+        data
+    }
+
+    #[test]
+    fn test_kdbx_import() {
+        let data = write_kdbx(
+            "password",
+            PayloadCipher::Aes256,
+            argon2_parameters(),
+            STREAM_CHACHA20,
+            true,
+        );
+        let import = import(&data, "password").unwrap();
+        let entries = &import.entries;
+
+        assert_eq!(entries.len(), 4);
+        let mail = &entries[0];
+        assert_eq!(mail.domain, "Mail & Calendar");
+        assert_eq!(mail.username, "krab@example.com");
+        assert_eq!(mail.password.expose_secret(), "correct horse");
+        assert_eq!(mail.url, "https://mail.example.com/login");
+        assert_eq!(mail.notes.expose_secret(), "Line one\nLine two");
+        assert_eq!(mail.totp.expose_secret(), PROTECTED[2]);
+        assert_eq!(mail.folder, "");
+        assert_eq!(mail.tags, vec!["work", "email"]);
+        assert_eq!(mail.created, 1_700_000_000);
+        assert_eq!(mail.modified, 1_700_000_128);
+        assert_eq!(mail.history.len(), 1);
+        assert_eq!(mail.history[0].password.expose_secret(), "battery staple");
+        assert_eq!(mail.history[0].replaced, 1_700_000_128);
+        assert_eq!(
+            mail.fields
+                .iter()
+                .map(|f| (f.name.as_str(), f.value.expose_secret(), f.hidden))
+                .collect::<Vec<_>>(),
+            vec![
+                ("PIN", "1234", true),
+                ("Recovery email", "backup@example.com", false)
+            ]
+        );
+
+        let bank = &entries[1];
+        assert_eq!(bank.folder, "Banking/Cards-Old");
+        let totp = Totp::parse(bank.totp.expose_secret()).unwrap();
+        assert_eq!(totp.code(59), "46119246");
+        assert!(bank.fields.is_empty());
+
+        assert_eq!(entries[2].kind, EntryKind::Note);
+        assert_eq!(entries[2].folder, "Banking");
+        assert_eq!(entries[3].domain, "shop.example.com");
+
+        let skipped: Vec<(&str, &str)> = import
+            .skipped
+            .iter()
+            .map(|item| (item.name.as_str(), item.reason.as_str()))
+            .collect();
+        assert_eq!(
+            skipped,
+            vec![
+                (
+                    "codes.txt (Mail & Calendar (krab@example.com))",
+                    "Attachments are not imported"
+                ),
+                ("Empty", "Domain and password cannot be empty"),
+                ("Deleted", "In the recycle bin"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_kdbx_import_ciphers_and_errors() {
+        let data = write_kdbx(
+            "password",
+            PayloadCipher::ChaCha20,
+            aes_parameters(),
+            STREAM_SALSA20,
+            false,
+        );
+        let mut tampered = data.clone();
+        let last = tampered.len() - 40;
+        tampered[last] ^= 1;
+        let mut kdbx3 = data.clone();
+        kdbx3[8..12].copy_from_slice(&0x0003_0001u32.to_le_bytes());
+
+        let import = import(&data, "password").unwrap();
+        assert_eq!(import.entries.len(), 4);
+        assert_eq!(import.entries[0].password.expose_secret(), "correct horse");
+        assert_eq!(
+            super::import(&data, "wrong").unwrap_err(),
+            ImportError::WrongPassword
+        );
+        assert!(matches!(
+            super::import(&tampered, "password").unwrap_err(),
+            ImportError::Malformed(_)
+        ));
+        assert!(matches!(
+            super::import(&kdbx3, "password").unwrap_err(),
+            ImportError::Unsupported(_)
+        ));
+        assert!(matches!(
+            super::import(b"entry,password", "password").unwrap_err(),
+            ImportError::Malformed(_)
+        ));
+        assert_eq!(
+            encode_base32(b"12345678901234567890"),
+            "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"
+        );
+        assert_eq!(parse_time("2023-11-14T22:13:20Z"), 1_700_000_000);
+    }
+
+    #[test]
+    fn test_kdbx_import_limits() {
+        let aes = variant_dictionary(&[
+            (0x42, "$UUID", KDF_AES.to_vec()),
+            (0x42, "S", random(32)),
+            (0x05, "R", (MAX_AES_ROUNDS + 1).to_le_bytes().to_vec()),
+        ]);
+        let argon2 = |iterations: u64, parallelism: u32| {
+            variant_dictionary(&[
+                (0x42, "$UUID", KDF_ARGON2ID.to_vec()),
+                (0x42, "S", random(32)),
+                (0x04, "P", parallelism.to_le_bytes().to_vec()),
+                (0x05, "M", (64u64 * 1024).to_le_bytes().to_vec()),
+                (0x05, "I", iterations.to_le_bytes().to_vec()),
+                (0x04, "V", 0x13u32.to_le_bytes().to_vec()),
+            ])
+        };
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(&[0u8; 1025]).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let hmac_key = random(64);
+        let blocks = write_blocks(&[0u8; 1025], &hmac_key);
+
+        for parameters in [aes, argon2(MAX_ARGON2_ITERATIONS + 1, 1), argon2(2, 65)] {
+            assert!(matches!(
+                Kdf::read(&parameters).unwrap_err(),
+                ImportError::Unsupported(_)
+            ));
+        }
+        assert!(Kdf::read(&argon2(MAX_ARGON2_ITERATIONS, 64)).is_ok());
+        assert_eq!(decompress(&compressed, 1025).unwrap().len(), 1025);
+        assert!(matches!(
+            decompress(&compressed, 1024).unwrap_err(),
+            ImportError::Unsupported(_)
+        ));
+        assert!(matches!(
+            decompress(b"not gzip", 1024).unwrap_err(),
+            ImportError::Malformed(_)
+        ));
+        assert_eq!(read_blocks(&blocks, &hmac_key, 1025).unwrap().len(), 1025);
+        assert_eq!(
+            read_blocks(&blocks, &hmac_key, 1024).unwrap_err(),
+            malformed("the content is too large")
+        );
+    }
+}
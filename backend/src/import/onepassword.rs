@@ -0,0 +1,368 @@
+use std::{
+    cmp::Reverse,
+    io::{Cursor, Read},
+};
+
+use serde_json::Value;
+use zeroize::Zeroizing;
+use zip::ZipArchive;
+
+use crate::{
+    entry::{Entry, PreviousPassword, MAX_PASSWORD_HISTORY},
+    import::{add_field, set_totp, url_host, Import, ImportError, Importer},
+    kind::{Card, EntryKind, Identity, SshKey},
+};
+
+/// The file of a 1PUX archive with the items
+const EXPORT_DATA: &str = "export.data";
+const ZIP_SIGNATURE: &[u8] = b"PK\x03\x04";
+/// The largest `export.data` that is read, attachments are separate files
+const MAX_EXPORT_SIZE: u64 = 256 * 1024 * 1024;
+
+const CATEGORY_LOGIN: &str = "001";
+const CATEGORY_CARD: &str = "002";
+const CATEGORY_NOTE: &str = "003";
+const CATEGORY_IDENTITY: &str = "004";
+const CATEGORY_PASSWORD: &str = "005";
+const CATEGORY_DOCUMENT: &str = "006";
+const CATEGORY_SSH_KEY: &str = "114";
+
+/// The importer of 1Password 1PUX exports
+/// A 1PUX file is a zip archive, the items are read from its `export.data` JSON
+///
+/// # Implements
+/// * `Importer`
+#[derive(Debug, Clone, Copy)]
+pub struct OnePux;
+
+impl Importer for OnePux {
+    fn name(&self) -> &'static str {
+        "1Password 1PUX"
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        data.starts_with(ZIP_SIGNATURE)
+            && ZipArchive::new(Cursor::new(data))
+                .is_ok_and(|archive| archive.index_for_name(EXPORT_DATA).is_some())
+    }
+
+    fn import(&self, data: &[u8], _password: &str) -> Result<Import, ImportError> {
+        let malformed = |reason: &str| ImportError::Malformed(reason.to_string());
+        let mut archive = ZipArchive::new(Cursor::new(data))
+            .map_err(|_| malformed("the zip archive is damaged"))?;
+        let file = archive
+            .by_name(EXPORT_DATA)
+            .map_err(|_| malformed("the archive has no export.data"))?;
+        let mut json = Zeroizing::new(vec![]);
+        file.take(MAX_EXPORT_SIZE)
+            .read_to_end(&mut json)
+            .map_err(|_| malformed("export.data cannot be decompressed"))?;
+        let export: Value = serde_json::from_slice(&json)
+            .map_err(|e| ImportError::Malformed(format!("the JSON cannot be read, {}", e)))?;
+
+        let mut import = Import::default();
+        for account in array(&export["accounts"]) {
+            for vault in array(&account["vaults"]) {
+                let folder = Entry::normalize_folder(text(&vault["attrs"]["name"]));
+                for item in array(&vault["items"]) {
+                    // older exports wrap each item in an object of its own
+                    let item = item.get("item").unwrap_or(item);
+                    let title = text(&item["overview"]["title"]);
+                    match item["categoryUuid"].as_str().unwrap_or("") {
+                        CATEGORY_DOCUMENT => import.skip(title, "Documents are not imported"),
+                        category => {
+                            let mut entry = read_item(item, category);
+                            entry.folder = folder.clone();
+                            import.push(entry);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(import)
+    }
+}
+
+/// Turns an item of the export into an entry
+/// The fields of the sections that have no place in the kind of the entry become custom fields
+///
+/// # Arguments
+/// * `item` - The item
+/// * `category` - The category of the item
+///
+/// # Returns
+/// The entry
+fn read_item(item: &Value, category: &str) -> Entry {
+    let overview = &item["overview"];
+    let details = &item["details"];
+    let mut entry = Entry {
+        domain: text(&overview["title"]).trim().to_string(),
+        url: text(&overview["url"]).to_string(),
+        notes: text(&details["notesPlain"]).into(),
+        created: item["createdAt"].as_i64().unwrap_or(0).max(0),
+        modified: item["updatedAt"].as_i64().unwrap_or(0).max(0),
+        tags: array(&overview["tags"])
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect(),
+        ..Default::default()
+    };
+    if entry.domain.is_empty() {
+        entry.domain = url_host(&entry.url);
+    }
+    for url in array(&overview["urls"]) {
+        let url = text(&url["url"]);
+        if url != entry.url {
+            add_field(&mut entry, "URL", url, false);
+        }
+    }
+
+    for field in array(&details["loginFields"]) {
+        let value = text(&field["value"]);
+        match text(&field["designation"]) {
+            "username" => entry.username = value.to_string(),
+            "password" => entry.password = value.into(),
+            _ => {}
+        }
+    }
+    if let Some(password) = details["password"].as_str() {
+        entry.password = password.into();
+    }
+
+    let mut card = Card::default();
+    let mut identity = Identity::default();
+    let mut ssh_key = SshKey::default();
+    for section in array(&details["sections"]) {
+        for field in array(&section["fields"]) {
+            let id = text(&field["id"]);
+            let name = match text(&field["title"]) {
+                "" => id,
+                title => title,
+            };
+            let value = &field["value"];
+            if let Some(totp) = value["totp"].as_str() {
+                set_totp(&mut entry, name, totp);
+                continue;
+            }
+            if let Some(key) = value.get("sshKey") {
+                ssh_key.private_key = text(&key["privateKey"]).into();
+                ssh_key.public_key = text(&key["metadata"]["publicKey"]).to_string();
+                continue;
+            }
+            let Some((value, hidden)) = field_value(value) else {
+                continue;
+            };
+            let value = Zeroizing::new(value);
+            let value = value.as_str();
+            match (category, id) {
+                (CATEGORY_CARD, "cardholder") => card.holder = value.to_string(),
+                (CATEGORY_CARD, "ccnum") => card.number = value.into(),
+                (CATEGORY_CARD, "cvv") => card.cvv = value.into(),
+                (CATEGORY_CARD, "expiry") => card.expiry = value.to_string(),
+                (CATEGORY_IDENTITY, "firstname" | "initial" | "lastname") => {
+                    if !identity.full_name.is_empty() {
+                        identity.full_name.push(' ');
+                    }
+                    identity.full_name.push_str(value);
+                }
+                (CATEGORY_IDENTITY, "email") => identity.email = value.to_string(),
+                (CATEGORY_IDENTITY, "defphone") => identity.phone = value.to_string(),
+                (CATEGORY_IDENTITY, "address") => identity.address = value.to_string(),
+                (CATEGORY_IDENTITY, "company") => identity.company = value.to_string(),
+                (_, "password") if entry.password.is_empty() => entry.password = value.into(),
+                (_, "username") if entry.username.is_empty() => entry.username = value.to_string(),
+                _ => add_field(&mut entry, name, value, hidden),
+            }
+        }
+    }
+
+    entry.kind = match category {
+        CATEGORY_LOGIN | CATEGORY_PASSWORD => EntryKind::Login,
+        CATEGORY_NOTE => EntryKind::Note,
+        CATEGORY_CARD => EntryKind::Card(card),
+        CATEGORY_IDENTITY => EntryKind::Identity(identity),
+        CATEGORY_SSH_KEY => EntryKind::SshKey(ssh_key),
+        // other categories, like servers or licenses, are logins if they have a password
+        _ if entry.password.is_empty() && !entry.notes.is_empty() => EntryKind::Note,
+        _ => EntryKind::Login,
+    };
+
+    let mut history: Vec<PreviousPassword> = array(&details["passwordHistory"])
+        .filter(|previous| !text(&previous["value"]).is_empty())
+        .map(|previous| PreviousPassword {
+            password: text(&previous["value"]).into(),
+            replaced: previous["time"].as_i64().unwrap_or(0).max(0),
+        })
+        .collect();
+    history.sort_by_key(|previous| Reverse(previous.replaced));
+    history.truncate(MAX_PASSWORD_HISTORY);
+    entry.history = history;
+    entry
+}
+
+/// Returns the text of a field value, which is an object with the type of the value as its key
+///
+/// # Arguments
+/// * `value` - The value, like `{"concealed": "secret"}`
+///
+/// # Returns
+/// The text and whether it is secret, or None if the value is empty or of an unknown type
+fn field_value(value: &Value) -> Option<(String, bool)> {
+    let (kind, value) = value.as_object()?.iter().next()?;
+    let text = match kind.as_str() {
+        "concealed" | "creditCardNumber" => {
+            return value
+                .as_str()
+                .filter(|value| !value.is_empty())
+                .map(|value| (value.to_string(), true))
+        }
+        // like 202712 for December 2027
+        "monthYear" => {
+            let month_year = value.as_i64()?;
+            format!("{:02}/{:02}", month_year % 100, month_year / 100 % 100)
+        }
+        "date" => chrono::DateTime::from_timestamp(value.as_i64()?, 0)?
+            .format("%Y-%m-%d")
+            .to_string(),
+        "email" => value
+            .as_str()
+            .or_else(|| value["email_address"].as_str())?
+            .to_string(),
+        "address" => ["street", "zip", "city", "state", "country"]
+            .iter()
+            .map(|part| text(&value[*part]).trim())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => value.as_str()?.to_string(),
+    };
+    (!text.is_empty()).then_some((text, false))
+}
+
+/// Returns the text of a JSON value
+///
+/// # Arguments
+/// * `value` - The value
+///
+/// # Returns
+/// The text, empty if the value is not a string
+fn text(value: &Value) -> &str {
+    value.as_str().unwrap_or("")
+}
+
+/// Returns the elements of a JSON array
+///
+/// # Arguments
+/// * `value` - The value
+///
+/// # Returns
+/// The elements, none if the value is not an array
+fn array(value: &Value) -> impl Iterator<Item = &Value> {
+    value.as_array().into_iter().flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    const EXPORT: &str = r#"{
+        "accounts": [{
+            "attrs": {"accountName": "Krab"},
+            "vaults": [{
+                "attrs": {"uuid": "v1", "name": "Personal"},
+                "items": [
+                    {
+                        "uuid": "1", "createdAt": 1700000000, "updatedAt": 1700000128, "state": "active",
+                        "categoryUuid": "001",
+                        "details": {
+                            "loginFields": [
+                                {"value": "krab", "name": "username", "fieldType": "T", "designation": "username"},
+                                {"value": "password", "name": "password", "fieldType": "P", "designation": "password"}
+                            ],
+                            "notesPlain": "notes",
+                            "sections": [{"title": "", "fields": [
+                                {"title": "one-time password", "id": "TOTP_1", "value": {"totp": "otpauth://totp/Example?secret=JBSWY3DPEHPK3PXP"}},
+                                {"title": "PIN", "id": "pin", "value": {"concealed": "1234"}},
+                                {"title": "Recovery", "id": "recovery", "value": {"string": ""}}
+                            ]}],
+                            "passwordHistory": [{"value": "old", "time": 1600000000}]
+                        },
+                        "overview": {
+                            "title": "Example", "url": "https://example.com", "tags": ["work"],
+                            "urls": [{"label": "", "url": "https://example.com"}, {"label": "", "url": "https://login.example.com"}]
+                        }
+                    },
+                    {
+                        "item": {
+                            "uuid": "2", "categoryUuid": "002",
+                            "details": {"sections": [{"title": "", "fields": [
+                                {"title": "cardholder name", "id": "cardholder", "value": {"string": "Krab"}},
+                                {"title": "number", "id": "ccnum", "value": {"creditCardNumber": "4111111111111111"}},
+                                {"title": "verification number", "id": "cvv", "value": {"concealed": "123"}},
+                                {"title": "expiry date", "id": "expiry", "value": {"monthYear": 203005}}
+                            ]}]},
+                            "overview": {"title": "Visa"}
+                        }
+                    },
+                    {"uuid": "3", "categoryUuid": "006", "details": {}, "overview": {"title": "Passport scan"}}
+                ]
+            }]
+        }]
+    }"#;
+
+    fn write_1pux(export: &str) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(vec![]));
+        writer
+            .start_file("export.attributes", SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(b"{\"version\": 3}").unwrap();
+        writer
+            .start_file(EXPORT_DATA, SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(export.as_bytes()).unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_1pux_import() {
+        let data = write_1pux(EXPORT);
+        let import = OnePux.import(&data, "").unwrap();
+
+        assert!(OnePux.detect(&data));
+        assert!(!OnePux.detect(b"PK\x03\x04 not a zip"));
+        assert_eq!(import.entries.len(), 2);
+        let login = &import.entries[0];
+        assert_eq!(login.domain, "Example");
+        assert_eq!(login.folder, "Personal");
+        assert_eq!(login.username, "krab");
+        assert_eq!(login.password.expose_secret(), "password");
+        assert_eq!(login.notes.expose_secret(), "notes");
+        assert!(login.totp.expose_secret().starts_with("otpauth://"));
+        assert_eq!(login.tags, vec!["work"]);
+        assert_eq!(login.created, 1_700_000_000);
+        let fields: Vec<(&str, &str, bool)> = login
+            .fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.value.expose_secret(), f.hidden))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("URL", "https://login.example.com", false),
+                ("PIN", "1234", true)
+            ]
+        );
+        assert_eq!(login.history[0].password.expose_secret(), "old");
+        match &import.entries[1].kind {
+            EntryKind::Card(card) => {
+                assert_eq!(card.holder, "Krab");
+                assert_eq!(card.number.expose_secret(), "4111111111111111");
+                assert_eq!(card.expiry, "05/30");
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(import.skipped[0].name, "Passport scan");
+    }
+}
@@ -0,0 +1,338 @@
+use argon2::{Algorithm, Argon2, Version};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+const KDF_SCRYPT: u8 = 0;
+const KDF_ARGON2ID: u8 = 1;
+const SCRYPT_LOG_N: u8 = 14;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+/// scrypt needs 128 * r * 2^log_n bytes, 2^20 keeps it at 1 GiB
+const SCRYPT_MAX_LOG_N: u8 = 20;
+const SCRYPT_MAX_P: u32 = 16;
+const ARGON2_MEMORY_KIB: u32 = 19 * 1024;
+const ARGON2_ITERATIONS: u32 = 2;
+const ARGON2_PARALLELISM: u32 = 1;
+const ARGON2_MAX_MEMORY_KIB: u32 = 1024 * 1024;
+const ARGON2_MAX_ITERATIONS: u32 = 64;
+/// Memory used by calibrated Argon2id parameters, the time is tuned with the iterations
+const ARGON2_CALIBRATION_MEMORY_KIB: u32 = 64 * 1024;
+const CALIBRATION_PASSWORD: &str = "krab calibration";
+const CALIBRATION_SALT: &[u8] = b"krab calibration salt";
+
+/// Time a calibrated key derivation should take to unlock a vault
+pub const DEFAULT_UNLOCK_TIME: Duration = Duration::from_secs(1);
+
+/// Parameters of the key derivation function stored in the vault header
+///
+/// # Variants
+/// * `Scrypt` - scrypt with the given cost parameters
+/// * `Argon2id` - Argon2id with the given memory (in KiB), iterations and parallelism
+///
+/// # Methods
+/// * `default_argon2id` - Returns the default Argon2id parameters
+/// * `calibrate` - Picks parameters of the same algorithm for a target unlock time
+/// * `is_supported` - Checks if the parameters can be used to derive a key
+///
+/// # Implements
+/// * `Default` - The default scrypt parameters
+/// * `Display`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KdfParams {
+    Scrypt {
+        log_n: u8,
+        r: u32,
+        p: u32,
+    },
+    Argon2id {
+        memory_kib: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams::Scrypt {
+            log_n: SCRYPT_LOG_N,
+            r: SCRYPT_R,
+            p: SCRYPT_P,
+        }
+    }
+}
+
+impl fmt::Display for KdfParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KdfParams::Scrypt { log_n, r, p } => {
+                write!(f, "scrypt (N=2^{}, r={}, p={})", log_n, r, p)
+            }
+            KdfParams::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => write!(
+                f,
+                "Argon2id (m={} MiB, t={}, p={})",
+                memory_kib / 1024,
+                iterations,
+                parallelism
+            ),
+        }
+    }
+}
+
+impl KdfParams {
+    /// Returns the default Argon2id parameters
+    ///
+    /// # Returns
+    /// The default Argon2id parameters
+    pub fn default_argon2id() -> Self {
+        KdfParams::Argon2id {
+            memory_kib: ARGON2_MEMORY_KIB,
+            iterations: ARGON2_ITERATIONS,
+            parallelism: ARGON2_PARALLELISM,
+        }
+    }
+
+    /// Picks parameters of the same algorithm so that deriving a key takes
+    /// about `target` on the current machine
+    /// The cost never goes below the defaults of the algorithm
+    ///
+    /// # Arguments
+    /// * `target` - The target unlock time
+    ///
+    /// # Returns
+    /// The calibrated parameters
+    pub fn calibrate(&self, target: Duration) -> Self {
+        match self {
+            KdfParams::Scrypt { .. } => {
+                let mut kdf = KdfParams::default();
+                let elapsed = kdf.measure();
+                if let KdfParams::Scrypt { log_n, .. } = &mut kdf {
+                    // every step doubles the time
+                    let mut expected = elapsed;
+                    while expected * 2 <= target && *log_n < SCRYPT_MAX_LOG_N {
+                        *log_n += 1;
+                        expected *= 2;
+                    }
+                }
+                kdf
+            }
+            KdfParams::Argon2id { .. } => {
+                let mut kdf = KdfParams::Argon2id {
+                    memory_kib: ARGON2_CALIBRATION_MEMORY_KIB,
+                    iterations: 1,
+                    parallelism: ARGON2_PARALLELISM,
+                };
+                let elapsed = kdf.measure().max(Duration::from_millis(1));
+                if let KdfParams::Argon2id { iterations, .. } = &mut kdf {
+                    // the time grows linearly with the iterations
+                    let scaled = (target.as_secs_f64() / elapsed.as_secs_f64()) as u32;
+                    *iterations = scaled.clamp(ARGON2_ITERATIONS, ARGON2_MAX_ITERATIONS);
+                }
+                kdf
+            }
+        }
+    }
+
+    /// Checks if the parameters can be used to derive a key
+    /// Parameters read from a vault header are rejected if they would
+    /// need an unreasonable amount of memory or time
+    ///
+    /// # Returns
+    /// `true` if the parameters are supported, otherwise `false`
+    pub fn is_supported(&self) -> bool {
+        match self {
+            KdfParams::Scrypt { log_n, r, p } => {
+                *log_n <= SCRYPT_MAX_LOG_N
+                    && *r <= SCRYPT_R
+                    && *p <= SCRYPT_MAX_P
+                    && scrypt::Params::new(*log_n, *r, *p, 16).is_ok()
+            }
+            KdfParams::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                *memory_kib <= ARGON2_MAX_MEMORY_KIB
+                    && *iterations <= ARGON2_MAX_ITERATIONS
+                    && argon2::Params::new(*memory_kib, *iterations, *parallelism, Some(16)).is_ok()
+            }
+        }
+    }
+
+    /// Derives a key from a password
+    ///
+    /// # Arguments
+    /// * `password` - The password
+    /// * `salt` - The salt
+    /// * `key` - The buffer the key is written to, its length is the key length
+    ///
+    /// # Panics
+    /// If the parameters are not supported
+    pub(crate) fn derive(&self, password: &[u8], salt: &[u8], key: &mut [u8]) {
+        match self {
+            KdfParams::Scrypt { log_n, r, p } => {
+                scrypt::scrypt(
+                    password,
+                    salt,
+                    &scrypt::Params::new(*log_n, *r, *p, key.len()).unwrap(),
+                    key,
+                )
+                .unwrap();
+            }
+            KdfParams::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                let params =
+                    argon2::Params::new(*memory_kib, *iterations, *parallelism, Some(key.len()))
+                        .unwrap();
+                Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                    .hash_password_into(password, salt, key)
+                    .unwrap();
+            }
+        }
+    }
+
+    /// Measures how long deriving a key takes with these parameters
+    ///
+    /// # Returns
+    /// The time it took
+    fn measure(&self) -> Duration {
+        let mut key = [0u8; 16];
+        let start = Instant::now();
+        self.derive(CALIBRATION_PASSWORD.as_bytes(), CALIBRATION_SALT, &mut key);
+        start.elapsed()
+    }
+
+    /// Writes the parameter block to a buffer
+    /// The block is the algorithm id, the length of the parameters and the parameters
+    ///
+    /// # Arguments
+    /// * `buffer` - The buffer to write to
+    pub(crate) fn write(&self, buffer: &mut Vec<u8>) {
+        let (id, mut params) = match self {
+            KdfParams::Scrypt { log_n, r, p } => {
+                let mut params = vec![*log_n];
+                params.extend_from_slice(&r.to_be_bytes());
+                params.extend_from_slice(&p.to_be_bytes());
+                (KDF_SCRYPT, params)
+            }
+            KdfParams::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                let mut params = memory_kib.to_be_bytes().to_vec();
+                params.extend_from_slice(&iterations.to_be_bytes());
+                params.extend_from_slice(&parallelism.to_be_bytes());
+                (KDF_ARGON2ID, params)
+            }
+        };
+        buffer.push(id);
+        buffer.push(params.len() as u8);
+        buffer.append(&mut params);
+    }
+
+    /// Reads a parameter block from bytes
+    ///
+    /// # Arguments
+    /// * `bytes` - The bytes to read from
+    ///
+    /// # Returns
+    /// The parameters and the number of bytes read, None if the block is invalid
+    /// or the parameters are not supported
+    pub(crate) fn read(bytes: &[u8]) -> Option<(Self, usize)> {
+        let id = *bytes.first()?;
+        let len = *bytes.get(1)? as usize;
+        let params = bytes.get(2..2 + len)?;
+        let kdf = match id {
+            KDF_SCRYPT if len == 9 => KdfParams::Scrypt {
+                log_n: params[0],
+                r: u32::from_be_bytes(params[1..5].try_into().ok()?),
+                p: u32::from_be_bytes(params[5..9].try_into().ok()?),
+            },
+            KDF_ARGON2ID if len == 12 => KdfParams::Argon2id {
+                memory_kib: u32::from_be_bytes(params[0..4].try_into().ok()?),
+                iterations: u32::from_be_bytes(params[4..8].try_into().ok()?),
+                parallelism: u32::from_be_bytes(params[8..12].try_into().ok()?),
+            },
+            _ => return None,
+        };
+        if !kdf.is_supported() {
+            return None;
+        }
+        Some((kdf, 2 + len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kdf_params_roundtrip() {
+        for kdf in [KdfParams::default(), KdfParams::default_argon2id()] {
+            let mut buffer = vec![];
+            kdf.write(&mut buffer);
+            buffer.push(0xFF);
+
+            let (read, len) = KdfParams::read(&buffer).unwrap();
+
+            assert_eq!(read, kdf);
+            assert_eq!(len, buffer.len() - 1);
+        }
+    }
+
+    #[test]
+    fn test_kdf_params_read_rejects_unsupported() {
+        let mut buffer = vec![];
+        KdfParams::Scrypt {
+            log_n: 30,
+            r: 8,
+            p: 1,
+        }
+        .write(&mut buffer);
+        assert!(KdfParams::read(&buffer).is_none());
+
+        let mut buffer = vec![];
+        KdfParams::Argon2id {
+            memory_kib: 0,
+            iterations: 1,
+            parallelism: 1,
+        }
+        .write(&mut buffer);
+        assert!(KdfParams::read(&buffer).is_none());
+
+        assert!(KdfParams::read(&[7, 0]).is_none());
+    }
+
+    #[test]
+    fn test_kdf_derive_differs_by_algorithm() {
+        let mut scrypt_key = [0u8; 16];
+        let mut argon2_key = [0u8; 16];
+        KdfParams::default().derive(b"password", b"salt-salt", &mut scrypt_key);
+        KdfParams::default_argon2id().derive(b"password", b"salt-salt", &mut argon2_key);
+
+        assert_ne!(scrypt_key, argon2_key);
+    }
+
+    #[test]
+    fn test_kdf_calibrate_keeps_minimum_cost() {
+        let scrypt = KdfParams::default().calibrate(Duration::ZERO);
+        let argon2 = KdfParams::default_argon2id().calibrate(Duration::ZERO);
+
+        assert_eq!(scrypt, KdfParams::default());
+        assert!(matches!(
+            argon2,
+            KdfParams::Argon2id { iterations, .. } if iterations == ARGON2_ITERATIONS
+        ));
+        assert!(argon2.is_supported());
+    }
+}
@@ -3,16 +3,39 @@ use rand::Rng;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::{
-    env,
-    fs::{self, File, OpenOptions},
+    env, fmt,
+    fs::{self, File, OpenOptions, TryLockError},
     io::{self, Read, Write},
     path::{Path, PathBuf},
-    str,
+    str, thread,
+    time::{Duration, Instant},
 };
 
+pub mod attachment;
+pub mod backup;
+pub mod cipher;
+pub mod csv;
+pub mod entry;
+pub mod error;
+pub mod export;
+pub mod import;
+pub mod kdf;
+pub mod kind;
 mod models;
+pub mod pass;
+pub mod payload;
+pub mod secret;
+pub mod session;
+pub mod store;
+#[cfg(test)]
+mod test_util;
+pub mod totp;
 pub mod user;
 
+use cipher::CipherKind;
+use kdf::KdfParams;
+use store::VaultStore;
+
 const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
 const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
 const NUMBERS: &str = "0123456789";
@@ -24,6 +47,8 @@ const DB_DIR: &str = "krab";
 const RELEASE_SUFFIX: &str = "release";
 const CONFIG_FILE: &str = "config.json";
 
+pub(crate) const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
 /// Configuration for password generation options
 ///
 /// # Fields
@@ -53,10 +78,43 @@ impl Default for PasswordConfig {
     }
 }
 
+/// Estimated strength of a password
+///
+/// # Variants
+/// * `Weak` - Less than 40 bits of entropy
+/// * `Fair` - Less than 60 bits of entropy
+/// * `Good` - Less than 80 bits of entropy
+/// * `Strong` - At least 80 bits of entropy
+///
+/// # Implements
+/// * `Display`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PasswordStrength {
+    Weak,
+    Fair,
+    Good,
+    Strong,
+}
+
+impl fmt::Display for PasswordStrength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let strength = match self {
+            PasswordStrength::Weak => "Weak",
+            PasswordStrength::Fair => "Fair",
+            PasswordStrength::Good => "Good",
+            PasswordStrength::Strong => "Strong",
+        };
+        write!(f, "{}", strength)
+    }
+}
+
 /// Configuration for the application
 ///
 /// # Fields
 /// * `password_config` - Configuration for password generation options
+/// * `ask_master_password` - Does every change to a vault ask for the master password
+/// * `kdf` - Key derivation parameters for new vaults and for raising the cost of a vault
+/// * `cipher` - Cipher for new vaults and for upgrading a vault
 ///
 /// # Methods
 /// * `get_config_path` - Gets the path to the configuration file
@@ -65,17 +123,15 @@ impl Default for PasswordConfig {
 ///
 /// # Implements
 /// * `Default`
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct Config {
     pub password_config: PasswordConfig,
-}
-
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            password_config: PasswordConfig::default(),
-        }
-    }
+    #[serde(default)]
+    pub ask_master_password: bool,
+    #[serde(default)]
+    pub kdf: KdfParams,
+    #[serde(default)]
+    pub cipher: CipherKind,
 }
 
 impl Config {
@@ -131,9 +187,7 @@ impl Config {
             )
         })?;
 
-        let mut file = File::create(config_path)?;
-        file.write_all(contents.as_bytes())?;
-        Ok(())
+        write_atomically(&config_path, contents.into_bytes())
     }
 }
 
@@ -163,17 +217,13 @@ pub fn init() -> Result<PathBuf, io::Error> {
 ///
 /// # Arguments
 /// * `username` - The username of the user
-/// * `path` - The path to the data directory
+/// * `store` - The store of the vaults
 ///
 /// # Returns
 ///
 /// `true` if the user exists, otherwise `false`
-pub fn check_user(username: &str, path: PathBuf) -> bool {
-    let hashed_username = hash(username.to_string());
-    match path.join(hashed_username).exists() {
-        true => true,
-        false => false,
-    }
+pub fn check_user(username: &str, store: &impl VaultStore) -> bool {
+    store.exists(&hash(username.to_string()))
 }
 
 /// Creates a hash of the input data
@@ -190,6 +240,19 @@ pub fn hash(data: String) -> String {
     format!("{:x}", result)
 }
 
+/// Creates a checksum of binary data
+///
+/// # Arguments
+/// * `data` - The data to checksum
+///
+/// # Returns
+/// The checksum as a hex string
+pub fn checksum(data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    format!("{:x}", hasher.finalize())
+}
+
 /// Helper function to check if a password contains uppercase characters
 ///
 /// # Arguments
@@ -296,83 +359,233 @@ pub fn generate_password() -> String {
     }
 }
 
+/// Estimates the strength of a password
+/// The entropy is estimated from the length and the character classes used,
+/// repeated characters only count once
+///
+/// # Arguments
+/// * `password` - The password to estimate
+///
+/// # Returns
+/// The estimated strength of the password
+pub fn password_strength(password: &str) -> PasswordStrength {
+    let mut charset_size = 0;
+    if password.chars().any(|c| LOWERCASE.contains(c)) {
+        charset_size += LOWERCASE.len();
+    }
+    if contains_uppercase(password) {
+        charset_size += UPPERCASE.len();
+    }
+    if contains_numbers(password) {
+        charset_size += NUMBERS.len();
+    }
+    if contains_special(password) {
+        charset_size += SPECIAL.len();
+    }
+    if password.chars().any(|c| {
+        !LOWERCASE.contains(c)
+            && !UPPERCASE.contains(c)
+            && !NUMBERS.contains(c)
+            && !SPECIAL.contains(c)
+    }) {
+        charset_size += SPECIAL.len();
+    }
+
+    let mut unique: Vec<char> = password.chars().collect();
+    unique.sort_unstable();
+    unique.dedup();
+
+    let entropy = unique.len() as f64 * (charset_size.max(1) as f64).log2();
+    match entropy {
+        e if e < 40.0 => PasswordStrength::Weak,
+        e if e < 60.0 => PasswordStrength::Fair,
+        e if e < 80.0 => PasswordStrength::Good,
+        _ => PasswordStrength::Strong,
+    }
+}
+
 /// Creates a new file in the specified directory
+/// The file is created only if it does not exist yet, in a single step,
+/// so of two processes creating the same file only one succeeds
 ///
 /// # Arguments
 /// * `p` - The path to the directory
 /// * `file_name` - The name of the file to create
 ///
 /// # Returns
-/// The path to the newly created file if successful, otherwise an `io::Error` is returned
-pub fn create_file(p: &PathBuf, file_name: &str) -> io::Result<PathBuf> {
+/// The path to the newly created file if successful, otherwise an `io::Error` is returned,
+/// with `io::ErrorKind::AlreadyExists` if the file exists already
+pub fn create_file(p: &Path, file_name: &str) -> io::Result<PathBuf> {
     let file_path = p.join(file_name);
-    if !file_path.exists() {
-        File::create(file_path.as_path())?;
-        return Ok(file_path);
-    } else {
-        return Err(io::Error::new(
-            io::ErrorKind::AlreadyExists,
-            "File already exists",
-        ));
-    }
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&file_path)?;
+    Ok(file_path)
 }
 
-/// Clears the content of a file
+/// Writes data to a new file only readable by the user
+/// An existing file is never overwritten
+///
+/// # Arguments
+/// * `p` - The path of the new file
+/// * `data` - The data
+///
+/// # Returns
+/// An error if the file exists or could not be written
+pub fn write_private_file(p: &Path, data: &[u8]) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(p)?;
+    file.write_all(data)
+}
+
+/// Writes data to a file atomically
+/// The data is written to a temporary file in the same directory, flushed
+/// to disk and renamed over the original file, so the file either holds
+/// the old or the new content even if the process crashes midway
 ///
 /// # Arguments
 /// * `p` - The path to the file
+/// * `data` - The data to write to the file
 ///
 /// # Returns
 /// An `io::Result` indicating success or failure
-pub fn clear_file_content(p: &PathBuf) -> io::Result<()> {
-    if !p.exists() {
-        return Err(io::Error::new(
-            io::ErrorKind::NotFound,
-            "File does not exist",
-        ));
+pub fn write_atomically(p: &Path, data: Vec<u8>) -> io::Result<()> {
+    let file_name = match p.file_name() {
+        Some(file_name) => file_name.to_string_lossy().to_string(),
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Path does not point to a file",
+            ))
+        }
+    };
+    let temp_path = p.with_file_name(format!(".{}.tmp", file_name));
+
+    let res = File::create(&temp_path).and_then(|mut f| {
+        f.write_all(&data)?;
+        f.sync_all()
+    });
+    if let Err(e) = res.and_then(|_| fs::rename(&temp_path, p)) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    // persist the rename itself, not supported on every platform
+    if let Some(parent) = p.parent() {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
     }
-    File::create(p)?;
     Ok(())
 }
 
-/// Writes data to a file
+/// Writes data to a file atomically and keeps previous generations of it
+/// Before the file is replaced, the current content becomes generation `1`
+/// (`<file>.1`), the existing generations are shifted by one and the
+/// oldest one is dropped, so at most `generations` previous files are kept
 ///
 /// # Arguments
 /// * `p` - The path to the file
 /// * `data` - The data to write to the file
+/// * `generations` - The number of previous generations to keep
 ///
 /// # Returns
 /// An `io::Result` indicating success or failure
-pub fn write_to_file(p: &PathBuf, data: Vec<u8>) -> io::Result<()> {
-    if !p.exists() {
-        return Err(io::Error::new(
-            io::ErrorKind::NotFound,
-            "File does not exist",
-        ));
+pub fn write_atomically_with_generations(
+    p: &Path,
+    data: Vec<u8>,
+    generations: usize,
+) -> io::Result<()> {
+    if generations > 0 && p.exists() {
+        let _ = fs::remove_file(generation_path(p, generations));
+        for generation in (1..generations).rev() {
+            let from = generation_path(p, generation);
+            if from.exists() {
+                fs::rename(&from, generation_path(p, generation + 1))?;
+            }
+        }
+
+        // a hard link keeps the previous content without copying it,
+        // the rename in `write_atomically` only replaces the directory entry
+        let first = generation_path(p, 1);
+        if fs::hard_link(p, &first).is_err() {
+            fs::copy(p, &first)?;
+        }
     }
-    let mut f = File::create(p)?;
-    f.write_all(&data)?;
-    Ok(())
+
+    write_atomically(p, data)
 }
 
-/// Appends data to a file
+/// Returns the path of a previous generation of a file
 ///
 /// # Arguments
 /// * `p` - The path to the file
-/// * `data` - The data to append to the file
+/// * `generation` - The generation, `1` being the most recent one
 ///
 /// # Returns
-/// An `io::Result` indicating success or failure
-pub fn append_to_file(p: &PathBuf, data: Vec<u8>) -> io::Result<()> {
-    if !p.exists() {
-        return Err(io::Error::new(
-            io::ErrorKind::NotFound,
-            "File does not exist",
-        ));
+/// The path to the generation
+pub fn generation_path(p: &Path, generation: usize) -> PathBuf {
+    let file_name = p
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_default();
+    p.with_file_name(format!("{}.{}", file_name, generation))
+}
+
+/// Locks a file for exclusive access across processes
+/// The lock is advisory and taken on a `<file>.lock` file next to the file,
+/// because atomic writes replace the file itself. The lock is released
+/// when the returned file is dropped
+///
+/// # Arguments
+/// * `p` - The path to the file
+/// * `timeout` - How long to wait for a lock held by another process
+///
+/// # Returns
+/// The locked file if successful, otherwise an `io::Error` is returned,
+/// with `io::ErrorKind::WouldBlock` if the lock could not be taken in time
+pub fn lock_file(p: &Path, timeout: Duration) -> io::Result<File> {
+    let lock = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_path(p))?;
+
+    let start = Instant::now();
+    loop {
+        match lock.try_lock() {
+            Ok(_) => return Ok(lock),
+            Err(TryLockError::WouldBlock) if start.elapsed() < timeout => {
+                thread::sleep(LOCK_RETRY_INTERVAL);
+            }
+            Err(TryLockError::WouldBlock) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::WouldBlock,
+                    "File is locked by another process",
+                ))
+            }
+            Err(TryLockError::Error(e)) => return Err(e),
+        }
     }
-    let mut f = OpenOptions::new().append(true).open(p)?;
-    f.write_all(&data)?;
-    Ok(())
+}
+
+/// Returns the path of the lock file of a file
+///
+/// # Arguments
+/// * `p` - The path to the file
+///
+/// # Returns
+/// The path to the lock file
+pub fn lock_path(p: &Path) -> PathBuf {
+    let file_name = p
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_default();
+    p.with_file_name(format!("{}.lock", file_name))
 }
 
 /// Creates a parent directory if it does not exist
@@ -383,11 +596,8 @@ pub fn append_to_file(p: &PathBuf, data: Vec<u8>) -> io::Result<()> {
 /// # Returns
 /// An `io::Result` indicating success or failure
 fn create_parent_dir(p: &Path) -> io::Result<()> {
-    match p.parent() {
-        Some(parent) => {
-            fs::create_dir_all(parent)?;
-        }
-        None => {}
+    if let Some(parent) = p.parent() {
+        fs::create_dir_all(parent)?;
     }
     Ok(())
 }
@@ -1,6 +1,9 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use crate::secret::SecretString;
 
 /// Represents a configuration for a record operation
+/// The master password and the password are zeroed on drop
 ///
 /// # Fields
 /// * `username` - The username
@@ -8,13 +11,15 @@ use std::path::PathBuf;
 /// * `domain` - The domain
 /// * `password` - The password
 /// * `path` - The path to the data directory
+/// * `id` - The id of the record to remove or modify, empty for new records
 #[derive(Debug, Clone, PartialEq)]
 pub struct RecordOperationConfig {
     pub username: String,
-    pub master_password: String,
+    pub master_password: SecretString,
     pub domain: String,
-    pub password: String,
+    pub password: SecretString,
     pub path: PathBuf,
+    pub id: String,
 }
 
 impl RecordOperationConfig {
@@ -34,14 +39,15 @@ impl RecordOperationConfig {
         master_password: &str,
         domain: &str,
         password: &str,
-        path: &PathBuf,
+        path: &Path,
     ) -> RecordOperationConfig {
         RecordOperationConfig {
             username: username.to_string(),
-            master_password: master_password.to_string(),
+            master_password: SecretString::from(master_password),
             domain: domain.to_string(),
-            password: password.to_string(),
-            path: path.clone(),
+            password: SecretString::from(password),
+            path: path.to_path_buf(),
+            id: String::new(),
         }
     }
 }
@@ -0,0 +1,767 @@
+use std::{
+    collections::HashSet,
+    env, fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::UNIX_EPOCH,
+};
+
+use directories::BaseDirs;
+use zeroize::Zeroizing;
+
+use crate::{
+    entry::Entry,
+    import::{set_totp, Import, SkippedItem},
+    kind::EntryKind,
+    write_private_file,
+};
+
+/// The gpg binary used when none is set
+pub const GPG: &str = "gpg";
+/// The name of the format, shown to the user
+pub const PASS_NAME: &str = "pass (password store)";
+/// The extension of the encrypted files of a password store
+const GPG_EXTENSION: &str = "gpg";
+/// The file listing the keys the files of a folder are encrypted for
+const GPG_ID: &str = ".gpg-id";
+/// The keys of the lines after the password that are read as the username
+const USERNAME_KEYS: [&str; 4] = ["login", "username", "user", "email"];
+/// The keys of the lines after the password that are read as the URL
+const URL_KEYS: [&str; 3] = ["url", "website", "site"];
+
+/// A password store of `pass`, a tree of files encrypted with gpg
+/// A folder is a path below the root and the name of a file is the title of its entry.
+/// The first line of a file is the password and the rest are the notes, where
+/// `login:`, `url:` and `otpauth://` lines right after the password are read as
+/// the username, the URL and the TOTP secret. Custom fields are written as
+/// `name: value` lines, which come back as notes
+///
+/// # Fields
+/// * `root` - The root of the store, usually `~/.password-store`
+/// * `gpg` - The gpg binary
+///
+/// # Methods
+/// * `new` - Creates a new `PasswordStore`
+/// * `with_gpg` - Uses another gpg binary
+/// * `default_root` - Returns the root of the store of the user
+/// * `import` - Reads and decrypts the entries of the store
+/// * `export` - Encrypts entries into the store
+/// * `recipients` - Returns the keys the files of a folder are encrypted for
+/// * `decrypt` - Decrypts a file of the store
+/// * `encrypt` - Encrypts the content of a file for keys
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordStore {
+    root: PathBuf,
+    gpg: PathBuf,
+}
+
+/// The result of an export into a password store
+///
+/// # Fields
+/// * `written` - The number of files written
+/// * `skipped` - The entries that were not exported
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PassExport {
+    pub written: usize,
+    pub skipped: Vec<SkippedItem>,
+}
+
+/// Error while reading or writing a password store
+/// It never contains any secret of the store
+///
+/// # Variants
+/// * `NotAStore` - The root is not a folder
+/// * `NoRecipients` - The store has no `.gpg-id` file naming the keys to encrypt for
+/// * `GpgNotFound` - The gpg binary could not be run
+/// * `Gpg` - gpg failed, with its last message
+/// * `Io` - A file of the store could not be read or written, with the kind of the I/O error
+///
+/// # Implements
+/// * `Display`
+/// * `Error`
+/// * `From<io::Error>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PassError {
+    NotAStore,
+    NoRecipients,
+    GpgNotFound,
+    Gpg(String),
+    Io(io::ErrorKind),
+}
+
+impl fmt::Display for PassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PassError::NotAStore => write!(f, "The password store is not a folder"),
+            PassError::NoRecipients => {
+                write!(f, "The password store has no {}, run pass init", GPG_ID)
+            }
+            PassError::GpgNotFound => write!(f, "gpg could not be run, is it installed?"),
+            PassError::Gpg(message) => write!(f, "gpg failed: {}", message),
+            PassError::Io(kind) => write!(f, "Could not access the password store: {}", kind),
+        }
+    }
+}
+
+impl std::error::Error for PassError {}
+
+impl From<io::Error> for PassError {
+    fn from(error: io::Error) -> Self {
+        PassError::Io(error.kind())
+    }
+}
+
+impl PasswordStore {
+    /// Creates a new password store, using the `gpg` binary of the `PATH`
+    ///
+    /// # Arguments
+    /// * `root` - The root of the store
+    ///
+    /// # Returns
+    /// A new `PasswordStore`
+    pub fn new(root: &Path) -> Self {
+        PasswordStore {
+            root: root.to_path_buf(),
+            gpg: PathBuf::from(GPG),
+        }
+    }
+
+    /// Uses another gpg binary
+    ///
+    /// # Arguments
+    /// * `gpg` - The gpg binary
+    ///
+    /// # Returns
+    /// The password store
+    pub fn with_gpg(mut self, gpg: &Path) -> Self {
+        self.gpg = gpg.to_path_buf();
+        self
+    }
+
+    /// Returns the root of the store of the user, like `pass` finds it
+    ///
+    /// # Returns
+    /// `PASSWORD_STORE_DIR` if it is set, otherwise `~/.password-store`
+    pub fn default_root() -> Option<PathBuf> {
+        match env::var_os("PASSWORD_STORE_DIR") {
+            Some(root) if !root.is_empty() => Some(PathBuf::from(root)),
+            _ => BaseDirs::new().map(|dirs| dirs.home_dir().join(".password-store")),
+        }
+    }
+
+    /// Reads and decrypts the entries of the store
+    /// Hidden files and folders, like `.git`, are ignored and a file gpg cannot
+    /// decrypt is skipped. The modification time of a file is the time of its entry
+    ///
+    /// # Arguments
+    /// * `passphrase` - The passphrase of the gpg key, empty to rely on gpg-agent
+    ///
+    /// # Returns
+    /// The entries, ordered by their path, or an error
+    pub fn import(&self, passphrase: &str) -> Result<Import, PassError> {
+        if !self.root.is_dir() {
+            return Err(PassError::NotAStore);
+        }
+
+        let mut files = vec![];
+        collect_files(&self.root, &mut files)?;
+        files.sort();
+
+        let mut import = Import::default();
+        for file in files {
+            let relative = file.strip_prefix(&self.root).unwrap_or(&file);
+            let name = relative
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let folder = relative
+                .parent()
+                .map(|parent| parent.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_default();
+            let content = match self.decrypt(&file, passphrase) {
+                Ok(content) => content,
+                Err(PassError::Gpg(message)) => {
+                    import.skip(&relative.to_string_lossy(), &message);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let time = fs::metadata(&file)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs() as i64)
+                .unwrap_or_default();
+
+            let mut entry = parse_entry(&name, &content);
+            entry.folder = Entry::normalize_folder(&folder);
+            entry.created = time;
+            entry.modified = time;
+            import.push(entry);
+        }
+        Ok(import)
+    }
+
+    /// Encrypts entries into the store, a file for each entry
+    /// Existing files are never overwritten, such an entry is skipped.
+    /// Entries sharing a title are told apart by their username.
+    /// Only logins and notes have a layout in a password store, and only
+    /// if their password fits on one line
+    ///
+    /// # Arguments
+    /// * `entries` - The entries
+    ///
+    /// # Returns
+    /// The number of written files and the skipped entries, or an error
+    pub fn export(&self, entries: &[Entry]) -> Result<PassExport, PassError> {
+        if !self.root.is_dir() {
+            return Err(PassError::NotAStore);
+        }
+        if !self.root.join(GPG_ID).is_file() {
+            return Err(PassError::NoRecipients);
+        }
+
+        let mut export = PassExport::default();
+        let mut used = HashSet::new();
+        for entry in entries.iter() {
+            if !matches!(entry.kind, EntryKind::Login | EntryKind::Note) {
+                export.skipped.push(SkippedItem {
+                    name: entry.label(),
+                    reason: "Only logins and notes can be exported to pass".to_string(),
+                });
+                continue;
+            }
+            if let Some(reason) = unwritable(entry) {
+                export.skipped.push(SkippedItem {
+                    name: entry.label(),
+                    reason: reason.to_string(),
+                });
+                continue;
+            }
+
+            let folder = self.root.join(folder_path(&entry.folder));
+            let file = [entry.domain.as_str(), &entry.label()]
+                .iter()
+                .map(|name| folder.join(format!("{}.{}", file_name(name), GPG_EXTENSION)))
+                .chain((2..).map(|n| {
+                    folder.join(format!(
+                        "{} {}.{}",
+                        file_name(&entry.label()),
+                        n,
+                        GPG_EXTENSION
+                    ))
+                }))
+                .find(|file| !used.contains(file))
+                .unwrap_or_default();
+            used.insert(file.clone());
+            if file.exists() {
+                export.skipped.push(SkippedItem {
+                    name: entry.label(),
+                    reason: "Already in the password store".to_string(),
+                });
+                continue;
+            }
+
+            let recipients = self.recipients(&folder)?;
+            let encrypted = self.encrypt(format_entry(entry).as_bytes(), &recipients)?;
+            fs::create_dir_all(&folder)?;
+            write_private_file(&file, &encrypted)?;
+            export.written += 1;
+        }
+        Ok(export)
+    }
+
+    /// Returns the keys the files of a folder are encrypted for,
+    /// from the `.gpg-id` file of the folder or of the closest folder above it
+    ///
+    /// # Arguments
+    /// * `folder` - The folder, below the root
+    ///
+    /// # Returns
+    /// The ids of the keys or an error
+    fn recipients(&self, folder: &Path) -> Result<Vec<String>, PassError> {
+        for dir in folder.ancestors() {
+            let path = dir.join(GPG_ID);
+            if path.is_file() {
+                let recipients: Vec<String> = fs::read_to_string(&path)?
+                    .lines()
+                    .map(|line| line.split('#').next().unwrap_or("").trim().to_string())
+                    .filter(|line| !line.is_empty())
+                    .collect();
+                if recipients.is_empty() {
+                    return Err(PassError::NoRecipients);
+                }
+                return Ok(recipients);
+            }
+            if dir == self.root {
+                break;
+            }
+        }
+        Err(PassError::NoRecipients)
+    }
+
+    /// Decrypts a file of the store
+    /// gpg never asks for the passphrase itself, it would break the terminal
+    ///
+    /// # Arguments
+    /// * `file` - The encrypted file
+    /// * `passphrase` - The passphrase of the gpg key, empty to rely on gpg-agent
+    ///
+    /// # Returns
+    /// The content of the file or an error
+    fn decrypt(&self, file: &Path, passphrase: &str) -> Result<Zeroizing<String>, PassError> {
+        let mut command = Command::new(&self.gpg);
+        command.args(["--quiet", "--batch", "--yes", "--pinentry-mode", "loopback"]);
+        if !passphrase.is_empty() {
+            command.args(["--passphrase-fd", "0"]);
+        }
+        command.arg("--decrypt").arg(file);
+
+        let input = Zeroizing::new(format!("{}\n", passphrase));
+        let output = run(command, input.as_bytes())?;
+        String::from_utf8(output.to_vec())
+            .map(Zeroizing::new)
+            .map_err(|_| PassError::Gpg("The file is not UTF-8 text".to_string()))
+    }
+
+    /// Encrypts the content of a file for keys, like `pass insert` does
+    ///
+    /// # Arguments
+    /// * `content` - The content of the file
+    /// * `recipients` - The ids of the keys
+    ///
+    /// # Returns
+    /// The encrypted file or an error
+    fn encrypt(&self, content: &[u8], recipients: &[String]) -> Result<Vec<u8>, PassError> {
+        let mut command = Command::new(&self.gpg);
+        command.args([
+            "--quiet",
+            "--batch",
+            "--yes",
+            "--compress-algo=none",
+            "--no-encrypt-to",
+            "--encrypt",
+        ]);
+        for recipient in recipients.iter() {
+            command.arg("-r").arg(recipient);
+        }
+        command.args(["--output", "-"]);
+        run(command, content).map(|output| output.to_vec())
+    }
+}
+
+/// Runs gpg, writing to its input while its output is read
+///
+/// # Arguments
+/// * `command` - The gpg command
+/// * `input` - The input of gpg
+///
+/// # Returns
+/// The output of gpg or its last message
+fn run(mut command: Command, input: &[u8]) -> Result<Zeroizing<Vec<u8>>, PassError> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied => PassError::GpgNotFound,
+            kind => PassError::Io(kind),
+        })?;
+    let mut stdin = child.stdin.take();
+    let output = thread::scope(|scope| {
+        scope.spawn(move || {
+            if let Some(stdin) = stdin.as_mut() {
+                // gpg may exit before reading everything, its status tells why
+                let _ = stdin.write_all(input);
+            }
+        });
+        child.wait_with_output()
+    })?;
+    let stdout = Zeroizing::new(output.stdout);
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr
+            .lines()
+            .rev()
+            .map(|line| line.trim_start_matches("gpg:").trim())
+            .find(|line| !line.is_empty())
+            .unwrap_or("Unknown error");
+        return Err(PassError::Gpg(message.to_string()));
+    }
+    Ok(stdout)
+}
+
+/// Collects the encrypted files below a folder, without hidden files and folders
+///
+/// # Arguments
+/// * `dir` - The folder
+/// * `files` - The collected files
+///
+/// # Returns
+/// An error if a folder cannot be read
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for item in fs::read_dir(dir)? {
+        let item = item?;
+        if item.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        // symbolic links are not followed, they could point outside of the store
+        let file_type = item.file_type()?;
+        let path = item.path();
+        if file_type.is_dir() {
+            collect_files(&path, files)?;
+        } else if file_type.is_file()
+            && path
+                .extension()
+                .is_some_and(|extension| extension == GPG_EXTENSION)
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Reads the content of a file of the store
+/// The `login:`, `url:`, `totp:` and `otpauth://` lines right after the password
+/// are read until any other line, an empty line right after them is left out.
+/// All other lines are the notes, as they are. A file without a password but with
+/// notes is a note
+///
+/// # Arguments
+/// * `name` - The name of the file, the title of the entry
+/// * `content` - The decrypted content
+///
+/// # Returns
+/// The entry
+fn parse_entry(name: &str, content: &str) -> Entry {
+    let mut lines = content.lines().peekable();
+    let mut entry = Entry::new(name, lines.next().unwrap_or(""));
+    while let Some(line) = lines.peek() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            // the empty line separates the notes
+            lines.next();
+            break;
+        }
+        if trimmed.starts_with("otpauth://") {
+            set_totp(&mut entry, "otpauth", trimmed);
+        } else {
+            // a `scheme://` line like a bare URL is not a `key: value` pair
+            let Some((key, value)) = trimmed
+                .split_once(':')
+                .filter(|(_, value)| !value.starts_with("//"))
+            else {
+                break;
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim();
+            if USERNAME_KEYS.contains(&key.as_str()) && entry.username.is_empty() {
+                entry.username = value.to_string();
+            } else if URL_KEYS.contains(&key.as_str()) && entry.url.is_empty() {
+                entry.url = value.to_string();
+            } else if key == "totp" {
+                set_totp(&mut entry, "totp", value);
+            } else {
+                break;
+            }
+        }
+        lines.next();
+    }
+
+    let mut notes = String::with_capacity(content.len());
+    for line in lines {
+        notes.push_str(line);
+        notes.push('\n');
+    }
+    notes.truncate(notes.trim_end().len());
+    entry.notes = notes.into();
+    if entry.password.is_empty() && !entry.notes.is_empty() {
+        entry.kind = EntryKind::Note;
+    }
+    entry
+}
+
+/// Writes the content of the file of an entry, the password first.
+/// Custom fields are written as `name: value` lines before the notes,
+/// both after an empty line
+///
+/// # Arguments
+/// * `entry` - The entry, a login or a note
+///
+/// # Returns
+/// The content
+fn format_entry(entry: &Entry) -> Zeroizing<String> {
+    let fields = entry
+        .fields
+        .iter()
+        .map(|field| field.name.len() + field.value.expose_secret().len() + 3)
+        .sum::<usize>();
+    // reserve everything up front, growing would leave copies of the secrets behind
+    let capacity = entry.password.expose_secret().len()
+        + entry.username.len()
+        + entry.url.len()
+        + entry.totp.expose_secret().len()
+        + entry.notes.expose_secret().len()
+        + fields
+        + 32;
+    let mut content = Zeroizing::new(String::with_capacity(capacity));
+
+    content.push_str(entry.password.expose_secret());
+    content.push('\n');
+    if !entry.username.is_empty() {
+        content.push_str("login: ");
+        content.push_str(&entry.username);
+        content.push('\n');
+    }
+    if !entry.url.is_empty() {
+        content.push_str("url: ");
+        content.push_str(&entry.url);
+        content.push('\n');
+    }
+    let totp = entry.totp.expose_secret();
+    if !totp.is_empty() {
+        if !totp.starts_with("otpauth://") {
+            content.push_str("totp: ");
+        }
+        content.push_str(totp);
+        content.push('\n');
+    }
+    // after the empty line nothing is read as the username, URL or TOTP secret
+    let notes = entry.notes.expose_secret();
+    if !entry.fields.is_empty() || !notes.is_empty() {
+        content.push('\n');
+    }
+    for field in entry.fields.iter() {
+        content.push_str(&field.name);
+        content.push_str(": ");
+        content.push_str(field.value.expose_secret());
+        content.push('\n');
+    }
+    if !notes.is_empty() {
+        content.push_str(notes);
+        content.push('\n');
+    }
+    content
+}
+
+/// Checks if an entry can be written in the layout of a file of the store,
+/// the password has to fit on the first line
+///
+/// # Arguments
+/// * `entry` - The entry
+///
+/// # Returns
+/// The reason it cannot be written or None
+fn unwritable(entry: &Entry) -> Option<&'static str> {
+    if entry.password.expose_secret().contains(['\n', '\r']) {
+        return Some("A password with a line break cannot be exported to pass");
+    }
+    None
+}
+
+/// Turns a title into the name of a file, without separators and leading dots
+///
+/// # Arguments
+/// * `name` - The title
+///
+/// # Returns
+/// The name of the file, without the extension
+fn file_name(name: &str) -> String {
+    let name = name.replace(['/', '\\'], "-");
+    let name = name.trim().trim_start_matches('.').trim();
+    if name.is_empty() {
+        "Untitled".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Turns a folder of an entry into a path below the root of the store
+/// Parts like `..` cannot leave the store
+///
+/// # Arguments
+/// * `folder` - The folder
+///
+/// # Returns
+/// The relative path
+fn folder_path(folder: &str) -> PathBuf {
+    folder
+        .split('/')
+        .map(|part| part.trim().trim_start_matches('.').trim())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.replace('\\', "-"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{entry::CustomField, test_util::TempDir};
+
+    #[test]
+    fn test_pass_entry_layout() {
+        let entry = parse_entry(
+            "example.com",
+            "hunter2\nLogin: krab\nurl: https://example.com/login\notpauth://totp/krab?secret=JBSWY3DPEHPK3PXP\nsecurity question: none\n\nsecond line\n",
+        );
+        let note = parse_entry("wifi", "\n\nssid: krab\npassword: hunter2\n");
+        let free_text = parse_entry(
+            "router",
+            "hunter2\nhttps://example.com\nNote: call support\nurl: https://example.com/login\n",
+        );
+        let mut login = Entry::new("example.com", "hunter2");
+        login.username = "krab".to_string();
+        login.totp = "JBSWY3DPEHPK3PXP".into();
+        login.fields.push(CustomField {
+            name: "pin".to_string(),
+            value: "1234".into(),
+            hidden: true,
+        });
+        login.notes = "login: first\nsecond".into();
+        let written = parse_entry("example.com", &format_entry(&login));
+        let mut multi_line = login.clone();
+        multi_line.password = "hunter2\nlogin: other".into();
+
+        assert_eq!(entry.password.expose_secret(), "hunter2");
+        assert_eq!(entry.username, "krab");
+        assert_eq!(entry.url, "https://example.com/login");
+        assert!(entry.totp.expose_secret().starts_with("otpauth://"));
+        assert_eq!(
+            entry.notes.expose_secret(),
+            "security question: none\n\nsecond line"
+        );
+        assert!(entry.fields.is_empty());
+        assert_eq!(entry.kind, EntryKind::Login);
+        assert_eq!(note.kind, EntryKind::Note);
+        assert_eq!(note.notes.expose_secret(), "ssid: krab\npassword: hunter2");
+        assert!(free_text.url.is_empty());
+        assert!(free_text.fields.is_empty());
+        assert_eq!(
+            free_text.notes.expose_secret(),
+            "https://example.com\nNote: call support\nurl: https://example.com/login"
+        );
+        assert_eq!(
+            format_entry(&login).as_str(),
+            "hunter2\nlogin: krab\ntotp: JBSWY3DPEHPK3PXP\n\npin: 1234\nlogin: first\nsecond\n"
+        );
+        assert_eq!(written.password, login.password);
+        assert_eq!(written.username, login.username);
+        assert_eq!(written.totp, login.totp);
+        assert!(written.fields.is_empty());
+        assert_eq!(
+            written.notes.expose_secret(),
+            "pin: 1234\nlogin: first\nsecond"
+        );
+        assert_eq!(unwritable(&login), None);
+        assert!(unwritable(&multi_line).is_some());
+        assert_eq!(file_name("../a/b"), "-a-b");
+        assert_eq!(file_name(" . "), "Untitled");
+        assert_eq!(
+            folder_path("Work/../.git/Mail"),
+            PathBuf::from("Work/git/Mail")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pass_store_roundtrip() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new();
+        let root = dir.path().join("store");
+        fs::create_dir_all(root.join("Work")).unwrap();
+        // stands in for gpg, the files are not encrypted
+        let gpg = dir.path().join("gpg");
+        fs::write(
+            &gpg,
+            "#!/bin/sh\nfor last; do :; done\ncase \"$*\" in\n*--decrypt*) exec cat \"$last\" ;;\n*) exec cat ;;\nesac\n",
+        )
+        .unwrap();
+        fs::set_permissions(&gpg, fs::Permissions::from_mode(0o700)).unwrap();
+        let store = PasswordStore::new(&root).with_gpg(&gpg);
+
+        let mut first = Entry::new("example.com", "first");
+        first.folder = "Work".to_string();
+        first.username = "krab".to_string();
+        first.fields.push(CustomField {
+            name: "pin".to_string(),
+            value: "1234".into(),
+            hidden: true,
+        });
+        let mut second = first.clone();
+        second.password = "second".into();
+        second.username = "other".to_string();
+        let mut note = Entry::new("wifi", "");
+        note.kind = EntryKind::Note;
+        note.notes = "ssid: krab".into();
+        let mut card = Entry::new("card", "");
+        card.kind = EntryKind::Card(Default::default());
+        let mut multi_line = Entry::new("multi", "first\nsecond");
+        multi_line.username = "krab".to_string();
+        let entries = vec![first, second, note, card, multi_line];
+
+        let no_recipients = store.export(&entries);
+        fs::write(root.join(GPG_ID), "# krab\nKRAB0001\n").unwrap();
+        fs::write(root.join("Work").join(GPG_ID), "KRAB0002\n").unwrap();
+        let recipients = store.recipients(&root.join("Work")).unwrap();
+        let export = store.export(&entries).unwrap();
+        let again = store.export(&entries).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join(".git").join("ignored.gpg"), "ignored").unwrap();
+        let import = store.import("").unwrap();
+        let mode = fs::metadata(root.join("wifi.gpg"))
+            .unwrap()
+            .permissions()
+            .mode();
+        let missing = PasswordStore::new(&root)
+            .with_gpg(&dir.path().join("missing"))
+            .import("");
+
+        assert_eq!(no_recipients, Err(PassError::NoRecipients));
+        assert_eq!(recipients, vec!["KRAB0002"]);
+        assert_eq!(export.written, 3);
+        assert_eq!(export.skipped.len(), 2);
+        assert_eq!(again.written, 0);
+        assert_eq!(again.skipped.len(), 5);
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(missing, Err(PassError::GpgNotFound));
+
+        let imported: Vec<(String, String, String)> = import
+            .entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.folder.clone(),
+                    entry.domain.clone(),
+                    entry.username.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            imported,
+            vec![
+                (
+                    "Work".to_string(),
+                    "example.com (other)".to_string(),
+                    "other".to_string()
+                ),
+                (
+                    "Work".to_string(),
+                    "example.com".to_string(),
+                    "krab".to_string()
+                ),
+                (String::new(), "wifi".to_string(), String::new()),
+            ]
+        );
+        assert_eq!(import.entries[0].password.expose_secret(), "second");
+        assert!(import.entries[1].fields.is_empty());
+        assert_eq!(import.entries[1].notes.expose_secret(), "pin: 1234");
+        assert_eq!(import.entries[2].kind, EntryKind::Note);
+        assert_eq!(import.entries[2].notes.expose_secret(), "ssid: krab");
+        assert!(import.entries[1].created > 0);
+    }
+}
@@ -0,0 +1,390 @@
+use std::{fmt, io, str};
+
+use zeroize::Zeroizing;
+
+use crate::{entry::Entry, secret::SecretString};
+
+/// Version of the payload format written by this version
+/// Versions are control characters, which neither JSON nor the legacy
+/// format start with, so older payloads are told apart by their first byte
+const PAYLOAD_VERSION: u8 = 1;
+/// The highest byte a version may have
+const MAX_PAYLOAD_VERSION: u8 = 0x1F;
+const VERSION_LENGTH: usize = 1;
+const LENGTH_PREFIX: usize = 4;
+
+/// Error while encoding or decoding the payload of a record
+/// It never contains any part of the payload itself
+///
+/// # Variants
+/// * `Decrypt` - The record could not be decrypted, the key is wrong or the data was tampered with
+/// * `TooLarge` - The entry is too large to be encoded, with its length in bytes
+/// * `Truncated` - The payload is shorter than its length prefix says
+/// * `TrailingBytes` - The payload has bytes after the entry, with their number
+/// * `UnsupportedVersion` - The payload was written by a newer version
+/// * `InvalidUtf8` - A legacy payload is not valid UTF-8
+/// * `MissingSeparator` - A legacy payload has no space between domain and password
+/// * `InvalidJson` - The entry is not valid JSON, with the position of the error
+///
+/// # Implements
+/// * `Display`
+/// * `Error`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadError {
+    Decrypt,
+    TooLarge(usize),
+    Truncated { expected: usize, actual: usize },
+    TrailingBytes(usize),
+    UnsupportedVersion(u8),
+    InvalidUtf8,
+    MissingSeparator,
+    InvalidJson { line: usize, column: usize },
+}
+
+impl fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PayloadError::Decrypt => write!(f, "the record could not be decrypted"),
+            PayloadError::TooLarge(length) => {
+                write!(f, "the entry is too large ({} bytes)", length)
+            }
+            PayloadError::Truncated { expected, actual } => write!(
+                f,
+                "the record is truncated ({} of {} bytes)",
+                actual, expected
+            ),
+            PayloadError::TrailingBytes(count) => {
+                write!(f, "the record has {} unexpected trailing bytes", count)
+            }
+            PayloadError::UnsupportedVersion(version) => {
+                write!(f, "the record format version {} is not supported", version)
+            }
+            PayloadError::InvalidUtf8 => write!(f, "the record is not valid UTF-8"),
+            PayloadError::MissingSeparator => {
+                write!(f, "the record has no domain-password separator")
+            }
+            PayloadError::InvalidJson { line, column } => write!(
+                f,
+                "the record is not a valid entry (line {}, column {})",
+                line, column
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PayloadError {}
+
+impl From<serde_json::Error> for PayloadError {
+    fn from(e: serde_json::Error) -> Self {
+        PayloadError::InvalidJson {
+            line: e.line(),
+            column: e.column(),
+        }
+    }
+}
+
+/// Counts the bytes written to it, used to size buffers holding secrets
+///
+/// # Fields
+/// * `0` - The number of bytes written
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Encodes an entry into the payload of a record
+/// The payload is the version, the length of the entry as a big-endian `u32`
+/// and the entry as JSON
+///
+/// # Arguments
+/// * `entry` - The entry
+///
+/// # Returns
+/// The payload or an error if the entry is too large
+pub(crate) fn encode(entry: &Entry) -> Result<Zeroizing<Vec<u8>>, PayloadError> {
+    // the entry is measured first, so the buffer never reallocates
+    // and leaves no copies of the secrets behind
+    let mut counter = ByteCounter(0);
+    serde_json::to_writer(&mut counter, entry)?;
+    let length = u32::try_from(counter.0).map_err(|_| PayloadError::TooLarge(counter.0))?;
+
+    let mut data = Zeroizing::new(Vec::with_capacity(
+        VERSION_LENGTH + LENGTH_PREFIX + counter.0,
+    ));
+    data.push(PAYLOAD_VERSION);
+    data.extend_from_slice(&length.to_be_bytes());
+    serde_json::to_writer(&mut *data, entry)?;
+    Ok(data)
+}
+
+/// Decodes the payload of a record
+/// Payloads written by older versions are either JSON without a version
+/// or a domain and a password, and are read as an entry without the other fields
+///
+/// # Arguments
+/// * `data` - The payload
+///
+/// # Returns
+/// The entry or an error describing why the payload is invalid
+pub(crate) fn decode(data: &[u8]) -> Result<Entry, PayloadError> {
+    match data.first() {
+        None => Err(PayloadError::Truncated {
+            expected: VERSION_LENGTH,
+            actual: 0,
+        }),
+        Some(&PAYLOAD_VERSION) => decode_versioned(&data[VERSION_LENGTH..]),
+        Some(&version) if version <= MAX_PAYLOAD_VERSION => {
+            Err(PayloadError::UnsupportedVersion(version))
+        }
+        Some(b'{') => serde_json::from_slice(data).or_else(|e| {
+            // a legacy domain may start with a brace as well
+            decode_legacy(data).map_err(|_| PayloadError::from(e))
+        }),
+        Some(_) => decode_legacy(data),
+    }
+}
+
+/// Decodes the length-prefixed entry of a versioned payload
+///
+/// # Arguments
+/// * `data` - The payload without the version
+///
+/// # Returns
+/// The entry or an error describing why the payload is invalid
+fn decode_versioned(data: &[u8]) -> Result<Entry, PayloadError> {
+    let prefix: [u8; LENGTH_PREFIX] = match data.get(..LENGTH_PREFIX) {
+        Some(prefix) => prefix.try_into().unwrap(),
+        None => {
+            return Err(PayloadError::Truncated {
+                expected: LENGTH_PREFIX,
+                actual: data.len(),
+            })
+        }
+    };
+    let length = u32::from_be_bytes(prefix) as usize;
+    let rest = &data[LENGTH_PREFIX..];
+    if rest.len() < length {
+        return Err(PayloadError::Truncated {
+            expected: length,
+            actual: rest.len(),
+        });
+    }
+    if rest.len() > length {
+        return Err(PayloadError::TrailingBytes(rest.len() - length));
+    }
+
+    Ok(serde_json::from_slice(rest)?)
+}
+
+/// Decodes the domain and password of older versions,
+/// unescaping spaces and backslashes in the process
+///
+/// # Arguments
+/// * `data` - The payload
+///
+/// # Returns
+/// An entry with the domain and password or an error if the payload is invalid
+fn decode_legacy(data: &[u8]) -> Result<Entry, PayloadError> {
+    let data = str::from_utf8(data).map_err(|_| PayloadError::InvalidUtf8)?;
+    let (domain_part, password_part) =
+        data.split_once(' ').ok_or(PayloadError::MissingSeparator)?;
+    let mut domain = String::with_capacity(domain_part.len());
+    unescape(domain_part, &mut domain);
+    let mut password = SecretString::new(String::with_capacity(password_part.len()));
+    unescape(password_part, password.expose_secret_mut());
+    Ok(Entry {
+        domain,
+        password,
+        ..Default::default()
+    })
+}
+
+/// Encodes the domain and password into the format of older versions,
+/// escaping spaces and backslashes in the process
+/// Domain and password are separated by a space after escaping
+///
+/// # Arguments
+/// * `domain` - The domain
+/// * `password` - The password
+///
+/// # Returns
+/// The encoded string
+#[cfg(test)]
+pub(crate) fn encode_legacy(domain: &str, password: &str) -> SecretString {
+    let mut data = String::with_capacity(2 * (domain.len() + password.len()) + 1);
+    escape(domain, &mut data);
+    data.push(' ');
+    escape(password, &mut data);
+    SecretString::new(data)
+}
+
+/// Escapes spaces and backslashes of a value into a buffer
+///
+/// # Arguments
+/// * `value` - The value to escape
+/// * `buffer` - The buffer to write to
+#[cfg(test)]
+fn escape(value: &str, buffer: &mut String) {
+    for c in value.chars() {
+        match c {
+            '\\' => buffer.push_str("\\\\"),
+            ' ' => buffer.push_str("\\s"),
+            c => buffer.push(c),
+        }
+    }
+}
+
+/// Unescapes spaces and backslashes of a value into a buffer
+///
+/// # Arguments
+/// * `value` - The value to unescape
+/// * `buffer` - The buffer to write to
+fn unescape(value: &str, buffer: &mut String) {
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('s')) => {
+                buffer.push(' ');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                buffer.push('\\');
+                chars.next();
+            }
+            (c, _) => buffer.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        entry::{CustomField, PreviousPassword},
+        kind::EntryKind,
+    };
+
+    #[test]
+    fn test_payload_roundtrip() {
+        let entry = Entry {
+            id: Entry::generate_id(),
+            domain: "example.com with spaces".to_string(),
+            username: "krab".to_string(),
+            password: SecretString::from("pass\tword with \\ and \"quotes\"\n\u{0}ü"),
+            totp: SecretString::from("GEZD GNBV GY3T QOJQ"),
+            url: "https://example.com".to_string(),
+            notes: SecretString::from("first line\r\nsecond line"),
+            fields: vec![CustomField {
+                name: "PIN".to_string(),
+                value: SecretString::from("1234"),
+                hidden: true,
+            }],
+            created: 1700000000,
+            modified: 1700000100,
+            last_used: 0,
+            history: vec![PreviousPassword {
+                password: SecretString::from("old\npassword"),
+                replaced: 1700000100,
+            }],
+            tags: vec!["work".to_string()],
+            folder: "Work".to_string(),
+            attachments: vec![],
+            kind: EntryKind::Note,
+        };
+
+        let data = encode(&entry).unwrap();
+
+        assert_eq!(data.len(), data.capacity());
+        assert_eq!(data[0], PAYLOAD_VERSION);
+        assert_eq!(decode(&data), Ok(entry));
+    }
+
+    #[test]
+    fn test_decode_legacy() {
+        let pairs = [
+            ("example.com", "password"),
+            ("example.com with spaces", "password with spaces"),
+            ("example.com with \\", "password with \\"),
+            (
+                "example.com with \\ and    spacessss",
+                "password with \\ and    spacessss",
+            ),
+            ("example.com\\s", "pass\\sword\\\\s"),
+            ("{example.com", "password"),
+        ];
+        for (domain, password) in pairs {
+            let data = encode_legacy(domain, password);
+            let entry = decode(data.expose_secret().as_bytes()).unwrap();
+            assert_eq!(entry, Entry::new(domain, password));
+        }
+
+        let data = encode_legacy(
+            "example.com with  spaces and \\",
+            "password with  spaces and \\",
+        );
+        assert_eq!(
+            data.expose_secret(),
+            "example.com\\swith\\s\\sspaces\\sand\\s\\\\ password\\swith\\s\\sspaces\\sand\\s\\\\"
+        );
+    }
+
+    #[test]
+    fn test_decode_unversioned_json() {
+        let entry = decode(br#"{"domain":"example.com","password":"password"}"#).unwrap();
+
+        assert_eq!(entry, Entry::new("example.com", "password"));
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let data = encode(&Entry::new("example.com", "password")).unwrap();
+        let mut trailing = data.to_vec();
+        trailing.push(b' ');
+        let mut invalid = data.to_vec();
+        let last = invalid.len() - 1;
+        invalid[last] = b']';
+
+        assert_eq!(
+            decode(&[]),
+            Err(PayloadError::Truncated {
+                expected: 1,
+                actual: 0
+            })
+        );
+        assert_eq!(
+            decode(&[PAYLOAD_VERSION, 0]),
+            Err(PayloadError::Truncated {
+                expected: 4,
+                actual: 1
+            })
+        );
+        assert_eq!(
+            decode(&data[..data.len() - 1]),
+            Err(PayloadError::Truncated {
+                expected: data.len() - 5,
+                actual: data.len() - 6
+            })
+        );
+        assert_eq!(decode(&trailing), Err(PayloadError::TrailingBytes(1)));
+        assert_eq!(decode(&[0x02, 0]), Err(PayloadError::UnsupportedVersion(2)));
+        assert!(matches!(
+            decode(&invalid),
+            Err(PayloadError::InvalidJson { .. })
+        ));
+        assert_eq!(decode(b"no-separator"), Err(PayloadError::MissingSeparator));
+        assert_eq!(decode(&[0xFF, b' ', 0xFE]), Err(PayloadError::InvalidUtf8));
+        assert!(matches!(
+            decode(b"{\"domain\":"),
+            Err(PayloadError::InvalidJson { .. })
+        ));
+    }
+}
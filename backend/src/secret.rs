@@ -0,0 +1,146 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+use zeroize::Zeroize;
+
+/// Capacity reserved for secrets typed in by the user
+/// Growing a `String` moves it to a new allocation and leaves the old one
+/// behind unzeroed, so editable secrets never grow past this capacity
+pub const SECRET_CAPACITY: usize = 1024;
+
+/// A string holding a secret, like a master password or a stored password
+/// The memory is zeroed when it is dropped and `Debug` never shows the secret
+///
+/// # Fields
+/// * `0` - The secret
+///
+/// # Methods
+/// * `new` - Creates a new `SecretString`
+/// * `with_capacity` - Creates an empty `SecretString` for editing
+/// * `expose_secret` - Returns the secret
+/// * `expose_secret_mut` - Returns the secret for editing
+/// * `is_empty` - Checks if the secret is empty
+///
+/// # Implements
+/// * `Debug` - Redacts the secret
+/// * `Drop` - Zeroes the secret
+/// * `Serialize` - As a plain string, only to be written encrypted
+/// * `Deserialize`
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString([REDACTED])")
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Serialize for SecretString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(SecretString)
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        SecretString(secret)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        SecretString(secret.to_string())
+    }
+}
+
+impl SecretString {
+    /// Creates a new `SecretString`
+    ///
+    /// # Arguments
+    /// * `secret` - The secret
+    ///
+    /// # Returns
+    /// A new `SecretString`
+    pub fn new(secret: String) -> Self {
+        SecretString(secret)
+    }
+
+    /// Creates an empty `SecretString` with `SECRET_CAPACITY` reserved,
+    /// so that editing it does not leave copies behind
+    ///
+    /// # Arguments
+    /// * `secret` - The initial value
+    ///
+    /// # Returns
+    /// A new `SecretString`
+    pub fn with_capacity(secret: &str) -> Self {
+        let mut value = String::with_capacity(SECRET_CAPACITY.max(secret.len()));
+        value.push_str(secret);
+        SecretString(value)
+    }
+
+    /// Returns the secret
+    ///
+    /// # Returns
+    /// The secret
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns the secret for editing
+    /// Edits must stay within the capacity, otherwise the old memory is not zeroed
+    ///
+    /// # Returns
+    /// The secret
+    pub fn expose_secret_mut(&mut self) -> &mut String {
+        &mut self.0
+    }
+
+    /// Checks if the secret is empty
+    ///
+    /// # Returns
+    /// `true` if the secret is empty, otherwise `false`
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_debug_is_redacted() {
+        let secret = SecretString::from("hunter2");
+
+        let debug = format!("{:?}", secret);
+
+        assert!(!debug.contains("hunter2"));
+        assert_eq!(secret.expose_secret(), "hunter2");
+    }
+
+    #[test]
+    fn test_secret_with_capacity_does_not_grow() {
+        let mut secret = SecretString::with_capacity("abc");
+        let capacity = secret.expose_secret_mut().capacity();
+
+        for _ in 0..200 {
+            secret.expose_secret_mut().push('x');
+        }
+
+        assert_eq!(secret.expose_secret_mut().capacity(), capacity);
+        assert!(capacity >= SECRET_CAPACITY);
+    }
+}
@@ -0,0 +1,1092 @@
+use std::{fmt, io, path::Path};
+
+use zeroize::Zeroizing;
+
+use crate::{
+    attachment::{Attachment, MAX_ATTACHMENTS},
+    backup::{AttachmentBlob, Backup},
+    cipher::CipherKind,
+    entry::timestamp,
+    import::{merge, DuplicatePolicy, ImportCounts},
+    kdf::KdfParams,
+    store::{FileStore, VaultStore},
+    user::{Entry, KrabError, Migration, ReadOnlyRecords, RecordKey, User},
+};
+
+/// VaultSession is an unlocked vault
+/// The key of the records is unwrapped once on login and kept in memory,
+/// so changes neither need the master password nor derive the key again
+///
+/// # Fields
+/// * `user` - The user
+/// * `record_key` - The key of the records
+#[derive(Clone, PartialEq)]
+pub struct VaultSession<S = FileStore> {
+    user: User<S>,
+    record_key: RecordKey,
+}
+
+impl<S: fmt::Debug> fmt::Debug for VaultSession<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VaultSession")
+            .field("user", &self.user)
+            .finish_non_exhaustive()
+    }
+}
+
+impl VaultSession {
+    /// Unlocks the vault of a user from the file system
+    /// A legacy vault is migrated to the versioned format,
+    /// the outcome is available through `migration`
+    ///
+    /// # Arguments
+    /// * `path` - The path to the user data
+    /// * `username` - The username
+    /// * `master_password` - The master password
+    ///
+    /// # Returns
+    /// A new `VaultSession` and the `ReadOnlyRecords` or an error
+    pub fn open(
+        path: &Path,
+        username: &str,
+        master_password: &str,
+    ) -> Result<(Self, ReadOnlyRecords), KrabError> {
+        Self::open_store(FileStore::new(path), username, master_password)
+    }
+
+    /// Creates a new user on the file system from a backup
+    ///
+    /// # Arguments
+    /// * `path` - The path to the user data
+    /// * `username` - The username
+    /// * `master_password` - The master password
+    /// * `kdf` - The key derivation parameters of the vault
+    /// * `cipher` - The cipher of the vault
+    /// * `backup` - The backup
+    ///
+    /// # Returns
+    /// A new `VaultSession` and the `ReadOnlyRecords` or an error
+    pub fn restore(
+        path: &Path,
+        username: &str,
+        master_password: &str,
+        kdf: &KdfParams,
+        cipher: CipherKind,
+        backup: &Backup,
+    ) -> Result<(Self, ReadOnlyRecords), KrabError> {
+        Self::restore_store(
+            FileStore::new(path),
+            username,
+            master_password,
+            kdf,
+            cipher,
+            backup,
+        )
+    }
+}
+
+impl<S: VaultStore> VaultSession<S> {
+    /// Unlocks the vault of a user from a store
+    /// A legacy vault is migrated to the versioned format,
+    /// the outcome is available through `migration`
+    ///
+    /// # Arguments
+    /// * `store` - The store holding the user data
+    /// * `username` - The username
+    /// * `master_password` - The master password
+    ///
+    /// # Returns
+    /// A new `VaultSession` and the `ReadOnlyRecords` or an error
+    pub fn open_store(
+        store: S,
+        username: &str,
+        master_password: &str,
+    ) -> Result<(Self, ReadOnlyRecords), KrabError> {
+        let (user, records, record_key) = User::open_store(store, username, master_password)?;
+        Ok((VaultSession { user, record_key }, records))
+    }
+
+    /// Creates a new user in a store from a backup
+    /// The user is created empty first, if the entries cannot be
+    /// imported afterwards the new user is deleted again
+    ///
+    /// # Arguments
+    /// * `store` - The store holding the user data
+    /// * `username` - The username
+    /// * `master_password` - The master password
+    /// * `kdf` - The key derivation parameters of the vault
+    /// * `cipher` - The cipher of the vault
+    /// * `backup` - The backup
+    ///
+    /// # Returns
+    /// A new `VaultSession` and the `ReadOnlyRecords` or an error
+    pub fn restore_store(
+        store: S,
+        username: &str,
+        master_password: &str,
+        kdf: &KdfParams,
+        cipher: CipherKind,
+        backup: &Backup,
+    ) -> Result<(Self, ReadOnlyRecords), KrabError> {
+        User::create_in_store(&store, username, master_password, kdf, cipher)?;
+        let (mut session, _) = Self::open_store(store, username, master_password)?;
+        match session.import_backup(backup) {
+            Ok((records, _)) => Ok((session, records)),
+            Err(e) => {
+                let _ = session.user.delete_vault();
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns the username of the user
+    ///
+    /// # Returns
+    /// The username
+    pub fn username(&self) -> String {
+        self.user.username()
+    }
+
+    /// Returns the outcome of the legacy format migration done on login
+    ///
+    /// # Returns
+    /// The migration outcome or None if the vault was already versioned
+    pub fn migration(&self) -> Option<Migration> {
+        self.user.migration()
+    }
+
+    /// Checks the master password, for changes that should be confirmed with it
+    ///
+    /// # Arguments
+    /// * `master_password` - The master password to check
+    ///
+    /// # Returns
+    /// `true` if the master password unlocks the vault, otherwise `false`
+    pub fn verify_master_password(&self, master_password: &str) -> bool {
+        self.user
+            .check_master_password(&self.record_key, master_password)
+    }
+
+    /// Adds a new record to the vault
+    /// An entry without an id gets a new one
+    ///
+    /// # Arguments
+    /// * `entry` - The entry
+    ///
+    /// # Returns
+    /// The read-only records or an error
+    pub fn add_record(&mut self, entry: &Entry) -> Result<ReadOnlyRecords, KrabError> {
+        let _lock = self.user.lock()?;
+        let records = self.user.read_records(&self.record_key, true)?;
+        self.user.insert_record(records, &self.record_key, entry)
+    }
+
+    /// Adds new records to the vault at once, like `add_record` for each entry
+    /// Either all or none of the entries are added
+    ///
+    /// # Arguments
+    /// * `entries` - The entries
+    ///
+    /// # Returns
+    /// The read-only records or an error
+    pub fn add_records(&mut self, entries: &[Entry]) -> Result<ReadOnlyRecords, KrabError> {
+        let _lock = self.user.lock()?;
+        let records = self.user.read_records(&self.record_key, true)?;
+        self.user.insert_records(records, &self.record_key, entries)
+    }
+
+    /// Adds the entries read from another password manager to the vault at once
+    /// An entry with the same domain and username as another one is a duplicate,
+    /// the policy decides whether it is left out, replaces the other one or is kept too
+    ///
+    /// # Arguments
+    /// * `entries` - The entries
+    /// * `policy` - What happens to duplicates
+    ///
+    /// # Returns
+    /// The read-only records and the number of entries by what happened to them, or an error
+    pub fn import_records(
+        &mut self,
+        entries: &[Entry],
+        policy: DuplicatePolicy,
+    ) -> Result<(ReadOnlyRecords, ImportCounts), KrabError> {
+        let _lock = self.user.lock()?;
+        let records = self.user.read_records(&self.record_key, true)?;
+        let merge = merge(records.records(), entries, policy, timestamp());
+        let records =
+            self.user
+                .update_records(records, &self.record_key, &merge.replaced, &merge.added)?;
+        Ok((records, merge.counts))
+    }
+
+    /// Removes a record from the vault, together with its attachments
+    ///
+    /// # Arguments
+    /// * `id` - The id of the record
+    ///
+    /// # Returns
+    /// The read-only records or an error
+    pub fn remove_record(&mut self, id: &str) -> Result<ReadOnlyRecords, KrabError> {
+        let _lock = self.user.lock()?;
+        let records = self.user.read_records(&self.record_key, true)?;
+        let attachments = records
+            .get(id)
+            .map(|entry| entry.attachments.clone())
+            .unwrap_or_default();
+        let records = self.user.delete_record(records, id)?;
+        // the blobs are deleted last, a failure only leaves unreferenced blobs behind
+        for attachment in attachments {
+            let _ = self.user.delete_attachment(&attachment.id);
+        }
+        Ok(records)
+    }
+
+    /// Replaces a record of the vault with an edited entry
+    /// A changed password is kept in the history of the entry
+    ///
+    /// # Arguments
+    /// * `id` - The id of the record
+    /// * `entry` - The edited entry, it keeps the id, creation time and history of the record
+    ///
+    /// # Returns
+    /// The read-only records or an error
+    pub fn modify_record(&mut self, id: &str, entry: &Entry) -> Result<ReadOnlyRecords, KrabError> {
+        let _lock = self.user.lock()?;
+        let records = self.user.read_records(&self.record_key, true)?;
+        let entry = match records.get(id) {
+            Some(previous) => previous.revise(entry, timestamp()),
+            None => return Err(KrabError::RecordNotFound),
+        };
+        self.user
+            .replace_record(records, &self.record_key, id, &entry)
+    }
+
+    /// Restores a previous password of a record
+    /// The restored password leaves the history and the current one takes its place
+    ///
+    /// # Arguments
+    /// * `id` - The id of the record
+    /// * `index` - The index of the password in the history, `0` being the most recent one
+    ///
+    /// # Returns
+    /// The read-only records or an error
+    pub fn restore_password(
+        &mut self,
+        id: &str,
+        index: usize,
+    ) -> Result<ReadOnlyRecords, KrabError> {
+        let _lock = self.user.lock()?;
+        let records = self.user.read_records(&self.record_key, true)?;
+        let mut previous = match records.get(id) {
+            Some(previous) if index < previous.history.len() => previous.clone(),
+            _ => return Err(KrabError::RecordNotFound),
+        };
+        let restored = previous.history.remove(index);
+        let mut edited = previous.clone();
+        edited.password = restored.password;
+        let entry = previous.revise(&edited, timestamp());
+        self.user
+            .replace_record(records, &self.record_key, id, &entry)
+    }
+
+    /// Records that the password or username of a record was used
+    /// The previous user file is not kept as a generation, so copying
+    /// secrets does not push out the generations of real changes
+    ///
+    /// # Arguments
+    /// * `id` - The id of the record
+    ///
+    /// # Returns
+    /// The read-only records or an error
+    pub fn mark_used(&mut self, id: &str) -> Result<ReadOnlyRecords, KrabError> {
+        let _lock = self.user.lock()?;
+        let records = self.user.read_records(&self.record_key, true)?;
+        let mut entry = match records.get(id) {
+            Some(entry) => entry.clone(),
+            None => return Err(KrabError::RecordNotFound),
+        };
+        entry.last_used = timestamp();
+        self.user
+            .touch_record(records, &self.record_key, id, &entry)
+    }
+
+    /// Attaches a file to a record
+    /// The content is encrypted into a blob of its own, which is stored
+    /// before the record references it
+    ///
+    /// # Arguments
+    /// * `id` - The id of the record
+    /// * `name` - The file name
+    /// * `content` - The content, at most `MAX_ATTACHMENT_SIZE` bytes
+    ///
+    /// # Returns
+    /// The read-only records or an error
+    pub fn add_attachment(
+        &mut self,
+        id: &str,
+        name: &str,
+        content: &[u8],
+    ) -> Result<ReadOnlyRecords, KrabError> {
+        let _lock = self.user.lock()?;
+        let records = self.user.read_records(&self.record_key, true)?;
+        let previous = records.get(id).ok_or(KrabError::RecordNotFound)?.clone();
+        if previous.attachments.len() >= MAX_ATTACHMENTS {
+            return Err(KrabError::TooManyAttachments);
+        }
+
+        let (attachment, blob) = Attachment::seal(name, content, self.user.cipher())?;
+        self.user.write_attachment(&attachment.id, blob)?;
+        let mut entry = previous.revise(&previous, timestamp());
+        entry.attachments.push(attachment.clone());
+        let result = self
+            .user
+            .replace_record(records, &self.record_key, id, &entry);
+        if result.is_err() {
+            let _ = self.user.delete_attachment(&attachment.id);
+        }
+        result
+    }
+
+    /// Reads the content of an attachment and verifies its integrity
+    ///
+    /// # Arguments
+    /// * `attachment` - The attachment, as listed in its entry
+    ///
+    /// # Returns
+    /// The content or an error
+    pub fn read_attachment(
+        &self,
+        attachment: &Attachment,
+    ) -> Result<Zeroizing<Vec<u8>>, KrabError> {
+        attachment.open(&self.user.read_attachment(&attachment.id)?)
+    }
+
+    /// Removes an attachment from a record and deletes its blob
+    ///
+    /// # Arguments
+    /// * `id` - The id of the record
+    /// * `attachment_id` - The id of the attachment
+    ///
+    /// # Returns
+    /// The read-only records or an error
+    pub fn remove_attachment(
+        &mut self,
+        id: &str,
+        attachment_id: &str,
+    ) -> Result<ReadOnlyRecords, KrabError> {
+        let _lock = self.user.lock()?;
+        let records = self.user.read_records(&self.record_key, true)?;
+        let previous = records.get(id).ok_or(KrabError::RecordNotFound)?;
+        if !previous.attachments.iter().any(|a| a.id == attachment_id) {
+            return Err(KrabError::AttachmentNotFound);
+        }
+
+        let mut entry = previous.revise(previous, timestamp());
+        entry.attachments.retain(|a| a.id != attachment_id);
+        let records = self
+            .user
+            .replace_record(records, &self.record_key, id, &entry)?;
+        // the blob is deleted last, a failure only leaves an unreferenced blob behind
+        let _ = self.user.delete_attachment(attachment_id);
+        Ok(records)
+    }
+
+    /// Changes the master password of the vault
+    /// The key of the records is wrapped again, so the records are kept as they are
+    ///
+    /// # Arguments
+    /// * `old_master_password` - The current master password
+    /// * `new_master_password` - The new master password
+    ///
+    /// # Returns
+    /// An error if the master password could not be changed
+    pub fn change_master_password(
+        &mut self,
+        old_master_password: &str,
+        new_master_password: &str,
+    ) -> Result<(), KrabError> {
+        if !self.verify_master_password(old_master_password) {
+            return Err(KrabError::WrongCredentials);
+        }
+
+        let _lock = self.user.lock()?;
+        let records = self.user.read_records(&self.record_key, true)?;
+        let kdf = self.user.kdf();
+        let cipher = self.user.cipher();
+        self.record_key = self.user.rewrap(
+            &records,
+            &self.record_key,
+            new_master_password,
+            &kdf,
+            cipher,
+        )?;
+        Ok(())
+    }
+
+    /// Returns the key derivation parameters of the vault
+    ///
+    /// # Returns
+    /// The key derivation parameters
+    pub fn kdf(&self) -> KdfParams {
+        self.user.kdf()
+    }
+
+    /// Returns the cipher of the vault
+    ///
+    /// # Returns
+    /// The cipher
+    pub fn cipher(&self) -> CipherKind {
+        self.user.cipher()
+    }
+
+    /// Changes the key derivation parameters and the cipher of the vault,
+    /// e.g. to raise its cost or to upgrade a 128-bit vault
+    /// The master password stays the same, it is needed to derive the new master key
+    /// Changing the cipher re-encrypts all records with a fresh key
+    ///
+    /// # Arguments
+    /// * `master_password` - The master password
+    /// * `kdf` - The new key derivation parameters
+    /// * `cipher` - The new cipher
+    ///
+    /// # Returns
+    /// An error if the parameters could not be changed
+    pub fn change_encryption(
+        &mut self,
+        master_password: &str,
+        kdf: &KdfParams,
+        cipher: CipherKind,
+    ) -> Result<(), KrabError> {
+        if !kdf.is_supported() {
+            return Err(KrabError::UnsupportedKdf);
+        }
+        if !self.verify_master_password(master_password) {
+            return Err(KrabError::WrongCredentials);
+        }
+
+        let _lock = self.user.lock()?;
+        let records = self.user.read_records(&self.record_key, true)?;
+        self.record_key =
+            self.user
+                .rewrap(&records, &self.record_key, master_password, kdf, cipher)?;
+        Ok(())
+    }
+
+    /// Exports the entries of the vault together with the blobs of their attachments
+    /// The backup holds the entries in clear text, it should be sealed with
+    /// `Backup::seal` before it is written anywhere
+    ///
+    /// # Returns
+    /// The backup or an error
+    pub fn export_backup(&mut self) -> Result<Backup, KrabError> {
+        let _lock = self.user.lock()?;
+        let records = self.user.read_records(&self.record_key, true)?;
+        let mut attachments = vec![];
+        for attachment in records.records().iter().flat_map(|e| e.attachments.iter()) {
+            let blob = self.user.read_attachment(&attachment.id)?;
+            attachments.push(AttachmentBlob::new(&attachment.id, &blob));
+        }
+        Ok(Backup::new(
+            &self.username(),
+            records.into_records(),
+            attachments,
+        ))
+    }
+
+    /// Merges the entries of a backup into the vault
+    /// Entries whose id is already in the vault are skipped, attachments
+    /// whose blob is missing from the backup are dropped from their entry
+    /// All entries are written at once, so either all or none are imported
+    ///
+    /// # Arguments
+    /// * `backup` - The backup
+    ///
+    /// # Returns
+    /// The read-only records and the number of imported entries or an error
+    pub fn import_backup(
+        &mut self,
+        backup: &Backup,
+    ) -> Result<(ReadOnlyRecords, usize), KrabError> {
+        let _lock = self.user.lock()?;
+        let records = self.user.read_records(&self.record_key, true)?;
+        let mut entries: Vec<Entry> = vec![];
+        let mut blobs = vec![];
+        for entry in backup.entries.iter() {
+            if !entry.id.is_empty()
+                && (records.get(&entry.id).is_some() || entries.iter().any(|e| e.id == entry.id))
+            {
+                continue;
+            }
+            let mut entry = entry.clone();
+            entry.attachments.retain(|attachment| {
+                let blob = backup
+                    .attachments
+                    .iter()
+                    .find(|blob| blob.id == attachment.id)
+                    .and_then(|blob| blob.blob());
+                match blob {
+                    Some(blob) => {
+                        blobs.push((attachment.id.clone(), blob));
+                        true
+                    }
+                    None => false,
+                }
+            });
+            entries.push(entry);
+        }
+        if entries.is_empty() {
+            return Ok((records, 0));
+        }
+
+        // the blobs are stored before the records reference them, a blob that
+        // is already stored comes from an earlier import of the same backup
+        let mut written = vec![];
+        let mut result = Ok(());
+        for (id, blob) in blobs {
+            match self.user.write_attachment(&id, blob) {
+                Ok(()) => written.push(id),
+                Err(KrabError::Io(io::ErrorKind::AlreadyExists)) => {}
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        let result = result.and_then(|_| {
+            self.user
+                .insert_records(records, &self.record_key, &entries)
+        });
+        match result {
+            Ok(records) => Ok((records, entries.len())),
+            Err(e) => {
+                for id in written {
+                    let _ = self.user.delete_attachment(&id);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Reads the vault again, including changes made by other processes
+    ///
+    /// # Returns
+    /// The read-only records or an error
+    pub fn reload(&mut self) -> Result<ReadOnlyRecords, KrabError> {
+        self.user.read_records(&self.record_key, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        attachment::MAX_ATTACHMENT_SIZE,
+        check_user, hash,
+        secret::SecretString,
+        store::MemoryStore,
+        test_util::{generate_random_username, setup_user_data},
+        user::{RecordOperationConfig, VAULT_GENERATIONS},
+    };
+    use std::{path::PathBuf, time::Duration};
+
+    fn open_session(
+        store: &MemoryStore,
+        config: &RecordOperationConfig,
+    ) -> Result<(VaultSession<MemoryStore>, ReadOnlyRecords), KrabError> {
+        VaultSession::open_store(
+            store.clone(),
+            &config.username,
+            config.master_password.expose_secret(),
+        )
+    }
+
+    #[test]
+    fn test_session_records_success() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut session, _) = open_session(&store, &user_data).unwrap();
+
+        let added = session
+            .add_record(&Entry::new("example2.com", "password2"))
+            .unwrap();
+        let mut entry = Entry::new("example.com", "password3");
+        entry.username = "krab".to_string();
+        let modified = session.modify_record(&added.records()[0].id, &entry);
+        let removed = session.remove_record(&added.records()[1].id);
+        let (reopened, records) = open_session(&store, &user_data).unwrap();
+
+        let stored = &records.records()[0];
+        assert_eq!(added.records().len(), 2);
+        assert!(modified.is_ok());
+        assert_eq!(removed.unwrap().records().len(), 1);
+        assert_eq!(reopened.username(), user_data.username);
+        assert_eq!(records.records().len(), 1);
+        assert_eq!(stored.id, added.records()[0].id);
+        assert_eq!(stored.username, "krab");
+        assert_eq!(stored.password, entry.password);
+        assert_eq!(stored.created, added.records()[0].created);
+        assert!(stored.modified >= stored.created);
+        assert_eq!(stored.history.len(), 1);
+        assert_eq!(stored.history[0].password.expose_secret(), "password");
+    }
+
+    #[test]
+    fn test_session_modify_record_rename() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut session, records) = open_session(&store, &user_data).unwrap();
+        let id = records.records()[0].id.clone();
+        session
+            .add_record(&Entry::new("example2.com", "password2"))
+            .unwrap();
+
+        let renamed = session.modify_record(&id, &Entry::new("example2.com", "password"));
+        let missing = session.modify_record("missing", &Entry::new("example3.com", "password"));
+
+        let renamed = renamed.unwrap();
+        let domains: Vec<String> = renamed.records().iter().map(|e| e.domain.clone()).collect();
+        assert_eq!(domains, vec!["example2.com", "example2.com"]);
+        // the record keeps its position
+        assert_eq!(renamed.records()[0].id, id);
+        assert_eq!(missing.unwrap_err(), KrabError::RecordNotFound);
+    }
+
+    #[test]
+    fn test_session_same_domain_different_usernames() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut session, _) = open_session(&store, &user_data).unwrap();
+
+        let mut personal = Entry::new("github.com", "password2");
+        personal.username = "personal".to_string();
+        let mut work = Entry::new("github.com", "password3");
+        work.username = "work".to_string();
+        session.add_record(&personal).unwrap();
+        let added = session.add_record(&work).unwrap();
+        let removed = session.remove_record(&added.records()[1].id);
+
+        let records = removed.unwrap();
+        assert_eq!(added.records().len(), 3);
+        assert_eq!(records.records().len(), 2);
+        assert_eq!(records.records()[1].username, "work");
+    }
+
+    #[test]
+    fn test_session_verify_master_password() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (session, _) = open_session(&store, &user_data).unwrap();
+
+        let correct = session.verify_master_password(user_data.master_password.expose_secret());
+        let wrong = session.verify_master_password("wrong_password");
+
+        assert!(correct);
+        assert!(!wrong);
+    }
+
+    #[test]
+    fn test_session_reload_after_conflict() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut session, _) = open_session(&store, &user_data).unwrap();
+        let (mut other_session, _) = open_session(&store, &user_data).unwrap();
+
+        let res = other_session.add_record(&Entry::new("example2.com", "password2"));
+        let conflict_res = session.add_record(&Entry::new("example3.com", "password3"));
+        let reloaded = session.reload();
+        let res_after_reload = session.add_record(&Entry::new("example3.com", "password3"));
+
+        assert!(res.is_ok());
+        assert_eq!(conflict_res.unwrap_err(), KrabError::Conflict);
+        assert_eq!(reloaded.unwrap().records().len(), 2);
+        assert_eq!(res_after_reload.unwrap().records().len(), 3);
+    }
+
+    #[test]
+    fn test_session_change_master_password() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut session, _) = open_session(&store, &user_data).unwrap();
+
+        let wrong = session.change_master_password("wrong_password", "new_password");
+        let res = session
+            .change_master_password(user_data.master_password.expose_secret(), "new_password");
+        let added = session.add_record(&Entry::new("example2.com", "password2"));
+        let old = open_session(&store, &user_data);
+        let new = VaultSession::open_store(store.clone(), &user_data.username, "new_password");
+
+        assert_eq!(wrong.unwrap_err(), KrabError::WrongCredentials);
+        assert!(res.is_ok());
+        assert!(added.is_ok());
+        assert_eq!(old.unwrap_err(), KrabError::WrongCredentials);
+        assert_eq!(new.unwrap().1.records().len(), 2);
+    }
+
+    #[test]
+    fn test_session_change_kdf() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut session, _) = open_session(&store, &user_data).unwrap();
+        let argon2 = KdfParams::default_argon2id();
+        let cipher = session.cipher();
+
+        let wrong = session.change_encryption("wrong_password", &argon2, cipher);
+        let res =
+            session.change_encryption(user_data.master_password.expose_secret(), &argon2, cipher);
+        let added = session.add_record(&Entry::new("example2.com", "password2"));
+        let (reopened, records) = open_session(&store, &user_data).unwrap();
+
+        assert_eq!(wrong.unwrap_err(), KrabError::WrongCredentials);
+        assert!(res.is_ok());
+        assert!(added.is_ok());
+        assert_eq!(session.kdf(), argon2);
+        assert_eq!(reopened.kdf(), argon2);
+        assert_eq!(records.records().len(), 2);
+    }
+
+    #[test]
+    fn test_session_upgrade_cipher() {
+        let store = MemoryStore::new();
+        let username = generate_random_username();
+        let user_data = RecordOperationConfig::new(
+            &username,
+            "password",
+            "example.com",
+            "password",
+            &PathBuf::new(),
+        );
+        User::create_in_store(
+            &store,
+            &username,
+            "password",
+            &KdfParams::default(),
+            CipherKind::Aes128GcmSiv,
+        )
+        .unwrap();
+        let (mut session, _) = open_session(&store, &user_data).unwrap();
+        session
+            .add_record(&Entry::new("example.com", "password"))
+            .unwrap();
+        let kdf = session.kdf();
+
+        let res = session.change_encryption("password", &kdf, CipherKind::XChaCha20Poly1305);
+        let added = session.add_record(&Entry::new("example2.com", "password2"));
+        let (reopened, records) = open_session(&store, &user_data).unwrap();
+
+        assert!(res.is_ok());
+        assert!(added.is_ok());
+        assert_eq!(session.cipher(), CipherKind::XChaCha20Poly1305);
+        assert_eq!(reopened.cipher(), CipherKind::XChaCha20Poly1305);
+        let domains: Vec<&str> = records
+            .records()
+            .iter()
+            .map(|e| e.domain.as_str())
+            .collect();
+        assert_eq!(domains, vec!["example.com", "example2.com"]);
+        assert_eq!(records.records()[1].password.expose_secret(), "password2");
+    }
+
+    #[test]
+    fn test_session_debug_hides_key() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (session, _) = open_session(&store, &user_data).unwrap();
+
+        let debug = format!("{:?}", session);
+
+        assert!(debug.starts_with("VaultSession"));
+        assert!(!debug.contains("record_key"));
+    }
+
+    #[test]
+    fn test_session_memory_store() {
+        let store = MemoryStore::new();
+        User::create_in_store(
+            &store,
+            "krab",
+            "password",
+            &KdfParams::default(),
+            CipherKind::default(),
+        )
+        .unwrap();
+        let (mut session, _) = VaultSession::open_store(store.clone(), "krab", "password").unwrap();
+
+        let added = session
+            .add_record(&Entry::new("example.com", "password"))
+            .unwrap();
+        let id = added.records()[0].id.clone();
+        let mut entry = added.records()[0].clone();
+        entry.username = "krab@example.com".to_string();
+        session.modify_record(&id, &entry).unwrap();
+        let lock = store
+            .lock(&hash("krab".to_string()), Duration::ZERO)
+            .unwrap();
+        let locked = session.add_record(&Entry::new("example2.com", "password2"));
+        drop(lock);
+        let (_, records) = VaultSession::open_store(store.clone(), "krab", "password").unwrap();
+
+        assert_eq!(locked, Err(KrabError::Locked));
+        assert_eq!(records.records(), &[entry]);
+        assert_eq!(session.user.generations().len(), 2);
+        assert!(check_user("krab", &store));
+        assert!(!check_user("other", &store));
+    }
+
+    #[test]
+    fn test_session_add_records() {
+        let store = MemoryStore::new();
+        User::create_in_store(
+            &store,
+            "krab",
+            "password",
+            &KdfParams::default(),
+            CipherKind::default(),
+        )
+        .unwrap();
+        let (mut session, _) = VaultSession::open_store(store.clone(), "krab", "password").unwrap();
+
+        let records = session
+            .add_records(&[
+                Entry::new("example.com", "password"),
+                Entry::new("example2.com", "password2"),
+            ])
+            .unwrap();
+        let mut duplicate = Entry::new("example3.com", "password3");
+        duplicate.id = records.records()[0].id.clone();
+        let rejected = session.add_records(&[Entry::new("example4.com", "password4"), duplicate]);
+        let (_, reopened) = VaultSession::open_store(store.clone(), "krab", "password").unwrap();
+
+        assert_eq!(records.records().len(), 2);
+        assert!(records.records().iter().all(|entry| entry.created > 0));
+        assert_eq!(rejected, Err(KrabError::RecordAlreadyExists));
+        assert_eq!(reopened.records(), records.records());
+        assert_eq!(session.user.generations().len(), 1);
+    }
+
+    #[test]
+    fn test_session_import_records() {
+        let store = MemoryStore::new();
+        User::create_in_store(
+            &store,
+            "krab",
+            "password",
+            &KdfParams::default(),
+            CipherKind::default(),
+        )
+        .unwrap();
+        let (mut session, _) = VaultSession::open_store(store.clone(), "krab", "password").unwrap();
+        let mut existing = Entry::new("example.com", "old");
+        existing.username = "krab".to_string();
+        let records = session
+            .add_records(&[existing.clone(), Entry::new("other.com", "password")])
+            .unwrap();
+        let id = records.records()[0].id.clone();
+        let mut imported = existing.clone();
+        imported.password = "new".into();
+        let entries = vec![imported, Entry::new("new.com", "password")];
+
+        let (skipped, skip_counts) = session
+            .import_records(&entries, DuplicatePolicy::Skip)
+            .unwrap();
+        let (overwritten, overwrite_counts) = session
+            .import_records(&entries, DuplicatePolicy::Overwrite)
+            .unwrap();
+        let (_, reopened) = VaultSession::open_store(store.clone(), "krab", "password").unwrap();
+
+        assert_eq!(skipped.records().len(), 3);
+        assert_eq!(skipped.get(&id).unwrap().password.expose_secret(), "old");
+        assert_eq!(
+            skip_counts,
+            ImportCounts {
+                added: 1,
+                replaced: 0,
+                duplicates: 1
+            }
+        );
+        // new.com was added by the first import, so both entries are replaced
+        assert_eq!(overwritten.records().len(), 3);
+        assert_eq!(overwritten.records()[0].id, id);
+        assert_eq!(
+            overwritten.get(&id).unwrap().password.expose_secret(),
+            "new"
+        );
+        assert_eq!(
+            overwritten.get(&id).unwrap().history[0]
+                .password
+                .expose_secret(),
+            "old"
+        );
+        assert_eq!(
+            overwrite_counts,
+            ImportCounts {
+                added: 0,
+                replaced: 2,
+                duplicates: 0
+            }
+        );
+        assert_eq!(reopened.records(), overwritten.records());
+    }
+
+    #[test]
+    fn test_session_password_history() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut session, records) = open_session(&store, &user_data).unwrap();
+        let entry = records.records()[0].clone();
+
+        for password in ["password2", "password3"] {
+            let mut edited = entry.clone();
+            edited.password = SecretString::from(password);
+            session.modify_record(&entry.id, &edited).unwrap();
+        }
+        let restored = session.restore_password(&entry.id, 1).unwrap();
+        let out_of_range = session.restore_password(&entry.id, 5);
+        let generations = |session: &VaultSession<MemoryStore>| -> Vec<Vec<u8>> {
+            session
+                .user
+                .generations()
+                .iter()
+                .map(|generation| store.read(generation).unwrap())
+                .collect()
+        };
+        let before = generations(&session);
+        for _ in 0..VAULT_GENERATIONS {
+            session.mark_used(&entry.id).unwrap();
+        }
+        let used = session.mark_used(&entry.id).unwrap();
+        let after = generations(&session);
+        let (_, records) = open_session(&store, &user_data).unwrap();
+
+        let history: Vec<&str> = restored.records()[0]
+            .history
+            .iter()
+            .map(|p| p.password.expose_secret())
+            .collect();
+        assert_eq!(restored.records()[0].password.expose_secret(), "password");
+        assert_eq!(history, vec!["password3", "password2"]);
+        assert_eq!(out_of_range.unwrap_err(), KrabError::RecordNotFound);
+        assert!(used.records()[0].last_used > 0);
+        assert_eq!(used.records()[0].modified, restored.records()[0].modified);
+        assert_eq!(records, used);
+        assert_eq!(before.len(), 3);
+        assert_eq!(after, before);
+    }
+
+    #[test]
+    fn test_session_attachments() {
+        let store = MemoryStore::new();
+        User::create_in_store(
+            &store,
+            "krab",
+            "password",
+            &KdfParams::default(),
+            CipherKind::default(),
+        )
+        .unwrap();
+        let (mut session, _) = VaultSession::open_store(store.clone(), "krab", "password").unwrap();
+        let added = session
+            .add_record(&Entry::new("example.com", "password"))
+            .unwrap();
+        let id = added.records()[0].id.clone();
+
+        session
+            .add_attachment(&id, "codes.txt", b"recovery codes")
+            .unwrap();
+        let attached = session
+            .add_attachment(&id, "id_ed25519", b"private key")
+            .unwrap();
+        let codes = attached.records()[0].attachments[0].clone();
+        let key = attached.records()[0].attachments[1].clone();
+        let content = session.read_attachment(&codes).unwrap();
+        let mut edited = attached.records()[0].clone();
+        edited.attachments.clear();
+        let modified = session.modify_record(&id, &edited).unwrap();
+        let too_large =
+            session.add_attachment(&id, "large.bin", &vec![0u8; MAX_ATTACHMENT_SIZE + 1]);
+        let removed = session.remove_attachment(&id, &codes.id).unwrap();
+        let missing = session.remove_attachment(&id, &codes.id);
+        let blob_name = format!("{}.attachment.{}", hash("krab".to_string()), key.id);
+        let blob_kept = store.exists(&blob_name);
+        session.remove_record(&id).unwrap();
+
+        assert_eq!(content.as_slice(), b"recovery codes");
+        assert_eq!(modified.records()[0].attachments.len(), 2);
+        assert_eq!(
+            too_large.unwrap_err(),
+            KrabError::AttachmentTooLarge(MAX_ATTACHMENT_SIZE + 1)
+        );
+        assert_eq!(removed.records()[0].attachments, vec![key.clone()]);
+        assert_eq!(
+            session.read_attachment(&codes),
+            Err(KrabError::AttachmentNotFound)
+        );
+        assert_eq!(missing.unwrap_err(), KrabError::AttachmentNotFound);
+        assert!(blob_kept);
+        assert!(!store.exists(&blob_name));
+    }
+
+    #[test]
+    fn test_session_backup_export_import_restore() {
+        let store = MemoryStore::new();
+        for username in ["krab", "other"] {
+            User::create_in_store(
+                &store,
+                username,
+                "password",
+                &KdfParams::default(),
+                CipherKind::default(),
+            )
+            .unwrap();
+        }
+        let (mut session, _) = VaultSession::open_store(store.clone(), "krab", "password").unwrap();
+        session
+            .add_record(&Entry::new("example.com", "password"))
+            .unwrap();
+        let added = session
+            .add_record(&Entry::new("example2.com", "password2"))
+            .unwrap();
+        session
+            .add_attachment(&added.records()[1].id, "codes.txt", b"recovery codes")
+            .unwrap();
+
+        let backup = session.export_backup().unwrap();
+        let (mut other, _) = VaultSession::open_store(store.clone(), "other", "password").unwrap();
+        let (imported, count) = other.import_backup(&backup).unwrap();
+        let (_, again) = other.import_backup(&backup).unwrap();
+        let attachment = imported.records()[1].attachments[0].clone();
+        let content = other.read_attachment(&attachment).unwrap();
+        let (_, restored) = VaultSession::restore_store(
+            store.clone(),
+            "restored",
+            "password2",
+            &KdfParams::default(),
+            CipherKind::default(),
+            &backup,
+        )
+        .unwrap();
+        let lock = store
+            .lock(&hash("failed".to_string()), Duration::ZERO)
+            .unwrap();
+        let failed = VaultSession::restore_store(
+            store.clone(),
+            "failed",
+            "password",
+            &KdfParams::default(),
+            CipherKind::default(),
+            &backup,
+        );
+        drop(lock);
+        let existing = VaultSession::restore_store(
+            store.clone(),
+            "krab",
+            "password",
+            &KdfParams::default(),
+            CipherKind::default(),
+            &backup,
+        );
+
+        assert_eq!(backup.username, "krab");
+        assert_eq!(backup.entries.len(), 2);
+        assert_eq!(backup.attachments.len(), 1);
+        assert_eq!(count, 2);
+        assert_eq!(again, 0);
+        assert_eq!(imported.records(), backup.entries.as_slice());
+        assert_eq!(content.as_slice(), b"recovery codes");
+        assert_eq!(restored.records(), backup.entries.as_slice());
+        assert_eq!(failed.unwrap_err(), KrabError::Locked);
+        assert!(!check_user("failed", &store));
+        assert_eq!(existing.unwrap_err(), KrabError::UserAlreadyExists);
+    }
+}
@@ -0,0 +1,484 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    create_file, lock_file, write_atomically, write_atomically_with_generations,
+    LOCK_RETRY_INTERVAL,
+};
+
+/// Storage of vault files
+/// Vaults are opaque, already encrypted blobs addressed by name,
+/// so a store never sees any secret
+///
+/// # Methods
+/// * `exists` - Checks if a vault exists
+/// * `read` - Reads a vault
+/// * `create` - Creates a new vault
+/// * `write` - Replaces a vault atomically and keeps previous generations of it
+/// * `copy` - Copies a vault to a new name
+/// * `delete` - Deletes a vault
+/// * `list` - Lists the names of the stored vaults
+/// * `lock` - Locks a vault for exclusive access
+pub trait VaultStore {
+    /// Guard of a locked vault, the lock is released when it is dropped
+    type Lock;
+
+    /// Checks if a vault exists
+    ///
+    /// # Arguments
+    /// * `name` - The name of the vault
+    ///
+    /// # Returns
+    /// `true` if the vault exists, otherwise `false`
+    fn exists(&self, name: &str) -> bool;
+
+    /// Reads a vault
+    ///
+    /// # Arguments
+    /// * `name` - The name of the vault
+    ///
+    /// # Returns
+    /// The content of the vault or an `io::Error`,
+    /// with `io::ErrorKind::NotFound` if it does not exist
+    fn read(&self, name: &str) -> io::Result<Vec<u8>>;
+
+    /// Creates a new vault
+    ///
+    /// # Arguments
+    /// * `name` - The name of the vault
+    /// * `data` - The content of the vault
+    ///
+    /// # Returns
+    /// An `io::Error` if the vault could not be created,
+    /// with `io::ErrorKind::AlreadyExists` if it exists already
+    fn create(&self, name: &str, data: Vec<u8>) -> io::Result<()>;
+
+    /// Replaces a vault atomically, so it either holds the old or the new content
+    /// The current content becomes generation `1`, the existing generations
+    /// are shifted by one and the oldest one is dropped
+    ///
+    /// # Arguments
+    /// * `name` - The name of the vault
+    /// * `data` - The new content of the vault
+    /// * `generations` - The number of previous generations to keep
+    ///
+    /// # Returns
+    /// An `io::Error` if the vault could not be written
+    fn write(&self, name: &str, data: Vec<u8>, generations: usize) -> io::Result<()>;
+
+    /// Copies a vault to a new name, replacing anything stored under it
+    ///
+    /// # Arguments
+    /// * `from` - The name of the vault
+    /// * `to` - The name of the copy
+    ///
+    /// # Returns
+    /// An `io::Error` if the vault could not be copied
+    fn copy(&self, from: &str, to: &str) -> io::Result<()>;
+
+    /// Deletes a vault
+    ///
+    /// # Arguments
+    /// * `name` - The name of the vault
+    ///
+    /// # Returns
+    /// An `io::Error` if the vault could not be deleted
+    fn delete(&self, name: &str) -> io::Result<()>;
+
+    /// Lists the names of everything stored, including generations and backups
+    ///
+    /// # Returns
+    /// The sorted names or an `io::Error`
+    fn list(&self) -> io::Result<Vec<String>>;
+
+    /// Locks a vault for exclusive access, also against other processes if the store is shared
+    ///
+    /// # Arguments
+    /// * `name` - The name of the vault
+    /// * `timeout` - How long to wait for a lock held by someone else
+    ///
+    /// # Returns
+    /// The lock or an `io::Error`, with `io::ErrorKind::WouldBlock`
+    /// if the lock could not be taken in time
+    fn lock(&self, name: &str, timeout: Duration) -> io::Result<Self::Lock>;
+}
+
+/// Returns the name of a previous generation of a vault
+///
+/// # Arguments
+/// * `name` - The name of the vault
+/// * `generation` - The generation, `1` being the most recent one
+///
+/// # Returns
+/// The name of the generation
+pub fn generation_name(name: &str, generation: usize) -> String {
+    format!("{}.{}", name, generation)
+}
+
+/// FileStore keeps vaults as files in a directory
+///
+/// # Fields
+/// * `dir` - The directory holding the vault files
+///
+/// # Methods
+/// * `new` - Creates a new `FileStore`
+/// * `path` - Returns the path of a vault file
+///
+/// # Implements
+/// * `VaultStore` - Locks are advisory `<file>.lock` files shared with other processes
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    /// Creates a new `FileStore`
+    ///
+    /// # Arguments
+    /// * `dir` - The directory holding the vault files
+    ///
+    /// # Returns
+    /// A new `FileStore`
+    pub fn new(dir: &Path) -> Self {
+        FileStore {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// Returns the path of a vault file
+    ///
+    /// # Arguments
+    /// * `name` - The name of the vault
+    ///
+    /// # Returns
+    /// The path to the vault file
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+}
+
+impl VaultStore for FileStore {
+    type Lock = File;
+
+    fn exists(&self, name: &str) -> bool {
+        self.path(name).exists()
+    }
+
+    fn read(&self, name: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.path(name))
+    }
+
+    fn create(&self, name: &str, data: Vec<u8>) -> io::Result<()> {
+        let path = create_file(&self.dir, name)?;
+        write_atomically(&path, data)
+    }
+
+    fn write(&self, name: &str, data: Vec<u8>, generations: usize) -> io::Result<()> {
+        write_atomically_with_generations(&self.path(name), data, generations)
+    }
+
+    fn copy(&self, from: &str, to: &str) -> io::Result<()> {
+        fs::copy(self.path(from), self.path(to)).map(|_| ())
+    }
+
+    fn delete(&self, name: &str) -> io::Result<()> {
+        fs::remove_file(self.path(name))
+    }
+
+    fn list(&self) -> io::Result<Vec<String>> {
+        let mut names = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                names.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    fn lock(&self, name: &str, timeout: Duration) -> io::Result<File> {
+        lock_file(&self.path(name), timeout)
+    }
+}
+
+/// MemoryStore keeps vaults in memory, for tests and embedders without a file system
+/// Clones share the same vaults and locks, like several handles to one directory
+///
+/// # Fields
+/// * `vaults` - The vaults by name
+/// * `locks` - The names of the locked vaults
+///
+/// # Methods
+/// * `new` - Creates a new, empty `MemoryStore`
+///
+/// # Implements
+/// * `VaultStore` - Locks are shared by all clones of the store
+/// * `Debug` - Shows the names of the vaults only
+/// * `PartialEq` - Stores are equal if they share their vaults
+#[derive(Clone, Default)]
+pub struct MemoryStore {
+    vaults: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    locks: Arc<Mutex<HashSet<String>>>,
+}
+
+/// Lock of a vault of a `MemoryStore`
+/// The vault is unlocked when it is dropped
+///
+/// # Fields
+/// * `name` - The name of the locked vault
+/// * `locks` - The names of the locked vaults of the store
+#[derive(Debug)]
+pub struct MemoryLock {
+    name: String,
+    locks: Arc<Mutex<HashSet<String>>>,
+}
+
+impl Drop for MemoryLock {
+    fn drop(&mut self) {
+        lock_ignoring_poison(&self.locks).remove(&self.name);
+    }
+}
+
+/// Locks a mutex, a panic of another holder leaves the data consistent
+/// because every change of the store is a single insert or remove
+///
+/// # Arguments
+/// * `mutex` - The mutex
+///
+/// # Returns
+/// The guard of the mutex
+fn lock_ignoring_poison<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl fmt::Debug for MemoryStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryStore")
+            .field("vaults", &self.list().unwrap_or_default())
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for MemoryStore {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.vaults, &other.vaults)
+    }
+}
+
+impl MemoryStore {
+    /// Creates a new, empty `MemoryStore`
+    ///
+    /// # Returns
+    /// A new `MemoryStore`
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl VaultStore for MemoryStore {
+    type Lock = MemoryLock;
+
+    fn exists(&self, name: &str) -> bool {
+        lock_ignoring_poison(&self.vaults).contains_key(name)
+    }
+
+    fn read(&self, name: &str) -> io::Result<Vec<u8>> {
+        lock_ignoring_poison(&self.vaults)
+            .get(name)
+            .cloned()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    }
+
+    fn create(&self, name: &str, data: Vec<u8>) -> io::Result<()> {
+        let mut vaults = lock_ignoring_poison(&self.vaults);
+        if vaults.contains_key(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "Vault already exists",
+            ));
+        }
+        vaults.insert(name.to_string(), data);
+        Ok(())
+    }
+
+    fn write(&self, name: &str, data: Vec<u8>, generations: usize) -> io::Result<()> {
+        let mut vaults = lock_ignoring_poison(&self.vaults);
+        if generations > 0 {
+            if let Some(current) = vaults.get(name).cloned() {
+                vaults.remove(&generation_name(name, generations));
+                for generation in (1..generations).rev() {
+                    if let Some(previous) = vaults.remove(&generation_name(name, generation)) {
+                        vaults.insert(generation_name(name, generation + 1), previous);
+                    }
+                }
+                vaults.insert(generation_name(name, 1), current);
+            }
+        }
+        vaults.insert(name.to_string(), data);
+        Ok(())
+    }
+
+    fn copy(&self, from: &str, to: &str) -> io::Result<()> {
+        let mut vaults = lock_ignoring_poison(&self.vaults);
+        let data = vaults
+            .get(from)
+            .cloned()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+        vaults.insert(to.to_string(), data);
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> io::Result<()> {
+        lock_ignoring_poison(&self.vaults)
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    }
+
+    fn list(&self) -> io::Result<Vec<String>> {
+        let mut names: Vec<String> = lock_ignoring_poison(&self.vaults).keys().cloned().collect();
+        names.sort();
+        Ok(names)
+    }
+
+    fn lock(&self, name: &str, timeout: Duration) -> io::Result<MemoryLock> {
+        let start = Instant::now();
+        loop {
+            if lock_ignoring_poison(&self.locks).insert(name.to_string()) {
+                return Ok(MemoryLock {
+                    name: name.to_string(),
+                    locks: Arc::clone(&self.locks),
+                });
+            }
+            if start.elapsed() >= timeout {
+                return Err(io::Error::new(
+                    io::ErrorKind::WouldBlock,
+                    "Vault is locked by someone else",
+                ));
+            }
+            thread::sleep(LOCK_RETRY_INTERVAL);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_util::TempDir;
+
+    fn check_store(store: &impl VaultStore, name: &str) {
+        assert!(!store.exists(name));
+        assert_eq!(
+            store.read(name).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+
+        store.create(name, b"first".to_vec()).unwrap();
+        assert_eq!(
+            store.create(name, b"again".to_vec()).unwrap_err().kind(),
+            io::ErrorKind::AlreadyExists
+        );
+        assert!(store.exists(name));
+        assert_eq!(store.read(name).unwrap(), b"first");
+
+        for content in ["second", "third", "fourth"] {
+            store.write(name, content.as_bytes().to_vec(), 2).unwrap();
+        }
+        assert_eq!(store.read(name).unwrap(), b"fourth");
+        assert_eq!(store.read(&generation_name(name, 1)).unwrap(), b"third");
+        assert_eq!(store.read(&generation_name(name, 2)).unwrap(), b"second");
+        assert!(!store.exists(&generation_name(name, 3)));
+
+        let backup = format!("{}.bak", name);
+        store.copy(name, &backup).unwrap();
+        assert_eq!(store.read(&backup).unwrap(), b"fourth");
+
+        let names = store.list().unwrap();
+        for stored in [
+            name.to_string(),
+            generation_name(name, 1),
+            generation_name(name, 2),
+            backup.clone(),
+        ] {
+            assert!(names.contains(&stored));
+        }
+
+        {
+            let _lock = store.lock(name, Duration::ZERO).unwrap();
+            assert_eq!(
+                store.lock(name, Duration::ZERO).err().unwrap().kind(),
+                io::ErrorKind::WouldBlock
+            );
+        }
+        assert!(store.lock(name, Duration::ZERO).is_ok());
+
+        for stored in [
+            name.to_string(),
+            generation_name(name, 1),
+            generation_name(name, 2),
+            backup,
+        ] {
+            store.delete(&stored).unwrap();
+        }
+        assert!(!store.exists(name));
+    }
+
+    #[test]
+    fn test_memory_store() {
+        let store = MemoryStore::new();
+
+        check_store(&store, "vault");
+
+        assert!(store.list().unwrap().is_empty());
+        assert_eq!(store, store.clone());
+        assert_ne!(store, MemoryStore::new());
+    }
+
+    #[test]
+    fn test_memory_store_lock_shared_by_clones() {
+        let store = MemoryStore::new();
+        let other = store.clone();
+
+        let lock = store.lock("vault", Duration::ZERO).unwrap();
+        assert!(other.lock("vault", Duration::ZERO).is_err());
+        drop(lock);
+        assert!(other.lock("vault", Duration::ZERO).is_ok());
+    }
+
+    #[test]
+    fn test_file_store() {
+        let dir = TempDir::new();
+        let store = FileStore::new(dir.path());
+
+        check_store(&store, "vault");
+
+        let created: Vec<bool> = (0..8)
+            .map(|n| {
+                let store = store.clone();
+                thread::spawn(move || store.create("raced", vec![n]))
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| match handle.join().unwrap() {
+                Ok(()) => true,
+                Err(e) => {
+                    assert_eq!(e.kind(), io::ErrorKind::AlreadyExists);
+                    false
+                }
+            })
+            .collect();
+        let winner = created.iter().position(|created| *created).unwrap();
+        assert_eq!(created.iter().filter(|created| **created).count(), 1);
+        assert_eq!(store.read("raced").unwrap(), vec![winner as u8]);
+    }
+}
@@ -0,0 +1,98 @@
+use rand::Rng;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    cipher::CipherKind,
+    entry::Entry,
+    error::KrabError,
+    kdf::KdfParams,
+    store::MemoryStore,
+    user::{RecordOperationConfig, User},
+};
+
+/// Returns a random eight digit number
+///
+/// # Returns
+/// The number
+pub fn random_number() -> u32 {
+    let mut rng = rand::thread_rng();
+    rng.gen_range(10000000..99999999)
+}
+
+/// Returns a random username, so tests sharing a store do not collide
+///
+/// # Returns
+/// The username
+pub fn generate_random_username() -> String {
+    format!("krab-{}", random_number())
+}
+
+/// Creates a user holding one record in a new `MemoryStore`
+///
+/// # Arguments
+/// * `domain` - The domain of the record
+///
+/// # Returns
+/// The store and the configuration of the user or an error
+pub fn setup_user_data(domain: &str) -> Result<(MemoryStore, RecordOperationConfig), KrabError> {
+    let store = MemoryStore::new();
+    let config = RecordOperationConfig::new(
+        &generate_random_username(),
+        "password",
+        domain,
+        "password",
+        &PathBuf::new(),
+    );
+    let mut entry = Entry::new(domain, "password");
+    entry.id = Entry::generate_id();
+    User::create_vault(
+        &store,
+        &config.username,
+        config.master_password.expose_secret(),
+        &KdfParams::default(),
+        CipherKind::default(),
+        &[entry],
+    )?;
+    Ok((store, config))
+}
+
+/// TempDir is a directory for tests that need real files
+/// The directory and everything in it is removed when it is dropped,
+/// also when the test panics
+///
+/// # Fields
+/// * `0` - The path of the directory
+///
+/// # Methods
+/// * `new` - Creates a new, empty directory
+/// * `path` - Returns the path of the directory
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Creates a new, empty directory in the temporary directory of the system
+    ///
+    /// # Returns
+    /// A new `TempDir`
+    pub fn new() -> Self {
+        let path = env::temp_dir().join(format!("krab-test-{}", random_number()));
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    /// Returns the path of the directory
+    ///
+    /// # Returns
+    /// The path
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
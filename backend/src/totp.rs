@@ -0,0 +1,367 @@
+use std::fmt;
+
+use hmac::{digest::KeyInit, Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use zeroize::{Zeroize, Zeroizing};
+
+const URI_PREFIX: &str = "otpauth://totp/";
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const DEFAULT_DIGITS: u32 = 6;
+const DEFAULT_PERIOD: u64 = 30;
+const MIN_DIGITS: u32 = 6;
+const MAX_DIGITS: u32 = 8;
+
+/// The hash algorithm of a TOTP
+///
+/// # Variants
+/// * `Sha1` - HMAC-SHA-1, used by almost every service
+/// * `Sha256` - HMAC-SHA-256
+/// * `Sha512` - HMAC-SHA-512
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TotpAlgorithm {
+    #[default]
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// Error while parsing a TOTP secret
+/// It never contains any part of the secret itself
+///
+/// # Variants
+/// * `InvalidSecret` - The secret is empty or not valid base32
+/// * `InvalidUri` - The URI is not an `otpauth://totp/` URI
+/// * `InvalidParameter` - A parameter of the URI has an unsupported value, with its name
+///
+/// # Implements
+/// * `Display`
+/// * `Error`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TotpError {
+    InvalidSecret,
+    InvalidUri,
+    InvalidParameter(&'static str),
+}
+
+impl fmt::Display for TotpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TotpError::InvalidSecret => write!(f, "The TOTP secret is not valid base32"),
+            TotpError::InvalidUri => write!(f, "Only otpauth://totp/ URIs are supported"),
+            TotpError::InvalidParameter(name) => {
+                write!(f, "The TOTP parameter {} is not supported", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TotpError {}
+
+/// A time-based one-time password generator as described in RFC 6238
+/// The key is zeroed when it is dropped
+///
+/// # Fields
+/// * `key` - The shared secret
+/// * `digits` - The number of digits of a code
+/// * `period` - The number of seconds a code is valid
+/// * `algorithm` - The hash algorithm
+///
+/// # Methods
+/// * `parse` - Parses a base32 secret or an `otpauth://totp/` URI
+/// * `code` - Returns the code at a time
+/// * `remaining` - Returns the seconds until the code changes
+/// * `period` - Returns the number of seconds a code is valid
+pub struct Totp {
+    key: Zeroizing<Vec<u8>>,
+    digits: u32,
+    period: u64,
+    algorithm: TotpAlgorithm,
+}
+
+impl fmt::Debug for Totp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Totp")
+            .field("digits", &self.digits)
+            .field("period", &self.period)
+            .field("algorithm", &self.algorithm)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Totp {
+    /// Parses a base32 secret or an `otpauth://totp/` URI
+    /// A plain secret uses 6 digits, 30 seconds and SHA-1, a URI may set
+    /// them with its `digits`, `period` and `algorithm` parameters
+    ///
+    /// # Arguments
+    /// * `value` - The secret or URI
+    ///
+    /// # Returns
+    /// The generator or an error describing why the value is invalid
+    pub fn parse(value: &str) -> Result<Totp, TotpError> {
+        let value = value.trim();
+        let is_uri = value
+            .get(..URI_PREFIX.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(URI_PREFIX));
+        if !is_uri {
+            if value.contains("://") {
+                return Err(TotpError::InvalidUri);
+            }
+            return Ok(Totp {
+                key: decode_base32(value)?,
+                digits: DEFAULT_DIGITS,
+                period: DEFAULT_PERIOD,
+                algorithm: TotpAlgorithm::default(),
+            });
+        }
+
+        let query = value.split_once('?').map(|(_, query)| query).unwrap_or("");
+        let mut totp = Totp {
+            key: Zeroizing::new(vec![]),
+            digits: DEFAULT_DIGITS,
+            period: DEFAULT_PERIOD,
+            algorithm: TotpAlgorithm::default(),
+        };
+        for parameter in query.split('&') {
+            let (name, parameter_value) = parameter.split_once('=').unwrap_or((parameter, ""));
+            let parameter_value = Zeroizing::new(percent_decode(parameter_value)?);
+            match name.to_ascii_lowercase().as_str() {
+                "secret" => totp.key = decode_base32(&parameter_value)?,
+                "digits" => {
+                    totp.digits = parameter_value
+                        .parse()
+                        .ok()
+                        .filter(|digits| (MIN_DIGITS..=MAX_DIGITS).contains(digits))
+                        .ok_or(TotpError::InvalidParameter("digits"))?;
+                }
+                "period" => {
+                    totp.period = parameter_value
+                        .parse()
+                        .ok()
+                        .filter(|&period| period > 0)
+                        .ok_or(TotpError::InvalidParameter("period"))?;
+                }
+                "algorithm" => {
+                    totp.algorithm = match parameter_value.to_ascii_uppercase().as_str() {
+                        "SHA1" => TotpAlgorithm::Sha1,
+                        "SHA256" => TotpAlgorithm::Sha256,
+                        "SHA512" => TotpAlgorithm::Sha512,
+                        _ => return Err(TotpError::InvalidParameter("algorithm")),
+                    };
+                }
+                _ => {}
+            }
+        }
+        if totp.key.is_empty() {
+            return Err(TotpError::InvalidSecret);
+        }
+        Ok(totp)
+    }
+
+    /// Returns the code at a time
+    ///
+    /// # Arguments
+    /// * `time` - The time as a Unix timestamp
+    ///
+    /// # Returns
+    /// The code, padded with zeros to the number of digits
+    pub fn code(&self, time: i64) -> String {
+        let counter = (time.max(0) as u64 / self.period).to_be_bytes();
+        let hash = match self.algorithm {
+            TotpAlgorithm::Sha1 => hmac::<Hmac<Sha1>>(&self.key, &counter),
+            TotpAlgorithm::Sha256 => hmac::<Hmac<Sha256>>(&self.key, &counter),
+            TotpAlgorithm::Sha512 => hmac::<Hmac<Sha512>>(&self.key, &counter),
+        };
+
+        // dynamic truncation as described in RFC 4226
+        let offset = (hash[hash.len() - 1] & 0x0F) as usize;
+        let binary = u32::from_be_bytes(hash[offset..offset + 4].try_into().unwrap()) & 0x7FFF_FFFF;
+        let code = binary % 10u32.pow(self.digits);
+        format!("{:0width$}", code, width = self.digits as usize)
+    }
+
+    /// Returns the seconds until the code changes
+    ///
+    /// # Arguments
+    /// * `time` - The time as a Unix timestamp
+    ///
+    /// # Returns
+    /// The remaining seconds, between 1 and the period
+    pub fn remaining(&self, time: i64) -> u64 {
+        self.period - time.max(0) as u64 % self.period
+    }
+
+    /// Returns the number of seconds a code is valid
+    ///
+    /// # Returns
+    /// The period in seconds
+    pub fn period(&self) -> u64 {
+        self.period
+    }
+}
+
+/// Computes the HMAC of a message
+///
+/// # Arguments
+/// * `key` - The key
+/// * `message` - The message
+///
+/// # Returns
+/// The authentication code
+fn hmac<M: Mac + KeyInit>(key: &[u8], message: &[u8]) -> Zeroizing<Vec<u8>> {
+    let mut mac = <M as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    Zeroizing::new(mac.finalize().into_bytes().to_vec())
+}
+
+/// Decodes a base32 secret as described in RFC 4648
+/// Case, spaces, dashes and padding are ignored, as authenticator apps show them
+///
+/// # Arguments
+/// * `value` - The secret
+///
+/// # Returns
+/// The decoded secret or an error if it is empty or not valid base32
+fn decode_base32(value: &str) -> Result<Zeroizing<Vec<u8>>, TotpError> {
+    let mut key = Zeroizing::new(Vec::with_capacity(value.len() * 5 / 8));
+    let mut buffer: u64 = 0;
+    let mut bits = 0;
+    for c in value.bytes() {
+        if matches!(c, b' ' | b'-' | b'=') {
+            continue;
+        }
+        let index = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a == c.to_ascii_uppercase())
+            .ok_or(TotpError::InvalidSecret)?;
+        buffer = (buffer << 5) | index as u64;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            key.push((buffer >> bits) as u8);
+        }
+    }
+    buffer.zeroize();
+    if key.is_empty() {
+        return Err(TotpError::InvalidSecret);
+    }
+    Ok(key)
+}
+
+/// Decodes the percent-encoded value of a URI parameter
+///
+/// # Arguments
+/// * `value` - The encoded value
+///
+/// # Returns
+/// The decoded value or an error if the encoding is invalid
+fn percent_decode(value: &str) -> Result<String, TotpError> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.bytes();
+    while let Some(b) = rest.next() {
+        match b {
+            b'%' => {
+                let hex = [rest.next(), rest.next()];
+                let hex = match hex {
+                    [Some(high), Some(low)] => [high, low],
+                    _ => return Err(TotpError::InvalidUri),
+                };
+                let hex = std::str::from_utf8(&hex).map_err(|_| TotpError::InvalidUri)?;
+                bytes.push(u8::from_str_radix(hex, 16).map_err(|_| TotpError::InvalidUri)?);
+            }
+            b'+' => bytes.push(b' '),
+            b => bytes.push(b),
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| TotpError::InvalidUri)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The secrets of the test vectors of RFC 6238, for each algorithm
+    fn rfc_totp(algorithm: TotpAlgorithm) -> Totp {
+        let key = match algorithm {
+            TotpAlgorithm::Sha1 => "12345678901234567890".to_string(),
+            TotpAlgorithm::Sha256 => "12345678901234567890123456789012".to_string(),
+            TotpAlgorithm::Sha512 => "1234567890".repeat(6) + "1234",
+        };
+        Totp {
+            key: Zeroizing::new(key.into_bytes()),
+            digits: 8,
+            period: 30,
+            algorithm,
+        }
+    }
+
+    #[test]
+    fn test_totp_rfc_vectors() {
+        let vectors = [
+            (59, "94287082", "46119246", "90693936"),
+            (1111111109, "07081804", "68084774", "25091201"),
+            (1234567890, "89005924", "91819424", "93441116"),
+            (20000000000, "65353130", "77737706", "47863826"),
+        ];
+        for (time, sha1, sha256, sha512) in vectors {
+            assert_eq!(rfc_totp(TotpAlgorithm::Sha1).code(time), sha1);
+            assert_eq!(rfc_totp(TotpAlgorithm::Sha256).code(time), sha256);
+            assert_eq!(rfc_totp(TotpAlgorithm::Sha512).code(time), sha512);
+        }
+    }
+
+    #[test]
+    fn test_totp_parse_secret() {
+        let totp = Totp::parse(" gezd gnbv-gy3t qojq gezd gnbv gy3t qojq ").unwrap();
+
+        assert_eq!(totp.key.as_slice(), b"12345678901234567890");
+        assert_eq!(totp.digits, 6);
+        assert_eq!(totp.period(), 30);
+        assert_eq!(totp.algorithm, TotpAlgorithm::Sha1);
+        assert_eq!(totp.code(59), "287082");
+        assert_eq!(totp.remaining(59), 1);
+        assert_eq!(totp.remaining(60), 30);
+    }
+
+    #[test]
+    fn test_totp_parse_uri() {
+        let totp = Totp::parse(
+            "otpauth://totp/Example:krab%40example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+             &issuer=Example&algorithm=sha256&digits=8&period=60",
+        )
+        .unwrap();
+
+        assert_eq!(totp.key.as_slice(), b"12345678901234567890");
+        assert_eq!(totp.digits, 8);
+        assert_eq!(totp.period(), 60);
+        assert_eq!(totp.algorithm, TotpAlgorithm::Sha256);
+        assert_eq!(totp.code(59).len(), 8);
+    }
+
+    #[test]
+    fn test_totp_parse_errors() {
+        assert_eq!(Totp::parse("").unwrap_err(), TotpError::InvalidSecret);
+        assert_eq!(Totp::parse("GEZD1").unwrap_err(), TotpError::InvalidSecret);
+        assert_eq!(
+            Totp::parse("otpauth://hotp/Example?secret=GEZDGNBV").unwrap_err(),
+            TotpError::InvalidUri
+        );
+        assert_eq!(
+            Totp::parse("otpauth://totp/Example?issuer=Example").unwrap_err(),
+            TotpError::InvalidSecret
+        );
+        assert_eq!(
+            Totp::parse("otpauth://totp/Example?secret=GEZDGNBV&digits=12").unwrap_err(),
+            TotpError::InvalidParameter("digits")
+        );
+        assert_eq!(
+            Totp::parse("otpauth://totp/Example?secret=GEZDGNBV&period=0").unwrap_err(),
+            TotpError::InvalidParameter("period")
+        );
+        assert_eq!(
+            Totp::parse("otpauth://totp/Example?secret=GEZDGNBV&algorithm=MD5").unwrap_err(),
+            TotpError::InvalidParameter("algorithm")
+        );
+    }
+}
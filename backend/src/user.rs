@@ -1,24 +1,82 @@
-use aes_gcm_siv::{
-    aead::{self, consts::U12, generic_array::GenericArray, Aead, KeyInit, OsRng},
-    AeadCore, Aes128GcmSiv, Key,
+use aes_gcm_siv::aead::{self, OsRng};
+use scrypt::password_hash::SaltString;
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    slice, str,
+    time::Duration,
+};
+use zeroize::Zeroizing;
+
+pub use crate::{
+    attachment::{Attachment, MAX_ATTACHMENTS, MAX_ATTACHMENT_SIZE},
+    cipher::CipherKind,
+    entry::{timestamp, CustomField, Entry, PreviousPassword, MAX_PASSWORD_HISTORY},
+    error::KrabError,
+    kdf::KdfParams,
+    kind::{Card, EntryError, EntryKind, Identity, SshKey},
+    secret::SecretString,
+    store::{FileStore, MemoryStore, VaultStore},
+};
+use crate::{
+    checksum,
+    cipher::CipherKey,
+    entry::ID_LENGTH,
+    hash,
+    payload::{self, PayloadError},
+    store::generation_name,
 };
-use scrypt::{password_hash::SaltString, scrypt, Params};
-use std::{fs, path::PathBuf, str};
-
-use crate::{append_to_file, clear_file_content, create_file, hash, write_to_file};
 
 pub use super::models::RecordOperationConfig;
 
+/// Magic bytes at the start of a versioned vault file
+/// The first byte lies outside of the salt alphabet, so a legacy
+/// (headerless) file can never be mistaken for a versioned one
+const VAULT_MAGIC: [u8; 4] = [0x89, b'K', b'R', b'B'];
+const VAULT_VERSION: u8 = 1;
+/// Cipher of legacy files and of versioned files written by older versions
+const LEGACY_CIPHER: CipherKind = CipherKind::Aes128GcmSiv;
+const LENGTH_PREFIX: usize = 4;
+const LEGACY_SALT_LENGTH: usize = 22;
+/// Number of previous generations of a user file kept for recovery
+pub const VAULT_GENERATIONS: usize = 3;
+/// How long to wait for another process holding the lock of a user file
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// User
 /// Data about a user is not exposed to the outside world
 /// Only methods to interact with the user data are exposed
 ///
 /// # Fields
 /// * `0` - The records
-/// * `1` - The path to the user data
+/// * `1` - The store holding the user data
 /// * `2` - The username
+/// * `3` - The on-disk format of the user data
+/// * `4` - The outcome of the legacy format migration done on login, if any
+/// * `5` - The checksum of the user file as it was last read or written
 #[derive(Debug, Clone, PartialEq)]
-pub struct User(Vec<Record>, PathBuf, Username);
+pub struct User<S = FileStore>(
+    Vec<Record>,
+    S,
+    Username,
+    VaultFormat,
+    Option<Migration>,
+    String,
+);
+
+/// Migration is the outcome of upgrading a legacy (headerless) user file
+/// to the versioned format, which happens transparently on login
+///
+/// # Variants
+/// * `Migrated` - The file was rewritten in the versioned format,
+///   `backup` is the name of a copy of the original file in the store
+///   and `records` the number of migrated records
+/// * `Failed` - The file could not be migrated and is still in the legacy format
+#[derive(Debug, Clone, PartialEq)]
+pub enum Migration {
+    Migrated { backup: String, records: usize },
+    Failed(KrabError),
+}
 
 /// ReadOnlyRecords is a read-only version of the records
 /// It is used to return records to the user
@@ -27,49 +85,81 @@ pub struct User(Vec<Record>, PathBuf, Username);
 /// and to ensure that the records are always encrypted
 ///
 /// # Fields
-/// * `0` - Vector of domain-password pairs
+/// * `0` - Vector of entries
 #[derive(Debug, Clone, PartialEq)]
-pub struct ReadOnlyRecords(Vec<(String, String)>);
-
-/// A domain-password pair
-///
-/// # Fields
-///
-/// * `domain` - The domain
-/// * `password` - The password
-#[derive(Debug, Clone, PartialEq)]
-struct DomainPasswordPair {
-    domain: String,
-    password: String,
-}
+pub struct ReadOnlyRecords(Vec<Entry>);
 
 /// CipherConfig is a configuration for the cipher
 ///
 /// # Fields
 /// * `key` - The key
-/// * `salt` - The salt
+/// * `salt` - The salt, empty for records of a versioned vault
 /// * `nonce` - The nonce
 /// * `ciphertext` - The ciphertext
 #[derive(Debug, Clone, PartialEq)]
 struct CipherConfig {
-    key: Key<Aes128GcmSiv>,
-    salt: Vec<u8>,                // 22 bytes
-    nonce: GenericArray<u8, U12>, // 12 bytes
+    key: CipherKey,
+    salt: Vec<u8>,  // 22 bytes (legacy) or 0 bytes
+    nonce: Vec<u8>, // 12 or 24 bytes, depending on the cipher
     ciphertext: Vec<u8>,
 }
 
 /// DerivedKey is a derived key from a master password
+/// The key is zeroed on drop and hidden from `Debug`
 ///
 /// # Fields
 /// * `key` - The key
 /// * `salt` - The salt
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Clone, PartialEq)]
 struct DerivedKey {
-    pub key: [u8; 16],
+    pub key: Zeroizing<Vec<u8>>,
     pub salt: Vec<u8>,
 }
 
-/// Record represents an encrypted domain-password pair
+/// Header of a versioned vault file
+/// The master password derives a key (once per vault) which unwraps
+/// the random data-encryption key used for every record
+/// The authentication tag of the wrapped key doubles as key-check value,
+/// so the master password can be verified even if the vault has no records
+///
+/// # Fields
+/// * `cipher` - The cipher of the data key and the records
+/// * `kdf` - The key derivation parameters
+/// * `salt` - The vault-level salt
+/// * `key_nonce` - The nonce used to wrap the data key
+/// * `wrapped_key` - The data key encrypted with the master key
+#[derive(Debug, Clone, PartialEq)]
+struct VaultHeader {
+    cipher: CipherKind,
+    kdf: KdfParams,
+    salt: Vec<u8>,
+    key_nonce: Vec<u8>,
+    wrapped_key: Vec<u8>,
+}
+
+/// The on-disk format of a user file
+///
+/// # Variants
+/// * `Legacy` - Headerless file where every record carries its own salt
+/// * `Versioned` - File starting with a `VaultHeader`
+#[derive(Debug, Clone, PartialEq)]
+enum VaultFormat {
+    Legacy,
+    Versioned(VaultHeader),
+}
+
+/// The key used to read and encrypt records
+///
+/// # Variants
+/// * `Legacy` - Every record derives its own key from the master password and its salt
+/// * `DataKey` - The data-encryption key unwrapped from the vault header
+#[derive(Clone, PartialEq)]
+pub(crate) enum RecordKey {
+    Legacy(SecretString),
+    DataKey(CipherKey),
+}
+
+/// Record represents an encrypted entry
 ///
 /// # Fields
 /// * `cypher` - The cipher configuration
@@ -87,6 +177,14 @@ struct Record {
 #[derive(Debug, Clone, PartialEq)]
 struct Username(String);
 
+impl fmt::Debug for DerivedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DerivedKey")
+            .field("salt", &self.salt)
+            .finish_non_exhaustive()
+    }
+}
+
 impl CipherConfig {
     /// Creates a new `CipherConfig`
     ///
@@ -98,12 +196,7 @@ impl CipherConfig {
     ///
     /// # Returns
     /// A new `CipherConfig`
-    fn new(
-        key: Key<Aes128GcmSiv>,
-        salt: Vec<u8>,
-        nonce: GenericArray<u8, U12>,
-        ciphertext: Vec<u8>,
-    ) -> Self {
+    fn new(key: CipherKey, salt: Vec<u8>, nonce: Vec<u8>, ciphertext: Vec<u8>) -> Self {
         CipherConfig {
             key,
             salt,
@@ -112,40 +205,6 @@ impl CipherConfig {
         }
     }
 
-    /// Marshals the domain and password into a string
-    /// escaping spaces and backslashes in the process
-    /// Domain and password are separated by a space after escaping
-    ///
-    /// # Arguments
-    /// * `domain` - The domain
-    /// * `password` - The password
-    ///
-    /// # Returns
-    /// The marshalled string
-    fn marshal(domain: &str, password: &str) -> String {
-        let marshal_domain = domain.to_string().replace("\\", "\\\\").replace(" ", "\\s");
-        let marshal_password = password
-            .to_string()
-            .replace("\\", "\\\\")
-            .replace(" ", "\\s");
-        format!("{} {}", marshal_domain, marshal_password)
-    }
-
-    /// Unmarshals the domain and password from a string
-    /// unescaping spaces and backslashes in the process
-    ///
-    /// # Arguments
-    /// * `data` - The data to unmarshal
-    ///
-    /// # Returns
-    /// A tuple of the domain and password
-    fn unmarshal(data: &str) -> (String, String) {
-        let parts: Vec<&str> = data.split_whitespace().collect();
-        let domain = parts[0].replace("\\s", " ").replace("\\\\", "\\");
-        let password = parts[1].replace("\\s", " ").replace("\\\\", "\\");
-        (domain, password)
-    }
-
     /// Writes the cipher configuration to a buffer modifying the buffer
     ///
     /// # Arguments
@@ -156,47 +215,63 @@ impl CipherConfig {
         let ciphertext_len: u32 = self.ciphertext.len() as u32;
         let mut data: Vec<u8> = self.salt.clone();
 
-        data.append(&mut self.nonce.to_vec());
+        data.append(&mut self.nonce.clone());
         data.append(&mut ciphertext_len.to_be_bytes().to_vec());
         data.append(&mut self.ciphertext.clone());
 
         buffer.append(&mut data);
     }
 
-    /// Encrypts the domain and password using the master password
+    /// Encrypts an entry
+    /// Legacy keys derive a new key with a fresh salt from the master password,
+    /// data keys are used as they are
     ///
     /// # Arguments
-    /// * `domain` - The domain
-    /// * `password` - The password
-    /// * `master_password` - The master password
+    /// * `entry` - The entry
+    /// * `record_key` - The key to encrypt with
     ///
     /// # Returns
     /// A new `CipherConfig` or an error
-    fn encrypt_data(
-        domain: &str,
-        password: &str,
-        master_password: &str,
-    ) -> Result<Self, aead::Error> {
-        let derived_key = DerivedKey::derive_key(master_password, None);
-        let salt = derived_key.salt;
-        let key = Key::<Aes128GcmSiv>::clone_from_slice(&derived_key.key);
-        let cipher = Aes128GcmSiv::new(&key);
-        let nonce = Aes128GcmSiv::generate_nonce(&mut OsRng);
-        let data = CipherConfig::marshal(domain, password);
+    fn encrypt_data(entry: &Entry, record_key: &RecordKey) -> Result<Self, aead::Error> {
+        let data = payload::encode(entry).map_err(|_| aead::Error)?;
+        CipherConfig::encrypt_bytes(&data, record_key)
+    }
 
-        let ciphertext = cipher.encrypt(&nonce, data.as_bytes())?;
+    /// Encrypts an encoded payload
+    ///
+    /// # Arguments
+    /// * `data` - The payload
+    /// * `record_key` - The key to encrypt with
+    ///
+    /// # Returns
+    /// A new `CipherConfig` or an error
+    fn encrypt_bytes(data: &[u8], record_key: &RecordKey) -> Result<Self, aead::Error> {
+        let (key, salt) = match record_key {
+            RecordKey::Legacy(master_password) => {
+                let derived_key = DerivedKey::derive_key(master_password.expose_secret(), None);
+                (
+                    CipherKey::from_slice(LEGACY_CIPHER, &derived_key.key).ok_or(aead::Error)?,
+                    derived_key.salt,
+                )
+            }
+            RecordKey::DataKey(key) => (key.clone(), vec![]),
+        };
+        let nonce = key.kind().generate_nonce();
+
+        let ciphertext = key.encrypt(&nonce, data, &[])?;
         Ok(CipherConfig::new(key, salt, nonce, ciphertext))
     }
 
-    /// Decrypts the domain and password
+    /// Decrypts the entry
     ///
     /// # Returns
-    /// A new `DomainPasswordPair` or an error if decryption fails
-    fn decrypt_data(&self) -> Result<DomainPasswordPair, aead::Error> {
-        let cipher = Aes128GcmSiv::new(&self.key);
-        let plaintext = cipher.decrypt(&self.nonce, self.ciphertext.as_ref())?;
-        let (domain, password) = CipherConfig::unmarshal(str::from_utf8(&plaintext).unwrap());
-        Ok(DomainPasswordPair { domain, password })
+    /// The entry or an error if decryption or decoding fails
+    fn decrypt_data(&self) -> Result<Entry, PayloadError> {
+        let plaintext = self
+            .key
+            .decrypt(&self.nonce, &self.ciphertext, &[])
+            .map_err(|_| PayloadError::Decrypt)?;
+        payload::decode(&Zeroizing::new(plaintext))
     }
 }
 
@@ -209,11 +284,11 @@ impl DerivedKey {
     ///
     /// # Returns
     /// A new `DerivedKey`
-    fn new(key: [u8; 16], salt: Vec<u8>) -> Self {
+    fn new(key: Zeroizing<Vec<u8>>, salt: Vec<u8>) -> Self {
         DerivedKey { key, salt }
     }
 
-    /// Derives a key from a master password
+    /// Derives a legacy record key from a master password with the default parameters
     /// If a salt is provided, it means that the key is being derived
     /// from an existing salt. If salt is None, a new salt is generated
     ///
@@ -224,6 +299,30 @@ impl DerivedKey {
     /// # Returns
     /// A new `DerivedKey`
     fn derive_key(data: &str, salt: Option<Vec<u8>>) -> Self {
+        DerivedKey::derive_key_with(
+            data,
+            salt,
+            &KdfParams::default(),
+            LEGACY_CIPHER.key_length(),
+        )
+    }
+
+    /// Derives a key from a master password with the given parameters
+    ///
+    /// # Arguments
+    /// * `data` - The data to derive the key from
+    /// * `salt` - The salt, a new one is generated if None
+    /// * `kdf` - The key derivation parameters
+    /// * `key_length` - The length of the key in bytes
+    ///
+    /// # Returns
+    /// A new `DerivedKey`
+    fn derive_key_with(
+        data: &str,
+        salt: Option<Vec<u8>>,
+        kdf: &KdfParams,
+        key_length: usize,
+    ) -> Self {
         let salt = match salt {
             Some(salt) => salt,
             None => SaltString::generate(&mut OsRng)
@@ -231,17 +330,190 @@ impl DerivedKey {
                 .as_bytes()
                 .to_vec(),
         };
-        let salt_copy = salt.clone();
-        let mut derived_key = [0u8; 16];
-        scrypt(
-            &data.as_bytes(),
-            &salt,
-            &Params::new(14 as u8, 8 as u32, 1 as u32, 16 as usize).unwrap(),
-            &mut derived_key,
-        )
-        .unwrap();
+        let mut derived_key = Zeroizing::new(vec![0u8; key_length]);
+        kdf.derive(data.as_bytes(), &salt, &mut derived_key);
+
+        DerivedKey::new(derived_key, salt)
+    }
+}
+
+impl VaultHeader {
+    /// Creates a new header with a fresh salt and a random data key
+    ///
+    /// # Arguments
+    /// * `master_password` - The master password
+    /// * `kdf` - The key derivation parameters
+    /// * `cipher` - The cipher of the data key and the records
+    ///
+    /// # Returns
+    /// The header and the unwrapped data key or an error
+    fn create(
+        master_password: &str,
+        kdf: &KdfParams,
+        cipher: CipherKind,
+    ) -> Result<(Self, CipherKey), aead::Error> {
+        let data_key = CipherKey::generate(cipher);
+
+        Ok((Self::wrap(master_password, &data_key, kdf)?, data_key))
+    }
+
+    /// Creates a new header wrapping an existing data key
+    /// A fresh salt is used, so the master key changes even for the same password
+    /// The master key is derived for the cipher of the data key
+    ///
+    /// # Arguments
+    /// * `master_password` - The master password
+    /// * `data_key` - The data key to wrap
+    /// * `kdf` - The key derivation parameters
+    ///
+    /// # Returns
+    /// The header or an error
+    fn wrap(
+        master_password: &str,
+        data_key: &CipherKey,
+        kdf: &KdfParams,
+    ) -> Result<Self, aead::Error> {
+        let cipher = data_key.kind();
+        let derived_key =
+            DerivedKey::derive_key_with(master_password, None, kdf, cipher.key_length());
+
+        let mut header = VaultHeader {
+            cipher,
+            kdf: *kdf,
+            salt: derived_key.salt,
+            key_nonce: cipher.generate_nonce(),
+            wrapped_key: vec![],
+        };
+        let master_key = CipherKey::from_slice(cipher, &derived_key.key).ok_or(aead::Error)?;
+        header.wrapped_key = master_key.encrypt(
+            &header.key_nonce,
+            data_key.as_slice(),
+            &header.authenticated_data(),
+        )?;
+
+        Ok(header)
+    }
+
+    /// Derives the master key and unwraps the data key
+    ///
+    /// # Arguments
+    /// * `master_password` - The master password
+    ///
+    /// # Returns
+    /// The data key or an error if the master password is wrong
+    fn unwrap_key(&self, master_password: &str) -> Result<CipherKey, aead::Error> {
+        let derived_key = DerivedKey::derive_key_with(
+            master_password,
+            Some(self.salt.clone()),
+            &self.kdf,
+            self.cipher.key_length(),
+        );
+        let master_key = CipherKey::from_slice(self.cipher, &derived_key.key).ok_or(aead::Error)?;
+        let data_key = master_key.decrypt(
+            &self.key_nonce,
+            &self.wrapped_key,
+            &self.authenticated_data(),
+        )?;
+
+        CipherKey::from_slice(self.cipher, &data_key).ok_or(aead::Error)
+    }
+
+    /// Returns the header fields preceding the wrapped key
+    /// They are authenticated when the data key is wrapped so that
+    /// tampering with the parameters is detected
+    ///
+    /// # Returns
+    /// The serialized header prefix
+    fn authenticated_data(&self) -> Vec<u8> {
+        let mut data = VAULT_MAGIC.to_vec();
+        data.push(VAULT_VERSION);
+        data.push(self.cipher.id());
+        self.kdf.write(&mut data);
+        data.push(self.salt.len() as u8);
+        data.extend_from_slice(&self.salt);
+        data
+    }
+
+    /// Writes the header to a buffer
+    ///
+    /// # Arguments
+    /// * `buffer` - The buffer to write to
+    fn write(&self, buffer: &mut Vec<u8>) {
+        buffer.append(&mut self.authenticated_data());
+        buffer.extend_from_slice(&self.key_nonce);
+        buffer.extend_from_slice(&(self.wrapped_key.len() as u32).to_be_bytes());
+        buffer.extend_from_slice(&self.wrapped_key);
+    }
 
-        DerivedKey::new(derived_key, salt_copy)
+    /// Reads a header from the start of a versioned vault file
+    ///
+    /// # Arguments
+    /// * `bytes` - The content of the file
+    ///
+    /// # Returns
+    /// The header and its length in bytes, None if the header is invalid
+    fn read(bytes: &[u8]) -> Option<(Self, usize)> {
+        if !VaultHeader::is_versioned(bytes) {
+            return None;
+        }
+        let mut offset = VAULT_MAGIC.len();
+        if *bytes.get(offset)? != VAULT_VERSION {
+            return None;
+        }
+        let cipher = CipherKind::from_id(*bytes.get(offset + 1)?)?;
+        offset += 2;
+
+        let (kdf, kdf_len) = KdfParams::read(bytes.get(offset..)?)?;
+        offset += kdf_len;
+
+        let salt_len = *bytes.get(offset)? as usize;
+        let salt = bytes.get(offset + 1..offset + 1 + salt_len)?.to_vec();
+        offset += 1 + salt_len;
+
+        let key_nonce = bytes.get(offset..offset + cipher.nonce_length())?.to_vec();
+        offset += cipher.nonce_length();
+
+        let wrapped_len =
+            u32::from_be_bytes(bytes.get(offset..offset + LENGTH_PREFIX)?.try_into().ok()?)
+                as usize;
+        offset += LENGTH_PREFIX;
+        let wrapped_key = bytes.get(offset..offset + wrapped_len)?.to_vec();
+        offset += wrapped_len;
+
+        Some((
+            VaultHeader {
+                cipher,
+                kdf,
+                salt,
+                key_nonce,
+                wrapped_key,
+            },
+            offset,
+        ))
+    }
+
+    /// Checks if the bytes start with the vault magic
+    ///
+    /// # Arguments
+    /// * `bytes` - The content of the file
+    ///
+    /// # Returns
+    /// `true` if the file is a versioned vault, otherwise `false`
+    fn is_versioned(bytes: &[u8]) -> bool {
+        bytes.starts_with(&VAULT_MAGIC)
+    }
+}
+
+impl VaultFormat {
+    /// Writes the header of the format (if any) to a buffer
+    ///
+    /// # Arguments
+    /// * `buffer` - The buffer to write to
+    fn write(&self, buffer: &mut Vec<u8>) {
+        match self {
+            VaultFormat::Legacy => {}
+            VaultFormat::Versioned(header) => header.write(buffer),
+        }
     }
 }
 
@@ -263,82 +535,168 @@ impl Record {
     ///
     /// # Arguments
     /// * `bytes` - The bytes to read from
-    /// * `master_password` - The master password
+    /// * `record_key` - The key of the records
     /// * `offset` - The offset
     ///
     /// # Returns
     /// A tuple of the record, the remaining bytes and the current offset
+    /// or an error if the record is truncated
     fn read_from_bytes(
         bytes: Vec<u8>,
-        master_password: &str,
+        record_key: &RecordKey,
         offset: u32,
-    ) -> Result<(Self, Vec<u8>, u32), aead::Error> {
-        let salt = bytes[0..22].to_vec();
-        let nonce = GenericArray::clone_from_slice(&bytes[22..34]);
-        let ciphertext_len = u32::from_be_bytes(bytes[34..38].try_into().unwrap());
-        let ciphertext = bytes[38..(38 + ciphertext_len as usize)].to_vec();
-        let derived_key = DerivedKey::derive_key(master_password, Some(salt.clone()));
-        let key = Key::<Aes128GcmSiv>::clone_from_slice(&derived_key.key);
+    ) -> Result<(Self, Vec<u8>, u32), KrabError> {
+        let (salt_len, nonce_len) = match record_key {
+            RecordKey::Legacy(_) => (LEGACY_SALT_LENGTH, LEGACY_CIPHER.nonce_length()),
+            RecordKey::DataKey(key) => (0, key.kind().nonce_length()),
+        };
+        let nonce_start = salt_len;
+        let len_start = nonce_start + nonce_len;
+        let ciphertext_start = len_start + LENGTH_PREFIX;
+        if bytes.len() < ciphertext_start {
+            return Err(KrabError::corrupted(
+                offset as usize,
+                "the record header is truncated",
+            ));
+        }
+
+        let salt = bytes[0..salt_len].to_vec();
+        let nonce = bytes[nonce_start..len_start].to_vec();
+        let ciphertext_len =
+            u32::from_be_bytes(bytes[len_start..ciphertext_start].try_into().unwrap()) as usize;
+        let ciphertext_end = ciphertext_start + ciphertext_len;
+        if bytes.len() < ciphertext_end {
+            return Err(KrabError::corrupted(
+                offset as usize,
+                format!(
+                    "the record is truncated ({} of {} bytes)",
+                    bytes.len(),
+                    ciphertext_end
+                ),
+            ));
+        }
+        let ciphertext = bytes[ciphertext_start..ciphertext_end].to_vec();
+        let key = match record_key {
+            RecordKey::Legacy(master_password) => {
+                let derived_key =
+                    DerivedKey::derive_key(master_password.expose_secret(), Some(salt.clone()));
+                CipherKey::from_slice(LEGACY_CIPHER, &derived_key.key)
+                    .ok_or(KrabError::Encryption)?
+            }
+            RecordKey::DataKey(key) => key.clone(),
+        };
         let cipher_config = CipherConfig::new(key, salt, nonce, ciphertext);
-        let current_offset = 38 + ciphertext_len as usize + offset as usize;
+        let current_offset = ciphertext_end + offset as usize;
 
         Ok((
             Record::new(cipher_config, offset),
-            bytes[(38 + ciphertext_len as usize)..].to_vec(),
+            bytes[ciphertext_end..].to_vec(),
             current_offset as u32,
         ))
     }
 
     /// Read user data from file
+    /// Versioned files derive the master key once to unwrap the data key,
+    /// legacy files derive a key for every record
     ///
     /// # Arguments
     ///
-    /// * `p` - Path to the directory where the file (users data) is stored
+    /// * `store` - The store holding the user data
     /// * `username` - The username of the user
     /// * `master_password` - The master password of the user
     ///
     /// # Returns
-    /// * `Result<Vec<Self>, String>` - A vector of records or an error message
-    fn read_user(p: &PathBuf, username: &str, master_password: &str) -> Result<Vec<Self>, String> {
-        let hash = hash(username.to_string());
-        let file_path = p.join(hash.as_str());
-        let mut data: Vec<Record> = Vec::new();
-        let mut offset = 0;
-        if file_path.exists() {
-            let mut bytes = fs::read(file_path).unwrap();
-            let mut run = true;
-            while run {
-                let res = Record::read_from_bytes(bytes, master_password, offset);
-                if res.is_err() {
-                    return Err("Could not read user".to_string());
-                }
-                let (cipher, remaining, next_offset) = res.unwrap();
-                data.push(cipher);
-                bytes = remaining;
-                if bytes.len() == 0 {
-                    run = false;
-                }
+    /// * `Result<(Vec<Self>, VaultFormat, RecordKey, String), KrabError>` - The records, the format
+    ///   of the file, the key of the records and the checksum of the file or an error
+    fn read_user(
+        store: &impl VaultStore,
+        username: &str,
+        master_password: &str,
+    ) -> Result<(Vec<Self>, VaultFormat, RecordKey, String), KrabError> {
+        let bytes = match store.read(&hash(username.to_string())) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(KrabError::UserNotFound),
+            Err(e) => return Err(e.into()),
+        };
+        let record_key = if VaultHeader::is_versioned(&bytes) {
+            let (header, _) = VaultHeader::read(&bytes)
+                .ok_or_else(|| KrabError::corrupted(0, "the vault header is invalid"))?;
+            let data_key = header
+                .unwrap_key(master_password)
+                .map_err(|_| KrabError::WrongCredentials)?;
+            RecordKey::DataKey(data_key)
+        } else {
+            RecordKey::Legacy(SecretString::from(master_password))
+        };
 
-                offset = next_offset;
-            }
+        let file_checksum = checksum(&bytes);
+        let (data, format) = Self::read_with_key(bytes, &record_key)?;
+
+        Ok((data, format, record_key, file_checksum))
+    }
+
+    /// Reads the records of a user file with an already known key
+    ///
+    /// # Arguments
+    /// * `bytes` - The content of the user file
+    /// * `record_key` - The key of the records
+    ///
+    /// # Returns
+    /// The records and the format of the file or an error
+    fn read_with_key(
+        mut bytes: Vec<u8>,
+        record_key: &RecordKey,
+    ) -> Result<(Vec<Self>, VaultFormat), KrabError> {
+        let (format, mut offset) = if VaultHeader::is_versioned(&bytes) {
+            let (header, header_len) = VaultHeader::read(&bytes)
+                .ok_or_else(|| KrabError::corrupted(0, "the vault header is invalid"))?;
+            bytes = bytes.split_off(header_len);
+            (VaultFormat::Versioned(header), header_len as u32)
         } else {
-            return Err("User not found".to_string());
+            (VaultFormat::Legacy, 0)
+        };
+
+        let mut data: Vec<Record> = Vec::new();
+        while !bytes.is_empty() {
+            let (cipher, remaining, next_offset) =
+                Record::read_from_bytes(bytes, record_key, offset)?;
+            data.push(cipher);
+            bytes = remaining;
+            offset = next_offset;
         }
-        Ok(data)
+
+        Ok((data, format))
     }
 
     /// Decrypts the data
+    /// Records written by older versions have no id, they get one derived from
+    /// their ciphertext, which stays the same until the record is written again
     ///
     /// # Returns
-    /// The decrypted data or an error if decryption fails
-    fn data(&self) -> Result<DomainPasswordPair, aead::Error> {
-        self.cypher.decrypt_data()
+    /// The decrypted entry or an error if decryption or decoding fails
+    fn data(&self) -> Result<Entry, PayloadError> {
+        let mut entry = self.cypher.decrypt_data()?;
+        if entry.id.is_empty() {
+            entry.id = checksum(&self.cypher.ciphertext)[..2 * ID_LENGTH].to_string();
+        }
+        Ok(entry)
+    }
+
+    /// Decrypts the data of a record whose key is known to be right
+    ///
+    /// # Returns
+    /// The decrypted entry or an error pointing at the damaged record
+    fn entry(&self) -> Result<Entry, KrabError> {
+        self.data()
+            .map_err(|e| KrabError::corrupted(self.offset as usize, e))
     }
 }
 
 impl User {
-    /// Creates a new `User` instance
+    /// Creates a new `User` instance from the file system
     /// Does not create a new user in the file system
+    /// A legacy user file is migrated to the versioned format,
+    /// the outcome is available through `migration`
     ///
     /// # Arguments
     /// * `path` - The path to the user data
@@ -346,64 +704,200 @@ impl User {
     /// * `master_password` - The master password
     ///
     /// # Returns
-    /// A new `User` and `ReadOnlyRecords` or an error message
+    /// A new `User` and `ReadOnlyRecords` or an error
     pub fn from(
-        path: &PathBuf,
+        path: &Path,
         username: &str,
         master_password: &str,
-    ) -> Result<(Self, ReadOnlyRecords), String> {
-        let records = Record::read_user(path, username, master_password);
-        let records = match records {
-            Ok(r) => r,
-            Err(e) => return Err(e),
-        };
-        let mut read_only_records = vec![];
-
-        for record in records.iter() {
-            let decrypted = record.cypher.decrypt_data();
-            match decrypted {
-                Ok(decrypted) => {
-                    read_only_records
-                        .push((decrypted.domain.to_string(), decrypted.password.to_string()));
+    ) -> Result<(Self, ReadOnlyRecords), KrabError> {
+        Self::from_store(FileStore::new(path), username, master_password)
+    }
+
+    /// Creates a new user and writes the user data to the file system
+    /// New users are always stored in the versioned format
+    ///
+    /// # Arguments
+    /// * `user` - The user configuration, holding the first record
+    ///
+    /// # Returns
+    /// An error if the user could not be created
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(user: &RecordOperationConfig) -> Result<(), KrabError> {
+        let mut entry = Entry::new(&user.domain, user.password.expose_secret());
+        entry.id = Entry::generate_id();
+        Self::create_vault(
+            &FileStore::new(&user.path),
+            &user.username,
+            user.master_password.expose_secret(),
+            &KdfParams::default(),
+            CipherKind::default(),
+            &[entry],
+        )
+    }
+
+    /// Creates a new user without any records and writes it to the file system
+    ///
+    /// # Arguments
+    /// * `path` - The path to the user data
+    /// * `username` - The username
+    /// * `master_password` - The master password
+    /// * `kdf` - The key derivation parameters of the vault
+    /// * `cipher` - The cipher of the vault
+    ///
+    /// # Returns
+    /// An error if the user could not be created
+    pub fn create(
+        path: &Path,
+        username: &str,
+        master_password: &str,
+        kdf: &KdfParams,
+        cipher: CipherKind,
+    ) -> Result<(), KrabError> {
+        Self::create_in_store(
+            &FileStore::new(path),
+            username,
+            master_password,
+            kdf,
+            cipher,
+        )
+    }
+
+    /// Returns the path to the user file
+    ///
+    /// # Returns
+    /// The path to the user file
+    pub fn path(&self) -> PathBuf {
+        self.1.path(&self.name())
+    }
+}
+
+impl<S: VaultStore> User<S> {
+    /// Creates a new `User` instance from a store
+    /// A legacy user file is migrated to the versioned format,
+    /// the outcome is available through `migration`
+    ///
+    /// # Arguments
+    /// * `store` - The store holding the user data
+    /// * `username` - The username
+    /// * `master_password` - The master password
+    ///
+    /// # Returns
+    /// A new `User` and `ReadOnlyRecords` or an error
+    pub fn from_store(
+        store: S,
+        username: &str,
+        master_password: &str,
+    ) -> Result<(Self, ReadOnlyRecords), KrabError> {
+        let (user, read_only_records, _) = Self::open_store(store, username, master_password)?;
+        Ok((user, read_only_records))
+    }
+
+    /// Opens a user and keeps the key of its records
+    /// A legacy user file is migrated to the versioned format,
+    /// in which case the key of the migrated records is returned
+    ///
+    /// # Arguments
+    /// * `store` - The store holding the user data
+    /// * `username` - The username
+    /// * `master_password` - The master password
+    ///
+    /// # Returns
+    /// A new `User`, its `ReadOnlyRecords` and the key of its records or an error
+    pub(crate) fn open_store(
+        store: S,
+        username: &str,
+        master_password: &str,
+    ) -> Result<(Self, ReadOnlyRecords, RecordKey), KrabError> {
+        let (records, format, mut record_key, file_checksum) =
+            Record::read_user(&store, username, master_password)?;
+        let read_only_records = decrypt_records(&records, &format)?;
+
+        let mut user = User(
+            records,
+            store,
+            Username(username.to_string()),
+            format,
+            None,
+            file_checksum,
+        );
+
+        if user.3 == VaultFormat::Legacy {
+            let migration = match user.lock() {
+                Ok(_lock) => user.migrate(master_password, &read_only_records),
+                Err(e) => Err(e),
+            };
+            user.4 = Some(match migration {
+                Ok((backup, data_key)) => {
+                    record_key = data_key;
+                    Migration::Migrated {
+                        backup,
+                        records: read_only_records.0.len(),
+                    }
                 }
-                Err(_) => return Err("Could not decrypt data".to_string()),
-            }
+                Err(e) => Migration::Failed(e),
+            });
         }
 
-        let path = path.join(hash(username.to_string()));
-
-        Ok((
-            User(records, path, Username(username.to_string())),
-            ReadOnlyRecords(read_only_records),
-        ))
+        Ok((user, read_only_records, record_key))
     }
 
-    /// Creates a new user and writes the user data to the file system
+    /// Creates a new user without any records and writes it to a store
+    /// New users are always stored in the versioned format
     ///
     /// # Arguments
-    /// * `user` - The user configuration
+    /// * `store` - The store holding the user data
+    /// * `username` - The username
+    /// * `master_password` - The master password
+    /// * `kdf` - The key derivation parameters of the vault
+    /// * `cipher` - The cipher of the vault
     ///
     /// # Returns
-    /// An error message if the user could not be created
-    pub fn new(user: &RecordOperationConfig) -> Result<(), String> {
-        let hashed_username = hash(user.username.to_string());
-        let res = create_file(&user.path, hashed_username.as_str());
-        let file_path = match res {
-            Ok(path) => path,
-            Err(_) => return Err("Could not create file.".to_string()),
-        };
+    /// An error if the user could not be created
+    pub fn create_in_store(
+        store: &S,
+        username: &str,
+        master_password: &str,
+        kdf: &KdfParams,
+        cipher: CipherKind,
+    ) -> Result<(), KrabError> {
+        Self::create_vault(store, username, master_password, kdf, cipher, &[])
+    }
 
-        let cipher =
-            CipherConfig::encrypt_data(&user.domain, &user.password, &user.master_password);
-        let cipher = match cipher {
-            Ok(cipher) => cipher,
-            Err(_) => return Err("Could not encrypt data.".to_string()),
-        };
+    /// Creates the user file in the versioned format with the given records
+    ///
+    /// # Arguments
+    /// * `store` - The store holding the user data
+    /// * `username` - The username
+    /// * `master_password` - The master password
+    /// * `kdf` - The key derivation parameters
+    /// * `cipher` - The cipher
+    /// * `records` - The entries to store
+    ///
+    /// # Returns
+    /// An error if the user could not be created
+    pub(crate) fn create_vault(
+        store: &S,
+        username: &str,
+        master_password: &str,
+        kdf: &KdfParams,
+        cipher: CipherKind,
+        records: &[Entry],
+    ) -> Result<(), KrabError> {
+        let (header, data_key) =
+            VaultHeader::create(master_password, kdf, cipher).map_err(|_| KrabError::Encryption)?;
+        let record_key = RecordKey::DataKey(data_key);
         let mut buffer = vec![];
-        cipher.write(&mut buffer);
-        match write_to_file(&file_path, buffer) {
-            Ok(_) => Ok(()),
-            Err(_) => Err("Could not write to file.".to_string()),
+        header.write(&mut buffer);
+        for entry in records.iter() {
+            let cipher = CipherConfig::encrypt_data(entry, &record_key)
+                .map_err(|_| KrabError::Encryption)?;
+            cipher.write(&mut buffer);
+        }
+
+        match store.create(&hash(username.to_string()), buffer) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(KrabError::UserAlreadyExists),
+            Err(e) => Err(e.into()),
         }
     }
 
@@ -415,284 +909,807 @@ impl User {
         self.2.clone().0
     }
 
-    /// Returns the path to the user data
+    /// Returns the name of the user file in the store
     ///
     /// # Returns
-    /// The path to the user data
-    fn path(&self) -> PathBuf {
-        self.1.clone()
+    /// The hashed username
+    fn name(&self) -> String {
+        hash(self.username())
     }
 
-    /// Adds a new record to the user data
-    /// The record is encrypted before being added
+    /// Returns the outcome of the legacy format migration done on login
+    ///
+    /// # Returns
+    /// The migration outcome or None if the user file was already versioned
+    pub fn migration(&self) -> Option<Migration> {
+        self.4.clone()
+    }
+
+    /// Migrates a legacy user file to the versioned format
+    /// The original file is copied to a timestamped `.bak` file in the store
+    /// and then atomically replaced with the re-encrypted records
     ///
     /// # Arguments
-    /// * `record` - The record configuration
+    /// * `master_password` - The master password
+    /// * `records` - The decrypted records of the legacy file
     ///
     /// # Returns
-    /// The read-only records or an error message
-    pub fn add_record(&mut self, record: RecordOperationConfig) -> Result<ReadOnlyRecords, String> {
-        let (integrity, ro_records) =
-            self.check_integrity(&record.username, &record.master_password, &record.path);
+    /// The name of the backup and the key of the migrated records or an error
+    fn migrate(
+        &mut self,
+        master_password: &str,
+        records: &ReadOnlyRecords,
+    ) -> Result<(String, RecordKey), KrabError> {
+        let (header, data_key) = VaultHeader::create(
+            master_password,
+            &KdfParams::default(),
+            CipherKind::default(),
+        )
+        .map_err(|_| KrabError::Encryption)?;
+        let format = VaultFormat::Versioned(header);
+        let record_key = RecordKey::DataKey(data_key);
 
-        let mut ro_records = match ro_records {
-            Some(ro_records) => ro_records,
-            None => return Err("Could not read user".to_string()),
-        };
+        let mut buffer = vec![];
+        format.write(&mut buffer);
+        let mut new_records = vec![];
+        for entry in records.0.iter() {
+            let cipher = CipherConfig::encrypt_data(entry, &record_key)
+                .map_err(|_| KrabError::Encryption)?;
+            let offset = buffer.len() as u32;
+            cipher.write(&mut buffer);
+            new_records.push(Record::new(cipher, offset));
+        }
 
-        if !integrity {
-            return Err("Integrity check failed".to_string());
+        let name = self.name();
+        if checksum(&self.1.read(&name)?) != self.5 {
+            return Err(KrabError::Conflict);
         }
 
-        if ro_records.0.iter().find(|r| r.0 == record.domain).is_some() {
-            return Err("Record already exists".to_string());
+        let backup = self.backup_name();
+        self.1.copy(&name, &backup)?;
+        let file_checksum = checksum(&buffer);
+        if let Err(e) = self.1.write(&name, buffer, 0) {
+            let _ = self.1.delete(&backup);
+            return Err(e.into());
         }
 
-        ro_records.add_record(&record.domain, &record.password);
-        let cipher =
-            CipherConfig::encrypt_data(&record.domain, &record.password, &record.master_password);
-        let cipher = match cipher {
-            Ok(cipher) => cipher,
-            Err(_) => return Err("Could not create user.".to_string()),
-        };
+        self.5 = file_checksum;
+        self.0 = new_records;
+        self.3 = format;
 
-        let offset = self.last_offset();
-        let record = Record::new(cipher, offset);
-        let mut buffer = vec![];
-        record.cypher.write(&mut buffer);
-        append_to_file(&self.path(), buffer).unwrap();
-        self.0.push(record);
+        Ok((backup, record_key))
+    }
 
-        Ok(ro_records)
+    /// Returns a timestamped backup name for the user file
+    ///
+    /// # Returns
+    /// The backup name
+    fn backup_name(&self) -> String {
+        let now = chrono::Local::now().format("%Y-%m-%d-%H-%M-%S");
+        format!("{}-{}.bak", self.name(), now)
     }
 
-    /// Removes a record from the user data
-    /// The record is removed by domain
+    /// Adds a new record to the user data
+    /// The record is encrypted before being added, other records may have the same domain
     ///
     /// # Arguments
     /// * `record` - The record configuration
     ///
     /// # Returns
     /// The read-only records or an error message
+    pub fn add_record(
+        &mut self,
+        record: RecordOperationConfig,
+    ) -> Result<ReadOnlyRecords, KrabError> {
+        let _lock = self.lock()?;
+        let (ro_records, record_key) =
+            self.check_integrity(&record.username, record.master_password.expose_secret())?;
+
+        let entry = Entry::new(&record.domain, record.password.expose_secret());
+        self.insert_record(ro_records, &record_key, &entry)
+    }
+
+    /// Removes a record from the user data
+    /// The record is removed by id
+    ///
+    /// # Arguments
+    /// * `record` - The record configuration
+    ///
+    /// # Returns
+    /// The read-only records or an error
     pub fn remove_record(
         &mut self,
         record: RecordOperationConfig,
-    ) -> Result<ReadOnlyRecords, String> {
-        let (integrity, ro_records) =
-            self.check_integrity(&record.username, &record.master_password, &record.path);
+    ) -> Result<ReadOnlyRecords, KrabError> {
+        let _lock = self.lock()?;
+        let (ro_records, _) =
+            self.check_integrity(&record.username, record.master_password.expose_secret())?;
 
-        let mut ro_records = match ro_records {
-            Some(ro_records) => ro_records,
-            None => return Err("Could not read user".to_string()),
+        self.delete_record(ro_records, &record.id)
+    }
+
+    /// Modifies the password of a record in the user data
+    /// The record is modified by id, its other fields are kept
+    ///
+    /// # Arguments
+    /// * `record` - The record configuration
+    ///
+    /// # Returns
+    /// The read-only records or an error
+    pub fn modify_record(
+        &mut self,
+        record: RecordOperationConfig,
+    ) -> Result<ReadOnlyRecords, KrabError> {
+        let _lock = self.lock()?;
+        let (ro_records, record_key) =
+            self.check_integrity(&record.username, record.master_password.expose_secret())?;
+
+        let previous = match ro_records.get(&record.id) {
+            Some(entry) => entry,
+            None => return Err(KrabError::RecordNotFound),
         };
+        let mut edited = previous.clone();
+        edited.password = record.password.clone();
+        let entry = previous.revise(&edited, timestamp());
+        self.replace_record(ro_records, &record_key, &record.id, &entry)
+    }
 
-        if !integrity {
-            return Err("Integrity check failed".to_string());
-        }
+    /// Changes the master password of the user
+    /// The data key is wrapped again with a key derived from the new
+    /// master password, so the records themselves are not touched
+    /// A legacy user file is re-encrypted in the versioned format
+    ///
+    /// # Arguments
+    /// * `old_master_password` - The current master password
+    /// * `new_master_password` - The new master password
+    ///
+    /// # Returns
+    /// An error if the master password could not be changed
+    pub fn change_master_password(
+        &mut self,
+        old_master_password: &str,
+        new_master_password: &str,
+    ) -> Result<(), KrabError> {
+        let _lock = self.lock()?;
+        let (ro_records, record_key) =
+            self.check_integrity(&self.username(), old_master_password)?;
+
+        self.rewrap(
+            &ro_records,
+            &record_key,
+            new_master_password,
+            &self.kdf(),
+            self.cipher(),
+        )?;
+        Ok(())
+    }
 
-        if ro_records.0.len() == 1 {
-            return Err("Cannot remove the last record".to_string());
+    /// Returns the key derivation parameters of the user file
+    ///
+    /// # Returns
+    /// The key derivation parameters, legacy files always use the default ones
+    pub fn kdf(&self) -> KdfParams {
+        match &self.3 {
+            VaultFormat::Legacy => KdfParams::default(),
+            VaultFormat::Versioned(header) => header.kdf,
         }
+    }
 
-        let mut new_records = vec![];
-        let mut found = false;
-        for r in self.0.iter() {
-            let data = match r.data() {
-                Ok(data) => data,
-                Err(_) => return Err("Could not read data".to_string()),
-            };
+    /// Returns the cipher of the user file
+    ///
+    /// # Returns
+    /// The cipher, legacy files always use AES-128-GCM-SIV
+    pub fn cipher(&self) -> CipherKind {
+        match &self.3 {
+            VaultFormat::Legacy => LEGACY_CIPHER,
+            VaultFormat::Versioned(header) => header.cipher,
+        }
+    }
 
-            if data.domain != record.domain {
-                new_records.push(r.clone());
-                ro_records.remove_record(&record.domain);
-            } else {
-                found = true;
+    /// Protects the user file with a new master password, new key derivation
+    /// parameters or a new cipher and writes it
+    /// The records are re-encrypted with a fresh data key if the file is legacy
+    /// or the cipher changes, otherwise only the data key is wrapped again
+    /// The user file must be locked and its integrity checked by the caller
+    ///
+    /// # Arguments
+    /// * `ro_records` - The current read-only records
+    /// * `record_key` - The key of the records
+    /// * `new_master_password` - The new master password
+    /// * `kdf` - The key derivation parameters
+    /// * `cipher` - The cipher
+    ///
+    /// # Returns
+    /// The key of the records, which changes if the records were re-encrypted,
+    /// or an error
+    pub(crate) fn rewrap(
+        &mut self,
+        ro_records: &ReadOnlyRecords,
+        record_key: &RecordKey,
+        new_master_password: &str,
+        kdf: &KdfParams,
+        cipher: CipherKind,
+    ) -> Result<RecordKey, KrabError> {
+        let (format, record_key, mut records) = match record_key {
+            RecordKey::DataKey(data_key) if data_key.kind() == cipher => {
+                let header = VaultHeader::wrap(new_master_password, data_key, kdf)
+                    .map_err(|_| KrabError::Encryption)?;
+                (
+                    VaultFormat::Versioned(header),
+                    record_key.clone(),
+                    self.0.clone(),
+                )
             }
-        }
+            _ => {
+                let (header, data_key) = VaultHeader::create(new_master_password, kdf, cipher)
+                    .map_err(|_| KrabError::Encryption)?;
+                let record_key = RecordKey::DataKey(data_key);
+                let mut records = vec![];
+                for entry in ro_records.0.iter() {
+                    let cipher = CipherConfig::encrypt_data(entry, &record_key)
+                        .map_err(|_| KrabError::Encryption)?;
+                    // the offset is set when the record is written
+                    records.push(Record::new(cipher, 0));
+                }
+                (VaultFormat::Versioned(header), record_key, records)
+            }
+        };
 
-        if !found {
-            return Err("Record not found".to_string());
+        let previous_format = std::mem::replace(&mut self.3, format);
+        if let Err(e) = self.write_records(&mut records, VAULT_GENERATIONS) {
+            self.3 = previous_format;
+            return Err(e);
         }
+        self.0 = records;
 
-        // TODO: calibrate offsets or remove them
+        Ok(record_key)
+    }
 
-        self.remove_records_from_file();
-        let path = self.path();
-        let mut buffer = vec![];
+    /// Encrypts a new record and writes it to the user file
+    /// An entry without an id gets a new one, an entry without
+    /// a creation time is stamped with the current time
+    /// The user file must be locked and its integrity checked by the caller
+    ///
+    /// # Arguments
+    /// * `ro_records` - The current read-only records
+    /// * `record_key` - The key of the records
+    /// * `entry` - The entry
+    ///
+    /// # Returns
+    /// The read-only records or an error message
+    pub(crate) fn insert_record(
+        &mut self,
+        ro_records: ReadOnlyRecords,
+        record_key: &RecordKey,
+        entry: &Entry,
+    ) -> Result<ReadOnlyRecords, KrabError> {
+        self.insert_records(ro_records, record_key, slice::from_ref(entry))
+    }
 
-        for record in new_records.iter() {
-            record.cypher.write(&mut buffer);
+    /// Encrypts new records and writes them to the user file at once
+    /// The entries are prepared like in `insert_record`, none of them
+    /// is written if one of them already exists
+    /// The user file must be locked and its integrity checked by the caller
+    ///
+    /// # Arguments
+    /// * `ro_records` - The current read-only records
+    /// * `record_key` - The key of the records
+    /// * `entries` - The entries
+    ///
+    /// # Returns
+    /// The read-only records or an error message
+    pub(crate) fn insert_records(
+        &mut self,
+        ro_records: ReadOnlyRecords,
+        record_key: &RecordKey,
+        entries: &[Entry],
+    ) -> Result<ReadOnlyRecords, KrabError> {
+        self.update_records(ro_records, record_key, &[], entries)
+    }
+
+    /// Replaces records and adds new ones, then writes them to the user file at once
+    /// The replacing entries keep the position of the records with their id, see
+    /// `replace_record`, the new entries are prepared like in `insert_records`
+    /// The user file must be locked and its integrity checked by the caller
+    ///
+    /// # Arguments
+    /// * `ro_records` - The current read-only records
+    /// * `record_key` - The key of the records
+    /// * `replaced` - The entries replacing the records with the same id
+    /// * `entries` - The new entries
+    ///
+    /// # Returns
+    /// The read-only records or an error message
+    pub(crate) fn update_records(
+        &mut self,
+        mut ro_records: ReadOnlyRecords,
+        record_key: &RecordKey,
+        replaced: &[Entry],
+        entries: &[Entry],
+    ) -> Result<ReadOnlyRecords, KrabError> {
+        let mut new_records = self.0.clone();
+        let mut ids = vec![];
+        if !replaced.is_empty() {
+            for r in new_records.iter() {
+                ids.push(r.entry()?.id);
+            }
+        }
+        for entry in replaced.iter() {
+            let position = ids
+                .iter()
+                .position(|id| *id == entry.id)
+                .ok_or(KrabError::RecordNotFound)?;
+            let cipher =
+                CipherConfig::encrypt_data(entry, record_key).map_err(|_| KrabError::Encryption)?;
+            new_records[position] = Record::new(cipher, new_records[position].offset);
         }
 
-        write_to_file(&path, buffer).unwrap();
+        let mut new_entries: Vec<Entry> = Vec::with_capacity(entries.len());
+        for entry in entries.iter() {
+            let mut entry = entry.clone();
+            if entry.id.is_empty() {
+                entry.id = Entry::generate_id();
+            }
+            if entry.created == 0 {
+                entry.created = timestamp();
+                entry.modified = entry.created;
+            }
+            if ro_records.get(&entry.id).is_some()
+                || new_entries.iter().any(|new| new.id == entry.id)
+            {
+                return Err(KrabError::RecordAlreadyExists);
+            }
+
+            let cipher = CipherConfig::encrypt_data(&entry, record_key)
+                .map_err(|_| KrabError::Encryption)?;
+            // the offset is set when the record is written
+            new_records.push(Record::new(cipher, 0));
+            new_entries.push(entry);
+        }
+
+        self.write_records(&mut new_records, VAULT_GENERATIONS)?;
         self.0 = new_records;
+        for entry in replaced.iter() {
+            ro_records.replace_record(entry.clone());
+        }
+        for entry in new_entries {
+            ro_records.add_record(entry);
+        }
 
         Ok(ro_records)
     }
 
-    /// Modifies a record in the user data
-    /// The record is modified by domain
+    /// Removes a record by id and writes the remaining ones to the user file
+    /// The user file must be locked and its integrity checked by the caller
     ///
     /// # Arguments
-    /// * `record` - The record configuration
+    /// * `ro_records` - The current read-only records
+    /// * `id` - The id of the record
     ///
     /// # Returns
     /// The read-only records or an error message
-    pub fn modify_record(
+    pub(crate) fn delete_record(
         &mut self,
-        record: RecordOperationConfig,
-    ) -> Result<ReadOnlyRecords, String> {
-        let (integrity, ro_records) =
-            self.check_integrity(&record.username, &record.master_password, &record.path);
-
-        let mut ro_records = match ro_records {
-            Some(ro_records) => ro_records,
-            None => return Err("Could not read user".to_string()),
-        };
-
-        if !integrity {
-            return Err("Integrity check failed".to_string());
+        mut ro_records: ReadOnlyRecords,
+        id: &str,
+    ) -> Result<ReadOnlyRecords, KrabError> {
+        // a legacy file without records could not prove the master password anymore
+        if self.3 == VaultFormat::Legacy && ro_records.0.len() == 1 {
+            return Err(KrabError::LastRecord);
         }
 
         let mut new_records = vec![];
         let mut found = false;
         for r in self.0.iter() {
-            let data = match r.data() {
-                Ok(data) => data,
-                Err(_) => return Err("Could not read data".to_string()),
-            };
+            let data = r.entry()?;
 
-            if data.domain != record.domain {
+            if data.id != id {
                 new_records.push(r.clone());
             } else {
-                ro_records.remove_record(&record.domain);
+                ro_records.remove_record(id);
                 found = true;
             }
         }
 
         if !found {
-            return Err("Record not found".to_string());
+            return Err(KrabError::RecordNotFound);
         }
 
-        ro_records.add_record(&record.domain, &record.password);
+        self.write_records(&mut new_records, VAULT_GENERATIONS)?;
+        self.0 = new_records;
+
+        Ok(ro_records)
+    }
+
+    /// Replaces a record by id with an entry and writes the records to the user file
+    /// The entry keeps the id and the position of the record it replaces, its other
+    /// fields are stored as they are, see `Entry::revise` to update its metadata
+    /// The user file must be locked and its integrity checked by the caller
+    ///
+    /// # Arguments
+    /// * `ro_records` - The current read-only records
+    /// * `record_key` - The key of the records
+    /// * `id` - The id of the record to replace
+    /// * `entry` - The new entry
+    ///
+    /// # Returns
+    /// The read-only records or an error message
+    pub(crate) fn replace_record(
+        &mut self,
+        ro_records: ReadOnlyRecords,
+        record_key: &RecordKey,
+        id: &str,
+        entry: &Entry,
+    ) -> Result<ReadOnlyRecords, KrabError> {
+        self.store_record(ro_records, record_key, id, entry, VAULT_GENERATIONS)
+    }
+
+    /// Replaces a record by id like `replace_record`, but without keeping the
+    /// previous user file as a generation, for changes that are not worth
+    /// a recovery point, like the time a record was last used
+    /// The user file must be locked and its integrity checked by the caller
+    ///
+    /// # Arguments
+    /// * `ro_records` - The current read-only records
+    /// * `record_key` - The key of the records
+    /// * `id` - The id of the record to replace
+    /// * `entry` - The new entry
+    ///
+    /// # Returns
+    /// The read-only records or an error message
+    pub(crate) fn touch_record(
+        &mut self,
+        ro_records: ReadOnlyRecords,
+        record_key: &RecordKey,
+        id: &str,
+        entry: &Entry,
+    ) -> Result<ReadOnlyRecords, KrabError> {
+        self.store_record(ro_records, record_key, id, entry, 0)
+    }
+
+    /// Replaces a record by id with an entry and writes the records to the user file
+    ///
+    /// # Arguments
+    /// * `ro_records` - The current read-only records
+    /// * `record_key` - The key of the records
+    /// * `id` - The id of the record to replace
+    /// * `entry` - The new entry
+    /// * `generations` - The number of previous generations of the user file to keep
+    ///
+    /// # Returns
+    /// The read-only records or an error message
+    fn store_record(
+        &mut self,
+        mut ro_records: ReadOnlyRecords,
+        record_key: &RecordKey,
+        id: &str,
+        entry: &Entry,
+        generations: usize,
+    ) -> Result<ReadOnlyRecords, KrabError> {
+        let mut entry = entry.clone();
+        entry.id = id.to_string();
+
+        let mut position = None;
+        for (index, r) in self.0.iter().enumerate() {
+            if r.entry()?.id == id {
+                position = Some(index);
+                break;
+            }
+        }
+        let position = position.ok_or(KrabError::RecordNotFound)?;
 
         let cipher =
-            CipherConfig::encrypt_data(&record.domain, &record.password, &record.master_password);
-        let cipher = match cipher {
-            Ok(cipher) => cipher,
-            Err(_) => return Err("Could not create user.".to_string()),
-        };
+            CipherConfig::encrypt_data(&entry, record_key).map_err(|_| KrabError::Encryption)?;
+
+        let mut new_records = self.0.clone();
+        new_records[position] = Record::new(cipher, new_records[position].offset);
 
-        let record = Record::new(cipher, self.last_offset());
+        self.write_records(&mut new_records, generations)?;
+        self.0 = new_records;
+        ro_records.replace_record(entry);
 
-        new_records.push(record);
+        Ok(ro_records)
+    }
 
+    /// Serializes the header (if any) and the given records
+    /// The offset of each record is set to where it starts in the user file
+    ///
+    /// # Arguments
+    /// * `records` - The records to serialize
+    ///
+    /// # Returns
+    /// The content of the user file
+    fn serialize(&self, records: &mut [Record]) -> Vec<u8> {
         let mut buffer = vec![];
-        for record in new_records.iter() {
+        self.3.write(&mut buffer);
+        for record in records.iter_mut() {
+            record.offset = buffer.len() as u32;
             record.cypher.write(&mut buffer);
         }
 
-        write_to_file(&self.path(), buffer).unwrap();
-        self.0 = new_records;
+        buffer
+    }
+
+    /// Writes the given records to the user file and updates their offsets
+    /// The file is replaced atomically and the given number of
+    /// previous versions of it are kept next to it
+    ///
+    /// # Arguments
+    /// * `records` - The records to write
+    /// * `generations` - The number of previous generations to keep,
+    ///   `0` leaves the existing generations as they are
+    ///
+    /// # Returns
+    /// An error if the file could not be written
+    fn write_records(
+        &mut self,
+        records: &mut [Record],
+        generations: usize,
+    ) -> Result<(), KrabError> {
+        let buffer = self.serialize(records);
+        let file_checksum = checksum(&buffer);
+        self.1.write(&self.name(), buffer, generations)?;
+        self.5 = file_checksum;
+        Ok(())
+    }
+
+    /// Checks the master password against the user file
+    ///
+    /// # Arguments
+    /// * `record_key` - The key of the records
+    /// * `master_password` - The master password to check
+    ///
+    /// # Returns
+    /// `true` if the master password unlocks the user file, otherwise `false`
+    pub(crate) fn check_master_password(
+        &self,
+        record_key: &RecordKey,
+        master_password: &str,
+    ) -> bool {
+        match (&self.3, record_key) {
+            (VaultFormat::Versioned(header), RecordKey::DataKey(data_key)) => header
+                .unwrap_key(master_password)
+                .is_ok_and(|key| key == *data_key),
+            (VaultFormat::Legacy, RecordKey::Legacy(key)) => key.expose_secret() == master_password,
+            _ => false,
+        }
+    }
+
+    /// Locks the user file against changes from other processes
+    /// The lock is held until the returned guard is dropped
+    ///
+    /// # Returns
+    /// The lock or an error if it could not be taken
+    pub(crate) fn lock(&self) -> Result<S::Lock, KrabError> {
+        self.1
+            .lock(&self.name(), LOCK_TIMEOUT)
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::WouldBlock => KrabError::Locked,
+                kind => KrabError::Io(kind),
+            })
+    }
+
+    /// Returns the previous generations of the user file that exist in the store
+    /// The most recent generation comes first
+    ///
+    /// # Returns
+    /// The names of the previous generations
+    pub fn generations(&self) -> Vec<String> {
+        let name = self.name();
+        (1..=VAULT_GENERATIONS)
+            .map(|generation| generation_name(&name, generation))
+            .filter(|generation| self.1.exists(generation))
+            .collect()
+    }
+
+    /// Returns the name of the blob of an attachment in the store
+    ///
+    /// # Arguments
+    /// * `id` - The id of the attachment
+    ///
+    /// # Returns
+    /// The name of the blob
+    fn attachment_name(&self, id: &str) -> String {
+        format!("{}.attachment.{}", self.name(), id)
+    }
+
+    /// Stores the blob of a new attachment
+    ///
+    /// # Arguments
+    /// * `id` - The id of the attachment
+    /// * `blob` - The encrypted blob
+    ///
+    /// # Returns
+    /// An error if the blob could not be stored
+    pub(crate) fn write_attachment(&self, id: &str, blob: Vec<u8>) -> Result<(), KrabError> {
+        self.1.create(&self.attachment_name(id), blob)?;
+        Ok(())
+    }
+
+    /// Reads the blob of an attachment
+    ///
+    /// # Arguments
+    /// * `id` - The id of the attachment
+    ///
+    /// # Returns
+    /// The encrypted blob or `KrabError::AttachmentNotFound` if it is missing
+    pub(crate) fn read_attachment(&self, id: &str) -> Result<Vec<u8>, KrabError> {
+        self.1
+            .read(&self.attachment_name(id))
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => KrabError::AttachmentNotFound,
+                kind => KrabError::Io(kind),
+            })
+    }
 
-        Ok(ro_records)
+    /// Deletes the blob of an attachment
+    ///
+    /// # Arguments
+    /// * `id` - The id of the attachment
+    ///
+    /// # Returns
+    /// An error if the blob could not be deleted
+    pub(crate) fn delete_attachment(&self, id: &str) -> Result<(), KrabError> {
+        self.1.delete(&self.attachment_name(id))?;
+        Ok(())
     }
 
-    /// Returns the last offset in the file
+    /// Deletes the user file and its previous generations
+    /// The blobs of attachments are not deleted
     ///
     /// # Returns
-    /// The last offset
-    fn last_offset(&self) -> u32 {
-        let mut offset = 0;
-        for record in self.0.iter() {
-            if record.offset > offset {
-                offset = record.offset;
-            }
+    /// An error if the user file could not be deleted
+    pub(crate) fn delete_vault(&self) -> Result<(), KrabError> {
+        for generation in self.generations() {
+            self.1.delete(&generation)?;
         }
-
-        offset
+        self.1.delete(&self.name())?;
+        Ok(())
     }
 
     /// Checks the integrity of the user data
     /// The integrity is checked by decrypting the data
     /// If the data cannot be decrypted, the integrity check fails
+    /// If the file changed since it was last read or written, the check
+    /// fails with `KrabError::Conflict`
     ///
     /// # Arguments
     /// * `username` - The username of the user
     /// * `master_password` - The master password of the user
-    /// * `path` - The path to the user data
     ///
     /// # Returns
-    /// A tuple of the integrity status and the read-only records if the integrity check passes
+    /// The read-only records and the key of the records if the integrity check passes,
+    /// otherwise an error
     fn check_integrity(
         &self,
         username: &str,
         master_password: &str,
-        path: &PathBuf,
-    ) -> (bool, Option<ReadOnlyRecords>) {
-        let records = Record::read_user(path, username, master_password);
-
-        match records {
-            Ok(r) => {
-                let mut read_only_records = vec![];
-                for record in r.iter() {
-                    let decrypted = record.cypher.decrypt_data();
-                    match decrypted {
-                        Ok(decrypted) => {
-                            read_only_records.push((
-                                decrypted.domain.to_string(),
-                                decrypted.password.to_string(),
-                            ));
-                        }
-                        Err(_) => return (false, None),
-                    }
-                }
-                (true, Some(ReadOnlyRecords(read_only_records)))
-            }
-            Err(_) => (false, None),
+    ) -> Result<(ReadOnlyRecords, RecordKey), KrabError> {
+        let (records, format, record_key, file_checksum) =
+            Record::read_user(&self.1, username, master_password)?;
+
+        if file_checksum != self.5 {
+            return Err(KrabError::Conflict);
         }
+
+        let read_only_records = decrypt_records(&records, &format)?;
+
+        Ok((read_only_records, record_key))
     }
 
-    /// Removes all records from the file
+    /// Reads the user file again with an already known key
+    /// Fails with `KrabError::Conflict` if `check_changes` is set and the file
+    /// changed since it was last read or written
+    ///
+    /// # Arguments
+    /// * `record_key` - The key of the records
+    /// * `check_changes` - Whether changes by other processes are an error
     ///
     /// # Returns
-    /// Nothing, panics if the records could not be removed (TODO: handle error)
-    fn remove_records_from_file(&mut self) {
-        let path = self.path();
-        match clear_file_content(&path) {
-            Ok(_) => {}
-            Err(_) => panic!("Could not clear file content"),
+    /// The read-only records or an error
+    pub(crate) fn read_records(
+        &mut self,
+        record_key: &RecordKey,
+        check_changes: bool,
+    ) -> Result<ReadOnlyRecords, KrabError> {
+        let bytes = self.1.read(&self.name())?;
+        let file_checksum = checksum(&bytes);
+        if check_changes && file_checksum != self.5 {
+            return Err(KrabError::Conflict);
         }
+
+        let (records, format) = Record::read_with_key(bytes, record_key)?;
+        let read_only_records = decrypt_records(&records, &format)?;
+
+        self.0 = records;
+        self.3 = format;
+        self.5 = file_checksum;
+
+        Ok(read_only_records)
     }
 }
 
+/// Decrypts records into read-only records
+/// The key of a versioned file was checked against its header, so a record that
+/// cannot be decrypted is damaged, whereas it means a wrong master password for legacy files
+///
+/// # Arguments
+/// * `records` - The records to decrypt
+/// * `format` - The format of the user file
+///
+/// # Returns
+/// The read-only records or an error if any record cannot be decrypted or decoded
+fn decrypt_records(records: &[Record], format: &VaultFormat) -> Result<ReadOnlyRecords, KrabError> {
+    let mut read_only_records = vec![];
+    for record in records.iter() {
+        let entry = match (record.data(), format) {
+            (Ok(entry), _) => entry,
+            (Err(PayloadError::Decrypt), VaultFormat::Legacy) => {
+                return Err(KrabError::WrongCredentials)
+            }
+            (Err(e), _) => return Err(KrabError::corrupted(record.offset as usize, e)),
+        };
+        read_only_records.push(entry);
+    }
+
+    Ok(ReadOnlyRecords(read_only_records))
+}
+
 impl ReadOnlyRecords {
     /// Returns the records
     ///
     /// # Returns
-    /// A vector of domain-password pairs
-    pub fn records(&self) -> Vec<(String, String)> {
-        self.0.clone()
+    /// The entries
+    pub fn records(&self) -> &[Entry] {
+        &self.0
+    }
+
+    /// Takes the records without copying the secrets
+    ///
+    /// # Returns
+    /// The entries
+    pub fn into_records(self) -> Vec<Entry> {
+        self.0
+    }
+
+    /// Returns the record with an id
+    ///
+    /// # Arguments
+    /// * `id` - The id of the record
+    ///
+    /// # Returns
+    /// The entry or None if no record has the id
+    pub fn get(&self, id: &str) -> Option<&Entry> {
+        self.0.iter().find(|e| e.id == id)
     }
 
     /// Adds a new record to the read-only records
     ///
     /// # Arguments
-    /// * `domain` - The domain
-    /// * `password` - The password
-    fn add_record(&mut self, domain: &String, password: &String) {
-        self.0.push((domain.clone(), password.clone()));
+    /// * `entry` - The entry
+    fn add_record(&mut self, entry: Entry) {
+        self.0.push(entry);
+    }
+
+    /// Replaces the record with the id of an entry in the read-only records
+    ///
+    /// # Arguments
+    /// * `entry` - The entry
+    fn replace_record(&mut self, entry: Entry) {
+        if let Some(record) = self.0.iter_mut().find(|r| r.id == entry.id) {
+            *record = entry;
+        }
     }
 
     /// Removes a record from the read-only records
-    /// The record is removed by domain
+    /// The record is removed by id
     ///
     /// # Arguments
-    /// * `domain` - The domain
-    fn remove_record(&mut self, domain: &String) {
+    /// * `id` - The id of the record
+    fn remove_record(&mut self, id: &str) {
         let mut new_records = vec![];
         for record in self.0.iter() {
-            if record.0 != *domain {
+            if record.id != id {
                 new_records.push(record.clone());
             }
         }
@@ -705,34 +1722,45 @@ impl ReadOnlyRecords {
 mod tests {
     use super::*;
 
-    use rand::Rng;
-    use std::{env, fs};
-
-    fn random_number() -> u32 {
-        let mut rng = rand::thread_rng();
-        rng.gen_range(10000000..99999999)
+    use crate::{
+        store::{generation_name, MemoryStore},
+        test_util::{generate_random_username, setup_user_data},
+    };
+
+    // ids and timestamps are generated, they are cleared to compare with `Entry::new`
+    fn without_ids(records: &ReadOnlyRecords) -> Vec<Entry> {
+        records
+            .records()
+            .iter()
+            .map(|r| Entry {
+                id: String::new(),
+                created: 0,
+                modified: 0,
+                last_used: 0,
+                ..r.clone()
+            })
+            .collect()
     }
 
-    fn generate_random_username() -> String {
-        format!("krab-{}", random_number())
+    fn record_id(records: &ReadOnlyRecords, domain: &str) -> String {
+        records
+            .records()
+            .iter()
+            .find(|r| r.domain == domain)
+            .unwrap()
+            .id
+            .clone()
     }
 
-    fn setup_user_data(domain: &str) -> Result<RecordOperationConfig, String> {
-        let username = generate_random_username();
-        let username = username.as_str().to_owned();
-        let master_password = "password";
-        let password = "password";
-        let path = PathBuf::from(env::var("KRAB_TEMP_DIR").unwrap());
-        let user =
-            RecordOperationConfig::new(username.as_str(), master_password, domain, password, &path);
-        match User::new(&user) {
-            Ok(_) => Ok(user.clone()),
-            Err(e) => Err(e),
-        }
-    }
-
-    fn create_user(config: &RecordOperationConfig) -> Result<(User, ReadOnlyRecords), String> {
-        User::from(&config.path, &config.username, &config.master_password)
+    fn create_user(
+        store: &MemoryStore,
+        config: &RecordOperationConfig,
+    ) -> Result<(User<MemoryStore>, ReadOnlyRecords), KrabError> {
+        User::from_store(
+            store.clone(),
+            &config.username,
+            config.master_password.expose_secret(),
+        )
     }
 
     #[test]
@@ -745,167 +1773,365 @@ mod tests {
         assert_eq!(salt.len(), 22);
     }
 
-    #[test]
-    fn test_marshalling() {
-        let domain = "example.com";
-        let password = "password";
-        let data = CipherConfig::marshal(domain, password);
-        let (d, p) = CipherConfig::unmarshal(data.as_str());
-        assert_eq!(d, domain);
-        assert_eq!(p, password);
-
-        let domain = "example.com with spaces";
-        let password = "password with spaces";
-        let data = CipherConfig::marshal(domain, password);
-        let (d, p) = CipherConfig::unmarshal(data.as_str());
-        assert_eq!(d, domain);
-        assert_eq!(p, password);
-
-        let domain = "example.com with \\";
-        let password = "password with \\";
-        let data = CipherConfig::marshal(domain, password);
-        let (d, p) = CipherConfig::unmarshal(data.as_str());
-        assert_eq!(d, domain);
-        assert_eq!(p, password);
-
-        let domain = "example.com with \\ and    spacessss";
-        let password = "password with \\ and    spacessss";
-        let data = CipherConfig::marshal(domain, password);
-        let (d, p) = CipherConfig::unmarshal(data.as_str());
-        assert_eq!(d, domain);
-        assert_eq!(p, password);
-    }
-
     #[test]
     fn test_cipher_config() {
-        let domain = "example.com";
-        let password = "password";
-        let data = CipherConfig::marshal(domain, password);
+        let mut entry = Entry::new("example.com", "password");
+        entry.notes = SecretString::from("notes");
         let master_password = "password";
-        let cipher = CipherConfig::encrypt_data(domain, password, master_password).unwrap();
+        let cipher = CipherConfig::encrypt_data(
+            &entry,
+            &RecordKey::Legacy(SecretString::from(master_password)),
+        )
+        .unwrap();
         let decrypted = cipher.decrypt_data().unwrap();
-        let decrypted = format!("{} {}", decrypted.domain, decrypted.password);
-        assert_eq!(decrypted, data);
+        assert_eq!(decrypted, entry);
+
+        let mut tampered = cipher.clone();
+        tampered.ciphertext[0] ^= 1;
+        assert_eq!(tampered.decrypt_data(), Err(PayloadError::Decrypt));
+    }
+
+    /// Writes a headerless (legacy) user file with the given records
+    fn setup_legacy_user_data(records: &[(&str, &str)]) -> (MemoryStore, RecordOperationConfig) {
+        let store = MemoryStore::new();
+        let username = generate_random_username();
+        let master_password = "password";
+        let record_key = RecordKey::Legacy(SecretString::from(master_password));
 
-        let domain = "example.com with  spaces and \\";
-        let password = "password with  spaces and \\";
-        let data = CipherConfig::marshal(domain, password);
-        let marshalled =
-            "example.com\\swith\\s\\sspaces\\sand\\s\\\\ password\\swith\\s\\sspaces\\sand\\s\\\\"
-                .to_string();
-        assert_eq!(data, marshalled);
+        let mut buffer = vec![];
+        for (domain, password) in records.iter() {
+            let data = payload::encode_legacy(domain, password);
+            CipherConfig::encrypt_bytes(data.expose_secret().as_bytes(), &record_key)
+                .unwrap()
+                .write(&mut buffer);
+        }
+        store.create(&hash(username.clone()), buffer).unwrap();
+
+        let config = RecordOperationConfig::new(
+            &username,
+            master_password,
+            records[0].0,
+            records[0].1,
+            &PathBuf::new(),
+        );
+        (store, config)
     }
 
     #[test]
-    fn test_create_user_success() {
-        let user_data = setup_user_data("example.com").unwrap();
-        let user = create_user(&user_data);
+    fn test_vault_header_roundtrip() {
+        for cipher in [
+            CipherKind::Aes128GcmSiv,
+            CipherKind::Aes256GcmSiv,
+            CipherKind::XChaCha20Poly1305,
+        ] {
+            let (header, data_key) =
+                VaultHeader::create("password", &KdfParams::default(), cipher).unwrap();
+            let mut buffer = vec![];
+            header.write(&mut buffer);
+            buffer.extend_from_slice(b"records");
+
+            let (read_header, header_len) = VaultHeader::read(&buffer).unwrap();
+
+            assert_eq!(read_header, header);
+            assert_eq!(header_len, buffer.len() - b"records".len());
+            assert_eq!(read_header.unwrap_key("password").unwrap(), data_key);
+            assert!(read_header.unwrap_key("wrong_password").is_err());
+        }
+    }
 
-        // delete the file (user)
-        let hashed_username = hash(user_data.username.to_string());
-        let file_path = user_data.path.join(hashed_username.as_str());
-        fs::remove_file(file_path).unwrap();
+    #[test]
+    fn test_vault_header_tampered_cipher() {
+        let (header, _) =
+            VaultHeader::create("password", &KdfParams::default(), CipherKind::Aes256GcmSiv)
+                .unwrap();
+        let mut tampered = header.clone();
+        tampered.cipher = CipherKind::XChaCha20Poly1305;
+        tampered.key_nonce = vec![0u8; CipherKind::XChaCha20Poly1305.nonce_length()];
+        let mut buffer = vec![];
+        header.write(&mut buffer);
+        buffer[VAULT_MAGIC.len() + 1] = 0xFF;
 
-        assert_eq!(user.is_ok(), true);
+        assert!(tampered.unwrap_key("password").is_err());
+        assert_eq!(VaultHeader::read(&buffer), None);
     }
 
     #[test]
-    fn test_create_user_fail_already_exists() {
-        // setup_user_data function not used here because we want to test
-        // the case where the user already exists thus we need to try to create
-        // a user with the same username twice (setup_user_data creates a new user each time
-        // with a unique username)
+    fn test_vault_header_tampered_params() {
+        let (header, _) =
+            VaultHeader::create("password", &KdfParams::default(), CipherKind::default()).unwrap();
+        let mut tampered = header.clone();
+        tampered.kdf = KdfParams::Scrypt {
+            log_n: 10,
+            r: 8,
+            p: 1,
+        };
 
-        let username = generate_random_username();
-        let username = username.as_str();
-        let master_password = "password";
-        let domain = "example.com";
-        let password = "password";
-        let path = PathBuf::from(env::var("KRAB_TEMP_DIR").unwrap());
-        let config = RecordOperationConfig::new(username, master_password, domain, password, &path);
-        let _ = User::new(&config);
+        assert!(tampered.unwrap_key("password").is_err());
+    }
+
+    #[test]
+    fn test_vault_header_legacy_detection() {
+        let record_key = RecordKey::Legacy(SecretString::from("password"));
+        let mut buffer = vec![];
+        CipherConfig::encrypt_data(&Entry::new("example.com", "password"), &record_key)
+            .unwrap()
+            .write(&mut buffer);
 
-        let config = RecordOperationConfig::new(username, master_password, domain, password, &path);
-        let res = User::new(&config);
+        assert!(!VaultHeader::is_versioned(&buffer));
+        assert_eq!(VaultHeader::read(&buffer), None);
+    }
 
-        // delete the file (user)
-        let hashed_username = hash(username.to_string());
-        let file_path = path.join(hashed_username.as_str());
-        fs::remove_file(file_path).unwrap();
+    #[test]
+    fn test_create_user_versioned_format() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (user, _) = create_user(&store, &user_data).unwrap();
+        let bytes = user.1.read(&user.name()).unwrap();
 
-        assert_eq!(res.is_err(), true);
+        assert!(VaultHeader::is_versioned(&bytes));
+        assert!(matches!(user.3, VaultFormat::Versioned(_)));
     }
 
     #[test]
-    fn test_integrity_success() {
-        let user_data = setup_user_data("example.com").unwrap();
-        let (user, _) = create_user(&user_data).unwrap();
+    fn test_read_legacy_user_success() {
+        let (store, user_data) =
+            setup_legacy_user_data(&[("example.com", "password"), ("example2.com", "password2")]);
+        let (_, records) = create_user(&store, &user_data).unwrap();
+        let records = without_ids(&records);
+
+        assert_eq!(records.len(), 2);
+        assert!(records.contains(&Entry::new("example2.com", "password2")));
+    }
+
+    #[test]
+    fn test_migrate_legacy_user_success() {
+        let (store, user_data) =
+            setup_legacy_user_data(&[("example.com", "password"), ("example2.com", "password2")]);
+        let original = store.read(&hash(user_data.username.clone())).unwrap();
+        let (user, migrated_records) = create_user(&store, &user_data).unwrap();
+        let migrated = user.1.read(&user.name()).unwrap();
+        let migration = user.migration();
+
+        let (reread_user, records) = create_user(&store, &user_data).unwrap();
+        let backup = match migration {
+            Some(Migration::Migrated { backup, records }) => {
+                assert_eq!(records, 2);
+                backup
+            }
+            _ => panic!("User was not migrated"),
+        };
+        let backup_content = user.1.read(&backup).unwrap();
+
+        assert!(VaultHeader::is_versioned(&migrated));
+        assert!(matches!(user.3, VaultFormat::Versioned(_)));
+        assert_eq!(backup_content, original);
+        assert!(backup.ends_with(".bak"));
+        assert_eq!(reread_user.migration(), None);
+        assert_eq!(records.records().len(), 2);
+        // the ids of legacy records are kept by the migration
+        assert_eq!(records, migrated_records);
+    }
+
+    #[test]
+    fn test_migrate_legacy_user_wrong_password() {
+        let (store, user_data) = setup_legacy_user_data(&[("example.com", "password")]);
+        let name = hash(user_data.username.clone());
+        let original = store.read(&name).unwrap();
+        let res = User::from_store(store.clone(), &user_data.username, "wrong_password");
+        let after = store.read(&name).unwrap();
+
+        assert!(res.is_err());
+        assert_eq!(after, original);
+    }
+
+    #[test]
+    fn test_modify_migrated_user_success() {
+        let (store, user_data) = setup_legacy_user_data(&[("example.com", "password")]);
+        let (mut user, _) = create_user(&store, &user_data).unwrap();
 
-        let (integrity, _) = user.check_integrity(
+        let add_record = RecordOperationConfig::new(
             &user_data.username,
-            &user_data.master_password,
+            user_data.master_password.expose_secret(),
+            "example2.com",
+            "password2",
             &user_data.path,
         );
+        let res = user.add_record(add_record);
+        let (reread_user, records) = create_user(&store, &user_data).unwrap();
+
+        assert!(res.is_ok());
+        assert_eq!(records.records().len(), 2);
+        assert!(matches!(reread_user.3, VaultFormat::Versioned(_)));
+    }
+
+    #[test]
+    fn test_create_user_success() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let user = create_user(&store, &user_data);
+
+        assert!(user.is_ok());
+    }
+
+    #[test]
+    fn test_create_user_fail_already_exists() {
+        // setup_user_data creates a new user with a unique username each time,
+        // so the same username is used to create a second user in its store
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let res = User::create_in_store(
+            &store,
+            &user_data.username,
+            user_data.master_password.expose_secret(),
+            &KdfParams::default(),
+            CipherKind::default(),
+        );
+
+        assert_eq!(res, Err(KrabError::UserAlreadyExists));
+    }
+
+    #[test]
+    fn test_integrity_success() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (user, _) = create_user(&store, &user_data).unwrap();
 
-        // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
+        let integrity = user
+            .check_integrity(
+                &user_data.username,
+                user_data.master_password.expose_secret(),
+            )
+            .is_ok();
 
-        assert_eq!(integrity, true);
+        assert!(integrity);
     }
 
     #[test]
     fn test_integrity_fail() {
-        let user_data = setup_user_data("example.com").unwrap();
-        let (user, _) = create_user(&user_data).unwrap();
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (user, _) = create_user(&store, &user_data).unwrap();
 
-        let (integrity, _) =
-            user.check_integrity(&user_data.username, "wrong_password", &user_data.path);
+        let integrity = user
+            .check_integrity(&user_data.username, "wrong_password")
+            .is_ok();
+
+        assert!(!integrity);
+    }
+
+    #[test]
+    fn test_read_user_corrupted() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (user, _) = create_user(&store, &user_data).unwrap();
+        let content = user.1.read(&user.name()).unwrap();
+        let record_offset = user.0[0].offset as usize;
+
+        let mut tampered = content.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        user.1.write(&user.name(), tampered, 0).unwrap();
+        let tampered_res = create_user(&store, &user_data);
+        user.1
+            .write(&user.name(), content[..content.len() - 1].to_vec(), 0)
+            .unwrap();
+        let truncated_res = create_user(&store, &user_data);
+        user.1
+            .write(&user.name(), content[..8].to_vec(), 0)
+            .unwrap();
+        let header_res = create_user(&store, &user_data);
+
+        assert!(matches!(
+            tampered_res,
+            Err(KrabError::Corrupted { offset, .. }) if offset == record_offset
+        ));
+        assert!(matches!(
+            truncated_res,
+            Err(KrabError::Corrupted { offset, .. }) if offset == record_offset
+        ));
+        assert!(matches!(
+            header_res,
+            Err(KrabError::Corrupted { offset: 0, .. })
+        ));
+    }
 
-        // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
+    #[test]
+    fn test_record_offsets_follow_layout() {
+        let offsets = |user: &User<MemoryStore>| -> Vec<u32> {
+            user.0.iter().map(|record| record.offset).collect()
+        };
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut user, records) = create_user(&store, &user_data).unwrap();
+        for domain in ["example2.com", "example3.com"] {
+            let add_record = RecordOperationConfig::new(
+                &user_data.username,
+                user_data.master_password.expose_secret(),
+                domain,
+                "password",
+                &user_data.path,
+            );
+            user.add_record(add_record).unwrap();
+        }
+        let mut remove_record = RecordOperationConfig::new(
+            &user_data.username,
+            user_data.master_password.expose_secret(),
+            "example.com",
+            "",
+            &user_data.path,
+        );
+        remove_record.id = record_id(&records, "example.com");
+        user.remove_record(remove_record).unwrap();
+        let written = offsets(&user);
+        let (removed, _) = create_user(&store, &user_data).unwrap();
+        let (ro_records, record_key) = user
+            .check_integrity(
+                &user_data.username,
+                user_data.master_password.expose_secret(),
+            )
+            .unwrap();
+        user.rewrap(
+            &ro_records,
+            &record_key,
+            "new_password",
+            &KdfParams::default_argon2id(),
+            CipherKind::XChaCha20Poly1305,
+        )
+        .unwrap();
+        let (rewrapped, _) =
+            User::from_store(store.clone(), &user_data.username, "new_password").unwrap();
 
-        assert_eq!(integrity, false);
+        assert_eq!(written.len(), 2);
+        assert_eq!(written, offsets(&removed));
+        assert_ne!(offsets(&removed), offsets(&rewrapped));
+        assert_eq!(offsets(&user), offsets(&rewrapped));
     }
 
     #[test]
     fn test_read_record_success() {
-        let user_data = setup_user_data("example.com").unwrap();
-        let (user, records) = create_user(&user_data).unwrap();
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (_, records) = create_user(&store, &user_data).unwrap();
 
         let records = records.records();
-        let (domain, password) = records.first().unwrap();
-
-        // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
+        let Entry {
+            domain, password, ..
+        } = records.first().unwrap();
 
         assert_eq!(records.len(), 1);
         assert_eq!(domain, "example.com");
-        assert_eq!(password, "password");
+        assert_eq!(password.expose_secret(), "password");
 
-        let user_data = setup_user_data("example2. com").unwrap();
-        let (user, records) = create_user(&user_data).unwrap();
+        let (store, user_data) = setup_user_data("example2. com").unwrap();
+        let (_, records) = create_user(&store, &user_data).unwrap();
 
         let records = records.records();
-        let (domain, password) = records.first().unwrap();
-
-        // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
+        let Entry {
+            domain, password, ..
+        } = records.first().unwrap();
 
         assert_eq!(records.len(), 1);
         assert_eq!(domain, "example2. com");
-        assert_eq!(password, "password");
+        assert_eq!(password.expose_secret(), "password");
     }
 
     #[test]
     #[should_panic]
     fn test_read_record_fail() {
-        let user_data = setup_user_data("example.com").unwrap();
-        let try_user = User::from(&user_data.path, &user_data.username, "wrong_password");
-
-        // delete the file (user)
-        let hashed_username = hash(user_data.username);
-        let file_path = user_data.path.join(hashed_username.as_str());
-        fs::remove_file(file_path).unwrap();
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let try_user = User::from_store(store.clone(), &user_data.username, "wrong_password");
 
         // this should panic
         let _ = try_user.unwrap();
@@ -913,51 +2139,45 @@ mod tests {
 
     #[test]
     fn test_add_record_success() {
-        let user_data = setup_user_data("example.com").unwrap();
-        let (mut user, _) = create_user(&user_data).unwrap();
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut user, _) = create_user(&store, &user_data).unwrap();
 
         let new_domain = "example2.com";
         let new_password = "password2";
         let add_record = RecordOperationConfig::new(
             &user_data.username,
-            &user_data.master_password,
+            user_data.master_password.expose_secret(),
             new_domain,
             new_password,
             &user_data.path,
         );
         let res = user.add_record(add_record);
 
-        let (user, records) = User::from(
-            &user_data.path,
+        let (_, records) = User::from_store(
+            store.clone(),
             &user_data.username,
-            &user_data.master_password,
+            user_data.master_password.expose_secret(),
         )
         .unwrap();
 
         let records = records.records();
 
-        let inserted_record = records.iter().find(|r| r.0 == new_domain);
-
-        // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
+        let inserted_record = records.iter().find(|r| r.domain == new_domain);
 
-        assert_eq!(res.is_ok(), true);
-        assert_eq!(inserted_record.is_some(), true);
+        assert!(res.is_ok());
+        assert!(inserted_record.is_some());
         assert_eq!(records.len(), 2);
-        assert_eq!(
-            records.iter().find(|r| r.0 == "example.com").is_some(),
-            true
-        );
-        assert_eq!(
-            records.iter().find(|r| r.0 == "example2.com").is_some(),
-            true
-        );
+        assert!(records.iter().find(|r| r.domain == "example.com").is_some());
+        assert!(records
+            .iter()
+            .find(|r| r.domain == "example2.com")
+            .is_some());
     }
 
     #[test]
     fn test_add_record_fail() {
-        let user_data = setup_user_data("example.com").unwrap();
-        let (mut user, records) = create_user(&user_data).unwrap();
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut user, records) = create_user(&store, &user_data).unwrap();
 
         let new_domain = "example2.com";
         let new_password = "password2";
@@ -970,46 +2190,44 @@ mod tests {
         );
         let res = user.add_record(add_record);
 
-        // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
-
         assert_eq!(records.records().len(), 1);
-        assert_eq!(res.is_err(), true);
+        assert!(res.is_err());
     }
 
     #[test]
-    fn test_add_record_fail_already_exists() {
-        let user_data = setup_user_data("example.com").unwrap();
-        let (mut user, records) = create_user(&user_data).unwrap();
+    fn test_add_record_same_domain_success() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut user, _) = create_user(&store, &user_data).unwrap();
 
         let new_domain = "example.com";
         let new_password = "password2";
         let add_record = RecordOperationConfig::new(
             &user_data.username,
-            &user_data.master_password,
+            user_data.master_password.expose_secret(),
             new_domain,
             new_password,
             &user_data.path,
         );
         let res = user.add_record(add_record);
+        let (_, records) = create_user(&store, &user_data).unwrap();
 
-        // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
-
-        assert_eq!(records.records().len(), 1);
-        assert_eq!(res.is_err(), true);
+        let records = records.records();
+        assert_eq!(res.unwrap().records(), records);
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|r| r.domain == "example.com"));
+        assert_ne!(records[0].id, records[1].id);
     }
 
     #[test]
     fn test_remove_record_success() {
-        let user_data = setup_user_data("example.com").unwrap();
-        let (mut user, _) = create_user(&user_data).unwrap();
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut user, _) = create_user(&store, &user_data).unwrap();
 
         let new_domain = "example2.com";
         let new_password = "password2";
         let add_record = RecordOperationConfig::new(
             &user_data.username,
-            &user_data.master_password,
+            user_data.master_password.expose_secret(),
             new_domain,
             new_password,
             &user_data.path,
@@ -1020,70 +2238,59 @@ mod tests {
         let new_password = "password3";
         let add_record = RecordOperationConfig::new(
             &user_data.username,
-            &user_data.master_password,
+            user_data.master_password.expose_secret(),
             new_domain,
             new_password,
             &user_data.path,
         );
-        let _ = user.add_record(add_record);
+        let records = user.add_record(add_record).unwrap();
 
-        let remove_record = RecordOperationConfig::new(
+        let mut remove_record = RecordOperationConfig::new(
             &user_data.username,
-            &user_data.master_password,
+            user_data.master_password.expose_secret(),
             "example2.com",
             "",
             &user_data.path,
         );
+        remove_record.id = record_id(&records, "example2.com");
         let res = user.remove_record(remove_record);
 
-        let (user, records) = User::from(
-            &user_data.path,
+        let (_, records) = User::from_store(
+            store.clone(),
             &user_data.username,
-            &user_data.master_password,
+            user_data.master_password.expose_secret(),
         )
         .unwrap();
 
         let records = records.records();
-        let domains: Vec<String> = records.iter().map(|r| r.0.clone()).collect();
+        let domains: Vec<String> = records.iter().map(|r| r.domain.clone()).collect();
 
-        // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
-
-        assert_eq!(res.is_ok(), true);
+        assert!(res.is_ok());
         assert_eq!(records.len(), 2);
-        assert_eq!(
-            domains
-                .iter()
-                .find(|d| d.as_str() == "example2.com")
-                .is_none(),
-            true
-        );
-        assert_eq!(
-            domains
-                .iter()
-                .find(|d| d.as_str() == "example3.com")
-                .is_some(),
-            true
-        );
-        assert_eq!(
-            domains
-                .iter()
-                .find(|d| d.as_str() == "example.com")
-                .is_some(),
-            true
-        );
+        assert!(domains
+            .iter()
+            .find(|d| d.as_str() == "example2.com")
+            .is_none());
+        assert!(domains
+            .iter()
+            .find(|d| d.as_str() == "example3.com")
+            .is_some());
+        assert!(domains
+            .iter()
+            .find(|d| d.as_str() == "example.com")
+            .is_some());
     }
 
     #[test]
     fn test_remove_record_read_user_success() {
-        let user_data = setup_user_data("example.com").unwrap();
-        let (mut user, _) = create_user(&user_data).unwrap();
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut user, _) = create_user(&store, &user_data).unwrap();
 
         let new_domain = "example2.com";
         let new_password = "password2";
         let add_record = RecordOperationConfig::new(
             &user_data.username,
-            &user_data.master_password,
+            user_data.master_password.expose_secret(),
             new_domain,
             new_password,
             &user_data.path,
@@ -1094,47 +2301,45 @@ mod tests {
         let new_password = "password3";
         let add_record = RecordOperationConfig::new(
             &user_data.username,
-            &user_data.master_password,
+            user_data.master_password.expose_secret(),
             new_domain,
             new_password,
             &user_data.path,
         );
-        let _ = user.add_record(add_record);
+        let records = user.add_record(add_record).unwrap();
 
-        let remove_record = RecordOperationConfig::new(
+        let mut remove_record = RecordOperationConfig::new(
             &user_data.username,
-            &user_data.master_password,
+            user_data.master_password.expose_secret(),
             "example2.com",
             "",
             &user_data.path,
         );
+        remove_record.id = record_id(&records, "example2.com");
         let res = user.remove_record(remove_record);
 
-        let (user, records) = User::from(
-            &user_data.path,
+        let (_, records) = User::from_store(
+            store.clone(),
             &user_data.username,
-            &user_data.master_password,
+            user_data.master_password.expose_secret(),
         )
         .unwrap();
         let records = records.records();
 
-        // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
-
-        assert_eq!(res.is_ok(), true);
+        assert!(res.is_ok());
         assert_eq!(records.len(), 2);
     }
 
     #[test]
     fn test_remove_record_fail_not_found() {
-        let user_data = setup_user_data("example.com").unwrap();
-        let (mut user, _) = create_user(&user_data).unwrap();
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut user, _) = create_user(&store, &user_data).unwrap();
 
         let new_domain = "example2.com";
         let new_password = "password2";
         let add_record = RecordOperationConfig::new(
             &user_data.username,
-            &user_data.master_password,
+            user_data.master_password.expose_secret(),
             new_domain,
             new_password,
             &user_data.path,
@@ -1143,29 +2348,26 @@ mod tests {
 
         let remove_record = RecordOperationConfig::new(
             &user_data.username,
-            &user_data.master_password,
+            user_data.master_password.expose_secret(),
             "example3.com",
             "",
             &user_data.path,
         );
         let res = user.remove_record(remove_record);
 
-        // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
-
-        assert_eq!(res.is_err(), true);
+        assert!(res.is_err());
     }
 
     #[test]
     fn test_remove_record_fail_integrity_check() {
-        let user_data = setup_user_data("example.com").unwrap();
-        let (mut user, _) = create_user(&user_data).unwrap();
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut user, _) = create_user(&store, &user_data).unwrap();
 
         let new_domain = "example2.com";
         let new_password = "password2";
         let add_record = RecordOperationConfig::new(
             &user_data.username,
-            &user_data.master_password,
+            user_data.master_password.expose_secret(),
             new_domain,
             new_password,
             &user_data.path,
@@ -1181,74 +2383,203 @@ mod tests {
         );
         let res = user.remove_record(remove_record);
 
-        // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
-
-        assert_eq!(res.is_err(), true);
+        assert!(res.is_err());
     }
 
     #[test]
-    pub fn test_remove_record_fail_only_record_should_not_remove() {
-        let user_data = setup_user_data("example.com").unwrap();
-        let (mut user, _) = create_user(&user_data).unwrap();
+    pub fn test_remove_record_last_record_success() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut user, records) = create_user(&store, &user_data).unwrap();
 
-        let remove_record = RecordOperationConfig::new(
+        let mut remove_record = RecordOperationConfig::new(
             &user_data.username,
-            &user_data.master_password,
+            user_data.master_password.expose_secret(),
             "example.com",
             "",
             &user_data.path,
         );
+        remove_record.id = record_id(&records, "example.com");
         let res = user.remove_record(remove_record);
+        let reread = create_user(&store, &user_data);
+        let wrong_password = User::from_store(store.clone(), &user_data.username, "wrong_password");
+
+        assert!(res.unwrap().records().is_empty());
+        assert!(reread.unwrap().1.records().is_empty());
+        assert_eq!(wrong_password, Err(KrabError::WrongCredentials));
+    }
+
+    #[test]
+    pub fn test_create_empty_user_success() {
+        let store = MemoryStore::new();
+        let user_data = RecordOperationConfig::new(
+            &generate_random_username(),
+            "password",
+            "",
+            "",
+            &PathBuf::new(),
+        );
+        let res = User::create_in_store(
+            &store,
+            &user_data.username,
+            user_data.master_password.expose_secret(),
+            &KdfParams::default(),
+            CipherKind::default(),
+        );
+        let (mut user, records) = create_user(&store, &user_data).unwrap();
+
+        let add_record = RecordOperationConfig::new(
+            &user_data.username,
+            user_data.master_password.expose_secret(),
+            "example.com",
+            "password",
+            &user_data.path,
+        );
+        let add_res = user.add_record(add_record);
+
+        assert!(res.is_ok());
+        assert!(records.records().is_empty());
+        assert_eq!(add_res.unwrap().records().len(), 1);
+    }
+
+    #[test]
+    pub fn test_create_argon2id_user_success() {
+        let store = MemoryStore::new();
+        let username = generate_random_username();
+        let kdf = KdfParams::default_argon2id();
+
+        let res = User::create_in_store(&store, &username, "password", &kdf, CipherKind::default());
+        let (user, records) = User::from_store(store.clone(), &username, "password").unwrap();
+        let wrong = User::from_store(store.clone(), &username, "wrong_password");
+
+        assert!(res.is_ok());
+        assert_eq!(user.kdf(), kdf);
+        assert!(records.records().is_empty());
+        assert_eq!(wrong, Err(KrabError::WrongCredentials));
+    }
+
+    #[test]
+    pub fn test_create_user_with_cipher_success() {
+        let store = MemoryStore::new();
+        for cipher in [
+            CipherKind::Aes128GcmSiv,
+            CipherKind::Aes256GcmSiv,
+            CipherKind::XChaCha20Poly1305,
+        ] {
+            let user_data = RecordOperationConfig::new(
+                &generate_random_username(),
+                "password",
+                "example.com",
+                "password",
+                &PathBuf::new(),
+            );
+
+            let res = User::create_in_store(
+                &store,
+                &user_data.username,
+                "password",
+                &KdfParams::default(),
+                cipher,
+            );
+            let (mut user, _) = create_user(&store, &user_data).unwrap();
+            let added = user.add_record(user_data.clone());
+            let (user, records) = create_user(&store, &user_data).unwrap();
+
+            assert!(res.is_ok());
+            assert!(added.is_ok());
+            assert_eq!(user.cipher(), cipher);
+            assert_eq!(
+                without_ids(&records),
+                vec![Entry::new("example.com", "password")]
+            );
+        }
+    }
 
-        // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
+    #[test]
+    pub fn test_read_user_wrong_password() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let res = User::from_store(store.clone(), &user_data.username, "wrong_password");
+
+        assert_eq!(res, Err(KrabError::WrongCredentials));
+    }
+
+    #[test]
+    pub fn test_change_master_password_success() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut user, _) = create_user(&store, &user_data).unwrap();
+
+        let res =
+            user.change_master_password(user_data.master_password.expose_secret(), "new_password");
+        let old = User::from_store(
+            store.clone(),
+            &user_data.username,
+            user_data.master_password.expose_secret(),
+        );
+        let new = User::from_store(store.clone(), &user_data.username, "new_password");
+
+        assert!(res.is_ok());
+        assert_eq!(old.unwrap_err(), KrabError::WrongCredentials);
+        assert_eq!(
+            without_ids(&new.unwrap().1),
+            vec![Entry::new("example.com", "password")]
+        );
+    }
+
+    #[test]
+    pub fn test_change_master_password_fail_wrong_password() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut user, _) = create_user(&store, &user_data).unwrap();
+
+        let res = user.change_master_password("wrong_password", "new_password");
+        let records = User::from_store(
+            store.clone(),
+            &user_data.username,
+            user_data.master_password.expose_secret(),
+        );
 
-        assert_eq!(res.is_err(), true);
+        assert_eq!(res, Err(KrabError::WrongCredentials));
+        assert!(records.is_ok());
     }
 
     #[test]
     pub fn test_modify_record_success() {
-        let user_data = setup_user_data("example.com").unwrap();
-        let (mut user, _) = create_user(&user_data).unwrap();
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut user, records) = create_user(&store, &user_data).unwrap();
 
         let new_password = "password2";
-        let modify_record = RecordOperationConfig::new(
+        let mut modify_record = RecordOperationConfig::new(
             &user_data.username,
-            &user_data.master_password,
+            user_data.master_password.expose_secret(),
             &user_data.domain,
             new_password,
             &user_data.path,
         );
+        modify_record.id = record_id(&records, &user_data.domain);
         let res = user.modify_record(modify_record);
 
-        let (user, records) = User::from(
-            &user_data.path,
+        let (_, records) = User::from_store(
+            store.clone(),
             &user_data.username,
-            &user_data.master_password,
+            user_data.master_password.expose_secret(),
         )
         .unwrap();
         let records = records.records();
-        let modified_record = records.iter().find(|r| r.0 == user_data.domain);
+        let modified_record = records.iter().find(|r| r.domain == user_data.domain);
         let modified_record = match modified_record {
             Some(record) => record,
             None => panic!("Record not found"),
         };
-        let password = modified_record.1.clone();
+        let password = modified_record.password.clone();
 
-        // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
-
-        assert_eq!(res.is_ok(), true);
+        assert!(res.is_ok());
         assert_eq!(res.unwrap().records().len(), 1);
-        assert_eq!(password, new_password);
+        assert_eq!(password.expose_secret(), new_password);
         assert_eq!(records.len(), 1);
     }
 
     #[test]
     pub fn test_modify_integrity_fail() {
-        let user_data = setup_user_data("example.com").unwrap();
-        let (mut user, _) = create_user(&user_data).unwrap();
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut user, _) = create_user(&store, &user_data).unwrap();
 
         let new_password = "password2";
         let modify_record = RecordOperationConfig::new(
@@ -1260,30 +2591,167 @@ mod tests {
         );
         let res = user.modify_record(modify_record);
 
-        // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
-
-        assert_eq!(res.is_err(), true);
+        assert!(res.is_err());
     }
 
     #[test]
     pub fn test_modify_record_fail_not_found() {
-        let user_data = setup_user_data("example.com").unwrap();
-        let (mut user, _) = create_user(&user_data).unwrap();
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut user, _) = create_user(&store, &user_data).unwrap();
 
         let new_password = "password2";
         let modify_record = RecordOperationConfig::new(
             &user_data.username,
-            &user_data.master_password,
+            user_data.master_password.expose_secret(),
             "example2.com",
             new_password,
             &user_data.path,
         );
         let res = user.modify_record(modify_record);
 
-        // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_write_keeps_generations() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut user, records) = create_user(&store, &user_data).unwrap();
+        let initial_content = user.1.read(&user.name()).unwrap();
+
+        let mut results = vec![];
+        for i in 0..VAULT_GENERATIONS + 2 {
+            let mut modify_record = RecordOperationConfig::new(
+                &user_data.username,
+                user_data.master_password.expose_secret(),
+                &user_data.domain,
+                &format!("password{}", i),
+                &user_data.path,
+            );
+            modify_record.id = record_id(&records, &user_data.domain);
+            results.push(user.modify_record(modify_record).is_ok());
+        }
+        let generations = user.generations();
+        let previous_content = user.1.read(&generations[0]).unwrap();
+        let oldest_content = user.1.read(&generations[VAULT_GENERATIONS - 1]).unwrap();
+        let leftover_exists = user
+            .1
+            .exists(&generation_name(&user.name(), VAULT_GENERATIONS + 1));
+        let (_, records) = create_user(&store, &user_data).unwrap();
+
+        assert!(results.iter().all(|r| *r));
+        assert_eq!(generations.len(), VAULT_GENERATIONS);
+        assert!(!leftover_exists);
+        assert_ne!(previous_content, oldest_content);
+        assert_ne!(oldest_content, initial_content);
+        assert_eq!(
+            records.records()[0].password.expose_secret(),
+            format!("password{}", VAULT_GENERATIONS + 1)
+        );
+    }
+
+    #[test]
+    fn test_restore_previous_generation() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut user, _) = create_user(&store, &user_data).unwrap();
+
+        let add_record = RecordOperationConfig::new(
+            &user_data.username,
+            user_data.master_password.expose_secret(),
+            "example2.com",
+            "password2",
+            &user_data.path,
+        );
+        let res = user.add_record(add_record);
+        let generations = user.generations();
+        user.1.copy(&generations[0], &user.name()).unwrap();
+        let restored = create_user(&store, &user_data);
+
+        assert!(res.is_ok());
+        assert_eq!(generations.len(), 1);
+        assert!(restored.is_ok());
+        assert_eq!(restored.unwrap().1.records().len(), 1);
+    }
+
+    #[test]
+    fn test_add_record_fail_conflict() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut user, _) = create_user(&store, &user_data).unwrap();
+        let (mut other_user, _) = create_user(&store, &user_data).unwrap();
+
+        let add_record = RecordOperationConfig::new(
+            &user_data.username,
+            user_data.master_password.expose_secret(),
+            "example2.com",
+            "password2",
+            &user_data.path,
+        );
+        let res = other_user.add_record(add_record.clone());
+        let conflict_res = user.add_record(add_record);
+        let (mut reloaded_user, records) = create_user(&store, &user_data).unwrap();
+        let mut modify_record = RecordOperationConfig::new(
+            &user_data.username,
+            user_data.master_password.expose_secret(),
+            "example2.com",
+            "password3",
+            &user_data.path,
+        );
+        modify_record.id = record_id(&records, "example2.com");
+        let reloaded_res = reloaded_user.modify_record(modify_record);
+
+        assert!(res.is_ok());
+        assert_eq!(conflict_res, Err(KrabError::Conflict));
+        assert_eq!(records.records().len(), 2);
+        assert!(reloaded_res.is_ok());
+    }
+
+    #[test]
+    fn test_modify_record_fail_locked() {
+        let (store, user_data) = setup_user_data("example.com").unwrap();
+        let (mut user, records) = create_user(&store, &user_data).unwrap();
+
+        let mut modify_record = RecordOperationConfig::new(
+            &user_data.username,
+            user_data.master_password.expose_secret(),
+            &user_data.domain,
+            "password2",
+            &user_data.path,
+        );
+        modify_record.id = record_id(&records, &user_data.domain);
+        let lock = store
+            .lock(&hash(user_data.username.clone()), Duration::ZERO)
+            .unwrap();
+        let locked_res = user.modify_record(modify_record.clone());
+        drop(lock);
+        let res = user.modify_record(modify_record);
+
+        assert_eq!(locked_res.unwrap_err(), KrabError::Locked);
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_user_memory_store() {
+        let store = MemoryStore::new();
+        let create = |username: &str| {
+            User::create_in_store(
+                &store,
+                username,
+                "password",
+                &KdfParams::default(),
+                CipherKind::default(),
+            )
+        };
 
-        assert_eq!(res.is_err(), true);
+        let created = create("krab");
+        let again = create("krab");
+        let wrong = User::from_store(store.clone(), "krab", "wrong_password");
+        let missing = User::from_store(store.clone(), "other", "password");
+        let (user, records) = User::from_store(store.clone(), "krab", "password").unwrap();
+
+        assert!(created.is_ok());
+        assert_eq!(again, Err(KrabError::UserAlreadyExists));
+        assert_eq!(wrong.unwrap_err(), KrabError::WrongCredentials);
+        assert_eq!(missing.unwrap_err(), KrabError::UserNotFound);
+        assert!(records.records().is_empty());
+        assert_eq!(user.1.list().unwrap(), vec![hash("krab".to_string())]);
     }
 }
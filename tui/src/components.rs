@@ -2,4 +2,5 @@
 pub mod button;
 pub mod input;
 pub mod scrollable_view;
+pub mod sidebar;
 pub mod window_too_small;
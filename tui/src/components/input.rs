@@ -6,6 +6,8 @@ use ratatui::{
     widgets::{Block, Paragraph, Widget},
 };
 
+use krab_backend::secret::SecretString;
+
 use crate::{from, COLOR_ORANGE, COLOR_WHITE};
 
 const INPUT_HEIGHT: u16 = 3;
@@ -14,6 +16,7 @@ const MAX_INPUT_LENGTH: u16 = 256;
 const PADDING: u16 = 2;
 
 /// Represents the configuration of an input
+/// The value is borrowed, so rendering a secret does not copy it
 ///
 /// # Fields
 /// * `focused` - Indicates if the input is focused
@@ -29,9 +32,9 @@ const PADDING: u16 = 2;
 /// * `height` - Returns the height of the input
 /// * `default_width` - Returns the default width of the input
 /// * `width` - Returns the width of the input
-pub struct InputConfig {
+pub struct InputConfig<'a> {
     focused: bool,
-    value: String,
+    value: &'a str,
     hidden: bool,
     title: String,
     cursor_position: Option<u16>,
@@ -44,9 +47,10 @@ pub struct InputConfig {
 /// # Methods
 /// * `render` - Renders the input
 /// * `handle_key` - Handles a key event
+/// * `handle_secret_key` - Handles a key event on an input holding a secret
 pub struct Input {}
 
-impl InputConfig {
+impl<'a> InputConfig<'a> {
     /// Creates a new `InputConfig`
     ///
     /// # Arguments
@@ -61,7 +65,7 @@ impl InputConfig {
     /// A new `InputConfig`
     pub fn new(
         focused: bool,
-        value: String,
+        value: &'a str,
         hidden: bool,
         title: String,
         cursor_position: Option<u16>,
@@ -114,7 +118,7 @@ impl Input {
     pub fn render(buffer: &mut Buffer, rect: Rect, config: &InputConfig) {
         assert!(rect.height >= INPUT_HEIGHT);
         assert!(config.value.len() as u16 <= MAX_INPUT_LENGTH);
-        assert_eq!(config.focused ^ config.cursor_position.is_some(), false);
+        assert!(!(config.focused ^ config.cursor_position.is_some()));
 
         let rect = Rect::new(rect.x, rect.y, rect.width, INPUT_HEIGHT);
 
@@ -125,7 +129,7 @@ impl Input {
             }
             hidden_text
         } else {
-            config.value.clone()
+            config.value.to_string()
         };
 
         let mut text = text.split_off(config.input_offset as usize);
@@ -137,7 +141,7 @@ impl Input {
             let mut first_part = text.clone();
             let mut second_part = first_part.split_off(cursor_position);
 
-            if second_part.len() > 0 {
+            if !second_part.is_empty() {
                 second_part = second_part.split_off(1);
             }
 
@@ -209,6 +213,43 @@ impl Input {
         previous_value: &str,
     ) -> (String, u16, u16) {
         let mut value = previous_value.to_string();
+        let (cursor_position, input_offset) = Input::edit(key, config, &mut value);
+
+        (value, cursor_position, input_offset)
+    }
+
+    /// Handles a key event on an input holding a secret
+    /// The new value is edited within a reserved capacity,
+    /// so typing does not leave copies of the secret behind
+    ///
+    /// # Arguments
+    /// * `key` - The key event to handle
+    /// * `config` - The configuration of the input
+    /// * `previous_value` - The previous value of the input
+    ///
+    /// # Returns
+    /// A tuple containing the new value and the new cursor position
+    pub fn handle_secret_key(
+        key: &KeyEvent,
+        config: &InputConfig,
+        previous_value: &SecretString,
+    ) -> (SecretString, u16, u16) {
+        let mut value = SecretString::with_capacity(previous_value.expose_secret());
+        let (cursor_position, input_offset) = Input::edit(key, config, value.expose_secret_mut());
+
+        (value, cursor_position, input_offset)
+    }
+
+    /// Applies a key event to a value
+    ///
+    /// # Arguments
+    /// * `key` - The key event to handle
+    /// * `config` - The configuration of the input
+    /// * `value` - The value to edit
+    ///
+    /// # Returns
+    /// A tuple containing the new cursor position and the new input offset
+    fn edit(key: &KeyEvent, config: &InputConfig, value: &mut String) -> (u16, u16) {
         let mut cursor_position = config.cursor_position.unwrap_or(value.len() as u16);
         let previous_cursor_position = config.cursor_position.unwrap_or(0);
         let mut input_offset = config.input_offset;
@@ -216,16 +257,14 @@ impl Input {
         match key.code {
             KeyCode::Char(c) => {
                 if value.len() as u16 == MAX_INPUT_LENGTH {
-                    return (value, cursor_position, input_offset);
+                    return (cursor_position, input_offset);
                 }
                 value.insert(cursor_position as usize, c);
                 cursor_position += 1;
             }
-            KeyCode::Backspace => {
-                if cursor_position > 0 {
-                    value.remove(cursor_position as usize - 1);
-                    cursor_position -= 1;
-                }
+            KeyCode::Backspace if cursor_position > 0 => {
+                value.remove(cursor_position as usize - 1);
+                cursor_position -= 1;
             }
             KeyCode::Delete => {
                 if cursor_position < value.len() as u16 {
@@ -236,13 +275,11 @@ impl Input {
                 }
             }
             KeyCode::Left => {
-                if cursor_position > 0 {
-                    cursor_position -= 1;
-                }
+                cursor_position = cursor_position.saturating_sub(1);
             }
             KeyCode::Right => {
                 if cursor_position == MAX_INPUT_LENGTH - 1 {
-                    return (value, cursor_position, input_offset);
+                    return (cursor_position, input_offset);
                 }
                 if cursor_position < value.len() as u16 {
                     cursor_position += 1;
@@ -256,14 +293,14 @@ impl Input {
             && cursor_position == input_offset + config.width.unwrap_or(DEFAULT_INPUT_WIDTH)
         {
             input_offset = cursor_position - config.width.unwrap_or(DEFAULT_INPUT_WIDTH) + 1;
-        } else if previous_cursor_position == cursor_position + 1 && cursor_position <= input_offset
+        } else if previous_cursor_position == cursor_position + 1
+            && cursor_position <= input_offset
+            && input_offset != 0
         {
-            if input_offset != 0 {
-                input_offset = input_offset - 1;
-            }
+            input_offset -= 1;
         }
 
-        (value, cursor_position, input_offset)
+        (cursor_position, input_offset)
     }
 }
 
@@ -275,15 +312,7 @@ mod tests {
 
     #[test]
     fn test_input_insert() {
-        let config = InputConfig::new(
-            true,
-            "test".to_string(),
-            false,
-            "Test".to_string(),
-            Some(4),
-            0,
-            None,
-        );
+        let config = InputConfig::new(true, "test", false, "Test".to_string(), Some(4), 0, None);
         let key = KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE);
         let (value, cursor_position, input_offset) = Input::handle_key(&key, &config, "test");
         assert_eq!(value, "testa".to_string());
@@ -297,15 +326,7 @@ mod tests {
         for _ in 0..MAX_INPUT_LENGTH {
             value.push('a');
         }
-        let config = InputConfig::new(
-            true,
-            value.clone(),
-            false,
-            "Test".to_string(),
-            Some(3),
-            0,
-            None,
-        );
+        let config = InputConfig::new(true, &value, false, "Test".to_string(), Some(3), 0, None);
         let key = KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE);
         let (value, cursor_position, input_offset) =
             Input::handle_key(&key, &config, value.as_str());
@@ -316,15 +337,7 @@ mod tests {
 
     #[test]
     fn test_input_backspace() {
-        let config = InputConfig::new(
-            true,
-            "test".to_string(),
-            false,
-            "Test".to_string(),
-            Some(4),
-            0,
-            None,
-        );
+        let config = InputConfig::new(true, "test", false, "Test".to_string(), Some(4), 0, None);
         let key = KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE);
         let (value, cursor_position, input_offset) = Input::handle_key(&key, &config, "test");
         assert_eq!(value, "tes".to_string());
@@ -334,15 +347,7 @@ mod tests {
 
     #[test]
     fn test_input_delete() {
-        let config = InputConfig::new(
-            true,
-            "test".to_string(),
-            false,
-            "Test".to_string(),
-            Some(1),
-            0,
-            None,
-        );
+        let config = InputConfig::new(true, "test", false, "Test".to_string(), Some(1), 0, None);
         let key = KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE);
         let (value, cursor_position, input_offset) = Input::handle_key(&key, &config, "test");
         assert_eq!(value, "tst".to_string());
@@ -352,15 +357,7 @@ mod tests {
 
     #[test]
     fn test_input_left() {
-        let config = InputConfig::new(
-            true,
-            "test".to_string(),
-            false,
-            "Test".to_string(),
-            Some(2),
-            0,
-            None,
-        );
+        let config = InputConfig::new(true, "test", false, "Test".to_string(), Some(2), 0, None);
         let key = KeyEvent::new(KeyCode::Left, KeyModifiers::NONE);
         let (value, cursor_position, input_offset) = Input::handle_key(&key, &config, "test");
         assert_eq!(value, "test".to_string());
@@ -370,15 +367,7 @@ mod tests {
 
     #[test]
     fn test_input_right() {
-        let config = InputConfig::new(
-            true,
-            "test".to_string(),
-            false,
-            "Test".to_string(),
-            Some(2),
-            0,
-            None,
-        );
+        let config = InputConfig::new(true, "test", false, "Test".to_string(), Some(2), 0, None);
         let key = KeyEvent::new(KeyCode::Right, KeyModifiers::NONE);
         let (value, cursor_position, input_offset) = Input::handle_key(&key, &config, "test");
         assert_eq!(value, "test".to_string());
@@ -394,7 +383,7 @@ mod tests {
         }
         let config = InputConfig::new(
             true,
-            value.clone(),
+            &value,
             false,
             "Test".to_string(),
             Some(255),
@@ -413,7 +402,7 @@ mod tests {
     fn test_input_first_offset() {
         let config = InputConfig::new(
             true,
-            "0123456789012345678901234567890".to_string(),
+            "0123456789012345678901234567890",
             false,
             "Test".to_string(),
             Some(31),
@@ -432,7 +421,7 @@ mod tests {
     fn test_input_first_offset_left() {
         let config = InputConfig::new(
             true,
-            "0123456789012345678901234567890123456789012345678901234567890".to_string(),
+            "0123456789012345678901234567890123456789012345678901234567890",
             false,
             "Test".to_string(),
             Some(29),
@@ -452,4 +441,24 @@ mod tests {
         assert_eq!(cursor_position, 28);
         assert_eq!(input_offset, 27);
     }
+
+    #[test]
+    fn test_input_secret_insert() {
+        let previous_value = SecretString::from("test");
+        let config = InputConfig::new(
+            true,
+            previous_value.expose_secret(),
+            true,
+            "Test".to_string(),
+            Some(4),
+            0,
+            None,
+        );
+        let key = KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE);
+        let (value, cursor_position, input_offset) =
+            Input::handle_secret_key(&key, &config, &previous_value);
+        assert_eq!(value.expose_secret(), "testa");
+        assert_eq!(cursor_position, 5);
+        assert_eq!(input_offset, 0);
+    }
 }
@@ -148,10 +148,10 @@ impl ScrollView {
         if scrollbar_x_size < 1.0 {
             let scrollbar_x_position_start = (position.offset_x() as f32
                 / buffer_to_render_width as f32)
-                * (area.width as f32 - 2.0) as f32
+                * (area.width as f32 - 2.0)
                 + area.x as f32;
             let scrollbar_x_position_end =
-                scrollbar_x_position_start + scrollbar_x_size * (area.width as f32 - 2.0) as f32;
+                scrollbar_x_position_start + scrollbar_x_size * (area.width as f32 - 2.0);
 
             for i in scrollbar_x_position_start as u16..scrollbar_x_position_end as u16 {
                 buffer[(i, scrollbar_y_end - 1)] = Cell::new("▒")
@@ -163,10 +163,10 @@ impl ScrollView {
         if scrollbar_y_size < 1.0 {
             let scrollbar_y_position_start = (position.offset_y() as f32
                 / buffer_to_render_height as f32)
-                * (area.height as f32 - 1.0) as f32
+                * (area.height as f32 - 1.0)
                 + area.y as f32;
             let scrollbar_y_position_end =
-                scrollbar_y_position_start + scrollbar_y_size * (area.height as f32 - 1.0) as f32;
+                scrollbar_y_position_start + scrollbar_y_size * (area.height as f32 - 1.0);
 
             for i in scrollbar_y_position_start as u16..scrollbar_y_position_end as u16 {
                 buffer[(scrollbar_x_end - 2, i)] = Cell::new("▒▒")
@@ -201,8 +201,8 @@ impl ScrollView {
         area: Rect,
         buffer_to_render: &Buffer,
     ) {
-        for i in 0 + area.x..area.width + area.x {
-            for j in 0 + area.y..area.height + area.y {
+        for i in area.x..area.width + area.x {
+            for j in area.y..area.height + area.y {
                 let cell = buffer_to_render.cell(RatatuiPosition {
                     x: i - area.x + position.offset_x(),
                     y: j - area.y + position.offset_y(),
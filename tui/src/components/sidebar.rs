@@ -0,0 +1,109 @@
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Widget},
+};
+
+use crate::{from, COLOR_BLACK, COLOR_ORANGE, COLOR_WHITE};
+
+/// Represents the configuration of a sidebar
+///
+/// # Fields
+/// * `title` - The title
+/// * `items` - The labels of the items
+/// * `selected` - The selected item
+/// * `active` - The item currently applied, if any
+/// * `focused` - Whether the sidebar is focused
+///
+/// # Methods
+/// * `new` - Creates a new `SidebarConfig`
+pub struct SidebarConfig<'a> {
+    title: &'a str,
+    items: &'a [String],
+    selected: usize,
+    active: Option<usize>,
+    focused: bool,
+}
+
+/// Represents a sidebar, a bordered list of items next to a view
+///
+/// # Methods
+/// * `render` - Renders the sidebar
+pub struct Sidebar {}
+
+impl<'a> SidebarConfig<'a> {
+    /// Creates a new `SidebarConfig`
+    ///
+    /// # Arguments
+    /// * `title` - The title
+    /// * `items` - The labels of the items
+    /// * `selected` - The selected item
+    /// * `active` - The item currently applied, if any
+    /// * `focused` - Whether the sidebar is focused
+    ///
+    /// # Returns
+    /// A new `SidebarConfig`
+    pub fn new(
+        title: &'a str,
+        items: &'a [String],
+        selected: usize,
+        active: Option<usize>,
+        focused: bool,
+    ) -> Self {
+        Self {
+            title,
+            items,
+            selected,
+            active,
+            focused,
+        }
+    }
+}
+
+impl Sidebar {
+    /// Renders the sidebar
+    /// The items are scrolled so the selected one is always visible
+    ///
+    /// # Arguments
+    /// * `buffer` - The mutable buffer to render to
+    /// * `rect` - The rectangle to render the sidebar in
+    /// * `config` - The configuration of the sidebar
+    pub fn render(buffer: &mut Buffer, rect: Rect, config: &SidebarConfig) {
+        let block = Block::bordered()
+            .title(format!(" {} ", config.title))
+            .border_style(Style::default().fg(from(COLOR_ORANGE).unwrap_or(Color::Yellow)));
+        let inner = block.inner(rect);
+        block.render(rect, buffer);
+
+        let rows = inner.height as usize;
+        let first = config.selected.saturating_sub(rows.saturating_sub(1));
+        for (row, (index, item)) in config
+            .items
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(rows)
+            .enumerate()
+        {
+            let style = if config.focused && index == config.selected {
+                Style::default()
+                    .bg(from(COLOR_WHITE).unwrap_or(Color::White))
+                    .fg(from(COLOR_BLACK).unwrap_or(Color::Black))
+            } else if config.active == Some(index) {
+                Style::default().fg(from(COLOR_ORANGE).unwrap_or(Color::Yellow))
+            } else {
+                Style::default().fg(from(COLOR_WHITE).unwrap_or(Color::White))
+            };
+            let marker = if config.active == Some(index) {
+                "•"
+            } else {
+                " "
+            };
+            Line::styled(format!("{}{}", marker, item), style).render(
+                Rect::new(inner.x, inner.y + row as u16, inner.width, 1),
+                buffer,
+            );
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::{cell::RefCell, error::Error, io, path::PathBuf};
+use std::{cell::RefCell, error::Error, io, path::PathBuf, time::Duration};
 
 use components::window_too_small::{WindowTooSmall, WindowTooSmallConfig};
 use ratatui::{
@@ -27,6 +27,8 @@ const _COLOR_CYAN: &str = "#1F9295";
 const COLOR_WHITE: &str = "#F0ECC9";
 const COLOR_ORANGE: &str = "#E3AD43";
 const COLOR_RED: &str = "#D44C1A";
+/// How long to wait for a key before redrawing the screen
+const TICK_RATE: Duration = Duration::from_millis(250);
 
 /// Represents the application state
 ///
@@ -227,6 +229,11 @@ fn run_app<B: Backend>(
         let _ = terminal.draw(|f| ui(f, &app));
         drop(app);
 
+        // the screen is redrawn on every tick while no key is pressed,
+        // so time dependent content like one-time codes stays current
+        if !event::poll(TICK_RATE)? {
+            continue;
+        }
         if let Event::Key(key) = event::read()? {
             if key.kind == event::KeyEventKind::Release || out_of_bounds.is_some() {
                 continue;
@@ -245,27 +252,59 @@ fn run_app<B: Backend>(
                 if let Some(last_state) = last_state {
                     let mut new_app: Application = app.clone();
                     match last_state.popup_type() {
-                        PopupType::InsertDomainPassword => match &mut app.state {
-                            ViewState::Register(s) => {
-                                new_app = s.handle_insert_record_popup(new_app, last_state);
-                            }
-                            ViewState::Home(s) => {
-                                new_app = s.handle_insert_record_popup(new_app, last_state);
+                        PopupType::EntryDetail => {
+                            if let ViewState::Home(s) = &mut app.state {
+                                new_app = s.handle_entry_popup(new_app, last_state);
                             }
-                            _ => {}
-                        },
+                        }
                         PopupType::InsertMaster => match &mut app.state {
                             ViewState::Home(s) => {
                                 new_app = s.handle_insert_master_popup(new_app, last_state);
                             }
+                            ViewState::Settings(s) => {
+                                new_app = s.handle_insert_master_popup(new_app, last_state);
+                            }
                             _ => {}
                         },
-                        PopupType::InsertPassword => match &mut app.state {
+                        PopupType::Confirm => {
+                            if let ViewState::Home(s) = &mut app.state {
+                                new_app = s.handle_confirm_popup(new_app, last_state);
+                            }
+                        }
+                        PopupType::ChangeMaster => {
+                            if let ViewState::Home(s) = &mut app.state {
+                                new_app = s.handle_change_master_popup(new_app, last_state);
+                            }
+                        }
+                        PopupType::History => {
+                            if let ViewState::Home(s) = &mut app.state {
+                                new_app = s.handle_history_popup(new_app, last_state);
+                            }
+                        }
+                        PopupType::Attachments => {
+                            if let ViewState::Home(s) = &mut app.state {
+                                new_app = s.handle_attachments_popup(new_app, last_state);
+                            }
+                        }
+                        PopupType::Backup => match &mut app.state {
                             ViewState::Home(s) => {
-                                new_app = s.handle_insert_password_popup(new_app, last_state);
+                                new_app = s.handle_backup_popup(new_app, last_state);
+                            }
+                            ViewState::Register(s) => {
+                                new_app = s.handle_backup_popup(new_app, last_state);
                             }
                             _ => {}
                         },
+                        PopupType::Import => {
+                            if let ViewState::Home(s) = &mut app.state {
+                                new_app = s.handle_import_popup(new_app, last_state);
+                            }
+                        }
+                        PopupType::Export => {
+                            if let ViewState::Home(s) = &mut app.state {
+                                new_app = s.handle_export_popup(new_app, last_state);
+                            }
+                        }
                         _ => {}
                     }
 
@@ -274,13 +313,12 @@ fn run_app<B: Backend>(
                 }
             } else {
                 let mut app = application.borrow_mut();
-                let changed_app: Application;
-                match &mut app.state {
-                    ViewState::Login(s) => changed_app = s.handle_key(&key, &app_copy),
-                    ViewState::StartUp(s) => changed_app = s.handle_key(&key, &app_copy),
-                    ViewState::Home(s) => changed_app = s.handle_key(&key, &app_copy),
-                    ViewState::Register(s) => changed_app = s.handle_key(&key, &app_copy),
-                    ViewState::Settings(s) => changed_app = s.handle_key(&key, &app_copy),
+                let changed_app = match &mut app.state {
+                    ViewState::Login(s) => s.handle_key(&key, &app_copy),
+                    ViewState::StartUp(s) => s.handle_key(&key, &app_copy),
+                    ViewState::Home(s) => s.handle_key(&key, &app_copy),
+                    ViewState::Register(s) => s.handle_key(&key, &app_copy),
+                    ViewState::Settings(s) => s.handle_key(&key, &app_copy),
                 };
 
                 app.mutable_app_state = changed_app.mutable_app_state;
@@ -5,26 +5,44 @@ use ratatui::{crossterm::event::KeyEvent, layout::Rect, Frame};
 
 use crate::Application;
 
+pub mod attachments;
+pub mod backup;
+pub mod change_master;
+pub mod confirm;
+pub mod entry_detail;
 pub mod exit;
-pub mod insert_domain_password;
+pub mod export;
+pub mod history;
+pub mod import;
 pub mod insert_master;
 pub mod message;
-pub mod insert_password;
 
 /// Represents the type of a popup
 ///
 /// # Variants
 /// * `Exit` - The exit popup
-/// * `InsertDomainPassword` - The insert domain password popup
+/// * `EntryDetail` - The entry detail popup
 /// * `Message` - The message popup
 /// * `InsertMaster` - The insert master password popup
-/// * `InsertPassword` - The insert password popup
+/// * `Confirm` - The confirm popup
+/// * `ChangeMaster` - The change master password popup
+/// * `History` - The password history popup
+/// * `Attachments` - The attachments popup
+/// * `Backup` - The popup exporting or importing a backup
+/// * `Import` - The popup previewing the entries read from another password manager
+/// * `Export` - The popup choosing the file, the format and the fields of a clear text export
 pub enum PopupType {
     Exit,
-    InsertDomainPassword,
+    EntryDetail,
     Message,
     InsertMaster,
-    InsertPassword,
+    Confirm,
+    ChangeMaster,
+    History,
+    Attachments,
+    Backup,
+    Import,
+    Export,
 }
 
 /// Represents a popup
@@ -83,12 +101,38 @@ impl_downcast!(Popup);
 
 pub fn min_area() -> (u16, u16) {
     let (im_width, im_height) = insert_master::InsertMaster::min_area();
-    let (idm_width, idm_height) = insert_domain_password::InsertDomainPassword::min_area();
+    let (ed_width, ed_height) = entry_detail::EntryDetail::min_area();
     let (e_width, e_height) = exit::Exit::min_area();
     let (m_width, m_height) = message::MessagePopup::min_area();
-    let (ip_width, ip_height) = insert_password::InsertPassword::min_area();
+    let (c_width, c_height) = confirm::Confirm::min_area();
+    let (cm_width, cm_height) = change_master::ChangeMaster::min_area();
+    let (h_width, h_height) = history::History::min_area();
+    let (a_width, a_height) = attachments::Attachments::min_area();
+    let (b_width, b_height) = backup::BackupPopup::min_area();
+    let (ip_width, ip_height) = import::ImportPreview::min_area();
+    let (ex_width, ex_height) = export::ExportPopup::min_area();
     (
-        im_width.max(idm_width).max(e_width).max(m_width).max(ip_width),
-        im_height.max(idm_height).max(e_height).max(m_height).max(ip_height),
+        im_width
+            .max(ed_width)
+            .max(e_width)
+            .max(m_width)
+            .max(c_width)
+            .max(cm_width)
+            .max(h_width)
+            .max(a_width)
+            .max(b_width)
+            .max(ip_width)
+            .max(ex_width),
+        im_height
+            .max(ed_height)
+            .max(e_height)
+            .max(m_height)
+            .max(c_height)
+            .max(cm_height)
+            .max(h_height)
+            .max(a_height)
+            .max(b_height)
+            .max(ip_height)
+            .max(ex_height),
     )
-}
\ No newline at end of file
+}
@@ -0,0 +1,348 @@
+use std::path::PathBuf;
+
+use directories::UserDirs;
+use krab_backend::user::{Attachment, Entry};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    prelude::Rect,
+    style::{Color, Style},
+    text::{Line, Text},
+    widgets::{Block, Clear, Padding, Paragraph},
+    Frame,
+};
+
+use crate::{
+    centered_absolute_rect,
+    components::input::{Input, InputConfig},
+    from,
+    popups::{Popup, PopupType},
+    Application, COLOR_BLACK, COLOR_ORANGE, COLOR_WHITE,
+};
+
+const ATTACHMENTS_WIDTH: u16 = 64;
+/// Borders, padding and the hint around the list of attachments
+const ATTACHMENTS_PADDING: u16 = 6;
+/// The width of the text of the path input
+const PATH_WIDTH: u16 = ATTACHMENTS_WIDTH - 12;
+const MAX_PATH_LENGTH: usize = 256;
+const HINT_TEXT: &str = "j/k select | a add | x extract | d remove | Esc close";
+const PATH_HINT_TEXT: &str = "Enter - confirm | Esc - back";
+
+/// Represents the state of the attachments popup
+///
+/// # Variants
+/// * `List` - The attachments are listed
+/// * `AddPath` - The path of a file to attach is entered
+/// * `ExtractPath` - The path to extract the selected attachment to is entered
+#[derive(Clone, PartialEq, Debug)]
+enum AttachmentsState {
+    List,
+    AddPath,
+    ExtractPath,
+}
+
+/// Represents the exit state of the attachments popup
+///
+/// # Variants
+/// * `Add` - The user chose a file to attach
+/// * `Extract` - The user chose where to extract the attachment at the index
+/// * `Remove` - The user chose to remove the attachment at the index
+/// * `Quit` - The user closed the popup
+#[derive(Clone, PartialEq, Debug)]
+pub enum AttachmentsExitState {
+    Add(PathBuf),
+    Extract(usize, PathBuf),
+    Remove(usize),
+    Quit,
+}
+
+/// Represents the popup listing the attachments of an entry
+///
+/// # Fields
+/// * `label` - The label of the entry
+/// * `attachments` - The attachments
+/// * `selected` - The selected attachment
+/// * `state` - The state
+/// * `path` - The value of the path input
+/// * `cursor` - The cursor position of the path input
+/// * `input_offset` - The input offset of the path input
+/// * `exit_state` - The exit state
+///
+/// # Methods
+/// * `new` - Creates a new `Attachments`
+/// * `exit_state` - Returns the exit state
+/// * `min_area` - Returns the minimum area of the popup
+/// * `height` - Returns the height of the popup
+/// * `edit_path` - Switches to the path input
+/// * `generate_input_config` - Generates the configuration of the path input
+///
+/// # Implements
+/// * `Popup` - The popup trait
+#[derive(Clone)]
+pub struct Attachments {
+    label: String,
+    attachments: Vec<Attachment>,
+    selected: usize,
+    state: AttachmentsState,
+    path: String,
+    cursor: u16,
+    input_offset: u16,
+    exit_state: Option<AttachmentsExitState>,
+}
+
+impl Attachments {
+    /// Creates a new attachments popup
+    ///
+    /// # Arguments
+    /// * `entry` - The entry
+    ///
+    /// # Returns
+    /// A new `Attachments`
+    pub fn new(entry: &Entry) -> Self {
+        Attachments {
+            label: entry.label(),
+            attachments: entry.attachments.clone(),
+            selected: 0,
+            state: AttachmentsState::List,
+            path: String::new(),
+            cursor: 0,
+            input_offset: 0,
+            exit_state: None,
+        }
+    }
+
+    /// Returns the exit state of the popup
+    ///
+    /// # Returns
+    /// The exit state of the popup
+    pub fn exit_state(&self) -> Option<AttachmentsExitState> {
+        self.exit_state.clone()
+    }
+
+    /// Returns the minimum area of the popup
+    ///
+    /// # Returns
+    /// The minimum area of the popup
+    pub fn min_area() -> (u16, u16) {
+        (
+            ATTACHMENTS_WIDTH,
+            ATTACHMENTS_PADDING + 1 + InputConfig::height(),
+        )
+    }
+
+    /// Returns the height of the popup
+    ///
+    /// # Returns
+    /// The height of the popup
+    fn height(&self) -> u16 {
+        let input = if self.state == AttachmentsState::List {
+            0
+        } else {
+            InputConfig::height()
+        };
+        ATTACHMENTS_PADDING + self.attachments.len().max(1) as u16 + input
+    }
+
+    /// Switches to the path input
+    /// A path too long for the input is not prefilled
+    ///
+    /// # Arguments
+    /// * `state` - The state of the path input
+    /// * `path` - The initial path
+    fn edit_path(&mut self, state: AttachmentsState, path: String) {
+        self.path = if path.len() <= MAX_PATH_LENGTH {
+            path
+        } else {
+            String::new()
+        };
+        self.cursor = self.path.len() as u16;
+        self.input_offset = (self.path.len() as u16 + 1).saturating_sub(PATH_WIDTH);
+        self.state = state;
+    }
+
+    /// Generates the configuration of the path input
+    ///
+    /// # Returns
+    /// The configuration of the path input
+    fn generate_input_config(&self) -> InputConfig<'_> {
+        let title = if self.state == AttachmentsState::AddPath {
+            "File to attach"
+        } else {
+            "Extract to"
+        };
+        InputConfig::new(
+            true,
+            &self.path,
+            false,
+            title.to_string(),
+            Some(self.cursor),
+            self.input_offset,
+            Some(PATH_WIDTH),
+        )
+    }
+}
+
+/// Formats a size in bytes for people
+///
+/// # Arguments
+/// * `size` - The size in bytes
+///
+/// # Returns
+/// The formatted size
+pub fn format_size(size: u64) -> String {
+    match size {
+        0..=1023 => format!("{} B", size),
+        1024..=1048575 => format!("{:.1} KiB", size as f64 / 1024.0),
+        _ => format!("{:.1} MiB", size as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+/// Returns the path an attachment is extracted to by default
+///
+/// # Arguments
+/// * `name` - The file name of the attachment
+///
+/// # Returns
+/// The path in the downloads folder, or the file name if there is none
+fn default_extract_path(name: &str) -> PathBuf {
+    // only the last component is used, a name must not point outside the folder
+    let name = PathBuf::from(name)
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_else(|| "attachment".into());
+    UserDirs::new()
+        .and_then(|dirs| dirs.download_dir().map(|dir| dir.join(&name)))
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
+impl Popup for Attachments {
+    fn render(&self, f: &mut Frame, _app: &Application, rect: Rect) {
+        let white = Style::default().fg(from(COLOR_WHITE).unwrap_or(Color::White));
+        let selected = Style::default()
+            .bg(from(COLOR_WHITE).unwrap_or(Color::White))
+            .fg(from(COLOR_BLACK).unwrap_or(Color::Black));
+
+        let mut lines = vec![];
+        if self.attachments.is_empty() {
+            lines.push(Line::styled("No attachments", white));
+        }
+        let name_width = ATTACHMENTS_WIDTH as usize - 20;
+        for (index, attachment) in self.attachments.iter().enumerate() {
+            let style = if index == self.selected {
+                selected
+            } else {
+                white
+            };
+            let name: String = attachment.name.chars().take(name_width).collect();
+            lines.push(Line::styled(
+                format!(
+                    "{} {:<name_width$} {:>10}",
+                    if index == self.selected { ">" } else { " " },
+                    name,
+                    format_size(attachment.size),
+                ),
+                style,
+            ));
+        }
+        lines.push(Line::default());
+        let hint = if self.state == AttachmentsState::List {
+            HINT_TEXT
+        } else {
+            PATH_HINT_TEXT
+        };
+        lines.push(Line::styled(hint, white).centered());
+
+        let block = Block::bordered()
+            .title(format!(" Attachments of {} ", self.label))
+            .padding(Padding::new(2, 2, 1, 0))
+            .border_style(Style::default().fg(from(COLOR_ORANGE).unwrap_or(Color::Yellow)));
+        let inner = block.inner(rect);
+        let paragraph = Paragraph::new(Text::from(lines)).block(block);
+
+        f.render_widget(Clear, rect);
+        f.render_widget(paragraph, rect);
+        if self.state != AttachmentsState::List {
+            let input_rect = Rect::new(
+                inner.x,
+                inner.y + inner.height - InputConfig::height(),
+                inner.width,
+                InputConfig::height(),
+            );
+            Input::render(f.buffer_mut(), input_rect, &self.generate_input_config());
+        }
+    }
+
+    fn handle_key(
+        &mut self,
+        key: &KeyEvent,
+        app: &Application,
+    ) -> (Application, Option<Box<dyn Popup>>) {
+        let mut app = app.clone();
+
+        match self.state {
+            AttachmentsState::List => match key.code {
+                KeyCode::Down | KeyCode::Char('j')
+                    if self.selected + 1 < self.attachments.len() =>
+                {
+                    self.selected += 1;
+                }
+                KeyCode::Up | KeyCode::Char('k') if self.selected > 0 => {
+                    self.selected -= 1;
+                }
+                KeyCode::Char('a') => {
+                    self.edit_path(AttachmentsState::AddPath, String::new());
+                }
+                KeyCode::Char('x') | KeyCode::Enter if !self.attachments.is_empty() => {
+                    let path = default_extract_path(&self.attachments[self.selected].name);
+                    self.edit_path(
+                        AttachmentsState::ExtractPath,
+                        path.to_string_lossy().to_string(),
+                    );
+                }
+                KeyCode::Char('d') if !self.attachments.is_empty() => {
+                    self.exit_state = Some(AttachmentsExitState::Remove(self.selected));
+                }
+                KeyCode::Esc | KeyCode::Char('q') => {
+                    self.exit_state = Some(AttachmentsExitState::Quit);
+                }
+                _ => {}
+            },
+            AttachmentsState::AddPath | AttachmentsState::ExtractPath => match key.code {
+                KeyCode::Esc => {
+                    self.state = AttachmentsState::List;
+                }
+                KeyCode::Enter if !self.path.trim().is_empty() => {
+                    let path = PathBuf::from(self.path.trim());
+                    self.exit_state = Some(if self.state == AttachmentsState::AddPath {
+                        AttachmentsExitState::Add(path)
+                    } else {
+                        AttachmentsExitState::Extract(self.selected, path)
+                    });
+                }
+                _ => {
+                    let config = self.generate_input_config();
+                    let (value, cursor, input_offset) = Input::handle_key(key, &config, &self.path);
+                    self.path = value;
+                    self.cursor = cursor;
+                    self.input_offset = input_offset;
+                }
+            },
+        }
+
+        app.mutable_app_state.popups.pop();
+        if self.exit_state.is_none() {
+            app.mutable_app_state.popups.push(Box::new(self.clone()));
+            return (app, None);
+        }
+
+        (app, Some(Box::new(self.clone())))
+    }
+
+    fn wrapper(&self, rect: Rect) -> Rect {
+        centered_absolute_rect(rect, ATTACHMENTS_WIDTH, self.height())
+    }
+
+    fn popup_type(&self) -> PopupType {
+        PopupType::Attachments
+    }
+}
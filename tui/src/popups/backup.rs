@@ -0,0 +1,579 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use directories::UserDirs;
+use krab_backend::{
+    backup::{Backup, BACKUP_EXTENSION},
+    pass::PasswordStore,
+    password_strength,
+    secret::SecretString,
+};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    prelude::{Constraint, Direction, Layout, Rect},
+    widgets::Clear,
+    Frame,
+};
+
+use crate::{
+    centered_absolute_rect,
+    components::{
+        button::{Button, ButtonConfig},
+        input::{Input, InputConfig},
+    },
+    popups::{message::MessagePopup, Popup, PopupType},
+    Application,
+};
+
+/// The width of the text of the inputs
+const BACKUP_INPUT_WIDTH: u16 = 56;
+const MAX_PATH_LENGTH: usize = 256;
+
+/// Represents what the backup popup is used for
+///
+/// # Variants
+/// * `Export` - A backup of the vault is written
+/// * `Import` - A backup is read
+/// * `Foreign` - The entries of a file of another password manager, or of a password store of pass, are read
+/// * `Pass` - The entries are exported to a password store of pass
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackupMode {
+    Export,
+    Import,
+    Foreign,
+    Pass,
+}
+
+/// Represents the backup popup input fields
+///
+/// # Variants
+/// * `Path` - The path of the backup file
+/// * `Passphrase` - The export passphrase
+/// * `Repeat` - The repeated export passphrase
+#[derive(Debug, Clone, PartialEq, Hash, Eq)]
+enum BackupInput {
+    Path,
+    Passphrase,
+    Repeat,
+}
+
+/// Represents the state of the backup popup
+///
+/// # Variants
+/// * `Path` - The path state
+/// * `Passphrase` - The export passphrase state
+/// * `Repeat` - The repeated export passphrase state, only when exporting
+/// * `Confirm` - The confirm state
+/// * `Quit` - The quit state
+#[derive(Clone, PartialEq)]
+enum BackupState {
+    Path,
+    Passphrase,
+    Repeat,
+    Confirm,
+    Quit,
+}
+
+/// Represents the exit state of the backup popup
+///
+/// # Variants
+/// * `Confirm` - The confirm state
+/// * `Quit` - The quit state
+#[derive(Clone, PartialEq, Debug)]
+pub enum BackupExitState {
+    Confirm,
+    Quit,
+}
+
+/// Represents the popup asking for the file and the export passphrase of a backup,
+/// for the file of another password manager and its password,
+/// or for the password store the secrets are exported to
+///
+/// # Fields
+/// * `mode` - Whether a backup is exported or imported
+/// * `path` - The path of the backup file
+/// * `passphrase` - The export passphrase, or the password of the file
+/// * `confirm_passphrase` - The repeated export passphrase
+/// * `state` - The state
+/// * `exit_state` - The exit state
+/// * `cursors` - The cursors
+/// * `input_offsets` - The input offsets
+/// * `hidden_passphrase` - Decides if the passphrases are hidden
+///
+/// # Methods
+/// * `new` - Creates a new `BackupPopup`
+/// * `exit_state` - Returns the exit state of the popup
+/// * `mode` - Returns whether a backup is exported or imported
+/// * `path` - Returns the path of the backup file
+/// * `passphrase` - Returns the export passphrase, or the password of the file
+/// * `confirm_passphrase` - Returns the repeated export passphrase
+/// * `min_area` - Returns the minimum area of the popup
+/// * `inputs` - Returns the inputs of the mode
+/// * `generate_input_config` - Generates the input config for the popup
+///
+/// # Implements
+/// * `Popup` - The popup trait
+#[derive(Clone)]
+pub struct BackupPopup {
+    mode: BackupMode,
+    path: String,
+    passphrase: SecretString,
+    confirm_passphrase: SecretString,
+    state: BackupState,
+    exit_state: Option<BackupExitState>,
+    cursors: HashMap<BackupInput, u16>,
+    input_offsets: HashMap<BackupInput, u16>,
+    hidden_passphrase: bool,
+}
+
+impl BackupPopup {
+    /// Creates a new backup popup
+    /// A backup is exported to the downloads folder and secrets to the password store of the user by default
+    ///
+    /// # Arguments
+    /// * `mode` - Whether a backup is exported or imported
+    ///
+    /// # Returns
+    /// A new `BackupPopup`
+    pub fn new(mode: BackupMode) -> Self {
+        let path = match mode {
+            BackupMode::Export => default_export_path().to_string_lossy().to_string(),
+            BackupMode::Pass => PasswordStore::default_root()
+                .map(|root| root.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            BackupMode::Import | BackupMode::Foreign => String::new(),
+        };
+        let path = if path.len() <= MAX_PATH_LENGTH {
+            path
+        } else {
+            String::new()
+        };
+
+        let mut cursors = HashMap::new();
+        let mut input_offsets = HashMap::new();
+        for input in [
+            BackupInput::Path,
+            BackupInput::Passphrase,
+            BackupInput::Repeat,
+        ] {
+            cursors.insert(input.clone(), 0);
+            input_offsets.insert(input, 0);
+        }
+        cursors.insert(BackupInput::Path, path.len() as u16);
+        input_offsets.insert(
+            BackupInput::Path,
+            (path.len() as u16 + 1).saturating_sub(BACKUP_INPUT_WIDTH),
+        );
+
+        BackupPopup {
+            mode,
+            path,
+            passphrase: SecretString::default(),
+            confirm_passphrase: SecretString::default(),
+            state: BackupState::Path,
+            exit_state: None,
+            cursors,
+            input_offsets,
+            hidden_passphrase: true,
+        }
+    }
+
+    /// Returns the exit state of the popup
+    ///
+    /// # Returns
+    /// An `Option<BackupExitState>` representing the exit state of the popup
+    pub fn exit_state(&self) -> Option<BackupExitState> {
+        self.exit_state.clone()
+    }
+
+    /// Returns whether a backup is exported or imported
+    ///
+    /// # Returns
+    /// The mode of the popup
+    pub fn mode(&self) -> BackupMode {
+        self.mode
+    }
+
+    /// Returns the path of the backup file
+    ///
+    /// # Returns
+    /// The path, empty if none was entered
+    pub fn path(&self) -> PathBuf {
+        PathBuf::from(self.path.trim())
+    }
+
+    /// Returns the export passphrase, or the password of the file
+    ///
+    /// # Returns
+    /// A `SecretString` representing the export passphrase
+    pub fn passphrase(&self) -> &SecretString {
+        &self.passphrase
+    }
+
+    /// Returns the repeated export passphrase
+    ///
+    /// # Returns
+    /// A `SecretString` representing the repeated export passphrase
+    pub fn confirm_passphrase(&self) -> &SecretString {
+        &self.confirm_passphrase
+    }
+
+    /// Returns the minimum area of the popup
+    ///
+    /// # Returns
+    /// A tuple representing the minimum area of the popup
+    pub fn min_area() -> (u16, u16) {
+        let height = 3 * InputConfig::height() + ButtonConfig::height();
+        let width = InputConfig::new(
+            false,
+            "",
+            false,
+            String::new(),
+            None,
+            0,
+            Some(BACKUP_INPUT_WIDTH),
+        )
+        .width();
+        (width, height)
+    }
+
+    /// Returns the inputs of the mode
+    /// The passphrase is only repeated when exporting, a password store only needs its path
+    ///
+    /// # Returns
+    /// The inputs
+    fn inputs(&self) -> Vec<BackupInput> {
+        match self.mode {
+            BackupMode::Export => vec![
+                BackupInput::Path,
+                BackupInput::Passphrase,
+                BackupInput::Repeat,
+            ],
+            BackupMode::Import | BackupMode::Foreign => {
+                vec![BackupInput::Path, BackupInput::Passphrase]
+            }
+            BackupMode::Pass => vec![BackupInput::Path],
+        }
+    }
+
+    /// Returns the state of an input
+    ///
+    /// # Arguments
+    /// * `input` - The input
+    ///
+    /// # Returns
+    /// The state
+    fn state_of(input: &BackupInput) -> BackupState {
+        match input {
+            BackupInput::Path => BackupState::Path,
+            BackupInput::Passphrase => BackupState::Passphrase,
+            BackupInput::Repeat => BackupState::Repeat,
+        }
+    }
+
+    /// Generates the input config for the popup
+    /// The title of the export passphrase shows its estimated strength
+    ///
+    /// # Arguments
+    /// * `input` - The input to generate the config for
+    ///
+    /// # Returns
+    /// An `InputConfig` representing the input config for the popup
+    fn generate_input_config(&self, input: BackupInput) -> InputConfig<'_> {
+        let (value, hidden, title) = match input {
+            BackupInput::Path => (
+                self.path.as_str(),
+                false,
+                match self.mode {
+                    BackupMode::Export => "Export to".to_string(),
+                    BackupMode::Import => "Backup file".to_string(),
+                    BackupMode::Foreign => {
+                        "File of another password manager or pass store".to_string()
+                    }
+                    BackupMode::Pass => "Password store to export to".to_string(),
+                },
+            ),
+            BackupInput::Passphrase => (
+                self.passphrase.expose_secret(),
+                self.hidden_passphrase,
+                if self.mode == BackupMode::Export && !self.passphrase.is_empty() {
+                    format!(
+                        "Export Passphrase | Strength: {} | CTRL + s - show/hide",
+                        password_strength(self.passphrase.expose_secret())
+                    )
+                } else if self.mode == BackupMode::Foreign {
+                    "Password, KeePass or gpg key only | CTRL + s - show/hide".to_string()
+                } else {
+                    "Export Passphrase | CTRL + s - show/hide".to_string()
+                },
+            ),
+            BackupInput::Repeat => (
+                self.confirm_passphrase.expose_secret(),
+                self.hidden_passphrase,
+                "Confirm Export Passphrase".to_string(),
+            ),
+        };
+        let focused = self.state == Self::state_of(&input);
+
+        InputConfig::new(
+            focused,
+            value,
+            hidden,
+            title,
+            if focused {
+                Some(*self.cursors.get(&input).unwrap())
+            } else {
+                None
+            },
+            *self.input_offsets.get(&input).unwrap(),
+            Some(BACKUP_INPUT_WIDTH),
+        )
+    }
+
+    /// Generates the button config for the popup
+    ///
+    /// # Arguments
+    /// * `state` - The state of the button
+    ///
+    /// # Returns
+    /// A `ButtonConfig` representing the button config for the popup
+    fn generate_button_config(&self, state: BackupState) -> ButtonConfig {
+        let title = if state == BackupState::Quit {
+            "Quit".to_string()
+        } else {
+            match self.mode {
+                BackupMode::Export | BackupMode::Pass => "Export".to_string(),
+                BackupMode::Import => "Import".to_string(),
+                BackupMode::Foreign => "Next".to_string(),
+            }
+        };
+        ButtonConfig::new(self.state == state, title)
+    }
+
+    /// Passes a key to an input
+    ///
+    /// # Arguments
+    /// * `key` - The key event
+    /// * `input` - The input
+    fn handle_input_key(&mut self, key: &KeyEvent, input: BackupInput) {
+        let config = self.generate_input_config(input.clone());
+        let (cursor_position, input_offset) = match input {
+            BackupInput::Path => {
+                let (value, cursor_position, input_offset) =
+                    Input::handle_key(key, &config, &self.path);
+                self.path = value;
+                (cursor_position, input_offset)
+            }
+            BackupInput::Passphrase => {
+                let (value, cursor_position, input_offset) =
+                    Input::handle_secret_key(key, &config, &self.passphrase);
+                self.passphrase = value;
+                (cursor_position, input_offset)
+            }
+            BackupInput::Repeat => {
+                let (value, cursor_position, input_offset) =
+                    Input::handle_secret_key(key, &config, &self.confirm_passphrase);
+                self.confirm_passphrase = value;
+                (cursor_position, input_offset)
+            }
+        };
+        self.cursors.insert(input.clone(), cursor_position);
+        self.input_offsets.insert(input, input_offset);
+    }
+
+    /// Moves the focus to the next or the previous element
+    ///
+    /// # Arguments
+    /// * `forward` - Whether the focus moves down
+    fn move_focus(&mut self, forward: bool) {
+        let mut states: Vec<BackupState> = self.inputs().iter().map(Self::state_of).collect();
+        states.push(BackupState::Quit);
+        states.push(BackupState::Confirm);
+        let index = states.iter().position(|s| *s == self.state).unwrap_or(0);
+        let index = match (forward, self.state.clone()) {
+            // both buttons share a row
+            (true, BackupState::Quit) => states.len() - 1,
+            (true, BackupState::Confirm) => 0,
+            (true, _) => index + 1,
+            (false, BackupState::Confirm) => states.len() - 3,
+            (false, _) if index == 0 => states.len() - 2,
+            (false, _) => index - 1,
+        };
+        self.state = states[index % states.len()].clone();
+    }
+}
+
+/// Returns the path a backup is exported to by default
+///
+/// # Returns
+/// The path in the downloads folder, or the file name if there is none
+fn default_export_path() -> PathBuf {
+    let name = format!(
+        "krab-backup-{}.{}",
+        chrono::Local::now().format("%Y-%m-%d-%H-%M-%S"),
+        BACKUP_EXTENSION
+    );
+    UserDirs::new()
+        .and_then(|dirs| dirs.download_dir().map(|dir| dir.join(&name)))
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
+/// Reads and decrypts a backup file
+///
+/// # Arguments
+/// * `path` - The path of the backup file
+/// * `passphrase` - The export passphrase
+///
+/// # Returns
+/// The backup or a message explaining why it cannot be read
+pub fn read_backup(path: &Path, passphrase: &SecretString) -> Result<Backup, MessagePopup> {
+    let bundle = fs::read(path)
+        .map_err(|e| MessagePopup::with_detail("Cannot read the backup", &e.to_string()))?;
+    Backup::open(&bundle, passphrase.expose_secret())
+        .map_err(|e| MessagePopup::from_error("Cannot open the backup", &e))
+}
+
+impl Popup for BackupPopup {
+    fn render(&self, f: &mut Frame, _app: &Application, rect: Rect) {
+        let inputs = self.inputs();
+        let mut constraints = vec![Constraint::Length(InputConfig::height()); inputs.len()];
+        constraints.push(Constraint::Length(ButtonConfig::height()));
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(constraints)
+            .split(rect);
+
+        let inner_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+            .split(layout[inputs.len()]);
+
+        let quit_config = self.generate_button_config(BackupState::Quit);
+        let confirm_config = self.generate_button_config(BackupState::Confirm);
+        f.render_widget(Clear, rect);
+        let buffer = f.buffer_mut();
+
+        for (index, input) in inputs.into_iter().enumerate() {
+            Input::render(buffer, layout[index], &self.generate_input_config(input));
+        }
+        Button::render(buffer, inner_layout[0], &quit_config);
+        Button::render(buffer, inner_layout[1], &confirm_config);
+    }
+
+    fn handle_key(
+        &mut self,
+        key: &KeyEvent,
+        app: &Application,
+    ) -> (Application, Option<Box<dyn Popup>>) {
+        let mut app = app.clone();
+
+        let input = match self.state {
+            BackupState::Path => Some(BackupInput::Path),
+            BackupState::Passphrase => Some(BackupInput::Passphrase),
+            BackupState::Repeat => Some(BackupInput::Repeat),
+            BackupState::Confirm | BackupState::Quit => None,
+        };
+        match input {
+            Some(input) => match key.code {
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.hidden_passphrase = !self.hidden_passphrase;
+                }
+                KeyCode::Up => self.move_focus(false),
+                KeyCode::Down | KeyCode::Tab | KeyCode::Enter => self.move_focus(true),
+                KeyCode::Esc => {
+                    self.exit_state = Some(BackupExitState::Quit);
+                }
+                _ => self.handle_input_key(key, input),
+            },
+            None => match key.code {
+                KeyCode::Enter if self.state == BackupState::Confirm => {
+                    self.exit_state = Some(BackupExitState::Confirm);
+                }
+                KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => {
+                    self.exit_state = Some(BackupExitState::Quit);
+                }
+                KeyCode::Right | KeyCode::Left | KeyCode::Char('h') | KeyCode::Char('l') => {
+                    self.state = if self.state == BackupState::Quit {
+                        BackupState::Confirm
+                    } else {
+                        BackupState::Quit
+                    };
+                }
+                KeyCode::Tab => self.move_focus(true),
+                KeyCode::Down | KeyCode::Char('j') => {
+                    self.state = BackupState::Path;
+                }
+                KeyCode::Up | KeyCode::Char('k') => self.move_focus(false),
+                _ => {}
+            },
+        }
+
+        app.mutable_app_state.popups.pop();
+        if self.exit_state.is_none() {
+            app.mutable_app_state.popups.push(Box::new(self.clone()));
+            return (app, None);
+        }
+
+        (app, Some(Box::new(self.clone())))
+    }
+
+    fn wrapper(&self, rect: Rect) -> Rect {
+        let (width, _) = Self::min_area();
+        let height = self.inputs().len() as u16 * InputConfig::height() + ButtonConfig::height();
+        centered_absolute_rect(rect, width, height)
+    }
+
+    fn popup_type(&self) -> PopupType {
+        PopupType::Backup
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_popup_focus() {
+        let mut export = BackupPopup::new(BackupMode::Export);
+        let mut import = BackupPopup::new(BackupMode::Import);
+        let mut pass = BackupPopup::new(BackupMode::Pass);
+        let mut export_states = vec![];
+        let mut import_states = vec![];
+        let mut pass_states = vec![];
+        for _ in 0..5 {
+            export.move_focus(true);
+            export_states.push(export.state.clone());
+            import.move_focus(true);
+            import_states.push(import.state.clone());
+            pass.move_focus(true);
+            pass_states.push(pass.state.clone());
+        }
+        import.move_focus(false);
+
+        assert!(export.path.ends_with(".krab"));
+        assert!(import.path.is_empty());
+        assert!(
+            export_states
+                == vec![
+                    BackupState::Passphrase,
+                    BackupState::Repeat,
+                    BackupState::Quit,
+                    BackupState::Confirm,
+                    BackupState::Path,
+                ]
+        );
+        assert!(
+            import_states[..3]
+                == [
+                    BackupState::Passphrase,
+                    BackupState::Quit,
+                    BackupState::Confirm,
+                ]
+        );
+        assert!(import.state == BackupState::Path);
+        assert!(pass_states[..3] == [BackupState::Quit, BackupState::Confirm, BackupState::Path]);
+    }
+}
@@ -0,0 +1,439 @@
+use std::collections::HashMap;
+
+use krab_backend::{password_strength, secret::SecretString};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    prelude::{Constraint, Direction, Layout, Rect},
+    widgets::Clear,
+    Frame,
+};
+
+use crate::{
+    centered_absolute_rect,
+    components::{
+        button::{Button, ButtonConfig},
+        input::{Input, InputConfig},
+    },
+    popups::{Popup, PopupType},
+    Application,
+};
+
+/// Represents the change master password input fields
+///
+/// # Variants
+/// * `Current` - The current master password field
+/// * `New` - The new master password field
+/// * `Repeat` - The repeated new master password field
+#[derive(Debug, Clone, PartialEq, Hash, Eq)]
+enum ChangeMasterInput {
+    Current,
+    New,
+    Repeat,
+}
+
+/// Represents the buttons in the change master password popup
+///
+/// # Variants
+/// * `Confirm` - The confirm button
+/// * `Quit` - The quit button
+#[derive(Debug, Clone, PartialEq)]
+enum ChangeMasterButton {
+    Confirm,
+    Quit,
+}
+
+/// Represents the state of the change master password popup
+///
+/// # Variants
+/// * `Current` - The current master password state
+/// * `New` - The new master password state
+/// * `Repeat` - The repeated new master password state
+/// * `Confirm` - The confirm state
+/// * `Quit` - The quit state
+#[derive(Clone, PartialEq)]
+pub enum ChangeMasterState {
+    Current,
+    New,
+    Repeat,
+    Confirm,
+    Quit,
+}
+
+/// Represents the exit state of the change master password popup
+///
+/// # Variants
+/// * `Confirm` - The confirm state
+/// * `Quit` - The quit state
+#[derive(Clone, PartialEq)]
+pub enum ChangeMasterExitState {
+    Confirm,
+    Quit,
+}
+
+/// Represents the change master password popup
+///
+/// # Fields
+/// * `current_password` - The current master password
+/// * `new_password` - The new master password
+/// * `confirm_password` - The repeated new master password
+/// * `state` - The state
+/// * `exit_state` - The exit state
+/// * `cursors` - The cursors
+/// * `input_offsets` - The input offsets
+/// * `hidden_password` - Decides if the passwords are hidden
+///
+/// # Methods
+/// * `new` - Creates a new `ChangeMaster`
+/// * `exit_state` - Returns the exit state of the popup
+/// * `current_password` - Returns the current master password
+/// * `new_password` - Returns the new master password
+/// * `confirm_password` - Returns the repeated new master password
+/// * `min_area` - Returns the minimum area of the popup
+/// * `generate_input_config` - Generates the input config for the popup
+/// * `generate_button_config` - Generates the button config for the popup
+///
+/// # Implements
+/// * `Popup` - The popup trait
+#[derive(Clone)]
+pub struct ChangeMaster {
+    current_password: SecretString,
+    new_password: SecretString,
+    confirm_password: SecretString,
+    state: ChangeMasterState,
+    exit_state: Option<ChangeMasterExitState>,
+    cursors: HashMap<ChangeMasterInput, u16>,
+    input_offsets: HashMap<ChangeMasterInput, u16>,
+    hidden_password: bool,
+}
+
+impl Default for ChangeMaster {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChangeMaster {
+    /// Creates a new change master password popup
+    ///
+    /// # Returns
+    /// A new `ChangeMaster`
+    pub fn new() -> Self {
+        let mut cursors = HashMap::new();
+        let mut input_offsets = HashMap::new();
+        for input in [
+            ChangeMasterInput::Current,
+            ChangeMasterInput::New,
+            ChangeMasterInput::Repeat,
+        ] {
+            cursors.insert(input.clone(), 0);
+            input_offsets.insert(input, 0);
+        }
+        ChangeMaster {
+            current_password: SecretString::default(),
+            new_password: SecretString::default(),
+            confirm_password: SecretString::default(),
+            state: ChangeMasterState::Current,
+            exit_state: None,
+            cursors,
+            input_offsets,
+            hidden_password: true,
+        }
+    }
+
+    /// Returns the exit state of the popup
+    ///
+    /// # Returns
+    /// An `Option<ChangeMasterExitState>` representing the exit state of the popup
+    pub fn exit_state(&self) -> Option<ChangeMasterExitState> {
+        self.exit_state.clone()
+    }
+
+    /// Returns the current master password
+    ///
+    /// # Returns
+    /// A `SecretString` representing the current master password
+    pub fn current_password(&self) -> &SecretString {
+        &self.current_password
+    }
+
+    /// Returns the new master password
+    ///
+    /// # Returns
+    /// A `SecretString` representing the new master password
+    pub fn new_password(&self) -> &SecretString {
+        &self.new_password
+    }
+
+    /// Returns the repeated new master password
+    ///
+    /// # Returns
+    /// A `SecretString` representing the repeated new master password
+    pub fn confirm_password(&self) -> &SecretString {
+        &self.confirm_password
+    }
+
+    /// Returns the minimum area of the popup
+    ///
+    /// # Returns
+    /// A tuple representing the minimum area of the popup
+    pub fn min_area() -> (u16, u16) {
+        let height = 3 * InputConfig::height() + ButtonConfig::height();
+        let width = InputConfig::default_width();
+        (width, height)
+    }
+
+    /// Returns the value of an input
+    ///
+    /// # Arguments
+    /// * `input` - The input
+    ///
+    /// # Returns
+    /// The value of the input
+    fn value(&self, input: &ChangeMasterInput) -> &SecretString {
+        match input {
+            ChangeMasterInput::Current => &self.current_password,
+            ChangeMasterInput::New => &self.new_password,
+            ChangeMasterInput::Repeat => &self.confirm_password,
+        }
+    }
+
+    /// Generates the input config for the popup
+    /// The title of the new master password shows its estimated strength
+    ///
+    /// # Arguments
+    /// * `input` - The input to generate the config for
+    ///
+    /// # Returns
+    /// An `InputConfig` representing the input config for the popup
+    fn generate_input_config(&self, input: ChangeMasterInput) -> InputConfig<'_> {
+        let (state, title) = match input {
+            ChangeMasterInput::Current => (
+                ChangeMasterState::Current,
+                "Current Master Password | CTRL + s - show/hide".to_string(),
+            ),
+            ChangeMasterInput::New => (
+                ChangeMasterState::New,
+                if self.new_password.is_empty() {
+                    "New Master Password".to_string()
+                } else {
+                    format!(
+                        "New Master Password | Strength: {}",
+                        password_strength(self.new_password.expose_secret())
+                    )
+                },
+            ),
+            ChangeMasterInput::Repeat => (
+                ChangeMasterState::Repeat,
+                "Confirm New Master Password".to_string(),
+            ),
+        };
+
+        InputConfig::new(
+            self.state == state,
+            self.value(&input).expose_secret(),
+            self.hidden_password,
+            title,
+            if self.state == state {
+                Some(*self.cursors.get(&input).unwrap())
+            } else {
+                None
+            },
+            *self.input_offsets.get(&input).unwrap(),
+            None,
+        )
+    }
+
+    /// Generates the button config for the popup
+    ///
+    /// # Arguments
+    /// * `input` - The input to generate the config for
+    ///
+    /// # Returns
+    /// A `ButtonConfig` representing the button config for the popup
+    fn generate_button_config(&self, input: ChangeMasterButton) -> ButtonConfig {
+        match input {
+            ChangeMasterButton::Confirm => ButtonConfig::new(
+                self.state == ChangeMasterState::Confirm,
+                "Confirm".to_string(),
+            ),
+            ChangeMasterButton::Quit => {
+                ButtonConfig::new(self.state == ChangeMasterState::Quit, "Quit".to_string())
+            }
+        }
+    }
+
+    /// Passes a key to an input
+    ///
+    /// # Arguments
+    /// * `key` - The key event
+    /// * `input` - The input
+    fn handle_input_key(&mut self, key: &KeyEvent, input: ChangeMasterInput) {
+        let config = self.generate_input_config(input.clone());
+        let (value, cursor_position, input_offset) =
+            Input::handle_secret_key(key, &config, self.value(&input));
+        match input {
+            ChangeMasterInput::Current => self.current_password = value,
+            ChangeMasterInput::New => self.new_password = value,
+            ChangeMasterInput::Repeat => self.confirm_password = value,
+        }
+        self.cursors.insert(input.clone(), cursor_position);
+        self.input_offsets.insert(input, input_offset);
+    }
+}
+
+impl Popup for ChangeMaster {
+    fn render(&self, f: &mut Frame, _app: &Application, rect: Rect) {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Length(InputConfig::height()),
+                Constraint::Length(InputConfig::height()),
+                Constraint::Length(InputConfig::height()),
+                Constraint::Length(ButtonConfig::height()),
+            ])
+            .split(rect);
+
+        let inner_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+            .split(layout[3]);
+
+        let current_config = self.generate_input_config(ChangeMasterInput::Current);
+        let new_config = self.generate_input_config(ChangeMasterInput::New);
+        let confirm_password_config = self.generate_input_config(ChangeMasterInput::Repeat);
+
+        let confirm_config = self.generate_button_config(ChangeMasterButton::Confirm);
+        let quit_config = self.generate_button_config(ChangeMasterButton::Quit);
+        f.render_widget(Clear, rect);
+        let buffer = f.buffer_mut();
+
+        Input::render(buffer, layout[0], &current_config);
+        Input::render(buffer, layout[1], &new_config);
+        Input::render(buffer, layout[2], &confirm_password_config);
+        Button::render(buffer, inner_layout[0], &quit_config);
+        Button::render(buffer, inner_layout[1], &confirm_config);
+    }
+
+    fn handle_key(
+        &mut self,
+        key: &KeyEvent,
+        app: &Application,
+    ) -> (Application, Option<Box<dyn Popup>>) {
+        let mut app = app.clone();
+        let mut poped = false;
+
+        match self.state {
+            ChangeMasterState::Current => match key.code {
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.hidden_password = !self.hidden_password;
+                }
+                KeyCode::Up => {
+                    self.state = ChangeMasterState::Quit;
+                }
+                KeyCode::Down | KeyCode::Tab | KeyCode::Enter => {
+                    self.state = ChangeMasterState::New;
+                }
+                KeyCode::Esc => {
+                    app.mutable_app_state.popups.pop();
+                    self.exit_state = Some(ChangeMasterExitState::Quit);
+                    poped = true;
+                }
+                _ => self.handle_input_key(key, ChangeMasterInput::Current),
+            },
+            ChangeMasterState::New => match key.code {
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.hidden_password = !self.hidden_password;
+                }
+                KeyCode::Up => {
+                    self.state = ChangeMasterState::Current;
+                }
+                KeyCode::Down | KeyCode::Tab | KeyCode::Enter => {
+                    self.state = ChangeMasterState::Repeat;
+                }
+                KeyCode::Esc => {
+                    app.mutable_app_state.popups.pop();
+                    self.exit_state = Some(ChangeMasterExitState::Quit);
+                    poped = true;
+                }
+                _ => self.handle_input_key(key, ChangeMasterInput::New),
+            },
+            ChangeMasterState::Repeat => match key.code {
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.hidden_password = !self.hidden_password;
+                }
+                KeyCode::Up => {
+                    self.state = ChangeMasterState::New;
+                }
+                KeyCode::Down | KeyCode::Tab | KeyCode::Enter => {
+                    self.state = ChangeMasterState::Quit;
+                }
+                KeyCode::Esc => {
+                    app.mutable_app_state.popups.pop();
+                    self.exit_state = Some(ChangeMasterExitState::Quit);
+                    poped = true;
+                }
+                _ => self.handle_input_key(key, ChangeMasterInput::Repeat),
+            },
+            ChangeMasterState::Quit => match key.code {
+                KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => {
+                    app.mutable_app_state.popups.pop();
+                    self.exit_state = Some(ChangeMasterExitState::Quit);
+                    poped = true;
+                }
+                KeyCode::Up | KeyCode::Char('k') => {
+                    self.state = ChangeMasterState::Repeat;
+                }
+                KeyCode::Right
+                | KeyCode::Tab
+                | KeyCode::Left
+                | KeyCode::Char('h')
+                | KeyCode::Char('l') => {
+                    self.state = ChangeMasterState::Confirm;
+                }
+                KeyCode::Down | KeyCode::Char('j') => {
+                    self.state = ChangeMasterState::Current;
+                }
+                _ => {}
+            },
+            ChangeMasterState::Confirm => match key.code {
+                KeyCode::Enter => {
+                    app.mutable_app_state.popups.pop();
+                    self.exit_state = Some(ChangeMasterExitState::Confirm);
+                    poped = true;
+                }
+                KeyCode::Left | KeyCode::Right | KeyCode::Char('h') | KeyCode::Char('l') => {
+                    self.state = ChangeMasterState::Quit;
+                }
+                KeyCode::Down | KeyCode::Tab | KeyCode::Char('j') => {
+                    self.state = ChangeMasterState::Current;
+                }
+                KeyCode::Up | KeyCode::Char('k') => {
+                    self.state = ChangeMasterState::Repeat;
+                }
+                KeyCode::Esc | KeyCode::Char('q') => {
+                    app.mutable_app_state.popups.pop();
+                    self.exit_state = Some(ChangeMasterExitState::Quit);
+                    poped = true;
+                }
+                _ => {}
+            },
+        }
+
+        if !poped {
+            app.mutable_app_state.popups.pop();
+            app.mutable_app_state.popups.push(Box::new(self.clone()));
+            return (app, None);
+        }
+
+        (app, Some(Box::new(self.clone())))
+    }
+
+    fn wrapper(&self, rect: Rect) -> Rect {
+        let (width, height) = Self::min_area();
+        centered_absolute_rect(rect, width, height)
+    }
+
+    fn popup_type(&self) -> PopupType {
+        PopupType::ChangeMaster
+    }
+}
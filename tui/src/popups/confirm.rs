@@ -0,0 +1,187 @@
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    prelude::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    widgets::{Block, Clear, Padding, Paragraph},
+    Frame,
+};
+
+use crate::{
+    centered_absolute_rect,
+    components::button::{Button, ButtonConfig},
+    from,
+    popups::{Popup, PopupType},
+    Application, COLOR_ORANGE,
+};
+
+const CONFIRM_WIDTH: u16 = 50;
+const MESSAGE_PADDING: u16 = 4;
+
+/// Represents the state of the confirm popup
+///
+/// # Variants
+/// * `Confirm` - The confirm button is focused
+/// * `Quit` - The quit button is focused
+#[derive(Clone, PartialEq)]
+enum ConfirmState {
+    Confirm,
+    Quit,
+}
+
+/// Represents the exit state of the confirm popup
+///
+/// # Variants
+/// * `Confirm` - The user confirmed
+/// * `Quit` - The user declined
+#[derive(Clone, PartialEq)]
+pub enum ConfirmExitState {
+    Confirm,
+    Quit,
+}
+
+/// Represents a popup asking the user to confirm an action
+///
+/// # Fields
+/// * `message` - The message describing the action
+/// * `state` - The state
+/// * `exit_state` - The exit state
+///
+/// # Methods
+/// * `new` - Creates a new `Confirm`
+/// * `exit_state` - Returns the exit state
+/// * `min_area` - Returns the minimum area of the popup
+/// * `height` - Returns the height of the popup
+///
+/// # Implements
+/// * `Popup` - The popup trait
+#[derive(Clone)]
+pub struct Confirm {
+    message: String,
+    state: ConfirmState,
+    exit_state: Option<ConfirmExitState>,
+}
+
+impl Confirm {
+    /// Creates a new confirm popup
+    /// The quit button is focused, so an action is never confirmed by accident
+    ///
+    /// # Arguments
+    /// * `message` - The message describing the action
+    ///
+    /// # Returns
+    /// A new `Confirm`
+    pub fn new(message: String) -> Self {
+        Confirm {
+            message,
+            state: ConfirmState::Quit,
+            exit_state: None,
+        }
+    }
+
+    /// Returns the exit state of the popup
+    ///
+    /// # Returns
+    /// The exit state of the popup
+    pub fn exit_state(&self) -> Option<ConfirmExitState> {
+        self.exit_state.clone()
+    }
+
+    /// Returns the minimum area of the popup
+    ///
+    /// # Returns
+    /// The minimum area of the popup
+    pub fn min_area() -> (u16, u16) {
+        (CONFIRM_WIDTH, MESSAGE_PADDING + ButtonConfig::height())
+    }
+
+    /// Returns the height of the popup
+    ///
+    /// # Returns
+    /// The height of the popup
+    fn height(&self) -> u16 {
+        self.message.lines().count() as u16 + MESSAGE_PADDING + ButtonConfig::height()
+    }
+}
+
+impl Popup for Confirm {
+    fn render(&self, f: &mut Frame, _app: &Application, rect: Rect) {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Min(0),
+                Constraint::Length(ButtonConfig::height()),
+            ])
+            .split(rect);
+
+        let inner_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+            .split(layout[1]);
+
+        let message_p = Paragraph::new(self.message.clone())
+            .style(Style::default().fg(from(COLOR_ORANGE).unwrap_or(Color::Yellow)))
+            .block(
+                Block::bordered()
+                    .padding(Padding::new(1, 1, 1, 0))
+                    .border_style(Style::default().fg(from(COLOR_ORANGE).unwrap_or(Color::Yellow))),
+            )
+            .alignment(Alignment::Center);
+
+        let quit_config = ButtonConfig::new(self.state == ConfirmState::Quit, "No".to_string());
+        let confirm_config =
+            ButtonConfig::new(self.state == ConfirmState::Confirm, "Yes".to_string());
+
+        f.render_widget(Clear, rect);
+        f.render_widget(message_p, layout[0]);
+        let buffer = f.buffer_mut();
+        Button::render(buffer, inner_layout[0], &quit_config);
+        Button::render(buffer, inner_layout[1], &confirm_config);
+    }
+
+    fn handle_key(
+        &mut self,
+        key: &KeyEvent,
+        app: &Application,
+    ) -> (Application, Option<Box<dyn Popup>>) {
+        let mut app = app.clone();
+
+        match key.code {
+            KeyCode::Left
+            | KeyCode::Right
+            | KeyCode::Tab
+            | KeyCode::Char('h')
+            | KeyCode::Char('l') => {
+                self.state = match self.state {
+                    ConfirmState::Confirm => ConfirmState::Quit,
+                    ConfirmState::Quit => ConfirmState::Confirm,
+                };
+            }
+            KeyCode::Enter if self.state == ConfirmState::Confirm => {
+                self.exit_state = Some(ConfirmExitState::Confirm);
+            }
+            KeyCode::Char('y') => {
+                self.exit_state = Some(ConfirmExitState::Confirm);
+            }
+            KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('n') => {
+                self.exit_state = Some(ConfirmExitState::Quit);
+            }
+            _ => {}
+        }
+
+        app.mutable_app_state.popups.pop();
+        if self.exit_state.is_none() {
+            app.mutable_app_state.popups.push(Box::new(self.clone()));
+            return (app, None);
+        }
+
+        (app, Some(Box::new(self.clone())))
+    }
+
+    fn wrapper(&self, rect: Rect) -> Rect {
+        centered_absolute_rect(rect, CONFIRM_WIDTH, self.height())
+    }
+
+    fn popup_type(&self) -> PopupType {
+        PopupType::Confirm
+    }
+}
@@ -0,0 +1,946 @@
+use std::{collections::HashMap, mem};
+
+use krab_backend::{
+    generate_password,
+    secret::SecretString,
+    user::{CustomField, Entry, EntryKind},
+};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    prelude::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    text::Text,
+    widgets::{Clear, Widget},
+    Frame,
+};
+
+use crate::{
+    centered_absolute_rect,
+    components::{
+        button::{Button, ButtonConfig},
+        input::{Input, InputConfig},
+    },
+    from,
+    popups::{Popup, PopupType},
+    Application, COLOR_WHITE,
+};
+
+const WIDE_INPUT_WIDTH: u16 = 64;
+const MAX_VISIBLE_FIELDS: usize = 3;
+/// Largest number of rows above the custom fields, of any entry kind
+const FIXED_ROWS: usize = 7;
+const HINT_HEIGHT: u16 = 1;
+const HINT_TEXT: &str = "CTRL + n - add field | CTRL + d - remove field | CTRL + p - hide field";
+
+/// Represents the input fields of the entry popup
+///
+/// # Variants
+/// * `Domain` - The domain field, the title for every kind but logins
+/// * `Username` - The username field
+/// * `Password` - The password field, the passphrase of SSH keys
+/// * `Totp` - The TOTP secret field
+/// * `Url` - The URL field
+/// * `Holder` - The card holder field
+/// * `CardNumber` - The card number field
+/// * `Expiry` - The card expiry date field
+/// * `Cvv` - The card security code field
+/// * `PrivateKeyFile` - The path of an SSH private key to read
+/// * `Fingerprint` - The fingerprint of the SSH public key, which cannot be focused
+/// * `FullName` - The full name field of an identity
+/// * `Email` - The email field of an identity
+/// * `Phone` - The phone field of an identity
+/// * `Address` - The address field of an identity
+/// * `Company` - The company field of an identity
+/// * `IdNumber` - The ID number field of an identity
+/// * `Folder` - The folder field
+/// * `Tags` - The tags field, separated by commas
+/// * `Notes` - The notes field, the note of secure notes
+/// * `FieldName` - The name of a custom field
+/// * `FieldValue` - The value of a custom field
+#[derive(Debug, Clone, Copy, PartialEq, Hash, Eq)]
+enum EntryInput {
+    Domain,
+    Username,
+    Password,
+    Totp,
+    Url,
+    Holder,
+    CardNumber,
+    Expiry,
+    Cvv,
+    PrivateKeyFile,
+    Fingerprint,
+    FullName,
+    Email,
+    Phone,
+    Address,
+    Company,
+    IdNumber,
+    Folder,
+    Tags,
+    Notes,
+    FieldName(usize),
+    FieldValue(usize),
+}
+
+/// Represents the buttons in the entry popup
+///
+/// # Variants
+/// * `Confirm` - The confirm button
+/// * `Quit` - The quit button
+#[derive(Debug, Clone, PartialEq)]
+enum EntryButton {
+    Confirm,
+    Quit,
+}
+
+/// Represents the state of the entry popup
+///
+/// # Variants
+/// * `Input` - An input is focused
+/// * `Confirm` - The confirm state
+/// * `Quit` - The quit state
+#[derive(Debug, Clone, PartialEq)]
+enum EntryDetailState {
+    Input(EntryInput),
+    Confirm,
+    Quit,
+}
+
+/// Represents the exit state of the entry popup
+///
+/// # Variants
+/// * `Confirm` - The confirm state
+/// * `Quit` - The quit state
+#[derive(Clone, PartialEq)]
+pub enum EntryDetailExitState {
+    Confirm,
+    Quit,
+}
+
+/// Represents the popup showing and editing all fields of an entry
+///
+/// # Fields
+/// * `entry` - The entry being edited
+/// * `tags` - The tags as typed, separated by commas
+/// * `private_key_file` - The path of an SSH private key to read when confirmed
+/// * `fingerprint` - The type and fingerprint of the stored SSH public key, if any
+/// * `kind_changeable` - Whether the kind can be changed, only for new entries
+/// * `state` - The state
+/// * `exit_state` - The exit state
+/// * `cursors` - The cursors
+/// * `input_offsets` - The input offsets
+/// * `hidden_secrets` - Decides if the password, the TOTP secret and hidden fields are hidden
+///
+/// # Methods
+/// * `new` - Creates a new `EntryDetail` for a new entry
+/// * `edit` - Creates a new `EntryDetail` for an existing entry
+/// * `exit_state` - Returns the exit state of the popup
+/// * `entry` - Returns the edited entry
+/// * `private_key_file` - Returns the path of the SSH private key to read
+/// * `min_area` - Returns the minimum area of the popup
+/// * `height` - Returns the height of the popup
+/// * `rows` - Returns the rows of inputs above the custom fields
+/// * `inputs` - Returns the inputs in focus order
+/// * `visible_fields` - Returns the custom fields that are shown
+/// * `generate_input_config` - Generates the input config for the popup
+/// * `generate_button_config` - Generates the button config for the popup
+/// * `text_mut` - Returns the value of an input holding plain text
+/// * `secret_mut` - Returns the value of an input holding a secret
+/// * `handle_input_key` - Passes a key to an input
+/// * `change_kind` - Changes the kind of a new entry to the next one
+/// * `move_focus` - Moves the focus to the previous or next input
+/// * `add_field` - Adds an empty custom field
+/// * `remove_field` - Removes a custom field
+/// * `last_input` - Returns the last input
+/// * `parse_tags` - Splits the typed tags
+/// * `clear_unused` - Clears the fields the kind of a new entry does not show
+/// * `exit` - Closes the popup
+///
+/// # Implements
+/// * `Popup` - The popup trait
+#[derive(Clone)]
+pub struct EntryDetail {
+    entry: Entry,
+    tags: String,
+    private_key_file: String,
+    fingerprint: String,
+    kind_changeable: bool,
+    state: EntryDetailState,
+    exit_state: Option<EntryDetailExitState>,
+    cursors: HashMap<EntryInput, u16>,
+    input_offsets: HashMap<EntryInput, u16>,
+    hidden_secrets: bool,
+}
+
+impl Default for EntryDetail {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EntryDetail {
+    /// Creates a new entry popup for a new entry
+    ///
+    /// # Returns
+    /// A new `EntryDetail`
+    pub fn new() -> Self {
+        EntryDetail {
+            kind_changeable: true,
+            ..Self::edit(Entry::default())
+        }
+    }
+
+    /// Creates a new entry popup for an existing entry
+    ///
+    /// # Arguments
+    /// * `entry` - The entry to show and edit
+    ///
+    /// # Returns
+    /// A new `EntryDetail`
+    pub fn edit(entry: Entry) -> Self {
+        let fingerprint = match &entry.kind {
+            EntryKind::SshKey(key) => key
+                .key_type()
+                .zip(key.fingerprint())
+                .map(|(key_type, fingerprint)| format!("{} {}", key_type, fingerprint))
+                .unwrap_or_default(),
+            _ => String::new(),
+        };
+        EntryDetail {
+            tags: entry.tags.join(", "),
+            entry,
+            private_key_file: String::new(),
+            fingerprint,
+            kind_changeable: false,
+            state: EntryDetailState::Input(EntryInput::Domain),
+            exit_state: None,
+            cursors: HashMap::new(),
+            input_offsets: HashMap::new(),
+            hidden_secrets: true,
+        }
+    }
+
+    /// Returns the exit state of the popup
+    ///
+    /// # Returns
+    /// An `Option<EntryDetailExitState>` representing the exit state of the popup
+    pub fn exit_state(&self) -> Option<EntryDetailExitState> {
+        self.exit_state.clone()
+    }
+
+    /// Returns the edited entry
+    ///
+    /// # Returns
+    /// The entry
+    pub fn entry(&self) -> &Entry {
+        &self.entry
+    }
+
+    /// Returns the path of the SSH private key to read into the entry
+    ///
+    /// # Returns
+    /// The path as typed, empty to keep the stored key
+    pub fn private_key_file(&self) -> &str {
+        self.private_key_file.trim()
+    }
+
+    /// Returns the minimum area of the popup
+    ///
+    /// # Returns
+    /// A tuple representing the minimum area of the popup
+    pub fn min_area() -> (u16, u16) {
+        let height = (FIXED_ROWS + MAX_VISIBLE_FIELDS) as u16 * InputConfig::height()
+            + HINT_HEIGHT
+            + ButtonConfig::height();
+        let width = 2 * InputConfig::default_width();
+        (width, height)
+    }
+
+    /// Returns the height of the popup, which grows with the custom fields
+    ///
+    /// # Returns
+    /// The height of the popup
+    fn height(&self) -> u16 {
+        (self.rows().len() + self.visible_fields().len()) as u16 * InputConfig::height()
+            + HINT_HEIGHT
+            + ButtonConfig::height()
+    }
+
+    /// Returns the rows of inputs above the custom fields, which depend on the kind of the entry
+    /// A row holds one wide input or two inputs side by side
+    ///
+    /// # Returns
+    /// The rows
+    fn rows(&self) -> Vec<Vec<EntryInput>> {
+        let organize = vec![EntryInput::Folder, EntryInput::Tags];
+        match self.entry.kind {
+            EntryKind::Login => vec![
+                vec![EntryInput::Domain],
+                vec![EntryInput::Username],
+                vec![EntryInput::Password],
+                vec![EntryInput::Totp],
+                vec![EntryInput::Url],
+                organize,
+                vec![EntryInput::Notes],
+            ],
+            EntryKind::Note => vec![vec![EntryInput::Domain], vec![EntryInput::Notes], organize],
+            EntryKind::Card(_) => vec![
+                vec![EntryInput::Domain],
+                vec![EntryInput::Holder],
+                vec![EntryInput::CardNumber],
+                vec![EntryInput::Expiry, EntryInput::Cvv],
+                organize,
+                vec![EntryInput::Notes],
+            ],
+            EntryKind::SshKey(_) => vec![
+                vec![EntryInput::Domain],
+                vec![EntryInput::PrivateKeyFile],
+                vec![EntryInput::Fingerprint],
+                vec![EntryInput::Password],
+                organize,
+                vec![EntryInput::Notes],
+            ],
+            EntryKind::Identity(_) => vec![
+                vec![EntryInput::Domain],
+                vec![EntryInput::FullName],
+                vec![EntryInput::Email, EntryInput::Phone],
+                vec![EntryInput::Address],
+                vec![EntryInput::Company, EntryInput::IdNumber],
+                organize,
+                vec![EntryInput::Notes],
+            ],
+        }
+    }
+
+    /// Returns the inputs in focus order
+    ///
+    /// # Returns
+    /// The inputs
+    fn inputs(&self) -> Vec<EntryInput> {
+        let mut inputs: Vec<EntryInput> = self
+            .rows()
+            .concat()
+            .into_iter()
+            .filter(|input| *input != EntryInput::Fingerprint)
+            .collect();
+        for index in 0..self.entry.fields.len() {
+            inputs.push(EntryInput::FieldName(index));
+            inputs.push(EntryInput::FieldValue(index));
+        }
+        inputs
+    }
+
+    /// Returns the custom fields that are shown
+    /// At most `MAX_VISIBLE_FIELDS` are shown, scrolled to the focused one
+    ///
+    /// # Returns
+    /// The indices of the shown custom fields
+    fn visible_fields(&self) -> std::ops::Range<usize> {
+        let focused = match self.state {
+            EntryDetailState::Input(EntryInput::FieldName(index))
+            | EntryDetailState::Input(EntryInput::FieldValue(index)) => index,
+            _ => 0,
+        };
+        let first = focused.saturating_sub(MAX_VISIBLE_FIELDS - 1);
+        first..self.entry.fields.len().min(first + MAX_VISIBLE_FIELDS)
+    }
+
+    /// Generates the input config for the popup
+    ///
+    /// # Arguments
+    /// * `input` - The input to generate the config for
+    ///
+    /// # Returns
+    /// An `InputConfig` representing the input config for the popup
+    fn generate_input_config(&self, input: EntryInput) -> InputConfig<'_> {
+        let card = match &self.entry.kind {
+            EntryKind::Card(card) => Some(card),
+            _ => None,
+        };
+        let identity = match &self.entry.kind {
+            EntryKind::Identity(identity) => Some(identity),
+            _ => None,
+        };
+        let (value, hidden, title) = match input {
+            EntryInput::Domain => {
+                let title = if self.entry.kind == EntryKind::Login {
+                    "Domain"
+                } else {
+                    "Title"
+                };
+                (
+                    self.entry.domain.as_str(),
+                    false,
+                    if self.kind_changeable {
+                        format!(
+                            "{} | {} · CTRL + t - change kind",
+                            title,
+                            self.entry.kind.name()
+                        )
+                    } else {
+                        format!("{} | {}", title, self.entry.kind.name())
+                    },
+                )
+            }
+            EntryInput::Username => (self.entry.username.as_str(), false, "Username".to_string()),
+            EntryInput::Password => (
+                self.entry.password.expose_secret(),
+                self.hidden_secrets,
+                if matches!(self.entry.kind, EntryKind::SshKey(_)) {
+                    "Passphrase | CTRL + s - show/hide".to_string()
+                } else {
+                    "Password | CTRL + g - generate | CTRL + s - show/hide".to_string()
+                },
+            ),
+            EntryInput::Totp => (
+                self.entry.totp.expose_secret(),
+                self.hidden_secrets,
+                "TOTP secret or otpauth:// URI".to_string(),
+            ),
+            EntryInput::Url => (self.entry.url.as_str(), false, "URL".to_string()),
+            EntryInput::Holder => (
+                card.map_or("", |card| card.holder.as_str()),
+                false,
+                "Card holder".to_string(),
+            ),
+            EntryInput::CardNumber => (
+                card.map_or("", |card| card.number.expose_secret()),
+                self.hidden_secrets,
+                "Card number | CTRL + s - show/hide".to_string(),
+            ),
+            EntryInput::Expiry => (
+                card.map_or("", |card| card.expiry.as_str()),
+                false,
+                "Expiry, MM/YY".to_string(),
+            ),
+            EntryInput::Cvv => (
+                card.map_or("", |card| card.cvv.expose_secret()),
+                self.hidden_secrets,
+                "CVV".to_string(),
+            ),
+            EntryInput::PrivateKeyFile => (
+                self.private_key_file.as_str(),
+                false,
+                if self.fingerprint.is_empty() {
+                    "Private key file, e.g. /home/krab/.ssh/id_ed25519".to_string()
+                } else {
+                    "Private key file, empty to keep the stored key".to_string()
+                },
+            ),
+            EntryInput::Fingerprint => (
+                self.fingerprint.as_str(),
+                false,
+                "Public key fingerprint".to_string(),
+            ),
+            EntryInput::FullName => (
+                identity.map_or("", |identity| identity.full_name.as_str()),
+                false,
+                "Full name".to_string(),
+            ),
+            EntryInput::Email => (
+                identity.map_or("", |identity| identity.email.as_str()),
+                false,
+                "Email".to_string(),
+            ),
+            EntryInput::Phone => (
+                identity.map_or("", |identity| identity.phone.as_str()),
+                false,
+                "Phone".to_string(),
+            ),
+            EntryInput::Address => (
+                identity.map_or("", |identity| identity.address.as_str()),
+                false,
+                "Address".to_string(),
+            ),
+            EntryInput::Company => (
+                identity.map_or("", |identity| identity.company.as_str()),
+                false,
+                "Company".to_string(),
+            ),
+            EntryInput::IdNumber => (
+                identity.map_or("", |identity| identity.id_number.expose_secret()),
+                self.hidden_secrets,
+                "ID or passport number".to_string(),
+            ),
+            EntryInput::Folder => (
+                self.entry.folder.as_str(),
+                false,
+                "Folder, e.g. Work/Clients".to_string(),
+            ),
+            EntryInput::Tags => (
+                self.tags.as_str(),
+                false,
+                "Tags, separated by commas".to_string(),
+            ),
+            EntryInput::Notes => (
+                self.entry.notes.expose_secret(),
+                false,
+                if self.entry.kind == EntryKind::Note {
+                    "Note".to_string()
+                } else {
+                    "Notes".to_string()
+                },
+            ),
+            EntryInput::FieldName(index) => (
+                self.entry.fields[index].name.as_str(),
+                false,
+                format!("Field {}", index + 1),
+            ),
+            EntryInput::FieldValue(index) => {
+                let field = &self.entry.fields[index];
+                (
+                    field.value.expose_secret(),
+                    field.hidden && self.hidden_secrets,
+                    if field.hidden {
+                        "Value (hidden)".to_string()
+                    } else {
+                        "Value".to_string()
+                    },
+                )
+            }
+        };
+        // inputs sharing a row with another one are narrow
+        let width = if self.rows().contains(&vec![input]) {
+            Some(WIDE_INPUT_WIDTH)
+        } else {
+            None
+        };
+        let focused = self.state == EntryDetailState::Input(input);
+
+        InputConfig::new(
+            focused,
+            value,
+            hidden,
+            title,
+            if focused {
+                Some(self.cursors.get(&input).copied().unwrap_or(0))
+            } else {
+                None
+            },
+            self.input_offsets.get(&input).copied().unwrap_or(0),
+            width,
+        )
+    }
+
+    /// Generates the button config for the popup
+    ///
+    /// # Arguments
+    /// * `input` - The input to generate the config for
+    ///
+    /// # Returns
+    /// A `ButtonConfig` representing the button config for the popup
+    fn generate_button_config(&self, input: EntryButton) -> ButtonConfig {
+        match input {
+            EntryButton::Confirm => ButtonConfig::new(
+                self.state == EntryDetailState::Confirm,
+                "Confirm".to_string(),
+            ),
+            EntryButton::Quit => {
+                ButtonConfig::new(self.state == EntryDetailState::Quit, "Quit".to_string())
+            }
+        }
+    }
+
+    /// Returns the value of an input holding plain text
+    ///
+    /// # Arguments
+    /// * `input` - The input
+    ///
+    /// # Returns
+    /// The value or `None` if the input holds a secret or is not part of the kind
+    fn text_mut(&mut self, input: EntryInput) -> Option<&mut String> {
+        match (input, &mut self.entry.kind) {
+            (EntryInput::Domain, _) => Some(&mut self.entry.domain),
+            (EntryInput::Username, _) => Some(&mut self.entry.username),
+            (EntryInput::Url, _) => Some(&mut self.entry.url),
+            (EntryInput::Folder, _) => Some(&mut self.entry.folder),
+            (EntryInput::Tags, _) => Some(&mut self.tags),
+            (EntryInput::PrivateKeyFile, _) => Some(&mut self.private_key_file),
+            (EntryInput::FieldName(index), _) => Some(&mut self.entry.fields[index].name),
+            (EntryInput::Holder, EntryKind::Card(card)) => Some(&mut card.holder),
+            (EntryInput::Expiry, EntryKind::Card(card)) => Some(&mut card.expiry),
+            (EntryInput::FullName, EntryKind::Identity(identity)) => Some(&mut identity.full_name),
+            (EntryInput::Email, EntryKind::Identity(identity)) => Some(&mut identity.email),
+            (EntryInput::Phone, EntryKind::Identity(identity)) => Some(&mut identity.phone),
+            (EntryInput::Address, EntryKind::Identity(identity)) => Some(&mut identity.address),
+            (EntryInput::Company, EntryKind::Identity(identity)) => Some(&mut identity.company),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of an input holding a secret
+    ///
+    /// # Arguments
+    /// * `input` - The input
+    ///
+    /// # Returns
+    /// The value or `None` if the input holds plain text or is not part of the kind
+    fn secret_mut(&mut self, input: EntryInput) -> Option<&mut SecretString> {
+        match (input, &mut self.entry.kind) {
+            (EntryInput::Password, _) => Some(&mut self.entry.password),
+            (EntryInput::Totp, _) => Some(&mut self.entry.totp),
+            (EntryInput::Notes, _) => Some(&mut self.entry.notes),
+            (EntryInput::FieldValue(index), _) => Some(&mut self.entry.fields[index].value),
+            (EntryInput::CardNumber, EntryKind::Card(card)) => Some(&mut card.number),
+            (EntryInput::Cvv, EntryKind::Card(card)) => Some(&mut card.cvv),
+            (EntryInput::IdNumber, EntryKind::Identity(identity)) => Some(&mut identity.id_number),
+            _ => None,
+        }
+    }
+
+    /// Passes a key to an input
+    /// The value is taken out of the entry while it is edited, the input config only provides
+    /// the cursor and the offset
+    ///
+    /// # Arguments
+    /// * `key` - The key event
+    /// * `input` - The input
+    fn handle_input_key(&mut self, key: &KeyEvent, input: EntryInput) {
+        let (cursor_position, input_offset) =
+            if let Some(previous_value) = self.secret_mut(input).map(mem::take) {
+                let config = self.generate_input_config(input);
+                let (value, cursor_position, input_offset) =
+                    Input::handle_secret_key(key, &config, &previous_value);
+                if let Some(secret) = self.secret_mut(input) {
+                    *secret = value;
+                }
+                (cursor_position, input_offset)
+            } else if let Some(previous_value) = self.text_mut(input).map(mem::take) {
+                let config = self.generate_input_config(input);
+                let (value, cursor_position, input_offset) =
+                    Input::handle_key(key, &config, &previous_value);
+                if let Some(text) = self.text_mut(input) {
+                    *text = value;
+                }
+                (cursor_position, input_offset)
+            } else {
+                return;
+            };
+        if input == EntryInput::Tags {
+            self.entry.tags = EntryDetail::parse_tags(&self.tags);
+        }
+        self.cursors.insert(input, cursor_position);
+        self.input_offsets.insert(input, input_offset);
+    }
+
+    /// Changes the kind of a new entry to the next one and focuses the domain
+    /// The cursors are reset, since the inputs of the kinds differ
+    fn change_kind(&mut self) {
+        self.entry.kind = self.entry.kind.next();
+        self.cursors.clear();
+        self.input_offsets.clear();
+        self.state = EntryDetailState::Input(EntryInput::Domain);
+    }
+
+    /// Moves the focus to the input before or after the focused one
+    /// Moving past the last input focuses the quit button
+    ///
+    /// # Arguments
+    /// * `input` - The focused input
+    /// * `forward` - Whether to move forward
+    fn move_focus(&mut self, input: EntryInput, forward: bool) {
+        let inputs = self.inputs();
+        let position = inputs.iter().position(|i| *i == input).unwrap_or(0);
+        self.state = if forward {
+            match inputs.get(position + 1) {
+                Some(next) => EntryDetailState::Input(*next),
+                None => EntryDetailState::Quit,
+            }
+        } else if position == 0 {
+            EntryDetailState::Quit
+        } else {
+            EntryDetailState::Input(inputs[position - 1])
+        };
+    }
+
+    /// Adds an empty custom field and focuses its name
+    fn add_field(&mut self) {
+        self.entry.fields.push(CustomField::default());
+        self.state = EntryDetailState::Input(EntryInput::FieldName(self.entry.fields.len() - 1));
+    }
+
+    /// Removes a custom field and focuses the previous input
+    /// The cursors of the custom fields are reset, since their indices shift
+    ///
+    /// # Arguments
+    /// * `index` - The index of the custom field
+    fn remove_field(&mut self, index: usize) {
+        self.entry.fields.remove(index);
+        let is_field = |input: &EntryInput| {
+            matches!(input, EntryInput::FieldName(_) | EntryInput::FieldValue(_))
+        };
+        self.cursors.retain(|input, _| !is_field(input));
+        self.input_offsets.retain(|input, _| !is_field(input));
+        self.state = EntryDetailState::Input(if index > 0 {
+            EntryInput::FieldValue(index - 1)
+        } else {
+            EntryInput::Notes
+        });
+    }
+
+    /// Returns the last input
+    ///
+    /// # Returns
+    /// The last input in focus order
+    fn last_input(&self) -> EntryInput {
+        *self.inputs().last().unwrap()
+    }
+
+    /// Splits the typed tags at commas, dropping empty and repeated ones
+    ///
+    /// # Arguments
+    /// * `text` - The typed tags
+    ///
+    /// # Returns
+    /// The tags
+    fn parse_tags(text: &str) -> Vec<String> {
+        let mut tags: Vec<String> = vec![];
+        for tag in text.split(',').map(str::trim) {
+            if !tag.is_empty() && !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                tags.push(tag.to_string());
+            }
+        }
+        tags
+    }
+
+    /// Clears the fields of a new entry that its kind does not show
+    /// They could have been typed before the kind was changed, existing entries keep them
+    fn clear_unused(&mut self) {
+        if !self.kind_changeable || self.entry.kind == EntryKind::Login {
+            return;
+        }
+        self.entry.username.clear();
+        self.entry.url.clear();
+        self.entry.totp = SecretString::default();
+        if !matches!(self.entry.kind, EntryKind::SshKey(_)) {
+            self.entry.password = SecretString::default();
+        }
+    }
+
+    /// Closes the popup
+    /// A confirmed folder is normalized, so it matches the folders of other entries
+    ///
+    /// # Arguments
+    /// * `app` - The application
+    /// * `exit_state` - The exit state
+    fn exit(&mut self, app: &mut Application, exit_state: EntryDetailExitState) {
+        if exit_state == EntryDetailExitState::Confirm {
+            self.entry.folder = Entry::normalize_folder(&self.entry.folder);
+            self.clear_unused();
+        }
+        app.mutable_app_state.popups.pop();
+        self.exit_state = Some(exit_state);
+    }
+}
+
+impl Popup for EntryDetail {
+    fn render(&self, f: &mut Frame, _app: &Application, rect: Rect) {
+        let rows = self.rows();
+        let visible_fields = self.visible_fields();
+        let mut constraints = vec![Constraint::Length(InputConfig::height()); rows.len()];
+        for _ in visible_fields.clone() {
+            constraints.push(Constraint::Length(InputConfig::height()));
+        }
+        constraints.push(Constraint::Length(HINT_HEIGHT));
+        constraints.push(Constraint::Length(ButtonConfig::height()));
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(constraints)
+            .split(rect);
+
+        f.render_widget(Clear, rect);
+        let buffer = f.buffer_mut();
+
+        for (row, inputs) in rows.iter().enumerate() {
+            let row_layout = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(vec![
+                    Constraint::Ratio(1, inputs.len() as u32);
+                    inputs.len()
+                ])
+                .split(layout[row]);
+            for (column, input) in inputs.iter().enumerate() {
+                Input::render(
+                    buffer,
+                    row_layout[column],
+                    &self.generate_input_config(*input),
+                );
+            }
+        }
+
+        for (row, index) in visible_fields.clone().enumerate() {
+            let field_layout = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(vec![Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+                .split(layout[rows.len() + row]);
+            Input::render(
+                buffer,
+                field_layout[0],
+                &self.generate_input_config(EntryInput::FieldName(index)),
+            );
+            Input::render(
+                buffer,
+                field_layout[1],
+                &self.generate_input_config(EntryInput::FieldValue(index)),
+            );
+        }
+
+        let hint_row = rows.len() + visible_fields.len();
+        Text::styled(
+            HINT_TEXT,
+            Style::default().fg(from(COLOR_WHITE).unwrap_or(Color::White)),
+        )
+        .centered()
+        .render(layout[hint_row], buffer);
+
+        let inner_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+            .split(layout[hint_row + 1]);
+        let confirm_config = self.generate_button_config(EntryButton::Confirm);
+        let quit_config = self.generate_button_config(EntryButton::Quit);
+        Button::render(buffer, inner_layout[0], &quit_config);
+        Button::render(buffer, inner_layout[1], &confirm_config);
+    }
+
+    fn handle_key(
+        &mut self,
+        key: &KeyEvent,
+        app: &Application,
+    ) -> (Application, Option<Box<dyn Popup>>) {
+        let mut app = app.clone();
+        let mut poped = false;
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        match self.state.clone() {
+            EntryDetailState::Input(input) => match key.code {
+                KeyCode::Char('g')
+                    if control
+                        && input == EntryInput::Password
+                        && self.entry.kind == EntryKind::Login =>
+                {
+                    self.entry.password = SecretString::new(generate_password());
+                    self.cursors.insert(input, 0);
+                    self.input_offsets.insert(input, 0);
+                }
+                KeyCode::Char('s') if control => {
+                    self.hidden_secrets = !self.hidden_secrets;
+                }
+                KeyCode::Char('t') if control && self.kind_changeable => {
+                    self.change_kind();
+                }
+                KeyCode::Char('n') if control => {
+                    self.add_field();
+                }
+                KeyCode::Char('d') if control => {
+                    if let EntryInput::FieldName(index) | EntryInput::FieldValue(index) = input {
+                        self.remove_field(index);
+                    }
+                }
+                KeyCode::Char('p') if control => {
+                    if let EntryInput::FieldName(index) | EntryInput::FieldValue(index) = input {
+                        self.entry.fields[index].hidden = !self.entry.fields[index].hidden;
+                    }
+                }
+                KeyCode::Up => {
+                    self.move_focus(input, false);
+                }
+                KeyCode::Down | KeyCode::Tab | KeyCode::Enter => {
+                    self.move_focus(input, true);
+                }
+                KeyCode::Esc => {
+                    self.exit(&mut app, EntryDetailExitState::Quit);
+                    poped = true;
+                }
+                _ => self.handle_input_key(key, input),
+            },
+            EntryDetailState::Quit => match key.code {
+                KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => {
+                    self.exit(&mut app, EntryDetailExitState::Quit);
+                    poped = true;
+                }
+                KeyCode::Up | KeyCode::Char('k') => {
+                    self.state = EntryDetailState::Input(self.last_input());
+                }
+                KeyCode::Right
+                | KeyCode::Tab
+                | KeyCode::Left
+                | KeyCode::Char('h')
+                | KeyCode::Char('l') => {
+                    self.state = EntryDetailState::Confirm;
+                }
+                KeyCode::Down | KeyCode::Char('j') => {
+                    self.state = EntryDetailState::Input(EntryInput::Domain);
+                }
+                _ => {}
+            },
+            EntryDetailState::Confirm => match key.code {
+                KeyCode::Enter => {
+                    self.exit(&mut app, EntryDetailExitState::Confirm);
+                    poped = true;
+                }
+                KeyCode::Left | KeyCode::Right | KeyCode::Char('h') | KeyCode::Char('l') => {
+                    self.state = EntryDetailState::Quit;
+                }
+                KeyCode::Down | KeyCode::Tab | KeyCode::Char('j') => {
+                    self.state = EntryDetailState::Input(EntryInput::Domain);
+                }
+                KeyCode::Up | KeyCode::Char('k') => {
+                    self.state = EntryDetailState::Input(self.last_input());
+                }
+                KeyCode::Esc | KeyCode::Char('q') => {
+                    self.exit(&mut app, EntryDetailExitState::Quit);
+                    poped = true;
+                }
+                _ => {}
+            },
+        }
+
+        if !poped {
+            app.mutable_app_state.popups.pop();
+            app.mutable_app_state.popups.push(Box::new(self.clone()));
+            return (app, None);
+        }
+
+        (app, Some(Box::new(self.clone())))
+    }
+
+    fn wrapper(&self, rect: Rect) -> Rect {
+        centered_absolute_rect(rect, 2 * InputConfig::default_width(), self.height())
+    }
+
+    fn popup_type(&self) -> PopupType {
+        PopupType::EntryDetail
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_entry_detail_plain_shortcut_letters_are_typed() {
+        let app = Application::create(PathBuf::new(), Rect::new(0, 0, 200, 60)).into_inner();
+        let mut popup = EntryDetail::new();
+        popup.state = EntryDetailState::Input(EntryInput::Password);
+        for key in [
+            KeyEvent::from(KeyCode::Char('g')),
+            KeyEvent::from(KeyCode::Char('s')),
+            KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL),
+        ] {
+            popup.handle_key(&key, &app);
+        }
+        let typed = popup.entry().password.expose_secret().to_string();
+        popup.handle_key(
+            &KeyEvent::new(KeyCode::Char('g'), KeyModifiers::CONTROL),
+            &app,
+        );
+
+        assert_eq!(typed, "gs");
+        assert!(!popup.hidden_secrets);
+        assert_ne!(popup.entry().password.expose_secret(), "gs");
+    }
+}
@@ -23,6 +23,12 @@ use crate::{
 #[derive(Clone)]
 pub struct Exit {}
 
+impl Default for Exit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Exit {
     /// Creates a new exit popup
     ///
@@ -33,7 +39,7 @@ impl Exit {
     }
 
     /// Returns the minimum area of the popup
-    /// 
+    ///
     /// # Returns
     /// The minimum area of the popup
     pub fn min_area() -> (u16, u16) {
@@ -59,11 +65,8 @@ impl Popup for Exit {
         app: &Application,
     ) -> (Application, Option<Box<dyn Popup>>) {
         let mut app = app.clone();
-        match key.code {
-            KeyCode::Char('q') => {
-                app.mutable_app_state.running = false;
-            }
-            _ => {}
+        if let KeyCode::Char('q') = key.code {
+            app.mutable_app_state.running = false;
         }
 
         (app, None)
@@ -0,0 +1,452 @@
+use std::path::PathBuf;
+
+use directories::UserDirs;
+use krab_backend::export::{ExportField, ExportFormat, ExportOptions, EXPORT_FIELDS};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    prelude::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    text::{Line, Text},
+    widgets::{Block, Clear, Padding, Paragraph},
+    Frame,
+};
+
+use crate::{
+    centered_absolute_rect,
+    components::{
+        button::{Button, ButtonConfig},
+        input::{Input, InputConfig},
+    },
+    from,
+    popups::{Popup, PopupType},
+    Application, COLOR_ORANGE, COLOR_WHITE,
+};
+
+/// The width of the text of the path input
+const EXPORT_INPUT_WIDTH: u16 = 56;
+const MAX_PATH_LENGTH: usize = 256;
+/// Borders and padding around the options
+const OPTIONS_PADDING: u16 = 3;
+
+/// Represents the state of the export popup
+///
+/// # Variants
+/// * `Path` - The path state
+/// * `Format` - The format state
+/// * `Field` - The state of a field, with its index in `EXPORT_FIELDS`
+/// * `Confirm` - The confirm state
+/// * `Quit` - The quit state
+#[derive(Debug, Clone, PartialEq)]
+enum ExportState {
+    Path,
+    Format,
+    Field(usize),
+    Confirm,
+    Quit,
+}
+
+/// Represents the exit state of the export popup
+///
+/// # Variants
+/// * `Confirm` - The confirm state
+/// * `Quit` - The quit state
+#[derive(Clone, PartialEq, Debug)]
+pub enum ExportExitState {
+    Confirm,
+    Quit,
+}
+
+/// Represents the popup choosing the file, the format and the fields of a clear text export
+///
+/// # Fields
+/// * `path` - The path of the export
+/// * `options` - The format and the fields
+/// * `state` - The state
+/// * `exit_state` - The exit state
+/// * `cursor` - The cursor of the path input
+/// * `input_offset` - The offset of the path input
+///
+/// # Methods
+/// * `new` - Creates a new `ExportPopup`
+/// * `exit_state` - Returns the exit state of the popup
+/// * `path` - Returns the path of the export
+/// * `options` - Returns the format and the fields
+/// * `min_area` - Returns the minimum area of the popup
+/// * `lines` - Returns the lines of the options
+/// * `toggle` - Changes the focused option
+/// * `set_path` - Replaces the path and moves the cursor to its end
+/// * `move_focus` - Moves the focus to the next or the previous element
+///
+/// # Implements
+/// * `Popup` - The popup trait
+#[derive(Clone)]
+pub struct ExportPopup {
+    path: String,
+    options: ExportOptions,
+    state: ExportState,
+    exit_state: Option<ExportExitState>,
+    cursor: u16,
+    input_offset: u16,
+}
+
+impl Default for ExportPopup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExportPopup {
+    /// Creates a new export popup
+    /// The secrets are exported as CSV with every field to the downloads folder by default
+    ///
+    /// # Returns
+    /// A new `ExportPopup`
+    pub fn new() -> Self {
+        let options = ExportOptions::default();
+        let mut popup = ExportPopup {
+            path: String::new(),
+            options,
+            state: ExportState::Path,
+            exit_state: None,
+            cursor: 0,
+            input_offset: 0,
+        };
+        let path = default_export_path(popup.options.format)
+            .to_string_lossy()
+            .to_string();
+        if path.len() <= MAX_PATH_LENGTH {
+            popup.set_path(path);
+        }
+        popup
+    }
+
+    /// Returns the exit state of the popup
+    ///
+    /// # Returns
+    /// An `Option<ExportExitState>` representing the exit state of the popup
+    pub fn exit_state(&self) -> Option<ExportExitState> {
+        self.exit_state.clone()
+    }
+
+    /// Returns the path of the export
+    ///
+    /// # Returns
+    /// The path, empty if none was entered
+    pub fn path(&self) -> PathBuf {
+        PathBuf::from(self.path.trim())
+    }
+
+    /// Returns the format and the fields of the export
+    ///
+    /// # Returns
+    /// The export options
+    pub fn options(&self) -> &ExportOptions {
+        &self.options
+    }
+
+    /// Returns the minimum area of the popup
+    ///
+    /// # Returns
+    /// A tuple representing the minimum area of the popup
+    pub fn min_area() -> (u16, u16) {
+        let width = InputConfig::new(
+            false,
+            "",
+            false,
+            String::new(),
+            None,
+            0,
+            Some(EXPORT_INPUT_WIDTH),
+        )
+        .width();
+        let height = InputConfig::height()
+            + EXPORT_FIELDS.len() as u16
+            + 1
+            + OPTIONS_PADDING
+            + ButtonConfig::height();
+        (width, height)
+    }
+
+    /// Returns the lines of the options, the format followed by a checkbox for each field
+    ///
+    /// # Returns
+    /// The lines
+    fn lines(&self) -> Vec<String> {
+        let checkbox = |checked: bool| if checked { "x" } else { " " };
+        let mut lines = vec![format!("Format: {} | h/l - change", self.options.format)];
+        for field in EXPORT_FIELDS.iter() {
+            lines.push(format!(
+                "[{}] {}",
+                checkbox(self.options.fields.contains(field)),
+                field.label()
+            ));
+        }
+        lines
+    }
+
+    /// Changes the focused option
+    /// The format cycles and changes the extension of the path with it, a field is added or removed
+    fn toggle(&mut self) {
+        match self.state {
+            ExportState::Format => {
+                let previous = self.options.format;
+                self.options.format = previous.next();
+                let path = self.path();
+                if path
+                    .extension()
+                    .is_some_and(|extension| extension == previous.extension())
+                {
+                    let path = path.with_extension(self.options.format.extension());
+                    self.set_path(path.to_string_lossy().to_string());
+                }
+            }
+            ExportState::Field(index) => {
+                let field: ExportField = EXPORT_FIELDS[index];
+                match self.options.fields.iter().position(|f| *f == field) {
+                    Some(position) => {
+                        self.options.fields.remove(position);
+                    }
+                    None => self.options.fields.push(field),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Replaces the path and moves the cursor to its end
+    ///
+    /// # Arguments
+    /// * `path` - The path
+    fn set_path(&mut self, path: String) {
+        self.cursor = path.len() as u16;
+        self.input_offset = (path.len() as u16 + 1).saturating_sub(EXPORT_INPUT_WIDTH);
+        self.path = path;
+    }
+
+    /// Generates the input config of the path
+    ///
+    /// # Returns
+    /// An `InputConfig` representing the input config for the popup
+    fn generate_input_config(&self) -> InputConfig<'_> {
+        let focused = self.state == ExportState::Path;
+        InputConfig::new(
+            focused,
+            self.path.as_str(),
+            false,
+            "Export to".to_string(),
+            if focused { Some(self.cursor) } else { None },
+            self.input_offset,
+            Some(EXPORT_INPUT_WIDTH),
+        )
+    }
+
+    /// Moves the focus to the next or the previous element
+    ///
+    /// # Arguments
+    /// * `forward` - Whether the focus moves down
+    fn move_focus(&mut self, forward: bool) {
+        let mut states = vec![ExportState::Path, ExportState::Format];
+        states.extend((0..EXPORT_FIELDS.len()).map(ExportState::Field));
+        states.push(ExportState::Quit);
+        states.push(ExportState::Confirm);
+        let index = states.iter().position(|s| *s == self.state).unwrap_or(0);
+        let index = match (forward, self.state.clone()) {
+            // both buttons share a row
+            (true, ExportState::Quit) => states.len() - 1,
+            (true, ExportState::Confirm) => 0,
+            (true, _) => index + 1,
+            (false, ExportState::Confirm) => states.len() - 3,
+            (false, _) if index == 0 => states.len() - 2,
+            (false, _) => index - 1,
+        };
+        self.state = states[index % states.len()].clone();
+    }
+}
+
+/// Returns the path secrets are exported to by default
+///
+/// # Arguments
+/// * `format` - The format of the export
+///
+/// # Returns
+/// The path in the downloads folder, or the file name if there is none
+fn default_export_path(format: ExportFormat) -> PathBuf {
+    let name = format!(
+        "krab-secrets-{}.{}",
+        chrono::Local::now().format("%Y-%m-%d-%H-%M-%S"),
+        format.extension()
+    );
+    UserDirs::new()
+        .and_then(|dirs| dirs.download_dir().map(|dir| dir.join(&name)))
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
+impl Popup for ExportPopup {
+    fn render(&self, f: &mut Frame, _app: &Application, rect: Rect) {
+        let white = Style::default().fg(from(COLOR_WHITE).unwrap_or(Color::White));
+        let orange = Style::default().fg(from(COLOR_ORANGE).unwrap_or(Color::Yellow));
+        let lines = self.lines();
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Length(InputConfig::height()),
+                Constraint::Length(lines.len() as u16 + OPTIONS_PADDING),
+                Constraint::Length(ButtonConfig::height()),
+            ])
+            .split(rect);
+        let inner_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+            .split(layout[2]);
+
+        let focused = match self.state {
+            ExportState::Format => Some(0),
+            ExportState::Field(index) => Some(index + 1),
+            _ => None,
+        };
+        let lines: Vec<Line> = lines
+            .into_iter()
+            .enumerate()
+            .map(|(index, line)| {
+                Line::styled(
+                    line,
+                    if focused == Some(index) {
+                        orange
+                    } else {
+                        white
+                    },
+                )
+            })
+            .collect();
+        let options = Paragraph::new(Text::from(lines)).block(
+            Block::bordered()
+                .title(" Clear Text Export | Space - toggle ")
+                .padding(Padding::new(1, 1, 0, 1))
+                .border_style(if focused.is_some() { orange } else { white }),
+        );
+
+        f.render_widget(Clear, rect);
+        f.render_widget(options, layout[1]);
+        let buffer = f.buffer_mut();
+        Input::render(buffer, layout[0], &self.generate_input_config());
+        Button::render(
+            buffer,
+            inner_layout[0],
+            &ButtonConfig::new(self.state == ExportState::Quit, "Quit".to_string()),
+        );
+        Button::render(
+            buffer,
+            inner_layout[1],
+            &ButtonConfig::new(self.state == ExportState::Confirm, "Export".to_string()),
+        );
+    }
+
+    fn handle_key(
+        &mut self,
+        key: &KeyEvent,
+        app: &Application,
+    ) -> (Application, Option<Box<dyn Popup>>) {
+        let mut app = app.clone();
+
+        match self.state {
+            ExportState::Path => match key.code {
+                KeyCode::Up => self.move_focus(false),
+                KeyCode::Down | KeyCode::Tab | KeyCode::Enter => self.move_focus(true),
+                KeyCode::Esc => {
+                    self.exit_state = Some(ExportExitState::Quit);
+                }
+                _ => {
+                    let (value, cursor, input_offset) =
+                        Input::handle_key(key, &self.generate_input_config(), &self.path);
+                    self.path = value;
+                    self.cursor = cursor;
+                    self.input_offset = input_offset;
+                }
+            },
+            ExportState::Format | ExportState::Field(_) => match key.code {
+                KeyCode::Enter
+                | KeyCode::Char(' ')
+                | KeyCode::Left
+                | KeyCode::Right
+                | KeyCode::Char('h')
+                | KeyCode::Char('l') => self.toggle(),
+                KeyCode::Down | KeyCode::Tab | KeyCode::Char('j') => self.move_focus(true),
+                KeyCode::Up | KeyCode::Char('k') => self.move_focus(false),
+                KeyCode::Esc | KeyCode::Char('q') => {
+                    self.exit_state = Some(ExportExitState::Quit);
+                }
+                _ => {}
+            },
+            ExportState::Confirm | ExportState::Quit => match key.code {
+                KeyCode::Enter if self.state == ExportState::Confirm => {
+                    self.exit_state = Some(ExportExitState::Confirm);
+                }
+                KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => {
+                    self.exit_state = Some(ExportExitState::Quit);
+                }
+                KeyCode::Right | KeyCode::Left | KeyCode::Char('h') | KeyCode::Char('l') => {
+                    self.state = if self.state == ExportState::Quit {
+                        ExportState::Confirm
+                    } else {
+                        ExportState::Quit
+                    };
+                }
+                KeyCode::Tab => self.move_focus(true),
+                KeyCode::Down | KeyCode::Char('j') => {
+                    self.state = ExportState::Path;
+                }
+                KeyCode::Up | KeyCode::Char('k') => self.move_focus(false),
+                _ => {}
+            },
+        }
+
+        app.mutable_app_state.popups.pop();
+        if self.exit_state.is_none() {
+            app.mutable_app_state.popups.push(Box::new(self.clone()));
+            return (app, None);
+        }
+
+        (app, Some(Box::new(self.clone())))
+    }
+
+    fn wrapper(&self, rect: Rect) -> Rect {
+        let (width, height) = Self::min_area();
+        centered_absolute_rect(rect, width, height)
+    }
+
+    fn popup_type(&self) -> PopupType {
+        PopupType::Export
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_popup_options() {
+        let mut popup = ExportPopup::new();
+        popup.move_focus(true);
+        popup.toggle();
+        popup.move_focus(true);
+        popup.move_focus(true);
+        popup.move_focus(true);
+        popup.toggle();
+        let lines = popup.lines();
+
+        assert!(popup.path.ends_with(".json"));
+        assert_eq!(popup.state, ExportState::Field(2));
+        assert_eq!(popup.options().format, ExportFormat::Json);
+        assert!(!popup.options().fields.contains(&ExportField::Password));
+        assert_eq!(popup.options().fields.len(), EXPORT_FIELDS.len() - 1);
+        assert_eq!(lines[0], "Format: JSON | h/l - change");
+        assert_eq!(lines[1], "[x] Title");
+        assert_eq!(lines[3], "[ ] Password");
+
+        popup.state = ExportState::Format;
+        popup.set_path("secrets.txt".to_string());
+        popup.toggle();
+        assert_eq!(popup.path(), PathBuf::from("secrets.txt"));
+        assert_eq!(popup.cursor, 11);
+    }
+}
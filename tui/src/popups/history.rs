@@ -0,0 +1,231 @@
+use krab_backend::user::{Entry, PreviousPassword};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    prelude::Rect,
+    style::{Color, Style},
+    text::{Line, Text},
+    widgets::{Block, Clear, Padding, Paragraph},
+    Frame,
+};
+
+use crate::{
+    centered_absolute_rect, from,
+    popups::{Popup, PopupType},
+    Application, COLOR_BLACK, COLOR_ORANGE, COLOR_WHITE,
+};
+
+const HISTORY_WIDTH: u16 = 64;
+/// Borders, padding, the timestamps and the hint around the list of passwords
+const HISTORY_PADDING: u16 = 10;
+const HINT_TEXT: &str = "j/k - select | s - show | r - restore | Esc - close";
+
+/// Represents the exit state of the history popup
+///
+/// # Variants
+/// * `Restore` - The user chose to restore the password at the index
+/// * `Quit` - The user closed the popup
+#[derive(Clone, PartialEq, Debug)]
+pub enum HistoryExitState {
+    Restore(usize),
+    Quit,
+}
+
+/// Represents the popup showing the timestamps and previous passwords of an entry
+///
+/// # Fields
+/// * `label` - The label of the entry
+/// * `created` - When the entry was added
+/// * `modified` - When the entry was last changed
+/// * `last_used` - When the entry was last used
+/// * `passwords` - The previous passwords, the most recent one first
+/// * `selected` - The selected password
+/// * `shown` - Whether the passwords are shown
+/// * `exit_state` - The exit state
+///
+/// # Methods
+/// * `new` - Creates a new `History`
+/// * `exit_state` - Returns the exit state
+/// * `min_area` - Returns the minimum area of the popup
+/// * `height` - Returns the height of the popup
+///
+/// # Implements
+/// * `Popup` - The popup trait
+#[derive(Clone)]
+pub struct History {
+    label: String,
+    created: i64,
+    modified: i64,
+    last_used: i64,
+    passwords: Vec<PreviousPassword>,
+    selected: usize,
+    shown: bool,
+    exit_state: Option<HistoryExitState>,
+}
+
+impl History {
+    /// Creates a new history popup
+    ///
+    /// # Arguments
+    /// * `entry` - The entry
+    ///
+    /// # Returns
+    /// A new `History`
+    pub fn new(entry: &Entry) -> Self {
+        History {
+            label: entry.label(),
+            created: entry.created,
+            modified: entry.modified,
+            last_used: entry.last_used,
+            passwords: entry.history.clone(),
+            selected: 0,
+            shown: false,
+            exit_state: None,
+        }
+    }
+
+    /// Returns the exit state of the popup
+    ///
+    /// # Returns
+    /// The exit state of the popup
+    pub fn exit_state(&self) -> Option<HistoryExitState> {
+        self.exit_state.clone()
+    }
+
+    /// Returns the minimum area of the popup
+    ///
+    /// # Returns
+    /// The minimum area of the popup
+    pub fn min_area() -> (u16, u16) {
+        (HISTORY_WIDTH, HISTORY_PADDING + 1)
+    }
+
+    /// Returns the height of the popup
+    ///
+    /// # Returns
+    /// The height of the popup
+    fn height(&self) -> u16 {
+        HISTORY_PADDING + self.passwords.len().max(1) as u16
+    }
+}
+
+/// Formats a Unix timestamp in the local time zone
+///
+/// # Arguments
+/// * `timestamp` - The Unix timestamp, `0` if unknown
+/// * `unknown` - The text shown for an unknown timestamp
+///
+/// # Returns
+/// The formatted date and time
+pub fn format_timestamp(timestamp: i64, unknown: &str) -> String {
+    match chrono::DateTime::from_timestamp(timestamp, 0) {
+        Some(time) if timestamp > 0 => time
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+        _ => unknown.to_string(),
+    }
+}
+
+impl Popup for History {
+    fn render(&self, f: &mut Frame, _app: &Application, rect: Rect) {
+        let white = Style::default().fg(from(COLOR_WHITE).unwrap_or(Color::White));
+        let selected = Style::default()
+            .bg(from(COLOR_WHITE).unwrap_or(Color::White))
+            .fg(from(COLOR_BLACK).unwrap_or(Color::Black));
+
+        let mut lines = vec![
+            Line::styled(
+                format!("Created     {}", format_timestamp(self.created, "unknown")),
+                white,
+            ),
+            Line::styled(
+                format!("Modified    {}", format_timestamp(self.modified, "unknown")),
+                white,
+            ),
+            Line::styled(
+                format!("Last used   {}", format_timestamp(self.last_used, "never")),
+                white,
+            ),
+            Line::default(),
+        ];
+        if self.passwords.is_empty() {
+            lines.push(Line::styled("No previous passwords", white));
+        }
+        for (index, previous) in self.passwords.iter().enumerate() {
+            let password = if self.shown {
+                previous.password.expose_secret().to_string()
+            } else {
+                "•".repeat(previous.password.expose_secret().chars().count())
+            };
+            let style = if index == self.selected {
+                selected
+            } else {
+                white
+            };
+            lines.push(Line::styled(
+                format!(
+                    "{} {}  {}",
+                    if index == self.selected { ">" } else { " " },
+                    format_timestamp(previous.replaced, "unknown"),
+                    password
+                ),
+                style,
+            ));
+        }
+        lines.push(Line::default());
+        lines.push(Line::styled(HINT_TEXT, white).centered());
+
+        let paragraph = Paragraph::new(Text::from(lines)).block(
+            Block::bordered()
+                .title(format!(" Password history of {} ", self.label))
+                .padding(Padding::new(2, 2, 1, 0))
+                .border_style(Style::default().fg(from(COLOR_ORANGE).unwrap_or(Color::Yellow))),
+        );
+
+        f.render_widget(Clear, rect);
+        f.render_widget(paragraph, rect);
+    }
+
+    fn handle_key(
+        &mut self,
+        key: &KeyEvent,
+        app: &Application,
+    ) -> (Application, Option<Box<dyn Popup>>) {
+        let mut app = app.clone();
+
+        match key.code {
+            KeyCode::Down | KeyCode::Char('j') if self.selected + 1 < self.passwords.len() => {
+                self.selected += 1;
+            }
+            KeyCode::Up | KeyCode::Char('k') if self.selected > 0 => {
+                self.selected -= 1;
+            }
+            KeyCode::Char('s') => {
+                self.shown = !self.shown;
+            }
+            KeyCode::Char('r') | KeyCode::Enter if !self.passwords.is_empty() => {
+                self.exit_state = Some(HistoryExitState::Restore(self.selected));
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.exit_state = Some(HistoryExitState::Quit);
+            }
+            _ => {}
+        }
+
+        app.mutable_app_state.popups.pop();
+        if self.exit_state.is_none() {
+            app.mutable_app_state.popups.push(Box::new(self.clone()));
+            return (app, None);
+        }
+
+        (app, Some(Box::new(self.clone())))
+    }
+
+    fn wrapper(&self, rect: Rect) -> Rect {
+        centered_absolute_rect(rect, HISTORY_WIDTH, self.height())
+    }
+
+    fn popup_type(&self) -> PopupType {
+        PopupType::History
+    }
+}
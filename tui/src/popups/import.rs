@@ -0,0 +1,337 @@
+use std::{fs, path::Path};
+
+use krab_backend::{
+    import::{detect, DuplicatePolicy, Import},
+    pass::{PasswordStore, PASS_NAME},
+    secret::SecretString,
+};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    prelude::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    text::{Line, Text},
+    widgets::{Block, Clear, Padding, Paragraph},
+    Frame,
+};
+
+use crate::{
+    centered_absolute_rect, from,
+    popups::{message::MessagePopup, Popup, PopupType},
+    Application, COLOR_ORANGE, COLOR_WHITE,
+};
+
+const PREVIEW_WIDTH: u16 = 72;
+const PREVIEW_HEIGHT: u16 = 20;
+/// Borders, padding, the summary and the hint around the list
+const PREVIEW_PADDING: u16 = 8;
+const HINT_TEXT: &str = "j/k - scroll | d - duplicates | Enter - import | Esc - cancel";
+
+/// Represents the exit state of the import preview popup
+///
+/// # Variants
+/// * `Import` - The user chose to import the entries
+/// * `Quit` - The user cancelled the import
+#[derive(Clone, PartialEq, Debug)]
+pub enum ImportExitState {
+    Import,
+    Quit,
+}
+
+/// Represents the popup previewing the entries read from another password manager
+/// It lists the entries that will be added and the items that are skipped with the reason,
+/// and lets the user choose what happens to entries that are already in the vault
+///
+/// # Fields
+/// * `source` - The name of the file the entries were read from
+/// * `format` - The name of the format of the file
+/// * `import` - The entries and the skipped items
+/// * `policy` - What happens to entries with the domain and username of another entry
+/// * `offset` - The first visible line of the list
+/// * `exit_state` - The exit state
+///
+/// # Methods
+/// * `new` - Creates a new `ImportPreview`
+/// * `exit_state` - Returns the exit state
+/// * `import` - Returns the entries and the skipped items
+/// * `policy` - Returns what happens to duplicates
+/// * `min_area` - Returns the minimum area of the popup
+/// * `lines` - Returns the lines of the list
+///
+/// # Implements
+/// * `Popup` - The popup trait
+#[derive(Clone)]
+pub struct ImportPreview {
+    source: String,
+    format: String,
+    import: Import,
+    policy: DuplicatePolicy,
+    offset: usize,
+    exit_state: Option<ImportExitState>,
+}
+
+impl ImportPreview {
+    /// Creates a new import preview popup
+    ///
+    /// # Arguments
+    /// * `source` - The name of the file the entries were read from
+    /// * `format` - The name of the format of the file
+    /// * `import` - The entries and the skipped items
+    ///
+    /// # Returns
+    /// A new `ImportPreview`
+    pub fn new(source: &str, format: &str, import: Import) -> Self {
+        ImportPreview {
+            source: source.to_string(),
+            format: format.to_string(),
+            import,
+            policy: DuplicatePolicy::default(),
+            offset: 0,
+            exit_state: None,
+        }
+    }
+
+    /// Returns the exit state of the popup
+    ///
+    /// # Returns
+    /// The exit state of the popup
+    pub fn exit_state(&self) -> Option<ImportExitState> {
+        self.exit_state.clone()
+    }
+
+    /// Returns the entries and the skipped items
+    ///
+    /// # Returns
+    /// The import
+    pub fn import(&self) -> &Import {
+        &self.import
+    }
+
+    /// Returns what happens to entries with the domain and username of another entry
+    ///
+    /// # Returns
+    /// The duplicate policy
+    pub fn policy(&self) -> DuplicatePolicy {
+        self.policy
+    }
+
+    /// Returns the minimum area of the popup
+    ///
+    /// # Returns
+    /// The minimum area of the popup
+    pub fn min_area() -> (u16, u16) {
+        (PREVIEW_WIDTH, PREVIEW_HEIGHT)
+    }
+
+    /// Returns the lines of the list, the entries with their folder
+    /// followed by the skipped items with the reason
+    ///
+    /// # Returns
+    /// The lines
+    fn lines(&self) -> Vec<String> {
+        let mut lines = vec![];
+        if !self.import.entries.is_empty() {
+            lines.push("Entries".to_string());
+        }
+        for entry in self.import.entries.iter() {
+            if entry.folder.is_empty() {
+                lines.push(format!("  {}", entry.label()));
+            } else {
+                lines.push(format!("  {}/{}", entry.folder, entry.label()));
+            }
+        }
+        if !self.import.skipped.is_empty() {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.push("Skipped".to_string());
+        }
+        for item in self.import.skipped.iter() {
+            lines.push(format!("  {} - {}", item.name, item.reason));
+        }
+        lines
+    }
+
+    /// Returns the largest offset of the list
+    ///
+    /// # Returns
+    /// The offset that shows the last line at the bottom
+    fn max_offset(&self) -> usize {
+        self.lines()
+            .len()
+            .saturating_sub((PREVIEW_HEIGHT - PREVIEW_PADDING) as usize)
+    }
+}
+
+/// Reads the entries of the file of another password manager
+/// The format is detected from the content of the file, a folder is a password store of pass
+///
+/// # Arguments
+/// * `path` - The path of the file or of the password store
+/// * `password` - The password of the file, only needed by encrypted formats, or of the gpg key
+///
+/// # Returns
+/// The name of the format with the entries and the skipped items,
+/// or a message explaining why the file cannot be read
+pub fn read_import(
+    path: &Path,
+    password: &SecretString,
+) -> Result<(&'static str, Import), MessagePopup> {
+    if path.is_dir() {
+        return PasswordStore::new(path)
+            .import(password.expose_secret())
+            .map(|import| (PASS_NAME, import))
+            .map_err(|e| {
+                MessagePopup::with_detail("Cannot read the password store", &e.to_string())
+            });
+    }
+    let data = fs::read(path)
+        .map_err(|e| MessagePopup::with_detail("Cannot read the file", &e.to_string()))?;
+    let importer = detect(&data).ok_or_else(|| {
+        MessagePopup::with_detail(
+            "Cannot import the file",
+            "It is not an export of a known password manager",
+        )
+    })?;
+    if importer.needs_password() && password.is_empty() {
+        return Err(MessagePopup::new(format!(
+            "The password of the\n{} file\nis required",
+            importer.name()
+        )));
+    }
+    importer
+        .import(&data, password.expose_secret())
+        .map(|import| (importer.name(), import))
+        .map_err(|e| MessagePopup::with_detail("Cannot open the file", &e.to_string()))
+}
+
+impl Popup for ImportPreview {
+    fn render(&self, f: &mut Frame, _app: &Application, rect: Rect) {
+        let white = Style::default().fg(from(COLOR_WHITE).unwrap_or(Color::White));
+        let orange = Style::default().fg(from(COLOR_ORANGE).unwrap_or(Color::Yellow));
+
+        let block = Block::bordered()
+            .title(format!(" Import from {} ({}) ", self.source, self.format))
+            .padding(Padding::new(2, 2, 1, 0))
+            .border_style(orange);
+        let inner = block.inner(rect);
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Length(3),
+                Constraint::Min(1),
+                Constraint::Length(2),
+            ])
+            .split(inner);
+
+        let summary = vec![
+            Line::styled(
+                format!(
+                    "{} secrets will be imported, {} items are skipped",
+                    self.import.entries.len(),
+                    self.import.skipped.len()
+                ),
+                white,
+            ),
+            Line::styled(
+                format!("Already in the vault: {}", self.policy.name()),
+                white,
+            ),
+        ];
+        let lines: Vec<Line> = self
+            .lines()
+            .into_iter()
+            .map(|line| {
+                if line.starts_with(' ') {
+                    Line::styled(line, white)
+                } else {
+                    Line::styled(line, orange)
+                }
+            })
+            .collect();
+        let hint = vec![Line::default(), Line::styled(HINT_TEXT, white).centered()];
+
+        f.render_widget(Clear, rect);
+        f.render_widget(block, rect);
+        f.render_widget(Paragraph::new(Text::from(summary)), layout[0]);
+        f.render_widget(
+            Paragraph::new(Text::from(lines)).scroll((self.offset as u16, 0)),
+            layout[1],
+        );
+        f.render_widget(Paragraph::new(Text::from(hint)), layout[2]);
+    }
+
+    fn handle_key(
+        &mut self,
+        key: &KeyEvent,
+        app: &Application,
+    ) -> (Application, Option<Box<dyn Popup>>) {
+        let mut app = app.clone();
+
+        match key.code {
+            KeyCode::Down | KeyCode::Char('j') if self.offset < self.max_offset() => {
+                self.offset += 1;
+            }
+            KeyCode::Up | KeyCode::Char('k') if self.offset > 0 => {
+                self.offset -= 1;
+            }
+            KeyCode::Char('d') => {
+                self.policy = self.policy.next();
+            }
+            KeyCode::Enter | KeyCode::Char('y') if !self.import.entries.is_empty() => {
+                self.exit_state = Some(ImportExitState::Import);
+            }
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('n') => {
+                self.exit_state = Some(ImportExitState::Quit);
+            }
+            _ => {}
+        }
+
+        app.mutable_app_state.popups.pop();
+        if self.exit_state.is_none() {
+            app.mutable_app_state.popups.push(Box::new(self.clone()));
+            return (app, None);
+        }
+
+        (app, Some(Box::new(self.clone())))
+    }
+
+    fn wrapper(&self, rect: Rect) -> Rect {
+        centered_absolute_rect(rect, PREVIEW_WIDTH, PREVIEW_HEIGHT)
+    }
+
+    fn popup_type(&self) -> PopupType {
+        PopupType::Import
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use krab_backend::{import::SkippedItem, user::Entry};
+
+    #[test]
+    fn test_import_preview_lines() {
+        let mut import = Import::default();
+        let mut entry = Entry::new("example.com", "password");
+        entry.folder = "Work".to_string();
+        import.entries.push(entry);
+        for index in 0..20 {
+            import
+                .entries
+                .push(Entry::new(&format!("{}.com", index), "password"));
+        }
+        import.skipped.push(SkippedItem {
+            name: "Deleted".to_string(),
+            reason: "In the recycle bin".to_string(),
+        });
+        let preview = ImportPreview::new("vault.kdbx", "KeePass (KDBX 4)", import);
+        let lines = preview.lines();
+
+        assert_eq!(lines[0], "Entries");
+        assert_eq!(lines[1], "  Work/example.com");
+        assert_eq!(lines[lines.len() - 2], "Skipped");
+        assert_eq!(lines[lines.len() - 1], "  Deleted - In the recycle bin");
+        assert_eq!(preview.max_offset(), lines.len() - 12);
+        assert_eq!(preview.policy(), DuplicatePolicy::Skip);
+    }
+}
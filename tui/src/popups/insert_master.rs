@@ -5,6 +5,8 @@ use ratatui::{
     Frame,
 };
 
+use krab_backend::secret::SecretString;
+
 use crate::{
     centered_absolute_rect,
     components::{
@@ -72,7 +74,7 @@ pub enum InsertMasterExitState {
 /// * `Popup` - The popup trait
 #[derive(Clone)]
 pub struct InsertMaster {
-    master: String,
+    master: SecretString,
     state: InsertMasterState,
     exit_state: Option<InsertMasterExitState>,
     cursor: u16,
@@ -80,6 +82,12 @@ pub struct InsertMaster {
     hidden_password: bool,
 }
 
+impl Default for InsertMaster {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl InsertMaster {
     /// Creates a new insert master popup
     ///
@@ -89,7 +97,7 @@ impl InsertMaster {
         let cursor = 0;
         let input_offset = 0;
         InsertMaster {
-            master: String::new(),
+            master: SecretString::default(),
             state: InsertMasterState::Master,
             exit_state: None,
             cursor,
@@ -102,8 +110,8 @@ impl InsertMaster {
     ///
     /// # Returns
     /// The master password
-    pub fn master(&self) -> String {
-        self.master.clone()
+    pub fn master(&self) -> &SecretString {
+        &self.master
     }
 
     /// Returns the state of the popup
@@ -129,10 +137,10 @@ impl InsertMaster {
     ///
     /// # Returns
     /// The maximum area of the popup
-    fn generate_input_config(&self) -> InputConfig {
+    fn generate_input_config(&self) -> InputConfig<'_> {
         InputConfig::new(
             self.state == InsertMasterState::Master,
-            self.master(),
+            self.master.expose_secret(),
             self.hidden_password,
             "Master password | CTRL + s - show/hide".to_string(),
             if self.state == InsertMasterState::Master {
@@ -188,11 +196,11 @@ impl Popup for InsertMaster {
         let confirm_config = self.generate_button_config(MasterPasswordButton::Confirm);
         let quit_config = self.generate_button_config(MasterPasswordButton::Quit);
         f.render_widget(Clear, rect);
-        let mut buffer = f.buffer_mut();
+        let buffer = f.buffer_mut();
 
-        Input::render(&mut buffer, layout[0], &master_config);
-        Button::render(&mut buffer, inner_layout[0], &quit_config);
-        Button::render(&mut buffer, inner_layout[1], &confirm_config);
+        Input::render(buffer, layout[0], &master_config);
+        Button::render(buffer, inner_layout[0], &quit_config);
+        Button::render(buffer, inner_layout[1], &confirm_config);
     }
 
     fn handle_key(
@@ -208,17 +216,8 @@ impl Popup for InsertMaster {
                 KeyCode::Down | KeyCode::Tab | KeyCode::Enter | KeyCode::Up => {
                     self.state = InsertMasterState::Quit;
                 }
-                KeyCode::Char('s') => {
-                    if key.modifiers.contains(KeyModifiers::CONTROL) {
-                        self.hidden_password = !self.hidden_password;
-                    } else {
-                        let config = self.generate_input_config();
-                        let (value, cursor_position, input_offset) =
-                            Input::handle_key(key, &config, self.master().as_str());
-                        self.master = value;
-                        self.cursor = cursor_position;
-                        self.input_offset = input_offset;
-                    }
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.hidden_password = !self.hidden_password;
                 }
                 KeyCode::Esc => {
                     app.mutable_app_state.popups.pop();
@@ -228,7 +227,7 @@ impl Popup for InsertMaster {
                 _ => {
                     let config = self.generate_input_config();
                     let (value, cursor_position, input_offset) =
-                        Input::handle_key(key, &config, self.master().as_str());
+                        Input::handle_secret_key(key, &config, &self.master);
                     self.master = value;
                     self.cursor = cursor_position;
                     self.input_offset = input_offset;
@@ -1,3 +1,7 @@
+use krab_backend::{
+    attachment::{MAX_ATTACHMENTS, MAX_ATTACHMENT_SIZE},
+    user::KrabError,
+};
 use ratatui::{
     crossterm::event::KeyEvent,
     prelude::{Alignment, Rect},
@@ -22,6 +26,8 @@ use crate::{
 /// # Methods
 /// * `new` - Creates a new `MessagePopup`
 /// * `new_with_size` - Creates a new `MessagePopup` with custom dimensions
+/// * `from_error` - Creates a new `MessagePopup` explaining an error
+/// * `with_detail` - Creates a new `MessagePopup` with a context and a detail
 /// * `min_area` - Returns the minimum area of the popup
 ///
 /// # Implements
@@ -66,6 +72,35 @@ impl MessagePopup {
         }
     }
 
+    /// Creates a new message popup explaining an error
+    /// The popup is widened to fit the longest line
+    ///
+    /// # Arguments
+    /// * `context` - What failed
+    /// * `error` - The error
+    ///
+    /// # Returns
+    /// A new `MessagePopup`
+    pub fn from_error(context: &str, error: &KrabError) -> Self {
+        MessagePopup::with_detail(context, &error_message(error))
+    }
+
+    /// Creates a new message popup with a context and a detail below it
+    /// The popup is widened to fit the longest line
+    ///
+    /// # Arguments
+    /// * `context` - What happened
+    /// * `detail` - Why it happened
+    ///
+    /// # Returns
+    /// A new `MessagePopup`
+    pub fn with_detail(context: &str, detail: &str) -> Self {
+        let message = format!("{}\n\n{}", context, detail);
+        let width = message.lines().map(|l| l.len()).max().unwrap_or(0) as u16 + 6;
+        let (min_width, min_height) = MessagePopup::min_area();
+        MessagePopup::new_with_size(message, width.max(min_width), min_height)
+    }
+
     /// Returns the minimum area of the popup
     ///
     /// # Returns
@@ -75,6 +110,60 @@ impl MessagePopup {
     }
 }
 
+/// Returns a message for an error telling the user what to do about it
+///
+/// # Arguments
+/// * `error` - The error
+///
+/// # Returns
+/// The message
+pub fn error_message(error: &KrabError) -> String {
+    match error {
+        KrabError::WrongCredentials => "Wrong master password".to_string(),
+        KrabError::Corrupted { offset, .. } => format!(
+            "The vault file is damaged\nat byte {}. Restore a previous\nversion (.1, .2, .3) or a backup",
+            offset
+        ),
+        KrabError::Io(kind) => format!(
+            "Could not access the vault\nfile ({}). Check its\npermissions and the disk space",
+            kind
+        ),
+        KrabError::UserNotFound => "No vault exists\nfor this username".to_string(),
+        KrabError::UserAlreadyExists => "A vault already exists\nfor this username".to_string(),
+        KrabError::RecordNotFound => {
+            "The secret does not exist\nanymore, reload the vault".to_string()
+        }
+        KrabError::RecordAlreadyExists => "The secret already exists".to_string(),
+        KrabError::Locked => "The vault is used by another\nkrab instance, try again".to_string(),
+        KrabError::Conflict => "The vault was changed by\nanother krab instance".to_string(),
+        KrabError::Encryption => "Could not encrypt the data".to_string(),
+        KrabError::UnsupportedKdf => {
+            "The key derivation parameters\nare not supported".to_string()
+        }
+        KrabError::LastRecord => "The last secret of an old\nvault cannot be removed".to_string(),
+        KrabError::AttachmentNotFound => {
+            "The attachment is missing,\nit may have been removed".to_string()
+        }
+        KrabError::AttachmentTooLarge(size) => format!(
+            "The file is too large\n({} KiB, at most {} KiB)",
+            size / 1024,
+            MAX_ATTACHMENT_SIZE / 1024
+        ),
+        KrabError::TooManyAttachments => {
+            format!("A secret can have at most\n{} attachments", MAX_ATTACHMENTS)
+        }
+        KrabError::AttachmentCorrupted => {
+            "The attachment is damaged\nor was tampered with".to_string()
+        }
+        KrabError::WrongPassphrase => {
+            "Wrong export passphrase, or\nthe backup was tampered with".to_string()
+        }
+        KrabError::InvalidBackup => {
+            "The file is not a krab backup\nor was made by a newer version".to_string()
+        }
+    }
+}
+
 impl Popup for MessagePopup {
     fn render(&self, f: &mut Frame, _app: &Application, rect: Rect) {
         // Count lines in the message to determine if we need special formatting
@@ -25,7 +25,7 @@ pub enum ViewState {
     Login(Login),
     StartUp(StartUp),
     Register(Register),
-    Home(Home),
+    Home(Box<Home>),
     Settings(Settings),
 }
 
@@ -36,8 +36,15 @@ pub enum ViewState {
 /// * `handle_key` - Handles a key event
 /// * `needs_header` - Returns whether the state needs a header
 /// * `min_area` - Returns the minimum area of the state
-/// * `handle_insert_record_popup` - Handles an insert record popup
+/// * `handle_entry_popup` - Handles an entry popup
 /// * `handle_insert_master_popup` - Handles an insert master popup
+/// * `handle_confirm_popup` - Handles a confirm popup
+/// * `handle_change_master_popup` - Handles a change master password popup
+/// * `handle_history_popup` - Handles a password history popup
+/// * `handle_attachments_popup` - Handles an attachments popup
+/// * `handle_backup_popup` - Handles a backup popup
+/// * `handle_import_popup` - Handles an import preview popup
+/// * `handle_export_popup` - Handles an export popup
 pub trait View {
     /// Renders the state
     ///
@@ -71,23 +78,19 @@ pub trait View {
     /// The minimum area of the state
     fn min_area(&self) -> (u16, u16);
 
-    /// Handles an insert record popup
+    /// Handles an entry popup
     ///
     /// # Arguments
     /// * `app` - The application
-    /// * `popup` - The insert record popup
+    /// * `popup` - The entry popup
     ///
     /// # Returns
     /// The updated application
     ///
     /// # Panics
-    /// This function panics if called on a state that does not handle insert record popups
-    fn handle_insert_record_popup(
-        &mut self,
-        _app: Application,
-        _popup: Box<dyn Popup>,
-    ) -> Application {
-        unreachable!("This view does not handle insert record popups");
+    /// This function panics if called on a state that does not handle entry popups
+    fn handle_entry_popup(&mut self, _app: Application, _popup: Box<dyn Popup>) -> Application {
+        unreachable!("This view does not handle entry popups");
     }
 
     /// Handles an insert master popup
@@ -109,19 +112,116 @@ pub trait View {
         unreachable!("This view does not handle insert master popups");
     }
 
-    /// Returns the type of the view
+    /// Handles a confirm popup
+    ///
+    /// # Arguments
+    /// * `app` - The application
+    /// * `popup` - The confirm popup
+    ///
+    /// # Returns
+    /// The updated application
+    ///
+    /// # Panics
+    /// This function panics if called on a state that does not handle confirm popups
+    fn handle_confirm_popup(&mut self, _app: Application, _popup: Box<dyn Popup>) -> Application {
+        unreachable!("This view does not handle confirm popups");
+    }
+
+    /// Handles a change master password popup
+    ///
+    /// # Arguments
+    /// * `app` - The application
+    /// * `popup` - The change master password popup
+    ///
+    /// # Returns
+    /// The updated application
+    ///
+    /// # Panics
+    /// This function panics if called on a state that does not handle change master password popups
+    fn handle_change_master_popup(
+        &mut self,
+        _app: Application,
+        _popup: Box<dyn Popup>,
+    ) -> Application {
+        unreachable!("This view does not handle change master password popups");
+    }
+
+    /// Handles a password history popup
+    ///
+    /// # Arguments
+    /// * `app` - The application
+    /// * `popup` - The password history popup
+    ///
+    /// # Returns
+    /// The updated application
+    ///
+    /// # Panics
+    /// This function panics if called on a state that does not handle password history popups
+    fn handle_history_popup(&mut self, _app: Application, _popup: Box<dyn Popup>) -> Application {
+        unreachable!("This view does not handle password history popups");
+    }
+
+    /// Handles an attachments popup
     ///
     /// # Arguments
     /// * `app` - The application
-    /// * `popup` - The insert password popup
+    /// * `popup` - The attachments popup
     ///
     /// # Returns
     /// The updated application
-    fn handle_insert_password_popup(
+    ///
+    /// # Panics
+    /// This function panics if called on a state that does not handle attachments popups
+    fn handle_attachments_popup(
         &mut self,
         _app: Application,
         _popup: Box<dyn Popup>,
     ) -> Application {
-        unreachable!("This view does not handle insert password popups");
+        unreachable!("This view does not handle attachments popups");
+    }
+
+    /// Handles a backup popup
+    ///
+    /// # Arguments
+    /// * `app` - The application
+    /// * `popup` - The backup popup
+    ///
+    /// # Returns
+    /// The updated application
+    ///
+    /// # Panics
+    /// This function panics if called on a state that does not handle backup popups
+    fn handle_backup_popup(&mut self, _app: Application, _popup: Box<dyn Popup>) -> Application {
+        unreachable!("This view does not handle backup popups");
+    }
+
+    /// Handles an import preview popup
+    ///
+    /// # Arguments
+    /// * `app` - The application
+    /// * `popup` - The import preview popup
+    ///
+    /// # Returns
+    /// The updated application
+    ///
+    /// # Panics
+    /// This function panics if called on a state that does not handle import preview popups
+    fn handle_import_popup(&mut self, _app: Application, _popup: Box<dyn Popup>) -> Application {
+        unreachable!("This view does not handle import preview popups");
+    }
+
+    /// Handles an export popup
+    ///
+    /// # Arguments
+    /// * `app` - The application
+    /// * `popup` - The export popup
+    ///
+    /// # Returns
+    /// The updated application
+    ///
+    /// # Panics
+    /// This function panics if called on a state that does not handle export popups
+    fn handle_export_popup(&mut self, _app: Application, _popup: Box<dyn Popup>) -> Application {
+        unreachable!("This view does not handle export popups");
     }
 }
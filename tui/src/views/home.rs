@@ -1,5 +1,11 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
 use cli_clipboard::{ClipboardContext, ClipboardProvider};
-use directories::UserDirs;
 use ratatui::{
     crossterm::event::{KeyCode, KeyEvent},
     prelude::{Buffer, Rect},
@@ -13,20 +19,38 @@ use crate::{
     components::{
         input::{Input, InputConfig},
         scrollable_view::ScrollView,
+        sidebar::{Sidebar, SidebarConfig},
     },
     from,
     popups::{
-        insert_domain_password::{InsertDomainPassword, InsertDomainPasswordExitState},
+        attachments::{Attachments, AttachmentsExitState},
+        backup::{read_backup, BackupExitState, BackupMode, BackupPopup},
+        change_master::{ChangeMaster, ChangeMasterExitState},
+        confirm::{Confirm, ConfirmExitState},
+        entry_detail::{EntryDetail, EntryDetailExitState},
+        export::{ExportExitState, ExportPopup},
+        history::{History, HistoryExitState},
+        import::{read_import, ImportExitState, ImportPreview},
         insert_master::{InsertMaster, InsertMasterExitState},
-        insert_password::{InsertPassword, InsertPasswordExitState},
         message::MessagePopup,
         Popup,
     },
-    views::{login::Login, View},
+    views::{login::Login, settings::Settings, View},
     Application, ViewState, COLOR_BLACK, COLOR_ORANGE, COLOR_WHITE,
 };
 use chrono;
-use krab_backend::user::{ReadOnlyRecords, RecordOperationConfig, User};
+use krab_backend::{
+    backup::Backup,
+    export::{self, ExportOptions},
+    import::DuplicatePolicy,
+    pass::{PassError, PassExport, PasswordStore},
+    password_strength,
+    secret::SecretString,
+    session::VaultSession,
+    totp::Totp,
+    user::{Entry, EntryError, EntryKind, KrabError, ReadOnlyRecords, SshKey, MAX_ATTACHMENT_SIZE},
+    write_private_file, Config, PasswordStrength,
+};
 
 const DOMAIN_PASSWORD_LIST_ITEM_HEIGHT: u16 = 4;
 const RIGHT_MARGIN: u16 = 6;
@@ -36,16 +60,51 @@ const DOMAIN_PASSWORD_MIDDLE_WIDTH: u16 = 3;
 const MIN_WIDTH: u16 = 128;
 const FILTER_INPUT_WIDTH: u16 = 64;
 const LEGEND_TEXT: &str = "Press ? for help";
+const SIDEBAR_WIDTH: u16 = 32;
+/// The sidebar is hidden if the list of secrets would be narrower
+const MIN_LIST_AREA_WIDTH: u16 = 40;
+const SIDEBAR_TITLE: &str = "Folders & Tags";
 
 /// Represents the home view state
 ///
 /// # Variants
 /// * `Normal` - The normal state
 /// * `Filter` - The filter state
+/// * `Sidebar` - The folders and tags are browsed
 #[derive(Debug, Clone, PartialEq)]
 enum HomeViewState {
     Normal,
     Filter,
+    Sidebar,
+}
+
+/// Represents what the listed secrets are narrowed to
+///
+/// # Variants
+/// * `All` - Every secret
+/// * `Folder` - The secrets in a folder or its subfolders
+/// * `Tag` - The secrets with a tag
+///
+/// # Methods
+/// * `matches` - Returns whether an entry is in the scope
+#[derive(Debug, Clone, PartialEq)]
+enum Scope {
+    All,
+    Folder(String),
+    Tag(String),
+}
+
+/// Represents an item of the sidebar
+///
+/// # Fields
+/// * `scope` - The scope applied when the item is chosen
+/// * `label` - The text shown for the item
+/// * `expandable` - Whether the item is a folder with subfolders
+#[derive(Debug, Clone, PartialEq)]
+struct SidebarItem {
+    scope: Scope,
+    label: String,
+    expandable: bool,
 }
 
 /// Represents the operation over a secret
@@ -54,11 +113,31 @@ enum HomeViewState {
 /// * `Add` - The add operation
 /// * `Remove` - The remove operation
 /// * `Modify` - The modify operation
+/// * `Reload` - Reloading the vault after it was changed by another process
+/// * `ChangeMaster` - Changing the master password of the vault
+/// * `Restore` - Restoring a previous password, with its index in the history
+/// * `Attach` - Attaching the file at the path
+/// * `RemoveAttachment` - Removing an attachment, with its index in the entry
+/// * `Export` - Exporting an encrypted backup to the path, sealed with the export passphrase
+/// * `Import` - Merging the entries of a backup into the vault
+/// * `ExportPlaintext` - Exporting the secrets in clear text to the path
+/// * `ImportEntries` - Adding the entries read from another password manager, with the number of skipped items and the duplicate policy
+/// * `ExportPass` - Exporting the secrets to the password store of pass at the path
 #[derive(Debug, Clone, PartialEq)]
 enum Operation {
     Add,
     Remove,
     Modify,
+    Reload,
+    ChangeMaster,
+    Restore(usize),
+    Attach(PathBuf),
+    RemoveAttachment(usize),
+    Export(PathBuf, SecretString),
+    Import(Backup),
+    ExportPlaintext(PathBuf, ExportOptions),
+    ImportEntries(Vec<Entry>, usize, DuplicatePolicy),
+    ExportPass(PathBuf),
 }
 
 /// Represents the position of the inner buffer
@@ -79,7 +158,8 @@ pub struct Position {
 /// Represents the home view
 ///
 /// # Fields
-/// * `user` - The user
+/// * `session` - The unlocked vault of the user
+/// * `all_secrets` - Every secret of the vault, regardless of the scope and filter
 /// * `secrets` - Secrets
 /// * `position` - The position of the inner buffer
 /// * `area` - The area of the view
@@ -87,8 +167,13 @@ pub struct Position {
 /// * `cursor` - The cursor position
 /// * `input_offset` - The input offset
 /// * `filter_value` - The filter value
-/// * `new_secret` - The new secret to add if any
+/// * `new_entry` - The new or edited entry if any
 /// * `operation` - The operation to perform if any
+/// * `master_change` - The master password change to perform if any
+/// * `ask_master_password` - Whether changes are confirmed with the master password
+/// * `scope` - What the listed secrets are narrowed to
+/// * `sidebar_selected` - The selected item of the sidebar
+/// * `collapsed_folders` - The folders whose subfolders are hidden in the sidebar
 ///
 /// # Methods
 ///
@@ -98,6 +183,11 @@ pub struct Position {
 /// * `set_shown_upwards` - Sets the shown upwards
 /// * `largest_prefix` - Returns the largest prefix
 /// * `fuzzy_filter` - Filters the secrets
+/// * `refilter` - Lists the secrets again, narrowed by the scope and the filter
+/// * `sidebar_items` - Returns the items of the sidebar
+/// * `has_sidebar` - Returns whether any secret has a folder or tags
+/// * `list_area` - Returns the area of the list of secrets
+/// * `handle_sidebar_key` - Handles a key while the sidebar is focused
 /// * `up` - Moves the cursor up
 /// * `scroll_to_top` - Scrolls to the top
 /// * `down` - Moves the cursor down
@@ -114,12 +204,23 @@ pub struct Position {
 /// * `legend_height` - Returns the legend height
 /// * `buffer_to_render` - Returns the buffer to render
 /// * `index_offset` - Returns the index offset
+/// * `session_mut` - Returns the unlocked vault of the user
+/// * `reload_config` - Reads the settings again after they were changed
+/// * `reload` - Replaces the secrets with freshly loaded ones
+/// * `has_secrets` - Returns whether there are secrets to select
+/// * `current_secret` - Returns the selected secret
+/// * `update_secrets` - Replaces the listed secrets after a change
+/// * `mark_used` - Records that the selected secret was used
+/// * `operation_error` - Reports a failed operation
+/// * `confirm_operation` - Confirms the operation before performing it
+/// * `perform_operation` - Performs the operation on the vault
 ///
 /// # Implements
 /// * `View` - The view trait
 #[derive(Debug, Clone, PartialEq)]
 pub struct Home {
-    user: User,
+    session: VaultSession,
+    all_secrets: Vec<Secret>,
     secrets: Vec<Secrets>,
     position: Position,
     area: Rect,
@@ -127,31 +228,41 @@ pub struct Home {
     cursor: u16,
     input_offset: u16,
     filter_value: String,
-    new_secret: Option<NewSecret>,
+    new_entry: Option<Entry>,
     operation: Option<Operation>,
+    master_change: Option<MasterChange>,
+    ask_master_password: bool,
+    scope: Scope,
+    sidebar_selected: usize,
+    collapsed_folders: Vec<String>,
 }
 
-/// Represents a new secret
+/// Represents a master password change
 ///
 /// # Fields
-/// * `domain` - The domain
-/// * `password` - The password
+/// * `current_password` - The current master password
+/// * `new_password` - The new master password
 #[derive(Debug, Clone, PartialEq)]
-struct NewSecret {
-    domain: String,
-    password: String,
+struct MasterChange {
+    current_password: SecretString,
+    new_password: SecretString,
 }
 
 /// Represents a secret
+/// The entry is shared by all filter levels and copies of the view,
+/// its secrets are zeroed once the last of them is dropped
 ///
 /// # Fields
-/// * `key` - The key
-/// * `value` - The value
+/// * `key` - The label of the entry, the domain followed by the username if any
+/// * `entry` - The entry
 /// * `last_suffix` - The last suffix
+///
+/// # Methods
+/// * `from_records` - Creates the secrets of the read only records
 #[derive(Debug, Clone, PartialEq)]
 struct Secret {
     key: String,
-    value: String,
+    entry: Rc<Entry>,
     last_suffix: String,
 }
 
@@ -193,30 +304,29 @@ impl Home {
     /// Creates a new `Home`
     ///
     /// # Arguments
-    /// * `user` - The user
+    /// * `session` - The unlocked vault of the user
     /// * `records` - The read only records
     /// * `position` - The position
     /// * `area` - The area
     ///
     /// # Returns
     /// A new `Home` view
-    pub fn new(user: User, records: ReadOnlyRecords, position: Position, area: Rect) -> Self {
+    pub fn new(
+        session: VaultSession,
+        records: ReadOnlyRecords,
+        position: Position,
+        area: Rect,
+    ) -> Self {
+        let all_secrets = Secret::from_records(records);
         let secrets = Secrets {
-            secrets: records
-                .records()
-                .iter()
-                .map(|x| Secret {
-                    key: x.0.clone(),
-                    value: x.1.clone(),
-                    last_suffix: x.0.clone(),
-                })
-                .collect(),
+            secrets: all_secrets.clone(),
             selected_secret: 0,
             shown_secrets: vec![],
         };
         let secrets = vec![secrets];
         Self {
-            user,
+            session,
+            all_secrets,
             secrets,
             position: Position {
                 offset_x: position.offset_x,
@@ -227,8 +337,13 @@ impl Home {
             cursor: 0,
             input_offset: 0,
             filter_value: "".to_string(),
-            new_secret: None,
+            new_entry: None,
             operation: None,
+            master_change: None,
+            ask_master_password: Config::load().unwrap_or_default().ask_master_password,
+            scope: Scope::All,
+            sidebar_selected: 0,
+            collapsed_folders: vec![],
         }
     }
 
@@ -236,12 +351,12 @@ impl Home {
     ///
     /// # Returns
     /// The input config for the popup
-    fn generate_input_config(&self) -> InputConfig {
+    fn generate_input_config(&self) -> InputConfig<'_> {
         InputConfig::new(
             self.state == HomeViewState::Filter,
             self.filter_value(),
             false,
-            "Filter".to_string(),
+            "Filter | tag:name folder:path".to_string(),
             if self.state == HomeViewState::Filter {
                 Some(self.cursor)
             } else {
@@ -256,8 +371,8 @@ impl Home {
     ///
     /// # Returns
     /// The input value
-    fn filter_value(&self) -> String {
-        self.filter_value.clone()
+    fn filter_value(&self) -> &str {
+        &self.filter_value
     }
 
     /// Generates the help text with all keybindings grouped by category
@@ -271,17 +386,36 @@ impl Home {
             "  k / Up       Move up",
             "  h / Left     Scroll left",
             "  l / Right    Scroll right",
+            "  Tab          Browse folders and tags",
+            "",
+            "FOLDERS AND TAGS:",
+            "  Enter        Show the secrets of the item",
+            "  Space        Collapse or expand a folder",
+            "  Tab / Esc    Back to the secrets",
             "",
             "ACTIONS:",
             "  a            Add new secret",
             "  d            Delete selected secret",
-            "  e            Edit selected secret",
-            "  c            Copy password to clipboard",
-            "  Enter        Toggle password visibility",
+            "  e            Show and edit selected secret",
+            "  c            Copy password to clipboard, or the",
+            "               number, note, key or ID number",
+            "  u            Copy username to clipboard",
+            "  t            Copy one-time code to clipboard",
+            "  H            Show and restore password history",
+            "  A            Manage file attachments",
+            "  m            Change master password",
+            "  Enter        Toggle secret visibility",
             "",
             "OTHER:",
             "  f            Enter filter/search mode",
-            "  x            Export secrets to CSV",
+            "               tag:name and folder:path narrow it",
+            "  x            Export an encrypted backup",
+            "  i            Import an encrypted backup",
+            "  K            Import from another password manager",
+            "               or from a pass password store",
+            "  P            Export secrets to a pass password store",
+            "  X            Export secrets to CSV or JSON in clear text",
+            "  s            Open settings",
             "  q            Quit application",
             "  ?            Show this help",
         ];
@@ -291,17 +425,17 @@ impl Home {
     /// Toggles the shown secret upwards to the root
     ///
     /// # Arguments
-    /// * `secret` - The secret
+    /// * `id` - The id of the entry of the secret
     /// * `is_shown` - The shown state
     ///
     /// # Panics
     /// If the secret is out of bounds
-    fn set_shown_upwards(&mut self, secret: String, is_shown: bool) {
+    fn set_shown_upwards(&mut self, id: String, is_shown: bool) {
         for i in 0..self.secrets.len() - 1 {
             let index = self.secrets[i]
                 .secrets
                 .iter()
-                .position(|x| x.key == secret)
+                .position(|x| x.entry.id == id)
                 .unwrap_or(0);
             if !is_shown {
                 self.secrets[i].shown_secrets.retain(|&x| x != index);
@@ -339,7 +473,7 @@ impl Home {
     ///
     /// # Panics
     /// If the secrets are out of bounds
-    fn fuzzy_filter(&mut self, previous_value: String, new_value: String) -> () {
+    fn fuzzy_filter(&mut self, previous_value: String, new_value: String) {
         let largest_prefix = self.largest_prefix(&previous_value, &new_value);
         let rest = new_value
             .chars()
@@ -348,11 +482,9 @@ impl Home {
 
         assert!(
             self.secrets.len() >= largest_prefix.len()
-                || self.secrets.last().unwrap().secrets.len() == 0,
+                || self.secrets.last().unwrap().secrets.is_empty(),
         );
-        for i in largest_prefix.len()..self.secrets.len() - 1 {
-            self.secrets.remove(i + 1);
-        }
+        self.secrets.truncate(largest_prefix.len() + 1);
 
         let mut current_secrets = self
             .secrets
@@ -378,10 +510,10 @@ impl Home {
                         .skip(1)
                         .collect::<String>();
 
-                    assert!(new_suffix.len() != secret.last_suffix.len() || new_suffix.len() == 0,);
+                    assert!(new_suffix.len() != secret.last_suffix.len() || new_suffix.is_empty(),);
                     let new_secret = Secret {
                         key: secret.key.clone(),
-                        value: secret.value.clone(),
+                        entry: secret.entry.clone(),
                         last_suffix: new_suffix,
                     };
                     new_secrets.push(new_secret);
@@ -390,19 +522,205 @@ impl Home {
                     }
                 }
             }
+            // a level is kept for every character, even once nothing matches,
+            // so the levels always line up with the filter
             self.secrets.push(Secrets {
                 secrets: new_secrets.clone(),
                 selected_secret: 0,
                 shown_secrets: shown_secrets.clone(),
             });
-            if new_secrets.is_empty() {
-                break;
-            }
             current_secrets = new_secrets;
             current_shown_secrets = shown_secrets;
         }
     }
 
+    /// Lists the secrets again from all secrets of the vault,
+    /// narrowed by the scope and the qualifiers of the filter
+    /// and fuzzy filtered by the rest of it. Shown secrets stay shown
+    fn refilter(&mut self) {
+        let (qualifiers, text) = parse_filter(&self.filter_value);
+        let root = &self.secrets[0];
+        let shown: Vec<String> = root
+            .shown_secrets
+            .iter()
+            .filter_map(|&index| root.secrets.get(index))
+            .map(|secret| secret.entry.id.clone())
+            .collect();
+
+        let secrets: Vec<Secret> = self
+            .all_secrets
+            .iter()
+            .filter(|secret| {
+                self.scope.matches(&secret.entry)
+                    && qualifiers.iter().all(|q| q.matches(&secret.entry))
+            })
+            .map(|secret| Secret {
+                key: secret.key.clone(),
+                entry: secret.entry.clone(),
+                last_suffix: secret.key.clone(),
+            })
+            .collect();
+        let shown_secrets = secrets
+            .iter()
+            .enumerate()
+            .filter(|(_, secret)| shown.contains(&secret.entry.id))
+            .map(|(index, _)| index)
+            .collect();
+        self.secrets = vec![Secrets {
+            secrets,
+            selected_secret: 0,
+            shown_secrets,
+        }];
+        self.fuzzy_filter(String::new(), text);
+    }
+
+    /// Returns the items of the sidebar
+    /// Every secret is listed first, then the folder tree and then the tags,
+    /// each with the number of secrets in it
+    ///
+    /// # Returns
+    /// The items of the sidebar
+    fn sidebar_items(&self) -> Vec<SidebarItem> {
+        let entries: Vec<&Entry> = self.all_secrets.iter().map(|s| s.entry.as_ref()).collect();
+        let mut items = vec![SidebarItem {
+            scope: Scope::All,
+            label: format!("All secrets ({})", entries.len()),
+            expandable: false,
+        }];
+
+        // folders are sorted by their parts, so subfolders follow their parent
+        let mut folders = BTreeSet::new();
+        for entry in &entries {
+            let folder = Entry::normalize_folder(&entry.folder);
+            if folder.is_empty() {
+                continue;
+            }
+            let parts: Vec<String> = folder.split('/').map(str::to_string).collect();
+            for depth in 1..=parts.len() {
+                folders.insert(parts[..depth].to_vec());
+            }
+        }
+        let is_below = |parts: &[String], folder: &[String]| {
+            parts.len() > folder.len() && parts.starts_with(folder)
+        };
+        for parts in &folders {
+            let path = parts.join("/");
+            let hidden = self.collapsed_folders.iter().any(|collapsed| {
+                let collapsed: Vec<String> = collapsed.split('/').map(str::to_string).collect();
+                is_below(parts, &collapsed)
+            });
+            if hidden {
+                continue;
+            }
+            let expandable = folders.iter().any(|other| is_below(other, parts));
+            let marker = if !expandable {
+                "  "
+            } else if self.collapsed_folders.contains(&path) {
+                "▸ "
+            } else {
+                "▾ "
+            };
+            let count = entries.iter().filter(|e| e.in_folder(&path)).count();
+            items.push(SidebarItem {
+                label: format!(
+                    "{}{}{} ({})",
+                    "  ".repeat(parts.len() - 1),
+                    marker,
+                    parts.last().unwrap(),
+                    count
+                ),
+                scope: Scope::Folder(path),
+                expandable,
+            });
+        }
+
+        // tags are grouped ignoring case, shown as first written
+        let mut tags: BTreeMap<String, (String, usize)> = BTreeMap::new();
+        for entry in &entries {
+            for tag in &entry.tags {
+                tags.entry(tag.to_lowercase())
+                    .or_insert_with(|| (tag.clone(), 0))
+                    .1 += 1;
+            }
+        }
+        for (tag, count) in tags.into_values() {
+            items.push(SidebarItem {
+                label: format!("#{} ({})", tag, count),
+                scope: Scope::Tag(tag),
+                expandable: false,
+            });
+        }
+        items
+    }
+
+    /// Returns whether any secret has a folder or tags
+    /// The sidebar is only shown if so
+    ///
+    /// # Returns
+    /// `true` if there is anything to organize the secrets by, otherwise `false`
+    fn has_sidebar(&self) -> bool {
+        self.all_secrets
+            .iter()
+            .any(|s| !s.entry.folder.is_empty() || !s.entry.tags.is_empty())
+    }
+
+    /// Returns the area of the list of secrets, next to the sidebar if it is shown
+    ///
+    /// # Arguments
+    /// * `area` - The area of the view
+    ///
+    /// # Returns
+    /// The area of the list
+    fn list_area(&self, area: Rect) -> Rect {
+        if !self.has_sidebar() || area.width < SIDEBAR_WIDTH + MIN_LIST_AREA_WIDTH {
+            return area;
+        }
+        Rect::new(
+            area.x + SIDEBAR_WIDTH,
+            area.y,
+            area.width - SIDEBAR_WIDTH,
+            area.height,
+        )
+    }
+
+    /// Handles a key while the sidebar is focused
+    /// Enter narrows the list to the selected item, space collapses or expands a folder
+    ///
+    /// # Arguments
+    /// * `key` - The key event
+    fn handle_sidebar_key(&mut self, key: &KeyEvent) {
+        let items = self.sidebar_items();
+        self.sidebar_selected = self.sidebar_selected.min(items.len() - 1);
+        let item = &items[self.sidebar_selected];
+
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down if self.sidebar_selected + 1 < items.len() => {
+                self.sidebar_selected += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up if self.sidebar_selected > 0 => {
+                self.sidebar_selected -= 1;
+            }
+            KeyCode::Enter => {
+                self.scope = item.scope.clone();
+                self.position.offset_y = 0;
+                self.refilter();
+            }
+            KeyCode::Char(' ') if item.expandable => {
+                if let Scope::Folder(path) = &item.scope {
+                    if self.collapsed_folders.contains(path) {
+                        self.collapsed_folders.retain(|f| f != path);
+                    } else {
+                        self.collapsed_folders.push(path.clone());
+                    }
+                }
+            }
+            KeyCode::Tab | KeyCode::Esc => {
+                self.state = HomeViewState::Normal;
+            }
+            _ => {}
+        }
+    }
+
     /// Moves the cursor up
     ///
     /// # Arguments
@@ -506,16 +824,17 @@ impl Home {
         );
 
         let selected_secret = self.secrets.last().unwrap().selected_secret;
-        let selecred_secret_domain = self.secrets.last().unwrap().secrets[selected_secret]
-            .key
+        let selected_secret_id = self.secrets.last().unwrap().secrets[selected_secret]
+            .entry
+            .id
             .clone();
         let mut shown_secrets = self.secrets.last().unwrap().shown_secrets.clone();
         if shown_secrets.contains(&selected_secret) {
             shown_secrets.retain(|&x| x != selected_secret);
-            self.set_shown_upwards(selecred_secret_domain, false);
+            self.set_shown_upwards(selected_secret_id, false);
         } else {
             shown_secrets.push(selected_secret);
-            self.set_shown_upwards(selecred_secret_domain, true);
+            self.set_shown_upwards(selected_secret_id, true);
         }
 
         self.secrets.last_mut().unwrap().shown_secrets = shown_secrets;
@@ -528,10 +847,10 @@ impl Home {
     ///
     /// # Returns
     /// A ascii separator
-    fn separator(&self, width: u16) -> Text {
+    fn separator(&self, width: u16) -> Text<'_> {
         let mut separator = String::new();
         for _ in 0..width {
-            separator.push_str("╍");
+            separator.push('╍');
         }
         Text::styled(
             separator,
@@ -549,19 +868,19 @@ impl Home {
     ///
     /// # Returns
     /// The current ascii secret with the cursor
-    fn current_secret_cursor(&self, height: u16, width: u16, index: u16, style: Style) -> Text {
+    fn current_secret_cursor(&self, height: u16, width: u16, index: u16, style: Style) -> Text<'_> {
         let mut cursor = String::new();
         for _ in 0..height {
             if self.secrets.last().unwrap().selected_secret == index as usize {
                 for _ in 0..width - 1 {
-                    cursor.push_str(">");
+                    cursor.push('>');
                 }
-                cursor.push_str("\n");
+                cursor.push('\n');
             } else {
                 for _ in 0..width - 1 {
-                    cursor.push_str(" ");
+                    cursor.push(' ');
                 }
-                cursor.push_str("\n");
+                cursor.push('\n');
             }
         }
 
@@ -591,9 +910,16 @@ impl Home {
     /// * `y_offset` - The y offset
     fn render_secrets(&self, buffer: &mut Buffer, cursor_offset: u16, y_offset: u16) {
         let mut y = y_offset;
-        let mut index = 0;
         let width = self.width();
-        for secret in self.secrets.last().unwrap().secrets.iter() {
+        if !self.has_secrets() && self.secrets.len() == 1 {
+            let text = Text::styled(
+                "No secrets yet, press a to add one",
+                Style::default().fg(from(COLOR_WHITE).unwrap_or(Color::White)),
+            );
+            text.render(Rect::new(cursor_offset, y, width, 1), buffer);
+            return;
+        }
+        for (index, secret) in self.secrets.last().unwrap().secrets.iter().enumerate() {
             let style = if self.secrets.last().unwrap().selected_secret == index {
                 Style::default()
                     .bg(from(COLOR_WHITE).unwrap_or(Color::White))
@@ -610,18 +936,24 @@ impl Home {
             } else {
                 cursor.render(Rect::new(0, y, cursor_offset, 3), buffer);
             }
-            let text = if self.secrets.last().unwrap().shown_secrets.contains(&index) {
-                format!("\n  {} : {}", secret.key, secret.value)
-            } else {
-                "\n".to_string() + &hidden_value(secret.key.to_string(), secret.value.len())
-            };
+            let shown = self.secrets.last().unwrap().shown_secrets.contains(&index);
+            let mut text = format!(
+                "\n  {} : {}",
+                secret.key,
+                entry_value(&secret.entry, shown).expose_secret()
+            );
+            if self.secrets.last().unwrap().selected_secret == index {
+                if let Some(code) = totp_text(&secret.entry, chrono::Utc::now().timestamp()) {
+                    text.push('\n');
+                    text.push_str(&code);
+                }
+            }
             let text = Text::styled(text, style);
             text.render(Rect::new(cursor_offset, y, width, 3), buffer);
             y += 3;
             let separator = self.separator(buffer.area().width);
             separator.render(Rect::new(cursor_offset, y, width, 1), buffer);
             y += 1;
-            index += 1;
         }
     }
 
@@ -636,7 +968,7 @@ impl Home {
     /// # Returns
     /// The cursor offset
     fn render_header(&self, buffer: &mut Buffer, area: Rect, cursor_offset: u16) -> u16 {
-        let mut username = self.user.username().clone();
+        let mut username = self.session.username();
         username.truncate(MAX_ENTRY_LENGTH as usize - cursor_offset as usize - "Welcome ".len());
         let text = " ".repeat(cursor_offset as usize) + "Welcome " + username.as_str();
         let header = Text::styled(
@@ -746,44 +1078,526 @@ impl Home {
         index * DOMAIN_PASSWORD_LIST_ITEM_HEIGHT + 1 + self.header_height() + self.legend_height()
     }
 
-    /// Exports secrets to csv file
+    /// Returns the unlocked vault of the user
+    ///
+    /// # Returns
+    /// The unlocked vault
+    pub fn session_mut(&mut self) -> &mut VaultSession {
+        &mut self.session
+    }
+
+    /// Reads the settings again after they were changed
+    pub fn reload_config(&mut self) {
+        self.ask_master_password = Config::load().unwrap_or_default().ask_master_password;
+    }
+
+    /// Replaces the secrets with freshly loaded ones
+    /// The filter is reset, since it refers to the previous secrets
+    ///
+    /// # Arguments
+    /// * `records` - The reloaded records
+    fn reload(&mut self, records: ReadOnlyRecords) {
+        let reloaded = Home::new(
+            self.session.clone(),
+            records,
+            self.position.clone(),
+            self.area,
+        );
+        self.all_secrets = reloaded.all_secrets;
+        self.secrets = reloaded.secrets;
+        self.scope = Scope::All;
+        self.sidebar_selected = 0;
+        self.state = HomeViewState::Normal;
+        self.cursor = 0;
+        self.input_offset = 0;
+        self.filter_value = "".to_string();
+    }
+
+    /// Reports a failed operation
+    /// If the vault was changed by another process, the user is offered
+    /// to reload it instead of overwriting the other changes
+    ///
+    /// # Arguments
+    /// * `app` - The application
+    /// * `error` - The error returned by the operation
+    /// * `message` - The message to show for any other error
+    ///
+    /// # Returns
+    /// The updated application
+    fn operation_error(
+        &mut self,
+        app: Application,
+        error: KrabError,
+        message: &str,
+    ) -> Application {
+        let mut app = app.clone();
+        if error == KrabError::Conflict {
+            self.operation = Some(Operation::Reload);
+            app.state = ViewState::Home(Box::new(self.clone()));
+            app.mutable_app_state
+                .popups
+                .push(Box::new(Confirm::new(
+                    "The vault was changed by another krab\ninstance, your change was not saved.\n\nReload the vault?"
+                        .to_string(),
+                )));
+        } else {
+            app.mutable_app_state
+                .popups
+                .push(Box::new(MessagePopup::from_error(message, &error)));
+        }
+        app
+    }
+
+    /// Confirms the operation before performing it
+    /// Changes are confirmed with the master password if configured so,
+    /// otherwise only removing a secret asks for a confirmation
+    ///
+    /// # Arguments
+    /// * `app` - The application
+    ///
+    /// # Returns
+    /// The updated application
+    fn confirm_operation(&mut self, app: Application) -> Application {
+        let mut app = app.clone();
+        if self.ask_master_password {
+            app.state = ViewState::Home(Box::new(self.clone()));
+            app.mutable_app_state
+                .popups
+                .push(Box::new(InsertMaster::new()));
+            return app;
+        }
+
+        match self.operation {
+            Some(Operation::Remove) => {
+                let current_secret = self.current_secret();
+                app.state = ViewState::Home(Box::new(self.clone()));
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(Confirm::new(format!(
+                        "Remove the secret for\n{}?",
+                        current_secret.key
+                    ))));
+                app
+            }
+            Some(Operation::RemoveAttachment(index)) => {
+                let current_secret = self.current_secret();
+                app.state = ViewState::Home(Box::new(self.clone()));
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(Confirm::new(format!(
+                        "Remove the attachment\n{}?",
+                        current_secret.entry.attachments[index].name
+                    ))));
+                app
+            }
+            Some(Operation::Import(ref backup)) => {
+                let message = format!(
+                    "Import {} secrets from\nthe backup of {}?",
+                    backup.entries.len(),
+                    backup.username
+                );
+                app.state = ViewState::Home(Box::new(self.clone()));
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(Confirm::new(message)));
+                app
+            }
+            _ => self.perform_operation(app),
+        }
+    }
+
+    /// Performs the operation on the vault
+    ///
+    /// # Arguments
+    /// * `app` - The application
     ///
     /// # Returns
-    /// Error if something went wrong
-    fn export_csv(&self) -> Result<(), String> {
-        let user_dirs = UserDirs::new().ok_or_else(|| "Could not create user dirs".to_string())?;
+    /// The updated application
+    fn perform_operation(&mut self, app: Application) -> Application {
+        let (res, message) = match self.operation.clone() {
+            None => {
+                unreachable!();
+            }
+            Some(Operation::Add) => {
+                let new_entry = self.new_entry.take().unwrap();
+                (self.session.add_record(&new_entry), "Cannot create record")
+            }
+            Some(Operation::Remove) => {
+                let current_secret = self.current_secret();
+                (
+                    self.session.remove_record(&current_secret.entry.id),
+                    "Cannot remove record",
+                )
+            }
+            Some(Operation::Modify) => {
+                let current_secret = self.current_secret();
+                let new_entry = self.new_entry.take().unwrap();
+                (
+                    self.session
+                        .modify_record(&current_secret.entry.id, &new_entry),
+                    "Cannot modify record",
+                )
+            }
+            Some(Operation::Restore(index)) => {
+                let current_secret = self.current_secret();
+                (
+                    self.session
+                        .restore_password(&current_secret.entry.id, index),
+                    "Cannot restore the password",
+                )
+            }
+            Some(Operation::Attach(path)) => {
+                let current_secret = self.current_secret();
+                (
+                    read_attachment_file(&path).and_then(|(name, content)| {
+                        self.session
+                            .add_attachment(&current_secret.entry.id, &name, &content)
+                    }),
+                    "Cannot attach the file",
+                )
+            }
+            Some(Operation::RemoveAttachment(index)) => {
+                let current_secret = self.current_secret();
+                (
+                    self.session.remove_attachment(
+                        &current_secret.entry.id,
+                        &current_secret.entry.attachments[index].id,
+                    ),
+                    "Cannot remove the attachment",
+                )
+            }
+            Some(Operation::Reload) => {
+                let mut app = app.clone();
+                match self.session.reload() {
+                    Ok(records) => {
+                        self.reload(records);
+                        app.state = ViewState::Home(Box::new(self.clone()));
+                    }
+                    Err(e) => {
+                        app.mutable_app_state
+                            .popups
+                            .push(Box::new(MessagePopup::from_error(
+                                "Cannot reload the vault",
+                                &e,
+                            )));
+                    }
+                }
+                return app;
+            }
+            Some(Operation::Export(path, passphrase)) => {
+                return match self.export_backup(&path, &passphrase) {
+                    Ok(count) => {
+                        let mut app = app.clone();
+                        app.state = ViewState::Home(Box::new(self.clone()));
+                        app.mutable_app_state
+                            .popups
+                            .push(Box::new(MessagePopup::with_detail(
+                                &format!("Backup of {} secrets exported to", count),
+                                &path.display().to_string(),
+                            )));
+                        app
+                    }
+                    Err(e) => self.operation_error(app, e, "Cannot export the backup"),
+                };
+            }
+            Some(Operation::Import(backup)) => {
+                return match self.session.import_backup(&backup) {
+                    Ok((records, imported)) => {
+                        self.update_secrets(records);
+                        let mut app = app.clone();
+                        app.state = ViewState::Home(Box::new(self.clone()));
+                        app.mutable_app_state
+                            .popups
+                            .push(Box::new(MessagePopup::new(format!(
+                                "Imported {} secrets\n\n{} skipped, they are\nalready in the vault",
+                                imported,
+                                backup.entries.len() - imported
+                            ))));
+                        app
+                    }
+                    Err(e) => self.operation_error(app, e, "Cannot import the backup"),
+                };
+            }
+            Some(Operation::ImportEntries(entries, skipped, policy)) => {
+                return match self.session.import_records(&entries, policy) {
+                    Ok((records, counts)) => {
+                        self.update_secrets(records);
+                        let mut app = app.clone();
+                        app.state = ViewState::Home(Box::new(self.clone()));
+                        app.mutable_app_state
+                            .popups
+                            .push(Box::new(MessagePopup::new(format!(
+                                "Imported {} secrets\n{} replaced\n\n{} duplicates and\n{} items skipped",
+                                counts.added,
+                                counts.replaced,
+                                counts.duplicates,
+                                skipped
+                            ))));
+                        app
+                    }
+                    Err(e) => self.operation_error(app, e, "Cannot import the secrets"),
+                };
+            }
+            Some(Operation::ExportPass(path)) => {
+                let mut app = app.clone();
+                let popup = match self.export_pass(&path) {
+                    Ok(export) => MessagePopup::with_detail(
+                        &format!(
+                            "{} secrets exported and\n{} skipped, to",
+                            export.written,
+                            export.skipped.len()
+                        ),
+                        &path.display().to_string(),
+                    ),
+                    Err(e) => MessagePopup::with_detail(
+                        "Cannot export to the password store",
+                        &e.to_string(),
+                    ),
+                };
+                app.state = ViewState::Home(Box::new(self.clone()));
+                app.mutable_app_state.popups.push(Box::new(popup));
+                return app;
+            }
+            Some(Operation::ExportPlaintext(path, options)) => {
+                let mut app = app.clone();
+                let popup = match self.export_plaintext(&path, &options) {
+                    Ok(count) => MessagePopup::with_detail(
+                        &format!("{} secrets exported to", count),
+                        &path.display().to_string(),
+                    ),
+                    Err(e) => {
+                        MessagePopup::with_detail("Cannot export the secrets", &e.to_string())
+                    }
+                };
+                app.state = ViewState::Home(Box::new(self.clone()));
+                app.mutable_app_state.popups.push(Box::new(popup));
+                return app;
+            }
+            Some(Operation::ChangeMaster) => {
+                let master_change = self.master_change.take().unwrap();
+                let res = self.session.change_master_password(
+                    master_change.current_password.expose_secret(),
+                    master_change.new_password.expose_secret(),
+                );
+                return match res {
+                    Ok(()) => {
+                        let mut app = app.clone();
+                        app.state = ViewState::Home(Box::new(self.clone()));
+                        app.mutable_app_state
+                            .popups
+                            .push(Box::new(MessagePopup::new(
+                                "Master password changed".to_string(),
+                            )));
+                        app
+                    }
+                    Err(KrabError::WrongCredentials) => {
+                        let mut app = app.clone();
+                        app.state = ViewState::Home(Box::new(self.clone()));
+                        app.mutable_app_state
+                            .popups
+                            .push(Box::new(MessagePopup::new(
+                                "Wrong master password".to_string(),
+                            )));
+                        app
+                    }
+                    Err(e) => self.operation_error(app, e, "Cannot change master password"),
+                };
+            }
+        };
 
-        let download_dir = user_dirs
-            .download_dir()
-            .ok_or_else(|| "Could not find download dir".to_string())?;
+        let records = match res {
+            Ok(records) => records,
+            Err(e) => return self.operation_error(app, e, message),
+        };
+
+        self.update_secrets(records);
+
+        let mut app = app.clone();
+        app.state = ViewState::Home(Box::new(self.clone()));
+        if matches!(
+            self.operation,
+            Some(Operation::Attach(_)) | Some(Operation::RemoveAttachment(_))
+        ) {
+            // back to the changed list of attachments
+            app.mutable_app_state
+                .popups
+                .push(Box::new(Attachments::new(&self.current_secret().entry)));
+        }
+        app
+    }
+
+    /// Replaces the listed secrets after a change
+    /// The selected secret stays selected if it is still listed,
+    /// a scope without secrets left is dropped
+    ///
+    /// # Arguments
+    /// * `records` - The changed records
+    fn update_secrets(&mut self, records: ReadOnlyRecords) {
+        let previous = self.secrets.last().unwrap();
+        let selected_secret = previous.selected_secret;
+        let selected_id = previous
+            .secrets
+            .get(selected_secret)
+            .map(|secret| secret.entry.id.clone());
+
+        self.all_secrets = Secret::from_records(records);
+        if !self
+            .all_secrets
+            .iter()
+            .any(|s| self.scope.matches(&s.entry))
+        {
+            self.scope = Scope::All;
+        }
+        self.refilter();
 
-        let now = chrono::Local::now();
-        let formatted_date = now.format("%Y-%m-%d-%H-%M-%S").to_string();
-        let filename = format!("krab-secrets-{}.csv", formatted_date);
-        let file_path = download_dir.join(filename);
+        let secrets = self.secrets.last_mut().unwrap();
+        let count = secrets.secrets.len();
+        secrets.selected_secret = secrets
+            .secrets
+            .iter()
+            .position(|secret| Some(&secret.entry.id) == selected_id.as_ref())
+            .unwrap_or(selected_secret.min(count.saturating_sub(1)));
+    }
 
-        let mut result = "domain,password\n".to_string();
-        if let Some(root_secrets) = self.secrets.first() {
-            for secret in &root_secrets.secrets {
-                result.push_str(&format!("{},{}\n", secret.key, secret.value));
+    /// Records that the selected secret was used
+    ///
+    /// # Arguments
+    /// * `app` - The application
+    ///
+    /// # Returns
+    /// The updated application
+    fn mark_used(&mut self, app: Application) -> Application {
+        let current_secret = self.current_secret();
+        match self.session.mark_used(&current_secret.entry.id) {
+            Ok(records) => {
+                self.update_secrets(records);
+                app
             }
+            Err(e) => self.operation_error(app, e, "Cannot save when the secret was used"),
         }
+    }
+
+    /// Returns whether there are secrets to select
+    ///
+    /// # Returns
+    /// `true` if at least one secret is listed, otherwise `false`
+    fn has_secrets(&self) -> bool {
+        !self.secrets.last().unwrap().secrets.is_empty()
+    }
 
-        std::fs::write(file_path, result).map_err(|e| e.to_string())?;
+    /// Returns the selected secret
+    ///
+    /// # Returns
+    /// The selected secret
+    fn current_secret(&self) -> Secret {
+        let secrets = self.secrets.last().unwrap();
+        secrets.secrets[secrets.selected_secret].clone()
+    }
 
+    /// Extracts an attachment of the selected secret to a new file
+    /// The file is only readable by the user, an existing file is never overwritten
+    ///
+    /// # Arguments
+    /// * `index` - The index of the attachment
+    /// * `path` - The path of the new file
+    ///
+    /// # Returns
+    /// An error if the attachment could not be read or written
+    fn extract_attachment(&self, index: usize, path: &Path) -> Result<(), KrabError> {
+        let current_secret = self.current_secret();
+        let content = self
+            .session
+            .read_attachment(&current_secret.entry.attachments[index])?;
+        write_private_file(path, &content)?;
         Ok(())
     }
+
+    /// Exports an encrypted backup of the vault to a new file
+    /// The backup is sealed with the key derivation parameters and the cipher of the vault
+    ///
+    /// # Arguments
+    /// * `path` - The path of the new file
+    /// * `passphrase` - The export passphrase
+    ///
+    /// # Returns
+    /// The number of exported secrets or an error
+    fn export_backup(
+        &mut self,
+        path: &Path,
+        passphrase: &SecretString,
+    ) -> Result<usize, KrabError> {
+        let backup = self.session.export_backup()?;
+        let bundle = backup.seal(
+            passphrase.expose_secret(),
+            &self.session.kdf(),
+            self.session.cipher(),
+        )?;
+        write_private_file(path, &bundle)?;
+        Ok(backup.entries.len())
+    }
+
+    /// Exports the secrets in clear text
+    /// Only done after an explicit confirmation, the file is only readable by the user
+    ///
+    /// # Arguments
+    /// * `path` - The path of the new file
+    /// * `options` - The format and the fields of the export
+    ///
+    /// # Returns
+    /// The number of exported secrets or an error
+    fn export_plaintext(&self, path: &Path, options: &ExportOptions) -> io::Result<usize> {
+        let entries: Vec<Entry> = self
+            .all_secrets
+            .iter()
+            .map(|secret| secret.entry.as_ref().clone())
+            .collect();
+        export::export(path, &entries, options)?;
+        Ok(entries.len())
+    }
+
+    /// Exports the secrets to a password store of pass, encrypted with gpg
+    ///
+    /// # Arguments
+    /// * `path` - The root of the password store
+    ///
+    /// # Returns
+    /// The number of exported and the skipped secrets or an error
+    fn export_pass(&self, path: &Path) -> Result<PassExport, PassError> {
+        let entries: Vec<Entry> = self
+            .all_secrets
+            .iter()
+            .map(|secret| secret.entry.as_ref().clone())
+            .collect();
+        PasswordStore::new(path).export(&entries)
+    }
 }
 
 impl View for Home {
     fn render(&self, f: &mut Frame, app: &Application, area: Rect) {
-        match app.immutable_app_state.rect {
-            Some(_) => {
-                let mut buffer = f.buffer_mut();
-                let buffer_to_render = self.buffer_to_render();
-                ScrollView::render(&mut buffer, &self.position, area, &buffer_to_render);
+        if app.immutable_app_state.rect.is_some() {
+            let buffer = f.buffer_mut();
+            let buffer_to_render = self.buffer_to_render();
+            let list_area = self.list_area(area);
+            if list_area != area {
+                let sidebar_items = self.sidebar_items();
+                let active = sidebar_items
+                    .iter()
+                    .position(|item| item.scope == self.scope);
+                let items: Vec<String> = sidebar_items.into_iter().map(|item| item.label).collect();
+                let config = SidebarConfig::new(
+                    SIDEBAR_TITLE,
+                    &items,
+                    self.sidebar_selected,
+                    active,
+                    self.state == HomeViewState::Sidebar,
+                );
+                Sidebar::render(
+                    buffer,
+                    Rect::new(area.x, area.y, SIDEBAR_WIDTH, area.height),
+                    &config,
+                );
             }
-            None => {}
+            ScrollView::render(buffer, &self.position, list_area, &buffer_to_render);
         }
     }
 
@@ -797,84 +1611,142 @@ impl View for Home {
                     app.state = ViewState::Login(Login::new(&app.immutable_app_state.db_path));
                     change_state = true;
                 }
-                KeyCode::Char('j') | KeyCode::Down => {
+                KeyCode::Char('j') | KeyCode::Down if self.has_secrets() => {
                     self.down(app.immutable_app_state.rect.unwrap());
                 }
                 KeyCode::Char('k') | KeyCode::Up => {
                     self.up(app.immutable_app_state.rect.unwrap());
                 }
-                KeyCode::Char('h') | KeyCode::Left => {
-                    if self.position.offset_x != 0 {
-                        self.position.offset_x -= 1;
-                    }
+                KeyCode::Char('h') | KeyCode::Left if self.position.offset_x != 0 => {
+                    self.position.offset_x -= 1;
                 }
-                KeyCode::Char('l') | KeyCode::Right => {
+                KeyCode::Char('l') | KeyCode::Right
                     if !ScrollView::check_if_width_out_of_bounds(
                         &self.position,
                         &self.buffer_to_render(),
-                        app.immutable_app_state.rect.unwrap_or(self.area),
-                    ) {
-                        self.position.offset_x += 1;
-                    }
+                        self.list_area(app.immutable_app_state.rect.unwrap_or(self.area)),
+                    ) =>
+                {
+                    self.position.offset_x += 1;
                 }
-                KeyCode::Enter => {
+                KeyCode::Enter if self.has_secrets() => {
                     self.toggle_shown_secret();
                 }
                 KeyCode::Char('a') => {
                     app.mutable_app_state
                         .popups
-                        .push(Box::new(InsertDomainPassword::new()));
+                        .push(Box::new(EntryDetail::new()));
                     self.operation = Some(Operation::Add);
                 }
-                KeyCode::Char('d') => {
-                    app.mutable_app_state
-                        .popups
-                        .push(Box::new(InsertMaster::new()));
+                KeyCode::Char('d') if self.has_secrets() => {
                     self.operation = Some(Operation::Remove);
+                    app = self.confirm_operation(app);
+                    change_state = true;
                 }
-                KeyCode::Char('e') => {
+                KeyCode::Char('e') if self.has_secrets() => {
+                    let current_secret = self.current_secret();
                     app.mutable_app_state
                         .popups
-                        .push(Box::new(InsertPassword::new(
-                            self.secrets.last().unwrap().secrets
-                                [self.secrets.last().unwrap().selected_secret]
-                                .key
-                                .clone(),
+                        .push(Box::new(EntryDetail::edit(
+                            current_secret.entry.as_ref().clone(),
                         )));
                     self.operation = Some(Operation::Modify);
                 }
-                KeyCode::Char('c') => {
-                    let current_secret = self
-                        .secrets
-                        .last()
-                        .unwrap()
-                        .secrets
-                        .get(self.secrets.last().unwrap().selected_secret)
-                        .unwrap();
-                    let mut ctx: ClipboardContext = ClipboardProvider::new().unwrap();
-                    ctx.set_contents(current_secret.value.clone()).unwrap();
+                KeyCode::Char('c') if self.has_secrets() => {
+                    let current_secret = self.current_secret();
+                    let value = copied_value(&current_secret.entry);
+                    if !value.is_empty() {
+                        let mut ctx: ClipboardContext = ClipboardProvider::new().unwrap();
+                        ctx.set_contents(value.expose_secret().to_string()).unwrap();
+                        app = self.mark_used(app);
+                    }
+                }
+                KeyCode::Char('u') if self.has_secrets() => {
+                    let current_secret = self.current_secret();
+                    if !current_secret.entry.username.is_empty() {
+                        let mut ctx: ClipboardContext = ClipboardProvider::new().unwrap();
+                        ctx.set_contents(current_secret.entry.username.clone())
+                            .unwrap();
+                        app = self.mark_used(app);
+                    }
+                }
+                KeyCode::Char('t') if self.has_secrets() => {
+                    let current_secret = self.current_secret();
+                    if !current_secret.entry.totp.is_empty() {
+                        match Totp::parse(current_secret.entry.totp.expose_secret()) {
+                            Ok(totp) => {
+                                let mut ctx: ClipboardContext = ClipboardProvider::new().unwrap();
+                                ctx.set_contents(totp.code(chrono::Utc::now().timestamp()))
+                                    .unwrap();
+                                app = self.mark_used(app);
+                            }
+                            Err(e) => {
+                                app.mutable_app_state.popups.push(Box::new(
+                                    MessagePopup::with_detail(
+                                        "Cannot copy the code",
+                                        &e.to_string(),
+                                    ),
+                                ));
+                            }
+                        }
+                    }
+                }
+                KeyCode::Char('A') if self.has_secrets() => {
+                    let current_secret = self.current_secret();
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(Attachments::new(&current_secret.entry)));
+                }
+                KeyCode::Char('H') if self.has_secrets() => {
+                    let current_secret = self.current_secret();
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(History::new(&current_secret.entry)));
                 }
                 KeyCode::Char('f') => {
                     self.state = HomeViewState::Filter;
                 }
-                KeyCode::Char('x') => match self.export_csv() {
-                    Ok(_) => {
-                        app.mutable_app_state
-                            .popups
-                            .push(Box::new(MessagePopup::new(
-                                "Secrets exported\nsuccessfully to the\ndownloads folder"
-                                    .to_string(),
-                            )));
-                    }
-                    Err(e) => {
-                        app.mutable_app_state
-                            .popups
-                            .push(Box::new(MessagePopup::new(format!(
-                                "Failed to export secrets: {}",
-                                e
-                            ))));
-                    }
-                },
+                KeyCode::Tab
+                    if self.list_area(app.immutable_app_state.rect.unwrap_or(self.area))
+                        != app.immutable_app_state.rect.unwrap_or(self.area) =>
+                {
+                    self.state = HomeViewState::Sidebar;
+                }
+                KeyCode::Char('s') => {
+                    app.state = ViewState::Settings(Settings::with_vault(self.clone()));
+                    change_state = true;
+                }
+                KeyCode::Char('m') => {
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(ChangeMaster::new()));
+                    self.operation = Some(Operation::ChangeMaster);
+                }
+                KeyCode::Char('x') => {
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(BackupPopup::new(BackupMode::Export)));
+                }
+                KeyCode::Char('i') => {
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(BackupPopup::new(BackupMode::Import)));
+                }
+                KeyCode::Char('K') => {
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(BackupPopup::new(BackupMode::Foreign)));
+                }
+                KeyCode::Char('P') => {
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(BackupPopup::new(BackupMode::Pass)));
+                }
+                KeyCode::Char('X') => {
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(ExportPopup::new()));
+                }
                 KeyCode::Char('?') => {
                     let rect = app.immutable_app_state.rect.unwrap_or(self.area);
                     // Calculate popup size: use 80% of screen width and height, but cap at reasonable maximums
@@ -903,13 +1775,20 @@ impl View for Home {
                     self.cursor = cursor_position;
                     self.input_offset = input_offset;
 
-                    self.fuzzy_filter(previous_value, self.filter_value.clone());
+                    let (previous_qualifiers, previous_text) = parse_filter(&previous_value);
+                    let (qualifiers, text) = parse_filter(&self.filter_value);
+                    if qualifiers == previous_qualifiers {
+                        self.fuzzy_filter(previous_text, text);
+                    } else {
+                        self.refilter();
+                    }
                 }
             },
+            HomeViewState::Sidebar => self.handle_sidebar_key(key),
         }
 
         if !change_state {
-            app.state = ViewState::Home(self.clone());
+            app.state = ViewState::Home(Box::new(self.clone()));
         }
 
         app
@@ -923,52 +1802,77 @@ impl View for Home {
         (40, 40)
     }
 
-    fn handle_insert_record_popup(
-        &mut self,
-        app: Application,
-        popup: Box<dyn Popup>,
-    ) -> Application {
-        let domain: String;
-        let password: String;
-        let insert_password = popup.downcast::<InsertDomainPassword>();
-
-        match insert_password {
-            Ok(insert_password) => {
-                if insert_password.exit_state() == Some(InsertDomainPasswordExitState::Quit) {
-                    return app;
-                }
-                domain = insert_password.domain();
-                password = insert_password.password();
-            }
+    fn handle_entry_popup(&mut self, app: Application, popup: Box<dyn Popup>) -> Application {
+        let entry_detail = match popup.downcast::<EntryDetail>() {
+            Ok(entry_detail) => entry_detail,
             Err(_) => {
                 unreachable!();
             }
+        };
+
+        if entry_detail.exit_state() != Some(EntryDetailExitState::Confirm) {
+            return app;
         }
 
-        if domain.is_empty() || password.is_empty() {
+        let mut entry = entry_detail.entry().clone();
+        entry
+            .fields
+            .retain(|field| !field.name.is_empty() || !field.value.is_empty());
+
+        if let EntryKind::SshKey(key) = &mut entry.kind {
+            let path = entry_detail.private_key_file();
+            if !path.is_empty() {
+                match read_ssh_key(Path::new(path)) {
+                    Ok(read) => *key = read,
+                    Err(detail) => {
+                        let mut app = app.clone();
+                        app.mutable_app_state
+                            .popups
+                            .push(Box::new(MessagePopup::with_detail(
+                                "Cannot read the private key",
+                                &detail,
+                            )));
+                        return app;
+                    }
+                }
+            }
+        }
+
+        if let Err(e) = entry.validate() {
+            let mut app = app.clone();
+            app.mutable_app_state
+                .popups
+                .push(Box::new(MessagePopup::with_detail(
+                    "Cannot save the secret",
+                    &e.to_string(),
+                )));
+            return app;
+        }
+        if entry.fields.iter().any(|field| field.name.is_empty()) {
             let mut app = app.clone();
             app.mutable_app_state
                 .popups
                 .push(Box::new(MessagePopup::new(
-                    "Cannot create record".to_string(),
+                    "Every field needs a name".to_string(),
                 )));
             return app;
         }
+        if !entry.totp.is_empty() {
+            if let Err(e) = Totp::parse(entry.totp.expose_secret()) {
+                let mut app = app.clone();
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(MessagePopup::with_detail(
+                        "Invalid TOTP secret",
+                        &e.to_string(),
+                    )));
+                return app;
+            }
+        }
 
-        self.new_secret = Some(NewSecret {
-            domain: domain.clone(),
-            password: password.clone(),
-        });
-
-        let mut app = app.clone();
-
-        app.state = ViewState::Home(self.clone());
-
-        app.mutable_app_state
-            .popups
-            .push(Box::new(InsertMaster::new()));
+        self.new_entry = Some(entry);
 
-        app
+        self.confirm_operation(app)
     }
 
     fn handle_insert_master_popup(
@@ -976,218 +1880,333 @@ impl View for Home {
         app: Application,
         popup: Box<dyn Popup>,
     ) -> Application {
-        let master_password: String;
         let insert_master = popup.downcast::<InsertMaster>();
 
-        match insert_master {
+        let master_password = match insert_master {
             Ok(insert_master) => {
                 if insert_master.exit_state() == Some(InsertMasterExitState::Quit) {
                     return app;
                 }
-                master_password = insert_master.master();
+                insert_master.master().clone()
             }
             Err(_) => {
                 unreachable!();
             }
-        }
+        };
 
-        if master_password.is_empty() {
+        if !self
+            .session
+            .verify_master_password(master_password.expose_secret())
+        {
             let mut app = app.clone();
             app.mutable_app_state
                 .popups
                 .push(Box::new(MessagePopup::new(
-                    "Cannot create record".to_string(),
+                    "Wrong master password".to_string(),
                 )));
             return app;
         }
 
-        match self.operation {
-            None => {
+        self.perform_operation(app)
+    }
+
+    fn handle_confirm_popup(&mut self, app: Application, popup: Box<dyn Popup>) -> Application {
+        let confirm = match popup.downcast::<Confirm>() {
+            Ok(confirm) => confirm,
+            Err(_) => {
                 unreachable!();
             }
-            Some(Operation::Add) => {
-                let config = RecordOperationConfig::new(
-                    &self.user.username(),
-                    &master_password,
-                    &self.new_secret.clone().unwrap().domain,
-                    &self.new_secret.clone().unwrap().password,
-                    &app.immutable_app_state.db_path,
-                );
-
-                let res = self.user.add_record(config);
-
-                if res.is_err() {
-                    let mut app = app.clone();
-                    app.mutable_app_state
-                        .popups
-                        .push(Box::new(MessagePopup::new(
-                            "Cannot create record".to_string(),
-                        )));
-                    return app;
-                }
+        };
 
-                let secrets = self.secrets.last_mut().unwrap();
-                secrets.secrets = res
-                    .unwrap()
-                    .records()
-                    .iter()
-                    .map(|x| Secret {
-                        key: x.0.clone(),
-                        value: x.1.clone(),
-                        last_suffix: x.0.clone(),
-                    })
-                    .collect();
-                secrets.selected_secret = secrets.selected_secret;
-                secrets.shown_secrets = secrets.shown_secrets.clone();
+        if confirm.exit_state() != Some(ConfirmExitState::Confirm) {
+            return app;
+        }
 
-                let mut app = app.clone();
-                app.state = ViewState::Home(self.clone());
-                app
+        match self.operation {
+            // confirmed before the master password is asked for
+            Some(Operation::ExportPlaintext(..)) | Some(Operation::Export(..)) => {
+                self.confirm_operation(app)
             }
-            Some(Operation::Remove) => {
-                let current_secret = self
-                    .secrets
-                    .last()
-                    .unwrap()
-                    .secrets
-                    .get(self.secrets.last().unwrap().selected_secret)
-                    .unwrap();
-
-                let config = RecordOperationConfig::new(
-                    &self.user.username(),
-                    &master_password,
-                    &current_secret.key,
-                    "",
-                    &app.immutable_app_state.db_path,
-                );
+            _ => self.perform_operation(app),
+        }
+    }
 
-                let res = self.user.remove_record(config);
+    fn handle_change_master_popup(
+        &mut self,
+        app: Application,
+        popup: Box<dyn Popup>,
+    ) -> Application {
+        let change_master = match popup.downcast::<ChangeMaster>() {
+            Ok(change_master) => change_master,
+            Err(_) => {
+                unreachable!();
+            }
+        };
 
-                if res.is_err() {
-                    let mut app = app.clone();
-                    app.mutable_app_state
-                        .popups
-                        .push(Box::new(MessagePopup::new(
-                            "Cannot remove record".to_string(),
-                        )));
-                    return app;
-                }
+        if change_master.exit_state() != Some(ChangeMasterExitState::Confirm) {
+            return app;
+        }
 
-                let secrets = self.secrets.last_mut().unwrap();
-                secrets.secrets = res
-                    .unwrap()
-                    .records()
-                    .iter()
-                    .map(|x| Secret {
-                        key: x.0.clone(),
-                        value: x.1.clone(),
-                        last_suffix: x.0.clone(),
-                    })
-                    .collect();
-                secrets.selected_secret = secrets.selected_secret;
-                secrets.shown_secrets = secrets.shown_secrets.clone();
+        let message = if change_master.new_password().is_empty() {
+            Some("Master password\ncannot be empty")
+        } else if change_master.new_password() != change_master.confirm_password() {
+            Some("Master passwords\ndo not match")
+        } else {
+            None
+        };
+        if let Some(message) = message {
+            let mut app = app.clone();
+            app.mutable_app_state
+                .popups
+                .push(Box::new(MessagePopup::new(message.to_string())));
+            return app;
+        }
 
-                let mut app = app.clone();
-                app.state = ViewState::Home(self.clone());
-                app
-            }
-            Some(Operation::Modify) => {
-                let current_secret = self
-                    .secrets
-                    .last()
-                    .unwrap()
-                    .secrets
-                    .get(self.secrets.last().unwrap().selected_secret)
-                    .unwrap();
-
-                let config = RecordOperationConfig::new(
-                    &self.user.username(),
-                    &master_password,
-                    &current_secret.key,
-                    &self.new_secret.clone().unwrap().password,
-                    &app.immutable_app_state.db_path,
-                );
+        self.master_change = Some(MasterChange {
+            current_password: change_master.current_password().clone(),
+            new_password: change_master.new_password().clone(),
+        });
 
-                let res = self.user.modify_record(config);
+        if password_strength(change_master.new_password().expose_secret()) == PasswordStrength::Weak
+        {
+            let mut app = app.clone();
+            app.state = ViewState::Home(Box::new(self.clone()));
+            app.mutable_app_state.popups.push(Box::new(Confirm::new(
+                "The new master password is weak.\n\nUse it anyway?".to_string(),
+            )));
+            return app;
+        }
 
-                if res.is_err() {
-                    let mut app = app.clone();
-                    app.mutable_app_state
-                        .popups
-                        .push(Box::new(MessagePopup::new(
-                            "Cannot modify record".to_string(),
-                        )));
-                    return app;
-                }
+        self.perform_operation(app)
+    }
 
-                let secrets = self.secrets.last_mut().unwrap();
-                secrets.secrets = res
-                    .unwrap()
-                    .records()
-                    .iter()
-                    .map(|x| Secret {
-                        key: x.0.clone(),
-                        value: x.1.clone(),
-                        last_suffix: x.0.clone(),
-                    })
-                    .collect();
-                secrets.selected_secret = secrets.selected_secret;
-                secrets.shown_secrets = secrets.shown_secrets.clone();
+    fn handle_history_popup(&mut self, app: Application, popup: Box<dyn Popup>) -> Application {
+        let history = match popup.downcast::<History>() {
+            Ok(history) => history,
+            Err(_) => {
+                unreachable!();
+            }
+        };
 
-                let mut app = app.clone();
-                app.state = ViewState::Home(self.clone());
-                app
+        match history.exit_state() {
+            Some(HistoryExitState::Restore(index)) => {
+                self.operation = Some(Operation::Restore(index));
+                self.confirm_operation(app)
             }
+            _ => app,
         }
     }
 
-    fn handle_insert_password_popup(
-        &mut self,
-        app: Application,
-        popup: Box<dyn Popup>,
-    ) -> Application {
-        let password: String;
-        let insert_password = popup.downcast::<InsertPassword>();
+    fn handle_attachments_popup(&mut self, app: Application, popup: Box<dyn Popup>) -> Application {
+        let attachments = match popup.downcast::<Attachments>() {
+            Ok(attachments) => attachments,
+            Err(_) => {
+                unreachable!();
+            }
+        };
 
-        match insert_password {
-            Ok(insert_password) => {
-                if insert_password.exit_state() == Some(InsertPasswordExitState::Quit) {
-                    return app;
+        match attachments.exit_state() {
+            Some(AttachmentsExitState::Add(path)) => {
+                self.operation = Some(Operation::Attach(path));
+                self.confirm_operation(app)
+            }
+            Some(AttachmentsExitState::Remove(index)) => {
+                self.operation = Some(Operation::RemoveAttachment(index));
+                self.confirm_operation(app)
+            }
+            Some(AttachmentsExitState::Extract(index, path)) => {
+                let mut app = app.clone();
+                match self.extract_attachment(index, &path) {
+                    Ok(()) => {
+                        app.mutable_app_state
+                            .popups
+                            .push(Box::new(MessagePopup::new(format!(
+                                "Attachment extracted to\n{}",
+                                path.display()
+                            ))));
+                    }
+                    Err(e) => {
+                        app.mutable_app_state
+                            .popups
+                            .push(Box::new(MessagePopup::from_error(
+                                "Cannot extract the attachment",
+                                &e,
+                            )));
+                    }
                 }
-                password = insert_password.password();
+                app
             }
+            _ => app,
+        }
+    }
+
+    fn handle_backup_popup(&mut self, app: Application, popup: Box<dyn Popup>) -> Application {
+        let backup_popup = match popup.downcast::<BackupPopup>() {
+            Ok(backup_popup) => backup_popup,
             Err(_) => {
                 unreachable!();
             }
+        };
+
+        if backup_popup.exit_state() != Some(BackupExitState::Confirm) {
+            return app;
+        }
+
+        let mut app = app.clone();
+        let path = backup_popup.path();
+        let passphrase = backup_popup.passphrase();
+        if backup_popup.mode() == BackupMode::Foreign {
+            let popup: Box<dyn Popup> = if path.as_os_str().is_empty() {
+                Box::new(MessagePopup::new("A file is required".to_string()))
+            } else {
+                match read_import(&path, passphrase) {
+                    Ok((format, import)) => {
+                        let source = path
+                            .file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        Box::new(ImportPreview::new(&source, format, import))
+                    }
+                    Err(popup) => Box::new(popup),
+                }
+            };
+            app.mutable_app_state.popups.push(popup);
+            return app;
+        }
+
+        if backup_popup.mode() == BackupMode::Pass {
+            if path.as_os_str().is_empty() {
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(MessagePopup::new(
+                        "A password store is required".to_string(),
+                    )));
+                return app;
+            }
+            self.operation = Some(Operation::ExportPass(path));
+            return self.confirm_operation(app);
         }
 
-        if password.is_empty() {
-            let mut app = app.clone();
+        if path.as_os_str().is_empty() || passphrase.is_empty() {
             app.mutable_app_state
                 .popups
                 .push(Box::new(MessagePopup::new(
-                    "Password cannot be empty".to_string(),
+                    "A file and an export\npassphrase are required".to_string(),
                 )));
             return app;
         }
 
-        self.new_secret = Some(NewSecret {
-            domain: "".to_string(),
-            password: password.clone(),
-        });
+        if backup_popup.mode() == BackupMode::Import {
+            return match read_backup(&path, passphrase) {
+                Ok(backup) => {
+                    self.operation = Some(Operation::Import(backup));
+                    self.confirm_operation(app)
+                }
+                Err(popup) => {
+                    app.mutable_app_state.popups.push(Box::new(popup));
+                    app
+                }
+            };
+        }
 
-        let mut app = app.clone();
+        if passphrase != backup_popup.confirm_passphrase() {
+            app.mutable_app_state
+                .popups
+                .push(Box::new(MessagePopup::new(
+                    "Export passphrases\ndo not match".to_string(),
+                )));
+            return app;
+        }
+
+        self.operation = Some(Operation::Export(path, passphrase.clone()));
+        if password_strength(passphrase.expose_secret()) == PasswordStrength::Weak {
+            app.state = ViewState::Home(Box::new(self.clone()));
+            app.mutable_app_state.popups.push(Box::new(Confirm::new(
+                "The export passphrase is weak.\n\nUse it anyway?".to_string(),
+            )));
+            return app;
+        }
+
+        self.confirm_operation(app)
+    }
+
+    fn handle_import_popup(&mut self, app: Application, popup: Box<dyn Popup>) -> Application {
+        let preview = match popup.downcast::<ImportPreview>() {
+            Ok(preview) => preview,
+            Err(_) => {
+                unreachable!();
+            }
+        };
 
-        app.state = ViewState::Home(self.clone());
+        if preview.exit_state() != Some(ImportExitState::Import) {
+            return app;
+        }
 
-        app.mutable_app_state
-            .popups
-            .push(Box::new(InsertMaster::new()));
+        let import = preview.import();
+        self.operation = Some(Operation::ImportEntries(
+            import.entries.clone(),
+            import.skipped.len(),
+            preview.policy(),
+        ));
+        self.confirm_operation(app)
+    }
+
+    fn handle_export_popup(&mut self, app: Application, popup: Box<dyn Popup>) -> Application {
+        let export_popup = match popup.downcast::<ExportPopup>() {
+            Ok(export_popup) => export_popup,
+            Err(_) => {
+                unreachable!();
+            }
+        };
+
+        if export_popup.exit_state() != Some(ExportExitState::Confirm) {
+            return app;
+        }
+
+        let mut app = app.clone();
+        let path = export_popup.path();
+        let options = export_popup.options();
+        if path.as_os_str().is_empty() || options.fields.is_empty() {
+            app.mutable_app_state
+                .popups
+                .push(Box::new(MessagePopup::new(
+                    "A file and at least\none field are required".to_string(),
+                )));
+            return app;
+        }
 
+        self.operation = Some(Operation::ExportPlaintext(path, options.clone()));
+        app.state = ViewState::Home(Box::new(self.clone()));
+        app.mutable_app_state.popups.push(Box::new(Confirm::new(
+            "Export every secret in\nclear text?\n\nAnyone who can read the file\ncan read your secrets."
+                .to_string(),
+        )));
         app
     }
 }
 
+impl Secret {
+    /// Creates the secrets of the read only records
+    /// The entries are moved, not copied
+    ///
+    /// # Arguments
+    /// * `records` - The read only records
+    ///
+    /// # Returns
+    /// The secrets
+    fn from_records(records: ReadOnlyRecords) -> Vec<Secret> {
+        records
+            .into_records()
+            .into_iter()
+            .map(|entry| Secret {
+                key: entry.label(),
+                last_suffix: entry.label(),
+                entry: Rc::new(entry),
+            })
+            .collect()
+    }
+}
+
 impl Secrets {
     /// Return the max length of the secrets
     ///
@@ -1196,39 +2215,236 @@ impl Secrets {
     fn max_length(&self) -> usize {
         self.secrets
             .iter()
-            .map(|x| x.key.len() + x.value.len() + DOMAIN_PASSWORD_MIDDLE_WIDTH as usize)
+            .map(|x| {
+                x.key.len()
+                    + entry_value(&x.entry, true).expose_secret().chars().count()
+                    + DOMAIN_PASSWORD_MIDDLE_WIDTH as usize
+            })
             .max()
             .unwrap_or(0)
     }
 }
 
+impl Scope {
+    /// Returns whether an entry is in the scope
+    ///
+    /// # Arguments
+    /// * `entry` - The entry
+    ///
+    /// # Returns
+    /// `true` if the entry is in the scope, otherwise `false`
+    fn matches(&self, entry: &Entry) -> bool {
+        match self {
+            Scope::All => true,
+            Scope::Folder(folder) => entry.in_folder(folder),
+            Scope::Tag(tag) => entry.has_tag(tag),
+        }
+    }
+}
+
+/// Splits a filter into its qualifiers and the text matched fuzzily
+/// `tag:name` and `folder:path` narrow the secrets like the sidebar does,
+/// qualifiers without a value are ignored
+///
+/// # Arguments
+/// * `value` - The filter
+///
+/// # Returns
+/// The qualifiers and the remaining text
+fn parse_filter(value: &str) -> (Vec<Scope>, String) {
+    let mut qualifiers = vec![];
+    let mut words = vec![];
+    for word in value.split(' ') {
+        if let Some(tag) = word.strip_prefix("tag:") {
+            if !tag.is_empty() {
+                qualifiers.push(Scope::Tag(tag.to_string()));
+            }
+        } else if let Some(folder) = word.strip_prefix("folder:") {
+            if !folder.is_empty() {
+                qualifiers.push(Scope::Folder(folder.to_string()));
+            }
+        } else {
+            words.push(word);
+        }
+    }
+    (qualifiers, words.join(" "))
+}
+
+/// Returns the line showing the current one-time code of an entry
+///
+/// # Arguments
+/// * `entry` - The entry
+/// * `now` - The current time as a Unix timestamp
+///
+/// # Returns
+/// The code with the seconds it stays valid, `None` if the entry has no TOTP secret
+fn totp_text(entry: &Entry, now: i64) -> Option<String> {
+    if entry.totp.is_empty() {
+        return None;
+    }
+    Some(match Totp::parse(entry.totp.expose_secret()) {
+        Ok(totp) => {
+            let code = totp.code(now);
+            let (first, second) = code.split_at(code.len() / 2);
+            format!(
+                "  TOTP {} {} · {}s left",
+                first,
+                second,
+                totp.remaining(now)
+            )
+        }
+        Err(_) => "  TOTP secret invalid".to_string(),
+    })
+}
+
 /// Returns a hidden value
 ///
 /// # Arguments
-/// * `domain` - The domain
+/// * `length` - The length of the value
 ///
 /// # Returns
 /// A hidden value
-fn hidden_value(domain: String, length: usize) -> String {
-    assert!(domain.len() <= MAX_ENTRY_LENGTH as usize);
+fn hidden_value(length: usize) -> String {
+    "•".repeat(length)
+}
+
+/// Returns the value shown next to the label of an entry, which depends on its kind
+/// Entries other than logins start with the name of their kind
+///
+/// # Arguments
+/// * `entry` - The entry
+/// * `shown` - Whether the secrets of the entry are shown
+///
+/// # Returns
+/// The value
+fn entry_value(entry: &Entry, shown: bool) -> SecretString {
+    let secret = |value: &SecretString| {
+        SecretString::new(if shown {
+            value.expose_secret().to_string()
+        } else {
+            hidden_value(value.expose_secret().chars().count())
+        })
+    };
+    let mut parts = vec![];
+    match &entry.kind {
+        EntryKind::Login => return secret(&entry.password),
+        EntryKind::Note => parts.push(secret(&entry.notes)),
+        EntryKind::Card(card) => {
+            parts.push(if shown {
+                secret(&card.number)
+            } else {
+                SecretString::new(format!("{} {}", hidden_value(4), card.last_digits()))
+            });
+            parts.push(SecretString::from(card.expiry.as_str()));
+            if shown && !card.cvv.is_empty() {
+                parts.push(SecretString::new(format!(
+                    "CVV {}",
+                    card.cvv.expose_secret()
+                )));
+            }
+        }
+        EntryKind::SshKey(key) => {
+            parts.push(SecretString::from(key.key_type().unwrap_or_default()));
+            parts.push(SecretString::new(key.fingerprint().unwrap_or_default()));
+        }
+        EntryKind::Identity(identity) => {
+            parts.push(SecretString::from(identity.full_name.as_str()));
+            parts.push(SecretString::from(identity.email.as_str()));
+            if shown {
+                parts.push(SecretString::from(identity.phone.as_str()));
+                parts.push(secret(&identity.id_number));
+            }
+        }
+    }
+    // reserve everything up front, growing would leave copies of the secrets behind
+    let capacity = parts
+        .iter()
+        .map(|part| part.expose_secret().len() + " · ".len())
+        .sum::<usize>();
+    let mut value = SecretString::new(String::with_capacity(entry.kind.name().len() + capacity));
+    value.expose_secret_mut().push_str(entry.kind.name());
+    for part in parts.iter().filter(|part| !part.is_empty()) {
+        let text = value.expose_secret_mut();
+        text.push_str(if text.len() == entry.kind.name().len() {
+            " "
+        } else {
+            " · "
+        });
+        text.push_str(part.expose_secret());
+    }
+    value
+}
 
-    let mut hidden_value = "  ".to_string() + &domain.clone();
-    hidden_value.push_str(" : ");
-    for _ in 0..length {
-        hidden_value.push_str("•");
+/// Returns the secret of an entry copied with `c`, which depends on its kind
+///
+/// # Arguments
+/// * `entry` - The entry
+///
+/// # Returns
+/// The password of logins, the note of secure notes, the number of cards,
+/// the private key of SSH keys and the ID number of identities
+fn copied_value(entry: &Entry) -> &SecretString {
+    match &entry.kind {
+        EntryKind::Login => &entry.password,
+        EntryKind::Note => &entry.notes,
+        EntryKind::Card(card) => &card.number,
+        EntryKind::SshKey(key) => &key.private_key,
+        EntryKind::Identity(identity) => &identity.id_number,
     }
+}
 
-    hidden_value
+/// Reads an SSH private key from a file
+///
+/// # Arguments
+/// * `path` - The path of the file
+///
+/// # Returns
+/// The key pair or a message describing why it could not be read
+fn read_ssh_key(path: &Path) -> Result<SshKey, String> {
+    let size = fs::metadata(path).map_err(|e| e.to_string())?.len();
+    if size > MAX_ATTACHMENT_SIZE as u64 {
+        return Err(EntryError::InvalidSshKey.to_string());
+    }
+    let data = fs::read(path).map_err(|e| e.to_string())?;
+    let private_key = SecretString::new(
+        String::from_utf8(data).map_err(|_| EntryError::InvalidSshKey.to_string())?,
+    );
+    SshKey::from_private_key(private_key).map_err(|e| e.to_string())
 }
 
 // WARNING: Make sure to remove files created by the tests
 // so that you don't clutter your filesystem with test files
+/// Reads a file to attach
+/// The size is checked before reading, so a large file is never loaded
+///
+/// # Arguments
+/// * `path` - The path of the file
+///
+/// # Returns
+/// The file name and the content or an error
+fn read_attachment_file(path: &Path) -> Result<(String, Vec<u8>), KrabError> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or(KrabError::Io(std::io::ErrorKind::InvalidInput))?;
+    let size = fs::metadata(path)?.len();
+    if size > MAX_ATTACHMENT_SIZE as u64 {
+        return Err(KrabError::AttachmentTooLarge(size as usize));
+    }
+    Ok((name, fs::read(path)?))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use krab_backend::user::{CipherKind, KdfParams, RecordOperationConfig, User};
     use rand::Rng;
-    use std::{env, path::PathBuf};
+    use ratatui::crossterm::event::KeyModifiers;
+    use std::{
+        env, fs,
+        path::{Path, PathBuf},
+    };
 
     fn random_number() -> u32 {
         let mut rng = rand::thread_rng();
@@ -1239,27 +2455,61 @@ mod tests {
         format!("krab-{}", random_number())
     }
 
-    fn setup_user_data(domain: &str) -> Result<RecordOperationConfig, String> {
+    /// TempDir is a directory for the files of a test
+    /// The directory and everything in it is removed when it is dropped,
+    /// also when the test panics
+    ///
+    /// # Fields
+    /// * `0` - The path of the directory
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let path = env::temp_dir().join(format!("krab-test-{}", random_number()));
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        fn path(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn setup_user_data(domain: &str) -> Result<(TempDir, RecordOperationConfig), KrabError> {
+        let dir = TempDir::new();
         let username = generate_random_username();
-        let username = username.as_str().to_owned();
         let master_password = "password";
         let password = "password";
-        let path = PathBuf::from(env::var("KRAB_TEMP_DIR").unwrap());
-        let user =
-            RecordOperationConfig::new(username.as_str(), master_password, domain, password, &path);
-        match User::new(&user) {
-            Ok(_) => Ok(user.clone()),
-            Err(e) => Err(e),
-        }
+        let user = RecordOperationConfig::new(
+            username.as_str(),
+            master_password,
+            domain,
+            password,
+            dir.path(),
+        );
+        User::new(&user)?;
+        Ok((dir, user))
     }
 
-    fn create_user(config: &RecordOperationConfig) -> Result<(User, ReadOnlyRecords), String> {
-        User::from(&config.path, &config.username, &config.master_password)
+    fn create_user(
+        config: &RecordOperationConfig,
+    ) -> Result<(VaultSession, ReadOnlyRecords), KrabError> {
+        VaultSession::open(
+            &config.path,
+            &config.username,
+            config.master_password.expose_secret(),
+        )
     }
 
     #[test]
     fn test_home_largest_prefix() {
-        let user_data = setup_user_data("example.com").unwrap();
+        let (_dir, user_data) = setup_user_data("example.com").unwrap();
         let (user, ror) = create_user(&user_data).unwrap();
 
         let home = Home::new(user, ror, Position::default(), Rect::default());
@@ -1282,7 +2532,7 @@ mod tests {
 
     #[test]
     fn test_home_fuzzy_filter() {
-        let user_data = setup_user_data("example.com").unwrap();
+        let (_dir, user_data) = setup_user_data("example.com").unwrap();
         let (user, ror) = create_user(&user_data).unwrap();
 
         let mut home = Home::new(user, ror, Position::default(), Rect::default());
@@ -1299,4 +2549,580 @@ mod tests {
         home.fuzzy_filter(previous_value, new_value);
         assert_eq!(home.secrets.len(), 1);
     }
+
+    #[test]
+    fn test_home_empty_vault() {
+        let dir = TempDir::new();
+        let path = dir.path().to_path_buf();
+        let username = generate_random_username();
+        User::create(
+            &path,
+            &username,
+            "password",
+            &KdfParams::default(),
+            CipherKind::default(),
+        )
+        .unwrap();
+        let (session, ror) = VaultSession::open(&path, &username, "password").unwrap();
+
+        let area = Rect::new(0, 0, 200, 60);
+        let mut home = Home::new(session, ror, Position::default(), area);
+        let app = Application::create(path.clone(), area).into_inner();
+        for key in [
+            KeyCode::Char('j'),
+            KeyCode::Enter,
+            KeyCode::Char('d'),
+            KeyCode::Char('e'),
+            KeyCode::Char('c'),
+        ] {
+            let app = home.handle_key(&KeyEvent::from(key), &app);
+            assert!(app.mutable_app_state.popups.is_empty());
+        }
+        let buffer = home.buffer_to_render();
+        let content: String = buffer.content().iter().map(|c| c.symbol()).collect();
+
+        assert!(!home.has_secrets());
+        assert!(content.contains("No secrets yet"));
+    }
+
+    #[test]
+    fn test_home_edit_entry() {
+        let (_dir, user_data) = setup_user_data("example.com").unwrap();
+        let (session, ror) = create_user(&user_data).unwrap();
+
+        let area = Rect::new(0, 0, 200, 60);
+        let mut home = Home::new(session, ror, Position::default(), area);
+        let app = Application::create(user_data.path.clone(), area).into_inner();
+        let mut app = home.handle_key(&KeyEvent::from(KeyCode::Char('e')), &app);
+        let mut popup = app.mutable_app_state.popups.last().unwrap().clone();
+        let mut keys = vec![KeyEvent::from(KeyCode::Down)];
+        keys.extend("krab".chars().map(|c| KeyEvent::from(KeyCode::Char(c))));
+        keys.push(KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL));
+        keys.extend("PIN".chars().map(|c| KeyEvent::from(KeyCode::Char(c))));
+        keys.push(KeyEvent::from(KeyCode::Tab));
+        keys.extend("1234".chars().map(|c| KeyEvent::from(KeyCode::Char(c))));
+        keys.push(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL));
+        keys.extend([KeyCode::Tab, KeyCode::Right, KeyCode::Enter].map(KeyEvent::from));
+        let mut exited = None;
+        for key in keys {
+            let (changed_app, last_state) = popup.handle_key(&key, &app);
+            app = changed_app;
+            if last_state.is_some() {
+                exited = last_state;
+                break;
+            }
+            popup = app.mutable_app_state.popups.last().unwrap().clone();
+        }
+        home.handle_entry_popup(app, exited.unwrap());
+        let (_, records) = create_user(&user_data).unwrap();
+
+        let entry = &records.records()[0];
+        assert_eq!(entry.domain, "example.com");
+        assert_eq!(entry.username, "krab");
+        assert_eq!(entry.password.expose_secret(), "password");
+        assert_eq!(entry.fields.len(), 1);
+        assert_eq!(entry.fields[0].name, "PIN");
+        assert_eq!(entry.fields[0].value.expose_secret(), "1234");
+        assert!(entry.fields[0].hidden);
+    }
+
+    #[test]
+    fn test_home_same_domain_entries() {
+        let (_dir, user_data) = setup_user_data("example.com").unwrap();
+        let (mut session, _) = create_user(&user_data).unwrap();
+        for username in ["personal", "work"] {
+            let mut entry = Entry::new("github.com", "password");
+            entry.username = username.to_string();
+            session.add_record(&entry).unwrap();
+        }
+        let ror = session.reload().unwrap();
+
+        let area = Rect::new(0, 0, 200, 60);
+        let mut home = Home::new(session, ror, Position::default(), area);
+        home.secrets[0].selected_secret = 2;
+        home.toggle_shown_secret();
+        let keys: Vec<String> = home.secrets[0]
+            .secrets
+            .iter()
+            .map(|s| s.key.clone())
+            .collect();
+
+        assert_eq!(
+            keys,
+            vec!["example.com", "github.com (personal)", "github.com (work)"]
+        );
+        assert_eq!(home.secrets[0].shown_secrets, vec![2]);
+    }
+
+    #[test]
+    fn test_home_change_master_password() {
+        let (_dir, user_data) = setup_user_data("example.com").unwrap();
+        let (session, ror) = create_user(&user_data).unwrap();
+
+        let area = Rect::new(0, 0, 200, 60);
+        let mut home = Home::new(session, ror, Position::default(), area);
+        let app = Application::create(user_data.path.clone(), area).into_inner();
+        let mut app = home.handle_key(&KeyEvent::from(KeyCode::Char('m')), &app);
+        let mut popup = app.mutable_app_state.popups.last().unwrap().clone();
+        let mut keys: Vec<KeyCode> = vec![];
+        for value in ["password", "new_password_Str0ng!", "new_password_Str0ng!"] {
+            keys.extend(value.chars().map(KeyCode::Char));
+            keys.push(KeyCode::Tab);
+        }
+        keys.extend([KeyCode::Right, KeyCode::Enter]);
+        let mut exited = None;
+        for key in keys {
+            let (changed_app, last_state) = popup.handle_key(&KeyEvent::from(key), &app);
+            app = changed_app;
+            if last_state.is_some() {
+                exited = last_state;
+                break;
+            }
+            popup = app.mutable_app_state.popups.last().unwrap().clone();
+        }
+        let app = home.handle_change_master_popup(app, exited.unwrap());
+        let old = create_user(&user_data);
+        let new = VaultSession::open(&user_data.path, &user_data.username, "new_password_Str0ng!");
+
+        assert_eq!(app.mutable_app_state.popups.len(), 1);
+        assert_eq!(old.unwrap_err(), KrabError::WrongCredentials);
+        assert_eq!(new.unwrap().1.records().len(), 1);
+    }
+
+    #[test]
+    fn test_home_restore_password() {
+        let (_dir, user_data) = setup_user_data("example.com").unwrap();
+        let (mut session, ror) = create_user(&user_data).unwrap();
+        let mut entry = ror.records()[0].clone();
+        entry.password = SecretString::from("new_password");
+        let ror = session.modify_record(&entry.id, &entry).unwrap();
+
+        let area = Rect::new(0, 0, 200, 60);
+        let mut home = Home::new(session, ror, Position::default(), area);
+        let app = Application::create(user_data.path.clone(), area).into_inner();
+        let mut app = home.handle_key(&KeyEvent::from(KeyCode::Char('H')), &app);
+        let mut popup = app.mutable_app_state.popups.last().unwrap().clone();
+        let mut exited = None;
+        for key in [KeyCode::Char('s'), KeyCode::Char('r')] {
+            let (changed_app, last_state) = popup.handle_key(&KeyEvent::from(key), &app);
+            app = changed_app;
+            if last_state.is_some() {
+                exited = last_state;
+                break;
+            }
+            popup = app.mutable_app_state.popups.last().unwrap().clone();
+        }
+        let app = home.handle_history_popup(app, exited.unwrap());
+        let (_, records) = create_user(&user_data).unwrap();
+
+        let entry = &records.records()[0];
+        assert!(app.mutable_app_state.popups.is_empty());
+        assert_eq!(entry.password.expose_secret(), "password");
+        assert_eq!(entry.history.len(), 1);
+        assert_eq!(entry.history[0].password.expose_secret(), "new_password");
+        assert_eq!(
+            home.current_secret().entry.password.expose_secret(),
+            "password"
+        );
+    }
+
+    #[test]
+    fn test_home_tags_and_folders() {
+        let (_dir, user_data) = setup_user_data("example.com").unwrap();
+        let (mut session, _) = create_user(&user_data).unwrap();
+        for (domain, folder, tags) in [
+            ("github.com", "Work/Code", vec!["work"]),
+            ("bank.com", "Personal", vec!["finance"]),
+            ("gitlab.com", "Work", vec!["Work", "2fa"]),
+        ] {
+            let mut entry = Entry::new(domain, "password");
+            entry.folder = folder.to_string();
+            entry.tags = tags.into_iter().map(str::to_string).collect();
+            session.add_record(&entry).unwrap();
+        }
+        let ror = session.reload().unwrap();
+
+        let area = Rect::new(0, 0, 200, 60);
+        let mut home = Home::new(session, ror, Position::default(), area);
+        let app = Application::create(user_data.path.clone(), area).into_inner();
+        let labels = |home: &Home| -> Vec<String> {
+            home.sidebar_items()
+                .into_iter()
+                .map(|item| item.label)
+                .collect()
+        };
+        let keys = |home: &Home| -> Vec<String> {
+            home.secrets
+                .last()
+                .unwrap()
+                .secrets
+                .iter()
+                .map(|s| s.key.clone())
+                .collect()
+        };
+        let sidebar = labels(&home);
+        let mut app = app;
+        for key in [KeyCode::Tab, KeyCode::Char('j'), KeyCode::Char('j')] {
+            app = home.handle_key(&KeyEvent::from(key), &app);
+        }
+        app = home.handle_key(&KeyEvent::from(KeyCode::Enter), &app);
+        let work_keys = keys(&home);
+        app = home.handle_key(&KeyEvent::from(KeyCode::Char(' ')), &app);
+        let collapsed = labels(&home);
+        for key in [KeyCode::Tab, KeyCode::Char('f')] {
+            app = home.handle_key(&KeyEvent::from(key), &app);
+        }
+        for c in "tag:2fa".chars() {
+            app = home.handle_key(&KeyEvent::from(KeyCode::Char(c)), &app);
+        }
+        let tagged_keys = keys(&home);
+
+        assert_eq!(
+            sidebar,
+            vec![
+                "All secrets (4)",
+                "  Personal (1)",
+                "▾ Work (2)",
+                "    Code (1)",
+                "#2fa (1)",
+                "#finance (1)",
+                "#work (2)",
+            ]
+        );
+        assert_eq!(work_keys, vec!["github.com", "gitlab.com"]);
+        assert!(!collapsed.contains(&"    Code (1)".to_string()));
+        assert!(collapsed.contains(&"▸ Work (2)".to_string()));
+        assert_eq!(tagged_keys, vec!["gitlab.com"]);
+        assert_eq!(
+            parse_filter("git tag:work folder:Work/Code tag:"),
+            (
+                vec![
+                    Scope::Tag("work".to_string()),
+                    Scope::Folder("Work/Code".to_string())
+                ],
+                "git".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_home_totp() {
+        let (_dir, user_data) = setup_user_data("example.com").unwrap();
+        let (mut session, ror) = create_user(&user_data).unwrap();
+        let mut entry = ror.records()[0].clone();
+        entry.totp = SecretString::from("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+        let ror = session.modify_record(&entry.id, &entry).unwrap();
+
+        let area = Rect::new(0, 0, 200, 60);
+        let home = Home::new(session, ror, Position::default(), area);
+        let buffer = home.buffer_to_render();
+        let content: String = buffer.content().iter().map(|c| c.symbol()).collect();
+
+        assert!(content.contains("TOTP "));
+        assert_eq!(
+            totp_text(&entry, 59),
+            Some("  TOTP 287 082 · 1s left".to_string())
+        );
+        entry.totp = SecretString::from("not base32!");
+        assert_eq!(
+            totp_text(&entry, 59),
+            Some("  TOTP secret invalid".to_string())
+        );
+        entry.totp = SecretString::default();
+        assert_eq!(totp_text(&entry, 59), None);
+    }
+
+    #[test]
+    fn test_home_entry_kinds() {
+        let (_dir, user_data) = setup_user_data("example.com").unwrap();
+        let (session, ror) = create_user(&user_data).unwrap();
+
+        let area = Rect::new(0, 0, 200, 60);
+        let mut home = Home::new(session, ror, Position::default(), area);
+        let app = Application::create(user_data.path.clone(), area).into_inner();
+        let mut app = home.handle_key(&KeyEvent::from(KeyCode::Char('a')), &app);
+        let mut popup = app.mutable_app_state.popups.last().unwrap().clone();
+        let mut keys = vec![
+            KeyEvent::new(KeyCode::Char('t'), KeyModifiers::CONTROL),
+            KeyEvent::new(KeyCode::Char('t'), KeyModifiers::CONTROL),
+        ];
+        for text in ["Visa", "Krab", "4111 1111 1111 1111", "12/27", "123"] {
+            keys.extend(text.chars().map(|c| KeyEvent::from(KeyCode::Char(c))));
+            keys.push(KeyEvent::from(KeyCode::Tab));
+        }
+        keys.extend([KeyCode::Tab, KeyCode::Tab, KeyCode::Tab].map(KeyEvent::from));
+        keys.extend([KeyCode::Right, KeyCode::Enter].map(KeyEvent::from));
+        let mut exited = None;
+        for key in keys {
+            let (changed_app, last_state) = popup.handle_key(&key, &app);
+            app = changed_app;
+            if last_state.is_some() {
+                exited = last_state;
+                break;
+            }
+            popup = app.mutable_app_state.popups.last().unwrap().clone();
+        }
+        home.handle_entry_popup(app, exited.unwrap());
+        let buffer = home.buffer_to_render();
+        let content: String = buffer.content().iter().map(|c| c.symbol()).collect();
+        let (_, records) = create_user(&user_data).unwrap();
+
+        let entry = records
+            .records()
+            .iter()
+            .find(|entry| entry.domain == "Visa")
+            .unwrap();
+        let card = match &entry.kind {
+            EntryKind::Card(card) => card,
+            kind => panic!("unexpected kind {:?}", kind),
+        };
+        assert_eq!(card.holder, "Krab");
+        assert_eq!(card.number.expose_secret(), "4111 1111 1111 1111");
+        assert_eq!(card.expiry, "12/27");
+        assert_eq!(card.cvv.expose_secret(), "123");
+        assert!(entry.password.is_empty());
+        assert!(content.contains("Visa : Card •••• 1111 · 12/27"));
+        assert_eq!(
+            entry_value(entry, true).expose_secret(),
+            "Card 4111 1111 1111 1111 · 12/27 · CVV 123"
+        );
+        assert_eq!(copied_value(entry).expose_secret(), "4111 1111 1111 1111");
+
+        let mut note = Entry::new("Wi-Fi", "");
+        note.kind = EntryKind::Note;
+        note.notes = SecretString::from("hunter2");
+        assert_eq!(
+            entry_value(&note, false).expose_secret(),
+            "Secure note •••••••"
+        );
+        note.kind = EntryKind::SshKey(SshKey::default());
+        assert_eq!(entry_value(&note, true).expose_secret(), "SSH key");
+    }
+
+    #[test]
+    fn test_home_attachments() {
+        let (_dir, user_data) = setup_user_data("example.com").unwrap();
+        let (session, ror) = create_user(&user_data).unwrap();
+        let source = user_data.path.join(format!("{}.txt", user_data.username));
+        let target = user_data.path.join(format!("{}.out", user_data.username));
+        std::fs::write(&source, "recovery codes").unwrap();
+
+        let area = Rect::new(0, 0, 200, 60);
+        let mut home = Home::new(session, ror, Position::default(), area);
+        let app = Application::create(user_data.path.clone(), area).into_inner();
+        let mut app = home.handle_key(&KeyEvent::from(KeyCode::Char('A')), &app);
+        let mut keys = vec![KeyCode::Char('a')];
+        keys.extend(source.to_string_lossy().chars().map(KeyCode::Char));
+        keys.push(KeyCode::Enter);
+        let mut popup = app.mutable_app_state.popups.last().unwrap().clone();
+        let mut exited = None;
+        for key in keys {
+            let (changed_app, last_state) = popup.handle_key(&KeyEvent::from(key), &app);
+            app = changed_app;
+            if last_state.is_some() {
+                exited = last_state;
+                break;
+            }
+            popup = app.mutable_app_state.popups.last().unwrap().clone();
+        }
+        let app = home.handle_attachments_popup(app, exited.unwrap());
+        let attached = home.current_secret().entry.attachments.clone();
+        let extracted = home.extract_attachment(0, &target);
+        let extracted_again = home.extract_attachment(0, &target);
+        let content = std::fs::read_to_string(&target).unwrap();
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::metadata(&target).unwrap().permissions().mode() & 0o777
+        };
+
+        let mut popup = app.mutable_app_state.popups.last().unwrap().clone();
+        let (app, exited) = popup.handle_key(&KeyEvent::from(KeyCode::Char('d')), &app);
+        let app = home.handle_attachments_popup(app, exited.unwrap());
+        let mut popup = app.mutable_app_state.popups.last().unwrap().clone();
+        let (app, exited) = popup.handle_key(&KeyEvent::from(KeyCode::Char('y')), &app);
+        let app = home.handle_confirm_popup(app, exited.unwrap());
+        let (_, records) = create_user(&user_data).unwrap();
+
+        let file_path = user_data.path.join(krab_backend::hash(user_data.username));
+        let blob_deleted = !PathBuf::from(format!(
+            "{}.attachment.{}",
+            file_path.display(),
+            attached[0].id
+        ))
+        .exists();
+
+        assert_eq!(attached.len(), 1);
+        assert_eq!(attached[0].size, 14);
+        assert!(extracted.is_ok());
+        assert_eq!(
+            extracted_again.unwrap_err(),
+            KrabError::Io(std::io::ErrorKind::AlreadyExists)
+        );
+        assert_eq!(content, "recovery codes");
+        #[cfg(unix)]
+        assert_eq!(mode, 0o600);
+        assert_eq!(app.mutable_app_state.popups.len(), 1);
+        assert!(records.records()[0].attachments.is_empty());
+        assert!(blob_deleted);
+    }
+
+    fn press_keys(app: Application, keys: Vec<KeyCode>) -> (Application, Option<Box<dyn Popup>>) {
+        let mut app = app;
+        for key in keys {
+            let mut popup = app.mutable_app_state.popups.last().unwrap().clone();
+            let (changed_app, last_state) = popup.handle_key(&KeyEvent::from(key), &app);
+            app = changed_app;
+            if last_state.is_some() {
+                return (app, last_state);
+            }
+        }
+        (app, None)
+    }
+
+    #[test]
+    fn test_home_backup() {
+        let (_dir, user_data) = setup_user_data("example.com").unwrap();
+        let (_other_dir, other_data) = setup_user_data("other.com").unwrap();
+        let (session, ror) = create_user(&user_data).unwrap();
+        let (other_session, other_ror) = create_user(&other_data).unwrap();
+        let target = user_data.path.join(format!("{}.krab", user_data.username));
+        let passphrase = "correct horse battery staple";
+
+        let area = Rect::new(0, 0, 200, 60);
+        let mut home = Home::new(session, ror, Position::default(), area);
+        let app = Application::create(user_data.path.clone(), area).into_inner();
+        let app = home.handle_key(&KeyEvent::from(KeyCode::Char('x')), &app);
+        let mut keys = vec![KeyCode::Backspace; 256];
+        keys.extend(target.to_string_lossy().chars().map(KeyCode::Char));
+        keys.push(KeyCode::Enter);
+        for _ in 0..2 {
+            keys.extend(passphrase.chars().map(KeyCode::Char));
+            keys.push(KeyCode::Enter);
+        }
+        keys.extend([KeyCode::Right, KeyCode::Enter]);
+        let (app, exited) = press_keys(app, keys);
+        let app = home.handle_backup_popup(app, exited.unwrap());
+        let bundle = std::fs::read(&target).unwrap();
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::metadata(&target).unwrap().permissions().mode() & 0o777
+        };
+
+        let plaintext = user_data.path.join(format!("{}.json", user_data.username));
+        let app = home.handle_key(&KeyEvent::from(KeyCode::Char('X')), &app);
+        let mut keys = vec![KeyCode::Backspace; 256];
+        keys.extend(plaintext.to_string_lossy().chars().map(KeyCode::Char));
+        keys.extend([KeyCode::Up, KeyCode::Right, KeyCode::Enter]);
+        let (app, exited) = press_keys(app, keys);
+        let app = home.handle_export_popup(app, exited.unwrap());
+        let (app, exited) = press_keys(app, vec![KeyCode::Char('n')]);
+        let app = home.handle_confirm_popup(app, exited.unwrap());
+
+        let mut other = Home::new(other_session, other_ror, Position::default(), area);
+        let app = other.handle_key(&KeyEvent::from(KeyCode::Char('i')), &app);
+        let mut keys: Vec<KeyCode> = target
+            .to_string_lossy()
+            .chars()
+            .map(KeyCode::Char)
+            .collect();
+        keys.push(KeyCode::Enter);
+        keys.extend(passphrase.chars().map(KeyCode::Char));
+        keys.extend([KeyCode::Enter, KeyCode::Right, KeyCode::Enter]);
+        let (app, exited) = press_keys(app, keys.clone());
+        let app = other.handle_backup_popup(app, exited.unwrap());
+        let (app, exited) = press_keys(app, vec![KeyCode::Char('y')]);
+        let app = other.handle_confirm_popup(app, exited.unwrap());
+        let imported: Vec<String> = other
+            .all_secrets
+            .iter()
+            .map(|secret| secret.entry.domain.clone())
+            .collect();
+        let mut wrong_keys = keys.clone();
+        wrong_keys.insert(wrong_keys.len() - 3, KeyCode::Char('!'));
+        let app = other.handle_key(&KeyEvent::from(KeyCode::Char('i')), &app);
+        let (app, exited) = press_keys(app, wrong_keys);
+        let app = other.handle_backup_popup(app, exited.unwrap());
+
+        let backup = Backup::open(&bundle, passphrase).unwrap();
+        assert_eq!(backup.username, user_data.username);
+        assert_eq!(backup.entries.len(), 1);
+        assert!(!bundle
+            .windows(b"password".len())
+            .any(|window| window == b"password"));
+        #[cfg(unix)]
+        assert_eq!(mode, 0o600);
+        assert!(!plaintext.exists());
+        assert_eq!(imported, vec!["other.com", "example.com"]);
+        assert_eq!(other.operation, Some(Operation::Import(backup)));
+        // the export, import and wrong passphrase messages
+        assert_eq!(app.mutable_app_state.popups.len(), 3);
+    }
+
+    #[test]
+    fn test_home_foreign_import() {
+        let (_dir, user_data) = setup_user_data("example.com").unwrap();
+        let (session, ror) = create_user(&user_data).unwrap();
+        let export = user_data.path.join(format!("{}.csv", user_data.username));
+        std::fs::write(
+            &export,
+            "name,url,username,password,note\n\
+             example.com,https://example.com,,new password,\n\
+             example.org,https://example.org,krab,\"pass,word\",\n\
+             ,https://empty.org,krab,,\n",
+        )
+        .unwrap();
+
+        let area = Rect::new(0, 0, 200, 60);
+        let mut home = Home::new(session, ror, Position::default(), area);
+        let app = Application::create(user_data.path.clone(), area).into_inner();
+        let app = home.handle_key(&KeyEvent::from(KeyCode::Char('K')), &app);
+        let mut keys: Vec<KeyCode> = export
+            .to_string_lossy()
+            .chars()
+            .map(KeyCode::Char)
+            .collect();
+        keys.extend([
+            KeyCode::Enter,
+            KeyCode::Enter,
+            KeyCode::Right,
+            KeyCode::Enter,
+        ]);
+        let (app, exited) = press_keys(app, keys);
+        let app = home.handle_backup_popup(app, exited.unwrap());
+        std::fs::remove_file(&export).unwrap();
+        let (app, exited) = press_keys(
+            app,
+            vec![KeyCode::Char('j'), KeyCode::Char('d'), KeyCode::Enter],
+        );
+        let app = home.handle_import_popup(app, exited.unwrap());
+        let imported: Vec<(String, String)> = home
+            .all_secrets
+            .iter()
+            .map(|secret| {
+                (
+                    secret.entry.domain.clone(),
+                    secret.entry.password.expose_secret().to_string(),
+                )
+            })
+            .collect();
+
+        // example.com is overwritten, the entry without a password is skipped
+        assert_eq!(
+            imported,
+            vec![
+                ("example.com".to_string(), "new password".to_string()),
+                ("example.org".to_string(), "pass,word".to_string()),
+            ]
+        );
+        assert_eq!(
+            home.all_secrets[0].entry.history[0]
+                .password
+                .expose_secret(),
+            "password"
+        );
+        // the import message
+        assert_eq!(app.mutable_app_state.popups.len(), 1);
+    }
 }
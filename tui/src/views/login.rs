@@ -1,4 +1,7 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use ratatui::{
     crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
@@ -9,7 +12,10 @@ use ratatui::{
 
 use krab_backend::{
     check_user,
-    user::{ReadOnlyRecords, User},
+    secret::SecretString,
+    session::VaultSession,
+    store::FileStore,
+    user::{KrabError, Migration, ReadOnlyRecords},
 };
 
 use crate::{
@@ -86,7 +92,7 @@ enum LoginState {
 #[derive(Debug, Clone)]
 pub struct Login {
     username: String,
-    master_password: String,
+    master_password: SecretString,
     state: LoginState,
     path: PathBuf,
     cursors: HashMap<LoginInput, u16>,
@@ -102,7 +108,7 @@ impl Login {
     ///
     /// # Returns
     /// A new `Login` view
-    pub fn new(path: &PathBuf) -> Self {
+    pub fn new(path: &Path) -> Self {
         let mut cursors = HashMap::new();
         let mut input_offsets = HashMap::new();
         cursors.insert(LoginInput::Username, 0);
@@ -111,9 +117,9 @@ impl Login {
         input_offsets.insert(LoginInput::MasterPassword, 0);
         Login {
             username: String::new(),
-            master_password: String::new(),
+            master_password: SecretString::default(),
             state: LoginState::Username,
-            path: path.clone(),
+            path: path.to_path_buf(),
             cursors,
             input_offsets,
             hidden_password: true,
@@ -123,20 +129,43 @@ impl Login {
     /// Logs in the user
     ///
     /// # Returns
-    /// The user and the read only records if the login is successful
-    /// An error message if the login is unsuccessful
-    fn login(&self) -> Result<(User, ReadOnlyRecords), String> {
-        let user_exists = check_user(&self.username, self.path.clone());
+    /// The unlocked vault and the read only records if the login is successful
+    /// An error if the login is unsuccessful
+    fn login(&self) -> Result<(VaultSession, ReadOnlyRecords), KrabError> {
+        let user_exists = check_user(&self.username, &FileStore::new(&self.path));
         if !user_exists {
-            return Err("Cannot login".to_string());
+            return Err(KrabError::UserNotFound);
         }
 
-        let user_creation_result = User::from(&self.path, &self.username, &self.master_password);
+        VaultSession::open(
+            &self.path,
+            &self.username,
+            self.master_password.expose_secret(),
+        )
+    }
 
-        match user_creation_result {
-            Ok(u) => Ok(u),
-            Err(_) => Err("Cannot login".to_string()),
-        }
+    /// Creates a popup describing the outcome of a vault migration
+    ///
+    /// # Arguments
+    /// * `migration` - The migration outcome
+    ///
+    /// # Returns
+    /// A message popup
+    fn migration_popup(migration: &Migration) -> MessagePopup {
+        let message = match migration {
+            Migration::Migrated { backup, records } => format!(
+                "Your vault ({} secrets) was upgraded\nto the new file format.\n\nThe original file was kept as\n{}",
+                records,
+                backup
+            ),
+            Migration::Failed(e) => format!(
+                "Your vault could not be upgraded\nto the new file format.\nIt is still usable as it is.\n\n{}",
+                e
+            ),
+        };
+        let width = message.lines().map(|l| l.len()).max().unwrap_or(0) as u16 + 6;
+        let (min_width, min_height) = MessagePopup::min_area();
+        MessagePopup::new_with_size(message, width.max(min_width), min_height)
     }
 
     /// Generates the input configuration
@@ -146,43 +175,32 @@ impl Login {
     ///
     /// # Returns
     /// The input configuration
-    fn generate_input_config(&self, input: LoginInput) -> InputConfig {
+    fn generate_input_config(&self, input: LoginInput) -> InputConfig<'_> {
         match input {
             LoginInput::Username => InputConfig::new(
                 self.state == LoginState::Username,
-                self.username.clone(),
+                &self.username,
                 false,
                 "Username".to_string(),
                 if self.state == LoginState::Username {
-                    Some(self.cursors.get(&LoginInput::Username).unwrap().clone())
+                    Some(*self.cursors.get(&LoginInput::Username).unwrap())
                 } else {
                     None
                 },
-                self.input_offsets
-                    .get(&LoginInput::Username)
-                    .unwrap()
-                    .clone(),
+                *self.input_offsets.get(&LoginInput::Username).unwrap(),
                 None,
             ),
             LoginInput::MasterPassword => InputConfig::new(
                 self.state == LoginState::MasterPassword,
-                self.master_password.clone(),
+                self.master_password.expose_secret(),
                 self.hidden_password,
                 "Master Password | CTRL + s - show/hide".to_string(),
                 if self.state == LoginState::MasterPassword {
-                    Some(
-                        self.cursors
-                            .get(&LoginInput::MasterPassword)
-                            .unwrap()
-                            .clone(),
-                    )
+                    Some(*self.cursors.get(&LoginInput::MasterPassword).unwrap())
                 } else {
                     None
                 },
-                self.input_offsets
-                    .get(&LoginInput::MasterPassword)
-                    .unwrap()
-                    .clone(),
+                *self.input_offsets.get(&LoginInput::MasterPassword).unwrap(),
                 None,
             ),
         }
@@ -230,12 +248,12 @@ impl View for Login {
         let master_password_config = self.generate_input_config(LoginInput::MasterPassword);
         let confirm_config = self.generate_button_config(LoginButton::Confirm);
         let quit_config = self.generate_button_config(LoginButton::Quit);
-        let mut buffer = f.buffer_mut();
+        let buffer = f.buffer_mut();
 
-        Input::render(&mut buffer, layout[0], &username_config);
-        Input::render(&mut buffer, layout[1], &master_password_config);
-        Button::render(&mut buffer, inner_layout[0], &quit_config);
-        Button::render(&mut buffer, inner_layout[1], &confirm_config);
+        Input::render(buffer, layout[0], &username_config);
+        Input::render(buffer, layout[1], &master_password_config);
+        Button::render(buffer, inner_layout[0], &quit_config);
+        Button::render(buffer, inner_layout[1], &confirm_config);
     }
 
     fn handle_key(&mut self, key: &KeyEvent, app: &Application) -> Application {
@@ -271,19 +289,8 @@ impl View for Login {
                 KeyCode::Up => {
                     self.state = LoginState::Username;
                 }
-                KeyCode::Char('s') => {
-                    if key.modifiers.contains(KeyModifiers::CONTROL) {
-                        self.hidden_password = !self.hidden_password;
-                    } else {
-                        let config = self.generate_input_config(LoginInput::MasterPassword);
-                        let (value, cursor_position, input_offset) =
-                            Input::handle_key(key, &config, self.master_password.as_str());
-                        self.master_password = value;
-                        self.cursors
-                            .insert(LoginInput::MasterPassword, cursor_position);
-                        self.input_offsets
-                            .insert(LoginInput::MasterPassword, input_offset);
-                    }
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.hidden_password = !self.hidden_password;
                 }
                 KeyCode::Esc => {
                     app.state = ViewState::StartUp(StartUp::new());
@@ -292,7 +299,7 @@ impl View for Login {
                 _ => {
                     let config = self.generate_input_config(LoginInput::MasterPassword);
                     let (value, cursor_position, input_offset) =
-                        Input::handle_key(key, &config, self.master_password.as_str());
+                        Input::handle_secret_key(key, &config, &self.master_password);
                     self.master_password = value;
                     self.cursors
                         .insert(LoginInput::MasterPassword, cursor_position);
@@ -324,19 +331,24 @@ impl View for Login {
                 KeyCode::Enter => {
                     let res = self.login();
                     match res {
-                        Ok((user, ro_records)) => {
-                            app.state = ViewState::Home(Home::new(
-                                user,
+                        Ok((session, ro_records)) => {
+                            if let Some(migration) = session.migration() {
+                                app.mutable_app_state
+                                    .popups
+                                    .push(Box::new(Self::migration_popup(&migration)));
+                            }
+                            app.state = ViewState::Home(Box::new(Home::new(
+                                session,
                                 ro_records,
                                 Position::default(),
                                 app.immutable_app_state.rect.unwrap(),
-                            ));
+                            )));
                             change_state = true;
                         }
-                        Err(_) => {
+                        Err(e) => {
                             app.mutable_app_state
                                 .popups
-                                .push(Box::new(MessagePopup::new("Cannot login".to_string())));
+                                .push(Box::new(MessagePopup::from_error("Cannot login", &e)));
                         }
                     }
                 }
@@ -1,4 +1,7 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use ratatui::{
     crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
@@ -6,7 +9,9 @@ use ratatui::{
     Frame,
 };
 
-use krab_backend::user::{RecordOperationConfig, User};
+use krab_backend::{
+    backup::Backup, secret::SecretString, session::VaultSession, user::User, Config,
+};
 
 use crate::{
     centered_absolute_rect,
@@ -15,7 +20,7 @@ use crate::{
         input::{Input, InputConfig},
     },
     popups::{
-        insert_domain_password::{InsertDomainPassword, InsertDomainPasswordExitState},
+        backup::{read_backup, BackupExitState, BackupMode, BackupPopup},
         message::MessagePopup,
         Popup,
     },
@@ -72,34 +77,33 @@ enum RegisterState {
 /// * `master_password` - The master password
 /// * `confirm_master_password` - The master password confirmation
 /// * `state` - The state
-/// * `domain` - The domain
-/// * `password` - The password
 /// * `path` - The path
 /// * `cursors` - The cursors
 /// * `input_offsets` - The input offsets
 /// * `hidden_password` - Decides if the master password is hidden
 /// * `hidden_confirm_password` - Decides if the confirm master password is hidden
+/// * `backup` - The backup the vault is restored from, if any
 ///
 /// # Methods
 /// * `new` - Creates a new `Register`
 /// * `generate_input_config` - Generates an input configuration
 /// * `generate_button_config` - Generates a button configuration
+/// * `register` - Creates the user with an empty vault or from the backup
 ///
 /// # Implements
 /// * `View` - The view trait
 #[derive(Debug, Clone, PartialEq)]
 pub struct Register {
     username: String,
-    master_password: String,
-    confirm_master_password: String,
+    master_password: SecretString,
+    confirm_master_password: SecretString,
     state: RegisterState,
-    domain: String,
-    password: String,
     path: PathBuf,
     cursors: HashMap<RegisterInput, u16>,
     input_offsets: HashMap<RegisterInput, u16>,
     hidden_password: bool,
     hidden_confirm_password: bool,
+    backup: Option<Backup>,
 }
 
 impl Register {
@@ -110,7 +114,7 @@ impl Register {
     ///
     /// # Returns
     /// A new `Register`
-    pub fn new(path: &PathBuf) -> Self {
+    pub fn new(path: &Path) -> Self {
         let mut cursors = HashMap::new();
         let mut input_offsets = HashMap::new();
         cursors.insert(RegisterInput::Username, 0);
@@ -121,16 +125,15 @@ impl Register {
         input_offsets.insert(RegisterInput::ConfirmMasterPassword, 0);
         Register {
             username: String::new(),
-            master_password: String::new(),
-            confirm_master_password: String::new(),
+            master_password: SecretString::default(),
+            confirm_master_password: SecretString::default(),
             state: RegisterState::Username,
-            domain: String::new(),
-            password: String::new(),
-            path: path.clone(),
+            path: path.to_path_buf(),
             cursors,
             input_offsets,
             hidden_password: true,
             hidden_confirm_password: true,
+            backup: None,
         }
     }
 
@@ -141,64 +144,61 @@ impl Register {
     ///
     /// # Returns
     /// An input configuration
-    fn generate_input_config(&self, input: RegisterInput) -> InputConfig {
+    fn generate_input_config(&self, input: RegisterInput) -> InputConfig<'_> {
         match input {
             RegisterInput::Username => InputConfig::new(
                 self.state == RegisterState::Username,
-                self.username.clone(),
+                &self.username,
                 false,
-                "Username".to_string(),
+                match &self.backup {
+                    Some(backup) => {
+                        format!("Username | restoring {} secrets", backup.entries.len())
+                    }
+                    None => "Username | CTRL + b - restore a backup".to_string(),
+                },
                 if self.state == RegisterState::Username {
-                    Some(self.cursors.get(&RegisterInput::Username).unwrap().clone())
+                    Some(*self.cursors.get(&RegisterInput::Username).unwrap())
                 } else {
                     None
                 },
-                self.input_offsets
-                    .get(&RegisterInput::Username)
-                    .unwrap()
-                    .clone(),
+                *self.input_offsets.get(&RegisterInput::Username).unwrap(),
                 None,
             ),
             RegisterInput::MasterPassword => InputConfig::new(
                 self.state == RegisterState::MasterPassword,
-                self.master_password.clone(),
+                self.master_password.expose_secret(),
                 self.hidden_password,
                 "Master Password | CTRL + s - show/hide".to_string(),
                 if self.state == RegisterState::MasterPassword {
-                    Some(
-                        self.cursors
-                            .get(&RegisterInput::MasterPassword)
-                            .unwrap()
-                            .clone(),
-                    )
+                    Some(*self.cursors.get(&RegisterInput::MasterPassword).unwrap())
                 } else {
                     None
                 },
-                self.input_offsets
+                *self
+                    .input_offsets
                     .get(&RegisterInput::MasterPassword)
-                    .unwrap()
-                    .clone(),
+                    .unwrap(),
                 None,
             ),
             RegisterInput::ConfirmMasterPassword => InputConfig::new(
                 self.state == RegisterState::ConfirmMasterPassword,
-                self.confirm_master_password.clone(),
+                self.confirm_master_password.expose_secret(),
                 self.hidden_confirm_password,
                 "Confirm Master Password | CTRL + s - show/hide".to_string(),
                 if self.state == RegisterState::ConfirmMasterPassword {
                     Some(
-                        self.cursors
+                        *self
+                            .cursors
                             .get(&RegisterInput::ConfirmMasterPassword)
-                            .unwrap()
-                            .clone(),
+                            .unwrap(),
                     )
                 } else {
                     None
                 },
-                self.input_offsets
+                *self
+                    .input_offsets
                     .get(&RegisterInput::ConfirmMasterPassword)
-                    .unwrap()
-                    .clone(),
+                    .unwrap(),
                 None,
             ),
         }
@@ -221,6 +221,61 @@ impl Register {
             }
         }
     }
+
+    /// Creates the user with an empty vault or from the backup
+    /// Secrets are added from the home view after logging in
+    ///
+    /// # Arguments
+    /// * `app` - The application
+    ///
+    /// # Returns
+    /// The updated application
+    fn register(&self, app: Application) -> Application {
+        let mut app = app.clone();
+        let config = Config::load().unwrap_or_default();
+        let error = if self.username.is_empty() || self.master_password.is_empty() {
+            Some(MessagePopup::new(
+                "Username and master\npassword are required".to_string(),
+            ))
+        } else if self.master_password != self.confirm_master_password {
+            Some(MessagePopup::new(
+                "Master passwords\ndo not match".to_string(),
+            ))
+        } else if let Some(backup) = &self.backup {
+            VaultSession::restore(
+                &self.path,
+                &self.username,
+                self.master_password.expose_secret(),
+                &config.kdf,
+                config.cipher,
+                backup,
+            )
+            .err()
+            .map(|e| MessagePopup::from_error("Could not restore the backup.", &e))
+        } else {
+            User::create(
+                &self.path,
+                &self.username,
+                self.master_password.expose_secret(),
+                &config.kdf,
+                config.cipher,
+            )
+            .err()
+            .map(|e| MessagePopup::from_error("Could not create user.", &e))
+        };
+
+        match error {
+            Some(popup) => {
+                app.state = ViewState::Register(self.clone());
+                app.mutable_app_state.popups.push(Box::new(popup));
+            }
+            None => {
+                app.state = ViewState::StartUp(StartUp::new());
+            }
+        }
+
+        app
+    }
 }
 
 impl View for Register {
@@ -249,19 +304,27 @@ impl View for Register {
             self.generate_input_config(RegisterInput::ConfirmMasterPassword);
         let confirm_config = self.generate_button_config(RegisterButton::Confirm);
         let quit_config = self.generate_button_config(RegisterButton::Quit);
-        let mut buffer = f.buffer_mut();
+        let buffer = f.buffer_mut();
 
-        Input::render(&mut buffer, layout[0], &username_config);
-        Input::render(&mut buffer, layout[1], &master_password_config);
-        Input::render(&mut buffer, layout[2], &confirm_master_password_config);
-        Button::render(&mut buffer, inner_layout[0], &quit_config);
-        Button::render(&mut buffer, inner_layout[1], &confirm_config);
+        Input::render(buffer, layout[0], &username_config);
+        Input::render(buffer, layout[1], &master_password_config);
+        Input::render(buffer, layout[2], &confirm_master_password_config);
+        Button::render(buffer, inner_layout[0], &quit_config);
+        Button::render(buffer, inner_layout[1], &confirm_config);
     }
 
     fn handle_key(&mut self, key: &KeyEvent, app: &Application) -> Application {
         let mut app = app.clone();
         let mut change_state = false;
 
+        if key.code == KeyCode::Char('b') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            app.state = ViewState::Register(self.clone());
+            app.mutable_app_state
+                .popups
+                .push(Box::new(BackupPopup::new(BackupMode::Import)));
+            return app;
+        }
+
         match self.state {
             RegisterState::Username => match key.code {
                 KeyCode::Enter | KeyCode::Tab | KeyCode::Down => {
@@ -292,19 +355,8 @@ impl View for Register {
                 KeyCode::Up => {
                     self.state = RegisterState::Username;
                 }
-                KeyCode::Char('s') => {
-                    if key.modifiers.contains(KeyModifiers::CONTROL) {
-                        self.hidden_password = !self.hidden_password;
-                    } else {
-                        let config = self.generate_input_config(RegisterInput::MasterPassword);
-                        let (value, cursor_position, input_offset) =
-                            Input::handle_key(key, &config, self.master_password.as_str());
-                        self.master_password = value;
-                        self.cursors
-                            .insert(RegisterInput::MasterPassword, cursor_position);
-                        self.input_offsets
-                            .insert(RegisterInput::MasterPassword, input_offset);
-                    }
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.hidden_password = !self.hidden_password;
                 }
                 KeyCode::Esc => {
                     app.state = ViewState::StartUp(StartUp::new());
@@ -313,7 +365,7 @@ impl View for Register {
                 _ => {
                     let config = self.generate_input_config(RegisterInput::MasterPassword);
                     let (value, cursor_position, input_offset) =
-                        Input::handle_key(key, &config, self.master_password.as_str());
+                        Input::handle_secret_key(key, &config, &self.master_password);
                     self.master_password = value;
                     self.cursors
                         .insert(RegisterInput::MasterPassword, cursor_position);
@@ -328,19 +380,8 @@ impl View for Register {
                 KeyCode::Up => {
                     self.state = RegisterState::MasterPassword;
                 }
-                KeyCode::Char('s') => {
-                    if key.modifiers.contains(KeyModifiers::CONTROL) {
-                        self.hidden_confirm_password = !self.hidden_confirm_password;
-                    } else {
-                        let config = self.generate_input_config(RegisterInput::ConfirmMasterPassword);
-                        let (value, cursor_position, input_offset) =
-                            Input::handle_key(key, &config, self.confirm_master_password.as_str());
-                        self.confirm_master_password = value;
-                        self.cursors
-                            .insert(RegisterInput::ConfirmMasterPassword, cursor_position);
-                        self.input_offsets
-                            .insert(RegisterInput::ConfirmMasterPassword, input_offset);
-                    }
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.hidden_confirm_password = !self.hidden_confirm_password;
                 }
                 KeyCode::Esc => {
                     app.state = ViewState::StartUp(StartUp::new());
@@ -349,7 +390,7 @@ impl View for Register {
                 _ => {
                     let config = self.generate_input_config(RegisterInput::ConfirmMasterPassword);
                     let (value, cursor_position, input_offset) =
-                        Input::handle_key(key, &config, self.confirm_master_password.as_str());
+                        Input::handle_secret_key(key, &config, &self.confirm_master_password);
                     self.confirm_master_password = value;
                     self.cursors
                         .insert(RegisterInput::ConfirmMasterPassword, cursor_position);
@@ -379,9 +420,7 @@ impl View for Register {
             },
             RegisterState::Confirm => match key.code {
                 KeyCode::Enter => {
-                    app.mutable_app_state
-                        .popups
-                        .push(Box::new(InsertDomainPassword::new()));
+                    app = self.register(app);
                     change_state = true;
                 }
                 KeyCode::Right | KeyCode::Left | KeyCode::Char('h') | KeyCode::Char('l') => {
@@ -408,69 +447,41 @@ impl View for Register {
         app
     }
 
-    fn handle_insert_record_popup(
-        &mut self,
-        app: Application,
-        _popup: Box<dyn Popup>,
-    ) -> Application {
-        if self.master_password != self.confirm_master_password {
-            let mut app = app.clone();
-            app.mutable_app_state
-                .popups
-                .push(Box::new(MessagePopup::new(
-                    "Could not create user.".to_string(),
-                )));
-            return app;
-        }
-
-        let domain: String;
-        let password: String;
-        let insert_password = _popup.downcast::<InsertDomainPassword>();
+    fn min_area(&self) -> (u16, u16) {
+        let height = 3 * InputConfig::height() + ButtonConfig::height();
+        let width = InputConfig::default_width();
+        (width * 3, height * 3)
+    }
 
-        match insert_password {
-            Ok(insert_password) => {
-                if insert_password.exit_state() == Some(InsertDomainPasswordExitState::Quit) {
-                    return app;
-                }
-                domain = insert_password.domain();
-                password = insert_password.password();
-            }
+    fn handle_backup_popup(&mut self, app: Application, popup: Box<dyn Popup>) -> Application {
+        let backup_popup = match popup.downcast::<BackupPopup>() {
+            Ok(backup_popup) => backup_popup,
             Err(_) => {
                 unreachable!();
             }
+        };
+
+        if backup_popup.exit_state() != Some(BackupExitState::Confirm) {
+            return app;
         }
 
         let mut app = app.clone();
-
-        let config = RecordOperationConfig::new(
-            &self.username,
-            &self.master_password,
-            &domain,
-            &password,
-            &self.path,
-        );
-
-        let res = User::new(&config);
-
-        match res {
-            Ok(_) => {
-                app.state = ViewState::StartUp(StartUp::new());
+        let popup = match read_backup(&backup_popup.path(), backup_popup.passphrase()) {
+            Ok(backup) => {
+                let popup = MessagePopup::with_detail(
+                    &format!("Opened the backup of {}", backup.username),
+                    &format!(
+                        "Its {} secrets are restored\ninto the new vault",
+                        backup.entries.len()
+                    ),
+                );
+                self.backup = Some(backup);
+                app.state = ViewState::Register(self.clone());
+                popup
             }
-            Err(_) => {
-                app.mutable_app_state
-                    .popups
-                    .push(Box::new(MessagePopup::new(
-                        "Could not create user.".to_string(),
-                    )));
-            }
-        }
-
+            Err(popup) => popup,
+        };
+        app.mutable_app_state.popups.push(Box::new(popup));
         app
     }
-
-    fn min_area(&self) -> (u16, u16) {
-        let height = 3 * InputConfig::height() + ButtonConfig::height();
-        let width = InputConfig::default_width();
-        (width * 3, height * 3)
-    }
 }
@@ -7,11 +7,21 @@ use ratatui::{
     Frame,
 };
 
-use krab_backend::Config;
+use krab_backend::{
+    cipher::CipherKind,
+    kdf::{KdfParams, DEFAULT_UNLOCK_TIME},
+    user::KrabError,
+    Config,
+};
 
 use crate::{
     from,
-    views::{startup::StartUp, View, ViewState},
+    popups::{
+        insert_master::{InsertMaster, InsertMasterExitState},
+        message::{error_message, MessagePopup},
+        Popup,
+    },
+    views::{home::Home, startup::StartUp, View, ViewState},
     Application, COLOR_ORANGE, COLOR_WHITE,
 };
 
@@ -20,12 +30,22 @@ use crate::{
 /// # Variants
 /// * `IncludeNumbers` - Include numbers in password generation
 /// * `IncludeSpecialChars` - Include special characters in password generation
+/// * `AskMasterPassword` - Ask for the master password on every change to a vault
+/// * `KdfAlgorithm` - The key derivation algorithm for new vaults
+/// * `CalibrateKdf` - Tune the key derivation cost to this machine
+/// * `Cipher` - The cipher for new vaults
+/// * `ApplyEncryption` - Use the key derivation and cipher settings for the open vault
 /// * `Save` - Save the current settings
-/// * `Back` - Go back to startup
+/// * `Back` - Go back to startup or to the open vault
 #[derive(Debug, Clone, PartialEq)]
 enum SettingsOption {
     IncludeNumbers,
     IncludeSpecialChars,
+    AskMasterPassword,
+    KdfAlgorithm,
+    CalibrateKdf,
+    Cipher,
+    ApplyEncryption,
     Save,
     Back,
 }
@@ -37,9 +57,11 @@ enum SettingsOption {
 /// * `config` - The password configuration to modify
 /// * `original_config` - The original configuration to compare against for unsaved changes
 /// * `has_unsaved_changes` - Whether there are unsaved changes
+/// * `home` - The home view of the open vault, if opened after logging in
 ///
 /// # Methods
 /// * `new` - Creates a new `Settings` view
+/// * `with_vault` - Creates a new `Settings` view for an open vault
 ///
 /// # Implements
 /// * `View` - The view trait
@@ -49,6 +71,13 @@ pub struct Settings {
     config: Config,
     original_config: Config,
     has_unsaved_changes: bool,
+    home: Option<Box<Home>>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Settings {
@@ -63,64 +92,129 @@ impl Settings {
             config: config.clone(),
             original_config: config.clone(),
             has_unsaved_changes: false,
+            home: None,
+        }
+    }
+
+    /// Creates a new `Settings` for an open vault
+    /// Going back returns to the vault, whose key derivation and cipher can be changed
+    ///
+    /// # Arguments
+    /// * `home` - The home view of the open vault
+    ///
+    /// # Returns
+    /// A new `Settings`
+    pub fn with_vault(home: Home) -> Self {
+        Settings {
+            home: Some(Box::new(home)),
+            ..Settings::new()
+        }
+    }
+
+    /// Returns the options in the order they are listed
+    /// The key derivation and cipher of a vault can only be changed while it is open
+    ///
+    /// # Returns
+    /// The listed options
+    fn options(&self) -> Vec<SettingsOption> {
+        let mut options = vec![
+            SettingsOption::IncludeNumbers,
+            SettingsOption::IncludeSpecialChars,
+            SettingsOption::AskMasterPassword,
+            SettingsOption::KdfAlgorithm,
+            SettingsOption::CalibrateKdf,
+            SettingsOption::Cipher,
+        ];
+        if self.home.is_some() {
+            options.push(SettingsOption::ApplyEncryption);
+        }
+        options.push(SettingsOption::Save);
+        options.push(SettingsOption::Back);
+        options
+    }
+
+    /// Returns the label of an option
+    ///
+    /// # Arguments
+    /// * `option` - The option
+    ///
+    /// # Returns
+    /// The label of the option
+    fn label(&self, option: &SettingsOption) -> String {
+        let checkbox = |checked: bool| if checked { "x" } else { " " };
+        match option {
+            SettingsOption::IncludeNumbers => format!(
+                "[{}] Include Numbers",
+                checkbox(self.config.password_config.include_numbers)
+            ),
+            SettingsOption::IncludeSpecialChars => format!(
+                "[{}] Include Special Characters",
+                checkbox(self.config.password_config.include_special)
+            ),
+            SettingsOption::AskMasterPassword => format!(
+                "[{}] Ask Master Password On Changes",
+                checkbox(self.config.ask_master_password)
+            ),
+            SettingsOption::KdfAlgorithm => format!("Key Derivation: {}", self.config.kdf),
+            SettingsOption::CalibrateKdf => format!(
+                "Calibrate Key Derivation ({}s unlock)",
+                DEFAULT_UNLOCK_TIME.as_secs()
+            ),
+            SettingsOption::Cipher => format!("Cipher: {}", self.config.cipher),
+            SettingsOption::ApplyEncryption => "Apply Encryption To Open Vault".to_string(),
+            SettingsOption::Save => "Save Settings".to_string(),
+            SettingsOption::Back => "< Back".to_string(),
         }
     }
 
+    /// Goes back to the open vault or to the startup view
+    ///
+    /// # Arguments
+    /// * `app` - The application
+    fn back(&self, app: &mut Application) {
+        app.state = match &self.home {
+            Some(home) => {
+                let mut home = home.clone();
+                home.reload_config();
+                ViewState::Home(home)
+            }
+            None => ViewState::StartUp(StartUp::new()),
+        };
+    }
+
+    /// Creates a message popup wide enough for the message
+    ///
+    /// # Arguments
+    /// * `message` - The message
+    ///
+    /// # Returns
+    /// A message popup
+    fn message_popup(message: String) -> MessagePopup {
+        let width = message.lines().map(|l| l.len()).max().unwrap_or(0) as u16 + 6;
+        let (min_width, min_height) = MessagePopup::min_area();
+        MessagePopup::new_with_size(message, width.max(min_width), min_height)
+    }
+
     /// Updates the unsaved changes flag by comparing current config with original
     fn update_unsaved_changes(&mut self) {
         self.has_unsaved_changes = self.config != self.original_config;
     }
 
     /// Gets the list of settings items for rendering
-    fn get_settings_items(&self) -> Vec<ListItem> {
-        vec![
-            ListItem::new(Line::from(vec![Span::styled(
-                format!(
-                    "[{}] Include Numbers",
-                    if self.config.password_config.include_numbers {
-                        "x"
-                    } else {
-                        " "
-                    }
-                ),
-                if self.selected_option == SettingsOption::IncludeNumbers {
-                    Style::default().fg(Color::Yellow)
-                } else {
-                    Style::default().fg(Color::White)
-                },
-            )])),
-            ListItem::new(Line::from(vec![Span::styled(
-                format!(
-                    "[{}] Include Special Characters",
-                    if self.config.password_config.include_special {
-                        "x"
+    fn get_settings_items(&self) -> Vec<ListItem<'_>> {
+        self.options()
+            .iter()
+            .map(|option| {
+                ListItem::new(Line::from(vec![Span::styled(
+                    self.label(option),
+                    if self.selected_option == *option {
+                        Style::default().fg(Color::Yellow)
                     } else {
-                        " "
-                    }
-                ),
-                if self.selected_option == SettingsOption::IncludeSpecialChars {
-                    Style::default().fg(Color::Yellow)
-                } else {
-                    Style::default().fg(Color::White)
-                },
-            )])),
-            ListItem::new(Line::from(vec![Span::styled(
-                "Save Settings",
-                if self.selected_option == SettingsOption::Save {
-                    Style::default().fg(Color::Yellow)
-                } else {
-                    Style::default().fg(Color::White)
-                },
-            )])),
-            ListItem::new(Line::from(vec![Span::styled(
-                "< Back",
-                if self.selected_option == SettingsOption::Back {
-                    Style::default().fg(Color::Yellow)
-                } else {
-                    Style::default().fg(Color::White)
-                },
-            )])),
-        ]
+                        Style::default().fg(Color::White)
+                    },
+                )]))
+            })
+            .collect()
     }
 }
 
@@ -153,7 +247,7 @@ impl View for Settings {
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title("Password Generation"),
+                    .title("Password Generation & Security"),
             )
             .style(Style::default().fg(from(COLOR_WHITE).unwrap_or(Color::Yellow)));
 
@@ -174,20 +268,20 @@ impl View for Settings {
 
         match key.code {
             KeyCode::Down | KeyCode::Char('j') => {
-                self.selected_option = match self.selected_option {
-                    SettingsOption::IncludeNumbers => SettingsOption::IncludeSpecialChars,
-                    SettingsOption::IncludeSpecialChars => SettingsOption::Save,
-                    SettingsOption::Save => SettingsOption::Back,
-                    SettingsOption::Back => SettingsOption::IncludeNumbers,
-                };
+                let options = self.options();
+                let index = options
+                    .iter()
+                    .position(|o| *o == self.selected_option)
+                    .unwrap_or(0);
+                self.selected_option = options[(index + 1) % options.len()].clone();
             }
             KeyCode::Up | KeyCode::Char('k') => {
-                self.selected_option = match self.selected_option {
-                    SettingsOption::IncludeNumbers => SettingsOption::Back,
-                    SettingsOption::IncludeSpecialChars => SettingsOption::IncludeNumbers,
-                    SettingsOption::Save => SettingsOption::IncludeSpecialChars,
-                    SettingsOption::Back => SettingsOption::Save,
-                };
+                let options = self.options();
+                let index = options
+                    .iter()
+                    .position(|o| *o == self.selected_option)
+                    .unwrap_or(0);
+                self.selected_option = options[(index + options.len() - 1) % options.len()].clone();
             }
             KeyCode::Enter | KeyCode::Char(' ') => match self.selected_option {
                 SettingsOption::IncludeNumbers => {
@@ -200,6 +294,41 @@ impl View for Settings {
                         !self.config.password_config.include_special;
                     self.update_unsaved_changes();
                 }
+                SettingsOption::AskMasterPassword => {
+                    self.config.ask_master_password = !self.config.ask_master_password;
+                    self.update_unsaved_changes();
+                }
+                SettingsOption::KdfAlgorithm => {
+                    self.config.kdf = match self.config.kdf {
+                        KdfParams::Scrypt { .. } => KdfParams::default_argon2id(),
+                        KdfParams::Argon2id { .. } => KdfParams::default(),
+                    };
+                    self.update_unsaved_changes();
+                }
+                SettingsOption::CalibrateKdf => {
+                    self.config.kdf = self.config.kdf.calibrate(DEFAULT_UNLOCK_TIME);
+                    self.update_unsaved_changes();
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(Self::message_popup(format!(
+                            "Calibrated to\n{}",
+                            self.config.kdf
+                        ))));
+                }
+                SettingsOption::Cipher => {
+                    self.config.cipher = match self.config.cipher {
+                        CipherKind::Aes128GcmSiv | CipherKind::XChaCha20Poly1305 => {
+                            CipherKind::Aes256GcmSiv
+                        }
+                        CipherKind::Aes256GcmSiv => CipherKind::XChaCha20Poly1305,
+                    };
+                    self.update_unsaved_changes();
+                }
+                SettingsOption::ApplyEncryption => {
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(InsertMaster::new()));
+                }
                 SettingsOption::Save => {
                     // Save the password config and update original config
                     if let Ok(()) = self.config.save() {
@@ -209,13 +338,13 @@ impl View for Settings {
                 }
                 SettingsOption::Back => {
                     // Go back without saving
-                    app.state = ViewState::StartUp(StartUp::new());
+                    self.back(&mut app);
                     change_state = true;
                 }
             },
             KeyCode::Esc | KeyCode::Char('q') => {
                 // Go back without saving
-                app.state = ViewState::StartUp(StartUp::new());
+                self.back(&mut app);
                 change_state = true;
             }
             _ => {}
@@ -235,4 +364,50 @@ impl View for Settings {
     fn needs_header(&self) -> bool {
         false
     }
+
+    fn handle_insert_master_popup(
+        &mut self,
+        app: Application,
+        popup: Box<dyn Popup>,
+    ) -> Application {
+        let master_password = match popup.downcast::<InsertMaster>() {
+            Ok(insert_master) => {
+                if insert_master.exit_state() == Some(InsertMasterExitState::Quit) {
+                    return app;
+                }
+                insert_master.master().clone()
+            }
+            Err(_) => {
+                unreachable!();
+            }
+        };
+        let home = match self.home.as_mut() {
+            Some(home) => home,
+            None => {
+                unreachable!();
+            }
+        };
+
+        let message = match home.session_mut().change_encryption(
+            master_password.expose_secret(),
+            &self.config.kdf,
+            self.config.cipher,
+        ) {
+            Ok(()) => format!(
+                "The vault now uses\n{}\n{}",
+                self.config.cipher, self.config.kdf
+            ),
+            Err(KrabError::Conflict) => {
+                "The vault was changed by another\nkrab instance, go back to reload it".to_string()
+            }
+            Err(e) => format!("Cannot change the encryption\n\n{}", error_message(&e)),
+        };
+
+        let mut app = app.clone();
+        app.state = ViewState::Settings(self.clone());
+        app.mutable_app_state
+            .popups
+            .push(Box::new(Self::message_popup(message)));
+        app
+    }
 }
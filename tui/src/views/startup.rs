@@ -60,6 +60,12 @@ pub struct StartUp {
     state: StartUpState,
 }
 
+impl Default for StartUp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl StartUp {
     /// Creates a new `StartUp`
     ///
@@ -115,12 +121,12 @@ impl View for StartUp {
         let register_config = self.generate_button_config(StartUpButton::Register);
         let settings_config = self.generate_button_config(StartUpButton::Settings);
         let quit_config = self.generate_button_config(StartUpButton::Quit);
-        let mut buffer = f.buffer_mut();
+        let buffer = f.buffer_mut();
 
-        Button::render(&mut buffer, layout[0], &login_config);
-        Button::render(&mut buffer, layout[1], &register_config);
-        Button::render(&mut buffer, layout[2], &settings_config);
-        Button::render(&mut buffer, layout[3], &quit_config);
+        Button::render(buffer, layout[0], &login_config);
+        Button::render(buffer, layout[1], &register_config);
+        Button::render(buffer, layout[2], &settings_config);
+        Button::render(buffer, layout[3], &quit_config);
     }
 
     fn handle_key(&mut self, key: &KeyEvent, app: &Application) -> Application {